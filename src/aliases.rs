@@ -0,0 +1,315 @@
+// src/aliases.rs
+
+//! Reading and writing `alias name='value'` files, so aliases can move
+//! between halo and bash/zsh, and the `[aliases]` table of halo.toml.
+//! Also the `{name}` placeholders an alias value may ask for when run.
+
+use crate::error::AppResult;
+use crate::parser::{self, Token};
+use anyhow::anyhow;
+use std::collections::HashMap;
+use std::ops::Range;
+use toml_edit::DocumentMut;
+
+/// A `{name}` or `{name:kind}` slot in an alias value. `kind` is a hint
+/// for the prompt: `path` and `dir` complete paths on Tab.
+pub struct Placeholder {
+    pub name: String,
+    pub kind: Option<String>,
+    pub span: Range<usize>,
+}
+
+/// Placeholders in `value`, in order. `${VAR}`, `{}` and brace
+/// expansions such as `{a,b}` are not placeholders.
+pub fn placeholders(value: &str) -> Vec<Placeholder> {
+    let mut found = Vec::new();
+    let mut open = None;
+    let mut prev = None;
+    for (i, c) in value.char_indices() {
+        match c {
+            '{' if prev != Some('$') => open = Some(i),
+            '}' => {
+                if let Some(start) = open.take() {
+                    let inner = &value[start + 1..i];
+                    let (name, kind) = match inner.split_once(':') {
+                        Some((name, kind)) => (name, Some(kind)),
+                        None => (inner, None),
+                    };
+                    if is_placeholder_name(name) && kind.is_none_or(is_placeholder_name) {
+                        found.push(Placeholder {
+                            name: name.to_string(),
+                            kind: kind.map(str::to_string),
+                            span: start..i + 1,
+                        });
+                    }
+                }
+            }
+            c if !(is_name_char(c) || c == ':') => open = None,
+            _ => {}
+        }
+        prev = Some(c);
+    }
+    found
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+fn is_placeholder_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(is_name_char)
+}
+
+/// `value` with each placeholder replaced by its answer, escaped for the
+/// quotes around it (as one word when unquoted). Unanswered ones stay.
+pub fn fill_placeholders(value: &str, answers: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut at = 0;
+    for placeholder in placeholders(value) {
+        out.push_str(&value[at..placeholder.span.start]);
+        match answers.get(&placeholder.name) {
+            Some(answer) => {
+                let quote = quote_state(&value[..placeholder.span.start]);
+                out.push_str(&escape_answer(answer, quote));
+            }
+            None => out.push_str(&value[placeholder.span.clone()]),
+        }
+        at = placeholder.span.end;
+    }
+    out.push_str(&value[at..]);
+    out
+}
+
+fn escape_answer(answer: &str, quote: Option<char>) -> String {
+    match quote {
+        Some('\'') => answer.replace('\'', r"'\''"),
+        Some(_) => answer
+            .chars()
+            .flat_map(|c| {
+                let escape = matches!(c, '"' | '\\' | '$' | '`').then_some('\\');
+                escape.into_iter().chain(std::iter::once(c))
+            })
+            .collect(),
+        None => parser::quote(answer),
+    }
+}
+
+/// The quote still open at the end of `text`, if any.
+fn quote_state(text: &str) -> Option<char> {
+    let mut open = None;
+    let mut escaped = false;
+    for c in text.chars() {
+        match (open, c) {
+            (_, _) if escaped => escaped = false,
+            (Some('\''), '\'') => open = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (None, '\'' | '"') => open = Some(c),
+            (Some('"'), '"') => open = None,
+            _ => {}
+        }
+    }
+    open
+}
+
+/// Collects definitions from bash/zsh-style `alias` lines. Other lines are
+/// ignored, so a whole `.bashrc` can be imported. zsh flags such as `-g`
+/// are skipped.
+pub fn parse_definitions(text: &str) -> Vec<(String, String)> {
+    let mut defs = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        let Some(rest) = line.strip_prefix("alias ") else {
+            continue;
+        };
+        let Ok(tokens) = parser::tokenize(rest) else {
+            continue;
+        };
+        for token in tokens {
+            let Token::Word(word) = token else {
+                // `alias a=b; alias c=d` and friends: stop at the first operator.
+                break;
+            };
+            if word.text.starts_with('-') {
+                continue;
+            }
+            if let Some((name, value)) = word.text.split_once('=')
+                && !name.is_empty()
+            {
+                defs.push((name.to_string(), value.to_string()));
+            }
+        }
+    }
+    defs
+}
+
+/// One `alias name='value'` line, quoted so bash, zsh and halo all read it back.
+pub fn format_definition(name: &str, value: &str) -> String {
+    format!("alias {name}='{}'", value.replace('\'', r"'\''"))
+}
+
+/// All definitions sorted by name, as a file body.
+pub fn format_all(map: &HashMap<String, String>) -> String {
+    let mut names: Vec<&String> = map.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| format_definition(name, &map[name]) + "\n")
+        .collect()
+}
+
+/// `config` with the `[section]` table holding exactly `map`. The file is
+/// edited as TOML, so comments, formatting and the other tables are kept
+/// and a header such as `[ aliases ] # mine` is still found; a missing
+/// table is appended. Fails rather than produce a file that won't parse.
+pub fn replace_section(
+    config: &str,
+    section: &str,
+    map: &HashMap<String, String>,
+) -> AppResult<String> {
+    let mut doc: DocumentMut = config.parse()?;
+    let table = doc
+        .entry(section)
+        .or_insert_with(toml_edit::table)
+        .as_table_like_mut()
+        .ok_or_else(|| anyhow!("`{section}` is not a table"))?;
+    let stale: Vec<String> = table
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .filter(|name| !map.contains_key(name))
+        .collect();
+    for name in stale {
+        table.remove(&name);
+    }
+    let mut names: Vec<&String> = map.keys().collect();
+    names.sort();
+    for name in names {
+        let value = map[name].as_str();
+        match table.get_mut(name).and_then(|item| item.as_value_mut()) {
+            Some(old) if old.as_str() == Some(value) => {}
+            Some(old) => {
+                let decor = old.decor().clone();
+                *old = value.into();
+                *old.decor_mut() = decor;
+            }
+            None => {
+                table.insert(name, toml_edit::value(value));
+            }
+        }
+    }
+    let out = doc.to_string();
+    out.parse::<toml::Value>()
+        .map_err(|e| anyhow!("edited config would not parse: {}", e.message()))?;
+    Ok(out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn placeholders_skip_variables_and_brace_expansions() {
+        let value = "scp {file:path} ${HOST}:{dest} {a,b} {} {_x}";
+        let found = placeholders(value);
+        let spans: Vec<&str> = found.iter().map(|p| &value[p.span.clone()]).collect();
+        assert_eq!(spans, ["{file:path}", "{dest}", "{_x}"]);
+        assert_eq!(found[0].name, "file");
+        assert_eq!(found[0].kind.as_deref(), Some("path"));
+        assert_eq!(found[1].kind, None);
+    }
+
+    #[test]
+    fn fill_placeholders_escapes_for_the_quotes_around_them() {
+        let answers = map(&[("msg", "it's $5"), ("f", "a b")]);
+        assert_eq!(fill_placeholders("echo {msg}", &answers), r"echo 'it'\''s $5'");
+        assert_eq!(fill_placeholders("echo '{msg}'", &answers), r"echo 'it'\''s $5'");
+        assert_eq!(fill_placeholders("echo \"{msg}\"", &answers), "echo \"it's \\$5\"");
+        assert_eq!(fill_placeholders("cat {f} {g}", &answers), "cat 'a b' {g}");
+    }
+
+    #[test]
+    fn definitions_round_trip() {
+        let text = "export X=1\nalias ll='ls -l'\nalias -g G='| grep' gs=\"git status\"\n";
+        assert_eq!(
+            parse_definitions(text),
+            [
+                ("ll".to_string(), "ls -l".to_string()),
+                ("G".to_string(), "| grep".to_string()),
+                ("gs".to_string(), "git status".to_string()),
+            ]
+        );
+        let tricky = map(&[("say", "echo 'hi'")]);
+        let line = format_all(&tricky);
+        assert_eq!(line, "alias say='echo '\\''hi'\\'''\n");
+        assert_eq!(parse_definitions(&line), [("say".into(), "echo 'hi'".into())]);
+    }
+
+    #[test]
+    fn replace_section_keeps_the_rest_of_the_file() {
+        let config = "\
+# my config
+[ui]
+theme = \"dark\"
+
+[ aliases ] # mine
+ll = \"ls -l\"   # long
+gone = \"x\"
+
+[lock]
+idle_minutes = 5
+";
+        let out = replace_section(
+            config,
+            "aliases",
+            &map(&[("ll", "ls -la"), ("gs", "git status")]),
+        )
+        .unwrap();
+        assert_eq!(
+            out,
+            "\
+# my config
+[ui]
+theme = \"dark\"
+
+[ aliases ] # mine
+ll = \"ls -la\"   # long
+gs = \"git status\"
+
+[lock]
+idle_minutes = 5
+"
+        );
+    }
+
+    #[test]
+    fn replace_section_appends_a_missing_table() {
+        let out = replace_section("[ui]\ntheme = \"dark\"\n", "aliases", &map(&[("l", "ls")]))
+            .unwrap();
+        let parsed: toml::Value = out.parse().unwrap();
+        assert_eq!(parsed["ui"]["theme"].as_str(), Some("dark"));
+        assert_eq!(parsed["aliases"]["l"].as_str(), Some("ls"));
+    }
+
+    #[test]
+    fn replace_section_edits_an_inline_table() {
+        let out = replace_section("aliases = { l = \"ls\", k = \"x\" }\n", "aliases", &map(&[]))
+            .unwrap();
+        let parsed: toml::Value = out.parse().unwrap();
+        assert!(parsed["aliases"].as_table().unwrap().is_empty());
+    }
+
+    #[test]
+    fn replace_section_refuses_bad_input() {
+        assert!(replace_section("[aliases\n", "aliases", &map(&[])).is_err());
+        assert!(replace_section("aliases = 3\n", "aliases", &map(&[])).is_err());
+    }
+}
@@ -1,60 +1,729 @@
 // src/app.rs
 
-use crate::command::{CommandLog, CommandManager, CommandUpdate};
-use crate::error::AppResult;
+//! The TUI frontend: [`App`] owns a [`State`] and the job manager, runs
+//! builtins and command lines, and drives the event loop that draws the
+//! screen and applies keys, job output and background results.
+
+use halo_core::aliases;
+use halo_core::builder::{self, Builder};
+use halo_core::cdfix;
+use halo_core::clipboard::{self, CopyTarget};
+use halo_core::command::{
+    self, CommandLog, CommandManager, CommandUpdate, JobExit, JobStatus, RichOutput,
+};
+use halo_core::completion::{BUILTINS, CompletionResult};
+use halo_core::diffview::DiffView;
+use halo_core::disk;
+use halo_core::dryrun::{self, Step};
+use halo_core::error::AppResult;
 use crate::event::EventHandler;
-use crate::state::State;
+use halo_core::export;
+use halo_core::exports;
+use halo_core::git::{GitMonitor, GitUpdate};
+use halo_core::gitpanel::{Area, GitPanel};
+use halo_core::handoff::{self, InteractiveRequest};
+use halo_core::hooks;
+use halo_core::links;
+use halo_core::notify::{self, AlertKind, Notification};
+use halo_core::output;
+use halo_core::panes::{MAX_PANES, Split};
+use halo_core::parser::{self, RedirectKind};
+use halo_core::perf::PerfStats;
+use halo_core::procs::ProcMonitor;
+use halo_core::restore::{self, RestoreConfig};
+use halo_core::scratch;
+use halo_core::sessions::{self, Snapshot};
+use halo_core::share::{self, Shared};
+use halo_core::state::{
+    self, ConfirmAction, Confirmation, ModeIndicators, PlaceholderPrompt, State,
+};
+use halo_core::summary;
+use halo_core::tabs::{MAX_TABS, Tab};
 use crate::ui;
 use ratatui::prelude::*;
+use std::io::Write;
+use std::iter::Peekable;
+use std::os::unix::fs::OpenOptionsExt;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::str::Chars;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
+/// Minimum time between frames in low-bandwidth mode.
+const LOW_BANDWIDTH_FRAME: Duration = Duration::from_millis(150);
+/// How long jobs get to exit after SIGHUP when halo quits.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A running halo session in a terminal.
 pub struct App {
+    /// Everything the session knows; the UI draws from it.
     pub state: State,
     command_manager: CommandManager,
     command_update_rx: UnboundedReceiver<CommandUpdate>,
     command_update_tx: UnboundedSender<CommandUpdate>,
+    completion_rx: UnboundedReceiver<CompletionResult>,
+    completion_tx: UnboundedSender<CompletionResult>,
+    share_rx: UnboundedReceiver<Shared>,
+    share_tx: UnboundedSender<Shared>,
+    pending_interactive: Option<InteractiveRequest>,
+    /// Ctrl-X Ctrl-E was pressed; open the input line in $EDITOR.
+    edit_requested: bool,
+    /// Terminal title last sent, so it is only written on change.
+    terminal_title: Option<String>,
+    git: GitMonitor,
 }
 
 impl App {
-    pub fn new() -> AppResult<Self> {
+    /// `safe_mode` starts on built-in defaults (`--safe`).
+    pub fn new(safe_mode: bool) -> AppResult<Self> {
         let (tx, rx) = mpsc::unbounded_channel();
+        let (completion_tx, completion_rx) = mpsc::unbounded_channel();
+        let (share_tx, share_rx) = mpsc::unbounded_channel();
+        let state = State::new(safe_mode)?;
+        let mut command_manager = CommandManager::new();
+        command_manager.max_parallel = state.max_parallel_jobs;
         Ok(Self {
-            state: State::new()?,
-            command_manager: CommandManager::new(),
+            state,
+            command_manager,
             command_update_rx: rx,
             command_update_tx: tx,
+            completion_rx,
+            completion_tx,
+            share_rx,
+            share_tx,
+            pending_interactive: None,
+            edit_requested: false,
+            terminal_title: None,
+            git: GitMonitor::default(),
         })
     }
 
-    /// Fetches git info and updates the state.
+    /// Applies finished git lookups to the state and starts the next.
     fn update_git_info(&mut self) {
-        self.state.git_branch = get_git_branch(&self.state.cwd);
+        let (info, unavailable) = match self.git.poll(&self.state.cwd) {
+            Some(GitUpdate::Info(info)) => (info, false),
+            Some(GitUpdate::Unavailable) => (Default::default(), true),
+            None => return,
+        };
+        if self.state.git_branch != info.branch
+            || self.state.git_root != info.root
+            || self.state.git_status != info.status
+            || self.state.git_unavailable != unavailable
+        {
+            self.state.git_branch = info.branch;
+            self.state.git_root = info.root;
+            self.state.git_status = info.status;
+            self.state.git_unavailable = unavailable;
+            self.state.needs_redraw = true;
+        }
+    }
+
+    /// Raises the `[notify]` backends configured for how `command` ended,
+    /// and marks its entry if the user was not watching it finish.
+    fn notify_finished(
+        &mut self,
+        entry: u64,
+        command: String,
+        exit: JobExit,
+        duration_ms: Option<u128>,
+    ) {
+        let Some(event) = self.state.notify.event_for(exit.code, duration_ms) else {
+            return;
+        };
+        let watching = self.state.is_watching();
+        if !watching && let Some(log) = self.state.entry_mut(entry) {
+            log.unseen = true;
+        }
+        let backends = self.state.notify.backends(event);
+        if backends.is_empty() || (watching && self.state.notify.only_when_away) {
+            return;
+        }
+        let ended = match exit.signal {
+            Some(signal) => command::describe_signal(signal),
+            None => format!("exit {}", exit.status()),
+        };
+        let summary = match duration_ms {
+            Some(ms) => format!("{ended} after {}", summary::format_ms(ms)),
+            None => ended,
+        };
+        let note = Notification {
+            event,
+            command,
+            exit_code: exit.code,
+            duration_ms,
+            summary,
+        };
+        for &kind in backends {
+            match kind {
+                // Flashing costs two full redraws; a bell is one byte.
+                AlertKind::Flash if self.state.low_bandwidth => notify::ring_bell(),
+                AlertKind::Flash => {
+                    self.state.flash_until = Some(Instant::now() + notify::FLASH_DURATION);
+                    self.state.needs_redraw = true;
+                }
+                _ => {
+                    if let Some(notifier) = self.state.notify.notifier(kind) {
+                        notifier.notify(&note);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Clears an expired failure flash.
+    fn update_flash(&mut self) {
+        if self.state.flash_until.is_some_and(|t| Instant::now() >= t) {
+            self.state.flash_until = None;
+            self.state.needs_redraw = true;
+        }
+        if self.state.notice.as_ref().is_some_and(|(_, t)| Instant::now() >= *t) {
+            self.state.notice = None;
+            self.state.needs_redraw = true;
+        }
+    }
+
+    /// Locks the screen once `[lock] idle_minutes` pass without input.
+    fn update_lock(&mut self) {
+        if let Some(idle) = self.state.lock.idle
+            && self.state.locked.is_none()
+            && self.state.last_input.elapsed() >= idle
+        {
+            self.state.lock_screen();
+        }
+    }
+
+    /// Copies the text selected with the mouse.
+    pub fn copy_selection(&mut self) {
+        let Some((anchor, head)) = self.state.selection else {
+            return;
+        };
+        let text = self.state.log_screen.text(anchor, head);
+        let message = match clipboard::copy(&text, state::over_ssh()) {
+            Ok(via) => format!("copied {} characters via {}", text.chars().count(), via.label()),
+            Err(e) => format!("copy: {e}"),
+        };
+        self.state.show_notice(message);
+    }
+
+    /// Copies part of a log entry to the clipboard (Alt+C/O/W or `copy`).
+    /// The builtin reports in its own entry, keys in the status bar.
+    pub fn copy_to_clipboard(&mut self, target: CopyTarget, from_builtin: bool) {
+        let result = match self.state.copy_source(from_builtin) {
+            Some(log) => clipboard::copy(&target.text(log), state::over_ssh())
+                .map(|via| format!("copied {} via {}", target.label(), via.label()))
+                .map_err(|e| format!("copy: {e}")),
+            None => Err("copy: nothing to copy yet".to_string()),
+        };
+        match (result, from_builtin) {
+            (Ok(message), true) => self.state.append_to_last_log(format!("[{message}]")),
+            (Err(message), true) => {
+                self.state.append_to_last_log(message);
+                self.state.last_exit_code = 1;
+            }
+            (Ok(message) | Err(message), false) => self.state.show_notice(message),
+        }
+    }
+
+    fn update_mode_indicators(&mut self) {
+        let jobs = &self.command_manager.jobs;
+        let foreground = jobs.foreground();
+        let modes = ModeIndicators {
+            background_jobs: jobs
+                .iter()
+                .filter(|j| j.status == JobStatus::Running && Some(j.id) != foreground)
+                .count(),
+            stopped_jobs: jobs
+                .iter()
+                .filter(|j| j.status == JobStatus::Stopped)
+                .count(),
+            queued_jobs: self.command_manager.queued_jobs(),
+            presentation: self.state.presentation_mode,
+            safe: self.state.safe_mode,
+        };
+        if modes != self.state.modes {
+            self.state.modes = modes;
+            self.state.needs_redraw = true;
+        }
+    }
+
+    /// Resamples the process overlay once a second while it is open.
+    fn update_procs(&mut self) {
+        if let Some(monitor) = &mut self.state.procs
+            && monitor.due()
+        {
+            monitor.refresh(&self.command_manager.jobs);
+            self.state.needs_redraw = true;
+        }
+    }
+
+    /// F3: opens or closes the process overlay.
+    pub fn toggle_procs(&mut self) {
+        self.state.procs = match self.state.procs {
+            Some(_) => None,
+            None => Some(ProcMonitor::new(false)),
+        };
+    }
+
+    /// Sends `signal` to the process selected in the overlay: the whole
+    /// process group for one of halo's jobs, the single process otherwise.
+    pub fn signal_selected_proc(&mut self, signal: libc::c_int) {
+        let Some(monitor) = &mut self.state.procs else {
+            return;
+        };
+        let Some((job, pid)) = monitor.selected_row().map(|row| (row.job, row.pid)) else {
+            return;
+        };
+        let result = match (job, pid) {
+            (Some(id), _) => self.command_manager.signal(id, signal),
+            (None, Some(pid)) => {
+                // SAFETY: kill has no memory effects.
+                if unsafe { libc::kill(pid as libc::pid_t, signal) } == 0 {
+                    Ok(())
+                } else {
+                    Err(std::io::Error::last_os_error().into())
+                }
+            }
+            (None, None) => Ok(()),
+        };
+        let name = command::signal_name(signal).unwrap_or("signal");
+        let target = match (job, pid) {
+            (Some(id), _) => format!("%{id}"),
+            (None, pid) => pid.unwrap_or_default().to_string(),
+        };
+        monitor.message = Some(match result {
+            Ok(()) => format!("sent {name} to {target}"),
+            Err(e) => format!("{target}: {e}"),
+        });
+        self.state.needs_redraw = true;
+    }
+
+    fn update_git_panel(&mut self) {
+        if let Some(panel) = &mut self.state.git_panel
+            && panel.poll()
+        {
+            self.state.needs_redraw = true;
+        }
+    }
+
+    /// F4: opens or closes the git panel.
+    pub fn toggle_git_panel(&mut self) {
+        self.state.git_panel = match self.state.git_panel {
+            Some(_) => None,
+            None => Some(GitPanel::open(&self.state.cwd)),
+        };
+    }
+
+    /// Space in the git panel: stages or unstages the selected file.
+    pub fn git_panel_toggle_stage(&mut self) {
+        let Some(panel) = &mut self.state.git_panel else {
+            return;
+        };
+        panel.message = Some(match panel.toggle_stage() {
+            Ok(done) => done,
+            Err(e) => e.to_string(),
+        });
+        panel.refresh();
+        self.git.invalidate();
+    }
+
+    /// Enter in the git panel: the selected file's diff.
+    pub fn git_panel_show_diff(&mut self) {
+        let Some(panel) = &mut self.state.git_panel else {
+            return;
+        };
+        let (Some(root), Some(row)) = (panel.root.as_deref(), panel.selected_row()) else {
+            return;
+        };
+        match DiffView::for_row(root, row) {
+            Ok(view) => self.state.diff_view = Some(view),
+            Err(e) => panel.message = Some(e.to_string()),
+        }
+    }
+
+    /// `d` in the git panel: asks before discarding the selected file's
+    /// changes, which can't be undone.
+    pub fn git_panel_discard(&mut self) {
+        let Some(row) = self.state.git_panel.as_ref().and_then(|p| p.selected_row()) else {
+            return;
+        };
+        let prompt = if row.area == Area::Untracked {
+            format!("Delete untracked {}?", row.path)
+        } else {
+            format!("Discard changes to {}?", row.path)
+        };
+        self.state.confirmation = Some(Confirmation {
+            prompt,
+            action: ConfirmAction::DiscardChanges {
+                path: row.path.clone(),
+                area: row.area,
+            },
+        });
+    }
+
+    /// Kicks off completion for the current input; see `update_completion`.
+    pub fn start_completion(&mut self) {
+        let state = &mut self.state;
+        state.completion_state.start_completion(
+            &state.input_buffer,
+            &state.cwd,
+            &state.history,
+            self.completion_tx.clone(),
+        );
+    }
+
+    /// Applies suggestions that finished in the background, and retries
+    /// flag completion once a `--help` fetch lands.
+    fn update_completion(&mut self) {
+        let state = &mut self.state;
+        while let Ok(result) = self.completion_rx.try_recv() {
+            if state.completion_state.receive(result, &state.input_buffer, &state.cwd) {
+                state.needs_redraw = true;
+            }
+        }
+        if state.completion_state.follow_cwd(
+            &state.input_buffer,
+            &state.cwd,
+            &state.history,
+            &self.completion_tx,
+        ) {
+            state.needs_redraw = true;
+        }
+        state.completion_state.poll_pending(
+            &state.input_buffer,
+            &state.cwd,
+            &state.history,
+            &self.completion_tx,
+        );
+    }
+
+    /// Refreshes the status bar clock, redrawing only when its text changes.
+    fn update_clock(&mut self) {
+        // A ticking clock redraws every second, too much over a slow link.
+        if !self.state.ui.show_clock || self.state.low_bandwidth {
+            self.state.clock = None;
+            return;
+        }
+        let now = self.state.ui.time_format.now();
+        if self.state.clock.as_deref() != Some(now.as_str()) {
+            self.state.clock = Some(now);
+            self.state.needs_redraw = true;
+        }
+    }
+
+    /// Names the terminal window after the workspace when it changes.
+    fn update_terminal_title(&mut self) {
+        if !self.state.ui.terminal_title {
+            return;
+        }
+        let title = format!("{} — halo", self.state.workspace_name());
+        if self.terminal_title.as_deref() != Some(title.as_str()) {
+            let _ = crossterm::execute!(std::io::stdout(), crossterm::terminal::SetTitle(&title));
+            self.terminal_title = Some(title);
+        }
+    }
+
+    /// `title` shows the workspace name, `title <name>` renames it and
+    /// `title -r` goes back to the automatic name.
+    fn handle_title(&mut self, args: &[String]) {
+        match args {
+            [] => {
+                let name = self.state.workspace_name();
+                self.state.append_to_last_log(name);
+            }
+            [flag] if flag == "-r" => self.state.title_override = None,
+            words => self.state.title_override = Some(words.join(" ")),
+        }
+    }
+
+    /// `build <tool>`: opens the command builder form; without a tool, lists them.
+    fn handle_build(&mut self, args: &[String]) {
+        match args.first().and_then(|name| builder::tool(name)) {
+            Some(tool) => {
+                let form = Builder::new(tool);
+                self.state.set_input(form.command());
+                self.state.builder = Some(form);
+            }
+            None => {
+                for tool in builder::TOOLS {
+                    let line = format!("  {:<6} {}", tool.name, tool.about);
+                    self.state.append_to_last_log(line);
+                }
+            }
+        }
+    }
+
+    /// `tmp`: changes into the session's scratch directory, creating it;
+    /// `tmp path` prints it and `tmp pin` / `tmp unpin` choose whether it
+    /// outlives the session.
+    fn handle_tmp(&mut self, args: &[String]) {
+        let valid = match args {
+            [] => true,
+            [action] => matches!(action.as_str(), "path" | "pin" | "unpin"),
+            _ => false,
+        };
+        if !valid {
+            self.state.append_to_last_log("usage: tmp [path | pin | unpin]".into());
+            self.state.last_exit_code = 2;
+            return;
+        }
+        let dir = match scratch::ensure() {
+            Ok(dir) => dir,
+            Err(e) => {
+                let message = format!("tmp: {}: {e}", scratch::path().display());
+                self.state.append_to_last_log(message);
+                self.state.last_exit_code = 1;
+                return;
+            }
+        };
+        let shown = dir.display().to_string();
+        match args.first().map(String::as_str) {
+            None => {
+                self.handle_cd(&[shown]);
+            }
+            Some("path") => self.state.append_to_last_log(shown),
+            Some(action) => {
+                let pinned = action == "pin";
+                scratch::set_pinned(pinned);
+                let message = if pinned {
+                    format!("[{shown} will be kept after exit]")
+                } else {
+                    format!("[{shown} will be removed on exit]")
+                };
+                self.state.append_to_last_log(message);
+            }
+        }
+    }
+
+    /// `log export [--format json|md|txt] <path>`: writes the log, minus
+    /// this command's own entry, to `path`.
+    fn handle_log(&mut self, args: &[String]) {
+        const USAGE: &str = "usage: log export [--format json|md|txt] <path>";
+        let mut format = None;
+        let mut path = None;
+        let mut rest = args.iter();
+        let mut valid = rest.next().map(String::as_str) == Some("export");
+        while let Some(arg) = rest.next() {
+            let name = match arg.strip_prefix("--format") {
+                Some("") => rest.next().map(String::as_str),
+                Some(value) => value.strip_prefix('='),
+                None if path.is_none() && !arg.starts_with('-') => {
+                    path = Some(arg);
+                    continue;
+                }
+                None => None,
+            };
+            match name.and_then(export::Format::parse) {
+                Some(parsed) => format = Some(parsed),
+                None => valid = false,
+            }
+        }
+        let Some(path) = path.filter(|_| valid) else {
+            self.state.append_to_last_log(USAGE.into());
+            self.state.last_exit_code = 2;
+            return;
+        };
+
+        let path = expand_cd_target(path, &self.state.cwd);
+        let format = format.unwrap_or_else(|| export::Format::for_path(&path));
+        let logs = &self.state.command_log;
+        let logs = &logs[..logs.len().saturating_sub(1)];
+        let count = logs.iter().filter(|log| export::is_exported(log)).count();
+        let text = export::render(logs, format, self.state.ui.timestamp_format());
+        match std::fs::write(&path, text) {
+            Ok(()) => {
+                let message = format!("[exported {count} entries to {}]", path.display());
+                self.state.append_to_last_log(message);
+            }
+            Err(e) => {
+                self.state.append_to_last_log(format!("log: {}: {e}", path.display()));
+                self.state.last_exit_code = 1;
+            }
+        }
     }
 
+    /// Runs the session until it quits, then stops its jobs, saves what
+    /// is left to save and runs the `on_exit` hooks. Needs a Tokio runtime.
     pub async fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> AppResult<()> {
+        self.state.persister.start();
+        // Reap children however the loop ends, errors included.
+        let result = self.event_loop(terminal).await;
+        self.command_manager.shutdown(SHUTDOWN_TIMEOUT).await;
+        if self.state.restore.entries > 0 {
+            // The log is only saved here; cd and theme changes save the rest.
+            let _ = self.state.save_session();
+        }
+        self.state.persister.flush().await;
+        hooks::run_exit_hooks(&self.state.hooks, &self.state.cwd).await;
+        output::remove_spills();
+        scratch::remove();
+        result
+    }
+
+    async fn event_loop<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> AppResult<()> {
         let event_handler = EventHandler;
+        let mut first_frame = true;
+        let mut last_draw: Option<Instant> = None;
 
         while !self.state.should_quit {
+            let tick_start = Instant::now();
+            let backlog = self.command_update_rx.len();
             self.process_command_updates();
             self.update_git_info();
+            self.update_terminal_title();
+            self.update_clock();
+            self.update_completion();
+            self.update_shares();
+            self.update_mode_indicators();
+            self.update_flash();
+            self.update_lock();
+            self.update_procs();
+            self.update_git_panel();
+            for err in self.state.persister.take_errors() {
+                self.state.warn(format!("not saved: {err}"));
+            }
+            if let Some(perf) = &mut self.state.perf {
+                perf.record_updates(tick_start.elapsed(), backlog);
+                if perf.due() {
+                    self.state.needs_redraw = true;
+                }
+            }
 
-            if self.state.needs_redraw {
+            // Over slow links, coalesce output and keystrokes into one frame
+            // per LOW_BANDWIDTH_FRAME instead of drawing after every event.
+            let throttled = self.state.low_bandwidth
+                && last_draw.is_some_and(|t| t.elapsed() < LOW_BANDWIDTH_FRAME);
+            self.state.anchor_scroll();
+            if self.state.needs_redraw && !throttled {
+                let draw_start = Instant::now();
+                last_draw = Some(draw_start);
                 terminal.draw(|frame| {
                     ui::draw(frame, &mut self.state);
                 })?;
                 self.state.needs_redraw = false;
+                if let Some(perf) = &mut self.state.perf {
+                    perf.record_frame(draw_start.elapsed());
+                }
+                if first_frame {
+                    first_frame = false;
+                    let startup = &mut self.state.startup;
+                    startup.record("first draw", draw_start.elapsed());
+                    startup.record("time to first frame", startup.elapsed());
+                }
             }
 
-            if crossterm::event::poll(Duration::from_millis(100))? {
+            let timeout = match last_draw {
+                Some(t) if throttled && self.state.needs_redraw => {
+                    LOW_BANDWIDTH_FRAME.saturating_sub(t.elapsed())
+                }
+                _ => self.state.tick,
+            };
+            if crossterm::event::poll(timeout)? {
                 let event = crossterm::event::read()?;
+                let event_start = Instant::now();
                 event_handler.handle_event(event, self).await?;
+                if let Some(perf) = &mut self.state.perf {
+                    perf.record_event(event_start.elapsed());
+                }
+                // Handle a pasted or fast-typed burst before drawing once.
+                while self.state.low_bandwidth
+                    && self.pending_interactive.is_none()
+                    && !self.edit_requested
+                    && crossterm::event::poll(Duration::ZERO)?
+                {
+                    let event = crossterm::event::read()?;
+                    event_handler.handle_event(event, self).await?;
+                }
+            }
+
+            if let Some(request) = self.pending_interactive.take() {
+                self.run_interactive(terminal, request)?;
+            }
+            if std::mem::take(&mut self.edit_requested) {
+                self.edit_input(terminal)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Ctrl+X Ctrl+E: opens the input line in `$VISUAL` / `$EDITOR` once
+    /// the current event is handled.
+    pub fn request_edit(&mut self) {
+        self.edit_requested = true;
+    }
+
+    /// Opens the input line in $VISUAL / $EDITOR (default `vi`) and loads
+    /// the saved text back, line breaks and all; a multi-line input runs
+    /// with `sh -c`. A failing editor (e.g. vim's `:cq`) leaves the input
+    /// alone.
+    fn edit_input<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> AppResult<()> {
+        let path = create_edit_file(&self.state.input_buffer)?;
+        let editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .unwrap_or_default();
+        let mut words = editor.split_whitespace().map(str::to_string);
+        let cmd = words.next().unwrap_or_else(|| "vi".to_string());
+        let mut args: Vec<String> = words.collect();
+        args.push(path.to_string_lossy().into_owned());
+
+        handoff::suspend_tui()?;
+        let result = handoff::run_interactive(&cmd, &args, &self.state.cwd);
+        handoff::resume_tui()?;
+        terminal.clear()?;
+        self.state.needs_redraw = true;
+
+        match result {
+            Ok(outcome) if outcome.exit.code == Some(0) => match std::fs::read_to_string(&path) {
+                // Kept line for line: joining would break `do`, heredocs and
+                // comments. `run_line` hands a multi-line buffer to sh as is.
+                Ok(text) => self.state.set_input(text.trim_matches('\n').to_string()),
+                Err(e) => self.state.warn(format!("could not read edited line: {e}")),
+            },
+            Ok(_) => {}
+            Err(e) => self.state.warn(format!("{cmd}: {e}")),
+        }
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+
+    /// Hands the real terminal to an interactive program and restores the TUI
+    /// once it exits.
+    fn run_interactive<B: Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        request: InteractiveRequest,
+    ) -> AppResult<()> {
+        handoff::suspend_tui()?;
+        let result = handoff::run_interactive(&request.cmd, &request.args, &request.cwd);
+        handoff::resume_tui()?;
+        terminal.clear()?;
+        self.git.invalidate();
+        match result {
+            Ok(handoff::Outcome { exit, stopped }) => {
+                if stopped {
+                    self.state.append_to_last_log(format!(
+                        "[{} was stopped and continued: programs given the terminal \
+                         cannot be suspended]",
+                        request.cmd
+                    ));
+                }
+                self.state.last_exit_code = exit.status();
+                self.state.finish_last_log_with_result(exit);
+                if let Some(log) = self.state.command_log.last() {
+                    let (command, duration) = (log.command.clone(), log.duration_ms);
+                    self.notify_finished(log.id, command, exit, duration);
+                }
+            }
+            Err(e) => {
+                self.state.last_exit_code = 127;
+                self.state.append_to_last_log(format!("{}: {e}", request.cmd));
+                self.state.finish_last_log();
             }
         }
+        self.state.needs_redraw = true;
         Ok(())
     }
 
+    /// Enter: logs the input line, adds it to history and runs it.
     pub fn submit_command(&mut self) {
         let input = self.state.input_buffer.trim().to_string();
         self.state.exit_preview_mode();
@@ -79,44 +748,246 @@ impl App {
         }
 
         self.state.add_log_entry(input.clone(), current_cwd);
-        if self.state.history.last() != Some(&input) {
-            self.state.history.push(input.clone());
-            if let Err(e) = self.state.save_history() {
-                self.state
-                    .append_to_last_log(format!("[history save error] {e}"));
-            }
+        if let Err(e) = self.state.record_history(&input) {
+            self.state
+                .append_to_last_log(format!("[history save error] {e}"));
         }
 
         self.state.input_buffer.clear();
         self.state.cursor_position = 0;
 
-        let parts = match shlex::split(&input) {
-            Some(parts) if !parts.is_empty() => parts,
-            _ => {
-                self.state
-                    .append_to_last_log("Error: Mismatched quotes.".into());
-                self.state.finish_last_log();
+        // A trailing `&` (but not `&&`) runs the command as a background job.
+        let (line, background) = match input.strip_suffix('&') {
+            Some(rest) if !rest.ends_with('&') => (rest.trim_end().to_string(), true),
+            _ => (input.clone(), false),
+        };
+        let line = expand_special_vars(&line, self.state.last_exit_code);
+        // Builtins succeed unless they say otherwise; external commands
+        // overwrite this when they finish.
+        self.state.last_exit_code = 0;
+        self.run_line(line, background, false);
+    }
+
+    /// Enter in the Ctrl+G popup: runs `cd` to the selected directory as if
+    /// typed, so it is logged and in history, then puts back the line that
+    /// was being edited. A directory that is gone is dropped instead.
+    pub fn accept_dir_picker(&mut self) {
+        let Some(dir) = self.state.dir_picker.take().and_then(|p| p.selected_dir()) else {
+            return;
+        };
+        if !dir.is_dir() {
+            self.state.forget_dir(&dir);
+            self.state
+                .show_notice(format!("{} no longer exists", state::tilde_path(&dir)));
+            return;
+        }
+        let editing = std::mem::take(&mut self.state.input_buffer);
+        let cursor = self.state.cursor_position;
+        self.state
+            .set_input(format!("cd {}", parser::quote(&state::tilde_path(&dir))));
+        self.submit_command();
+        self.state.set_input(editing);
+        self.state.cursor_position = cursor;
+    }
+
+    /// Alt+Enter: what `submit_command` would run for the input, expansion
+    /// by expansion, without running or recording anything.
+    pub fn show_dry_run(&mut self) {
+        let mut steps = Vec::new();
+        let mut input = self.state.input_buffer.clone();
+        if let Some((span, expansion)) = self.state.abbreviation_at(self.state.cursor_position) {
+            steps.push(Step::new("abbr", format!("{} → {expansion}", &input[span.clone()])));
+            input.replace_range(span, &expansion);
+        }
+        let input = input.trim();
+        if input.is_empty() {
+            return;
+        }
+        let (line, background) = match input.strip_suffix('&') {
+            Some(rest) if !rest.ends_with('&') => (rest.trim_end(), true),
+            _ => (input, false),
+        };
+        let expanded = expand_special_vars(line, self.state.last_exit_code);
+        if expanded != line {
+            steps.push(Step::new("expands", expanded.clone()));
+        }
+        self.dry_run_command(&expanded, &mut steps);
+        if background {
+            steps.push(Step::new("job", "runs in the background (trailing &)"));
+        }
+        self.state.dry_run = Some(steps);
+    }
+
+    /// The parse, builtin, alias and program steps of `show_dry_run`, in
+    /// the order `run_line` takes them.
+    fn dry_run_command(&self, line: &str, steps: &mut Vec<Step>) {
+        if line.contains('\n') {
+            return steps.push(Step::new("sh -c", line));
+        }
+        let parsed = match parser::parse(line) {
+            Ok(list) => list,
+            Err(e) => return steps.push(Step::new("error", e.to_string())),
+        };
+        if parsed.is_empty() {
+            return steps.push(Step::new("runs", "nothing"));
+        }
+        let Some(simple) = parsed.as_simple() else {
+            steps.push(Step::new("sh -c", line));
+            return steps.extend(dryrun::shell_expansions(line, &self.state.cwd));
+        };
+        let mut argv = simple.argv();
+        let force_handoff = argv[0] == "handoff" && argv.len() > 1;
+        if force_handoff {
+            argv.remove(0);
+        }
+        let quoted = |argv: &[String]| argv.iter().map(|w| parser::quote(w)).collect::<Vec<_>>();
+        if BUILTINS.contains(&argv[0].as_str()) && self.takes_builtin(&argv[0], &argv[1..]) {
+            return steps.push(Step::new("builtin", quoted(&argv).join(" ")));
+        }
+        if let Some(value) = self.state.aliases.get(&argv[0]) {
+            let combined = alias_line(value, &argv[1..]);
+            steps.push(Step::new("alias", format!("{} → {combined}", argv[0])));
+            let names: Vec<_> = aliases::placeholders(value)
+                .into_iter()
+                .map(|p| p.name)
+                .collect();
+            if !names.is_empty() {
+                return steps.push(Step::new("asks for", names.join(", ")));
+            }
+            if let Ok(list) = parser::parse(&combined) {
+                let Some(simple) = list.as_simple() else {
+                    steps.push(Step::new("sh -c", combined.clone()));
+                    return steps.extend(dryrun::shell_expansions(&combined, &self.state.cwd));
+                };
+                argv = simple.argv();
+            }
+        }
+        steps.push(Step::new("runs", quoted(&argv).join(" ")));
+        steps.push(match dryrun::program_path(&argv[0], &self.state.cwd) {
+            Some(path) => Step::new("program", path.display().to_string()),
+            None => Step::new("program", format!("{} not found", argv[0])),
+        });
+        let literal = dryrun::literal_words(&argv);
+        if !literal.is_empty() {
+            steps.push(Step::new(
+                "as typed",
+                format!("{} (no shell, so no globs or $VARS)", literal.join(" ")),
+            ));
+        }
+        if force_handoff || self.state.is_interactive_command(&argv[0]) {
+            steps.push(Step::new("terminal", "takes over the screen until it exits"));
+        }
+    }
+
+    /// Builtins named like common programs, such as split(1), diff(1) or
+    /// macOS's log(1), only take lines in their own syntax. Anything else, or a
+    /// name the user has aliased, goes on to the alias or the program.
+    fn takes_builtin(&self, cmd: &str, args: &[String]) -> bool {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let own = match cmd {
+            "split" => matches!(args.as_slice(), [] | ["-h" | "-v"]),
+            "title" => !args.first().is_some_and(|a| a.starts_with('-')) || args == ["-r"],
+            "lock" => args.is_empty(),
+            "log" => args.first() == Some(&"export"),
+            "build" => match args.as_slice() {
+                [] => true,
+                [name] => builder::tool(name).is_some(),
+                _ => false,
+            },
+            "session" => matches!(args.first(), None | Some(&("list" | "save" | "load" | "rm"))),
+            "diff" => matches!(args.as_slice(), [path] if DiffView::applies(&self.state.cwd, path)),
+            _ => return true,
+        };
+        own && !self.state.aliases.contains_key(cmd)
+    }
+
+    /// Parses and runs one expanded command line in the current log entry.
+    /// `confirmed` skips the noclobber prompt once the user has said yes.
+    fn run_line(&mut self, line: String, background: bool, confirmed: bool) {
+        // Several lines (from Ctrl+X Ctrl+E) are a script, which only sh reads
+        // right; the parser would take the newlines for spaces.
+        let parsed = if line.contains('\n') {
+            None
+        } else {
+            match parser::parse(&line) {
+                Ok(list) if !list.is_empty() => Some(list),
+                Ok(_) => {
+                    self.state.finish_last_log();
+                    return;
+                }
+                Err(e) => {
+                    self.state.append_to_last_log(format!("halo: {e}"));
+                    self.state.last_exit_code = 2;
+                    self.state.finish_last_log();
+                    return;
+                }
+            }
+        };
+        if let Some(parsed) = &parsed
+            && self.state.noclobber
+            && !confirmed
+        {
+            let existing = clobbered_files(parsed, &self.state.cwd);
+            if !existing.is_empty() {
+                let names = existing.join(", ");
+                self.state.append_to_last_log(format!(
+                    "halo: {names} already exists (noclobber); use >| to force"
+                ));
+                self.state.confirmation = Some(Confirmation {
+                    prompt: format!("Overwrite {names}?"),
+                    action: ConfirmAction::RunLine { line, background },
+                });
                 return;
             }
+        }
+
+        // Pipes, chaining, redirections and assignments are left to sh for
+        // now; only plain commands go through builtins and aliases.
+        let parts = match parsed.as_ref().and_then(|list| list.as_simple()) {
+            Some(simple) => simple.argv(),
+            None => vec!["sh".to_string(), "-c".to_string(), line.clone()],
         };
 
         let mut cmd = parts[0].clone();
         let mut args: Vec<String> = parts[1..].to_vec();
 
+        // `handoff <cmd>` gives the terminal to programs not in the config list.
+        let mut force_handoff = false;
+        if cmd == "handoff" && !args.is_empty() {
+            force_handoff = true;
+            cmd = args.remove(0);
+        }
+
+        let builtin = self.takes_builtin(&cmd, &args);
         match cmd.as_str() {
             "exit" => self.state.should_quit = true,
+            ":perf" => {
+                let open = self.state.perf.is_none();
+                self.state.perf = open.then(PerfStats::default);
+                self.state.append_to_last_log(
+                    if open { "[perf overlay on]" } else { "[perf overlay off]" }.into(),
+                );
+            }
+            ":reload" if self.state.safe_mode => {
+                self.state
+                    .append_to_last_log(":reload: safe mode; restart without --safe".into());
+                self.state.last_exit_code = 1;
+            }
             ":reload" => {
                 self.state.load_config();
+                self.command_manager.max_parallel = self.state.max_parallel_jobs;
                 self.state.append_to_last_log("[config reloaded]".into());
             }
             "theme" => {
                 if args.is_empty() {
                     self.state
                         .append_to_last_log(format!("theme: {}", self.state.theme_name.clone()));
-                } else if args.get(0).map(|s| s.as_str()) == Some("set") {
+                } else if args.first().map(|s| s.as_str()) == Some("set") {
                     if let Some(name) = args.get(1) {
                         if self.state.load_theme_from_file(name) {
-                            let _ = self.state.save_session();
+                            if let Err(e) = self.state.save_session() {
+                                self.state.warn(format!("session not saved: {e}"));
+                            }
                             self.state.append_to_last_log(format!("[theme set to {}]", name));
                         } else {
                             self.state.append_to_last_log(format!("[error: theme '{}' not found]", name));
@@ -126,14 +997,14 @@ impl App {
                         self.state.enter_theme_selection_mode();
                         self.state.append_to_last_log("Theme selection mode - use ↑/↓ to navigate, Enter to select, Esc to cancel".into());
                     }
-                } else if args.get(0).map(|s| s.as_str()) == Some("list") {
+                } else if args.first().map(|s| s.as_str()) == Some("list") {
                     let themes = self.state.get_available_themes();
                     self.state.append_to_last_log("Available themes:".into());
                     for theme in themes {
                         self.state.append_to_last_log(format!("  {}", theme));
                     }
-                } else if args.get(0).map(|s| s.as_str()) == Some("refresh") {
-                    if let Err(e) = crate::themes::refresh_themes() {
+                } else if args.first().map(|s| s.as_str()) == Some("refresh") {
+                    if let Err(e) = halo_core::themes::refresh_themes() {
                         self.state.append_to_last_log(format!("[error: failed to refresh themes: {}]", e));
                     } else {
                         self.state.append_to_last_log("[themes refreshed successfully]".into());
@@ -142,62 +1013,181 @@ impl App {
                     self.state.append_to_last_log("usage: theme [set <name> | list | refresh]".into());
                 }
             }
-            "alias" => {
-                if args.is_empty() {
-                    if self.state.aliases.is_empty() {
-                        self.state.append_to_last_log("(no aliases)".into());
-                    } else {
-                        let mut pairs: Vec<(String, String)> = self
-                            .state
-                            .aliases
-                            .iter()
-                            .map(|(k, v)| (k.clone(), v.clone()))
-                            .collect();
-                        pairs.sort_by(|a, b| a.0.cmp(&b.0));
-                        for (k, v) in pairs {
-                            self.state.append_to_last_log(format!("alias {k}='{v}'"));
-                        }
+            "alias" if args.first().is_some_and(|a| a.contains('=')) => {
+                self.define_aliases(&args)
+            }
+            "alias" | "abbr" => self.handle_definitions(cmd.as_str(), &args),
+            "unalias" => self.handle_unalias(&args),
+            "handoff" => self
+                .state
+                .append_to_last_log("usage: handoff <command> [args...]".into()),
+            "present" => {
+                self.state.toggle_presentation_mode();
+                self.state
+                    .append_to_last_log("[presentation mode on – press F2 to leave]".into());
+            }
+            "history" => self.handle_history(&args),
+            "note" => self.handle_note(&args),
+            "share" => self.handle_share(&args),
+            "set" => self.handle_set(&args),
+            "notes" => self.handle_notes(args.first().map(|s| s.as_str())),
+            "jobs" => self.handle_jobs(),
+            "disk" => self.handle_disk(&args),
+            "clear" => self.state.clear_log(),
+            "copy" => {
+                let target = match args.as_slice() {
+                    [] => Some(CopyTarget::Output),
+                    [word] => CopyTarget::parse(word),
+                    _ => None,
+                };
+                match target {
+                    Some(target) => self.copy_to_clipboard(target, true),
+                    None => {
+                        self.state
+                            .append_to_last_log("usage: copy [command | output | block]".into());
+                        self.state.last_exit_code = 2;
                     }
-                } else {
-                    self.state
-                        .append_to_last_log("usage: alias  # lists aliases".into());
                 }
             }
-            "cd" => self.handle_cd(&args),
+            "procs" => match args.first().map(|s| s.as_str()) {
+                None => self.state.procs = Some(ProcMonitor::new(false)),
+                Some("-a") => self.state.procs = Some(ProcMonitor::new(true)),
+                Some(_) => {
+                    self.state.append_to_last_log("usage: procs [-a]".into());
+                    self.state.last_exit_code = 2;
+                }
+            },
+            ":git" => self.toggle_git_panel(),
+            "diff" if builtin => match DiffView::for_file(&self.state.cwd, &args[0]) {
+                Ok(view) => self.state.diff_view = Some(view),
+                Err(e) => {
+                    self.state.append_to_last_log(format!("diff: {e}"));
+                    self.state.last_exit_code = 1;
+                }
+            },
+            "title" if builtin => self.handle_title(&args),
+            "doctor" => {
+                for line in halo_core::doctor::report(&self.state.cwd) {
+                    self.state.append_to_last_log(line);
+                }
+            }
+            "build" if builtin => self.handle_build(&args),
+            "log" if builtin => self.handle_log(&args),
+            "tmp" => self.handle_tmp(&args),
+            "lock" if builtin => self.state.lock_screen(),
+            "export" => self.handle_export(&args),
+            "unset" => {
+                for name in &args {
+                    exports::remove(name);
+                }
+            }
+            "session" if builtin => self.handle_session(&args),
+            "summary" => {
+                for line in self.state.session_stats.report() {
+                    self.state.append_to_last_log(line);
+                }
+            }
+            "split" if builtin => {
+                if self.handle_split(&args) {
+                    return;
+                }
+            }
+            "pane" => {
+                if self.handle_pane(&args) {
+                    return;
+                }
+            }
+            "tab" => {
+                if self.handle_tab(&args) {
+                    return;
+                }
+            }
+            "fg" | "bg" => {
+                if self.handle_resume_job(cmd == "fg", args.first().map(|s| s.as_str())) {
+                    return;
+                }
+            }
+            "cd" => {
+                if self.handle_cd(&args) {
+                    return;
+                }
+            }
+            "pushd" => self.handle_pushd(&args),
+            "popd" => self.handle_popd(&args),
+            "dirs" => self.handle_dirs(&args),
+            "z" => self.handle_z(&args),
             "pwd" => self
                 .state
                 .append_to_last_log(self.state.cwd.display().to_string()),
             _ => {
+                // Aliases with `{name}` placeholders ask for them first.
+                if let Some(value) = self.state.aliases.get(&cmd)
+                    && let Some(prompt) = PlaceholderPrompt::new(&cmd, value, &args, background)
+                {
+                    self.state.placeholder_prompt = Some(prompt);
+                    self.continue_placeholder_prompt();
+                    return;
+                }
                 // Minimal alias expansion (from halo.toml)
                 if let Some(expanded) = self.state.aliases.get(&cmd) {
-                    let rest = if args.is_empty() {
-                        String::new()
-                    } else {
-                        args.join(" ")
-                    };
-                    let combined = if rest.is_empty() {
-                        expanded.clone()
-                    } else {
-                        format!("{expanded} {rest}")
-                    };
-                    if let Some(new_parts) = shlex::split(&combined) {
-                        if !new_parts.is_empty() {
-                            cmd = new_parts[0].clone();
-                            args = new_parts[1..].to_vec();
+                    let combined = alias_line(expanded, &args);
+                    if let Ok(list) = parser::parse(&combined) {
+                        let new_parts = match list.as_simple() {
+                            Some(simple) => simple.argv(),
+                            None => vec!["sh".to_string(), "-c".to_string(), combined],
+                        };
+                        if let Some((first, rest)) = new_parts.split_first() {
+                            cmd = first.clone();
+                            args = rest.to_vec();
                         }
                     }
                 }
 
                 // track start time for duration
                 self.state.mark_last_log_started();
-                if let Err(e) = self.command_manager.spawn_command(
+                if !background && (force_handoff || self.state.is_interactive_command(&cmd)) {
+                    self.pending_interactive = Some(InteractiveRequest {
+                        cmd,
+                        args,
+                        cwd: self.state.cwd.clone(),
+                    });
+                    return;
+                }
+                match self.command_manager.spawn_command(
                     &cmd,
                     &args,
                     &self.state.cwd,
+                    &line,
+                    background,
                     self.command_update_tx.clone(),
                 ) {
-                    self.state.append_to_last_log(format!("{cmd}: {e}"));
-                    self.state.finish_last_log();
+                    Ok(id) => {
+                        self.state.attach_job_to_last_log(id);
+                        let queued = self
+                            .command_manager
+                            .jobs
+                            .get(id)
+                            .is_some_and(|j| j.status == JobStatus::Queued);
+                        if queued {
+                            self.state.append_to_last_log(format!(
+                                "[{id}] queued – {} jobs already running",
+                                self.command_manager.max_parallel.unwrap_or_default()
+                            ));
+                        } else if background {
+                            let pid = self
+                                .command_manager
+                                .jobs
+                                .get(id)
+                                .and_then(|j| j.pid)
+                                .map_or_else(|| "?".to_string(), |p| p.to_string());
+                            self.state.append_to_last_log(format!("[{id}] {pid}"));
+                        }
+                    }
+                    Err(e) => {
+                        self.state.append_to_last_log(format!("{cmd}: {e}"));
+                        self.state.last_exit_code = 127;
+                        self.state.finish_last_log();
+                    }
                 }
                 return;
             }
@@ -205,62 +1195,1206 @@ impl App {
         self.state.finish_last_log();
     }
 
-    fn handle_cd(&mut self, args: &[String]) {
+    /// Returns true if it asks whether a misspelled directory was meant
+    /// instead; the entry stays open until that is answered.
+    fn handle_cd(&mut self, args: &[String]) -> bool {
         let target = args.first().map_or("~", |s| s.as_str());
-        let new_dir = expand_cd_target(target, &self.state.cwd);
+        // `cd -` goes back to where the last `cd` came from.
+        if target == "-" {
+            let Some(dir) = self.state.dir_stack.top().map(Path::to_path_buf) else {
+                self.state.append_to_last_log("cd: no previous directory".into());
+                self.state.last_exit_code = 1;
+                return false;
+            };
+            if self.change_dir("cd", &dir, true) {
+                self.state.append_to_last_log(state::tilde_path(&self.state.cwd));
+            }
+            return false;
+        }
+        let mut new_dir = expand_cd_target(target, &self.state.cwd);
+        // `cd tmp` means the scratch directory unless there is a ./tmp.
+        if target == "tmp" && !new_dir.is_dir() {
+            match scratch::ensure() {
+                Ok(dir) => new_dir = dir,
+                Err(e) => {
+                    let message = format!("cd: {}: {e}", scratch::path().display());
+                    self.state.append_to_last_log(message);
+                    self.state.last_exit_code = 1;
+                    return false;
+                }
+            }
+        }
+        if !new_dir.exists()
+            && let visited = self.state.frecency.ranked()
+            && let Some(fix) = cdfix::correct(target, &self.state.cwd, &visited)
+        {
+            self.state.append_to_last_log(format!(
+                "cd: {target}: no such directory; did you mean `{fix}`? [y/n]"
+            ));
+            self.state.confirmation = Some(Confirmation {
+                prompt: format!("cd to {fix}?"),
+                action: ConfirmAction::RunLine {
+                    line: format!("cd {}", parser::quote(&fix)),
+                    background: false,
+                },
+            });
+            return true;
+        }
+
+        self.change_dir("cd", &new_dir, true);
+        false
+    }
 
-        if let Err(e) = std::env::set_current_dir(&new_dir) {
-            self.state.append_to_last_log(format!("cd: {e}"));
-        } else if let Ok(cwd) = std::env::current_dir() {
-            self.state.cwd = cwd;
+    /// Moves to `dir`, pushing the directory left on the stack if `push`.
+    /// False, with `builtin`'s error in the log, if it can't.
+    fn change_dir(&mut self, builtin: &str, dir: &Path, push: bool) -> bool {
+        if let Err(e) = std::env::set_current_dir(dir) {
+            self.state.append_to_last_log(format!("{builtin}: {e}"));
+            self.state.last_exit_code = 1;
+            return false;
+        }
+        if let Ok(cwd) = std::env::current_dir() {
+            self.state.session_stats.visit(&cwd);
+            self.state.visit_dir(&cwd);
+            let old = std::mem::replace(&mut self.state.cwd, cwd);
             let _ = self.state.save_session();
+            if old != self.state.cwd {
+                if push {
+                    self.state.dir_stack.push(&old);
+                }
+                for err in hooks::run_chdir_hooks(&self.state.hooks, &old, &self.state.cwd) {
+                    self.state.append_to_last_log(err);
+                }
+            }
         }
+        true
     }
 
-    pub fn kill_command(&mut self) -> AppResult<()> {
-        self.command_manager.kill_running_command()?;
+    /// `pushd DIR` changes to DIR as `cd` does (which pushes the directory
+    /// it leaves); `pushd` swaps with the top of the stack and `pushd +N`
+    /// brings entry N to the top. Each prints the stack, as bash does.
+    fn handle_pushd(&mut self, args: &[String]) {
+        let (dir, from_stack) = match args {
+            [] => match self.state.dir_stack.pop() {
+                Some(dir) => (dir, true),
+                None => {
+                    self.state
+                        .append_to_last_log("pushd: no other directory".into());
+                    self.state.last_exit_code = 1;
+                    return;
+                }
+            },
+            [arg] if let Some(n) = arg.strip_prefix('+').and_then(|n| n.parse().ok()) => {
+                match self.state.dir_stack.take(n) {
+                    Some(dir) => (dir, true),
+                    None => {
+                        let message = format!("pushd: {arg}: directory stack index out of range");
+                        self.state.append_to_last_log(message);
+                        self.state.last_exit_code = 1;
+                        return;
+                    }
+                }
+            }
+            [arg] => (expand_cd_target(arg, &self.state.cwd), false),
+            _ => {
+                self.state.append_to_last_log("usage: pushd [dir | +N]".into());
+                self.state.last_exit_code = 2;
+                return;
+            }
+        };
+        if self.change_dir("pushd", &dir, true) {
+            self.print_dirs(false);
+        } else if from_stack {
+            self.state.dir_stack.push(&dir);
+        }
+    }
+
+    /// `popd` returns to the directory on top of the stack; `popd +N` drops
+    /// entry N without moving.
+    fn handle_popd(&mut self, args: &[String]) {
+        match args {
+            [] => match self.state.dir_stack.pop() {
+                Some(dir) => {
+                    if self.change_dir("popd", &dir, false) {
+                        self.print_dirs(false);
+                    }
+                }
+                None => {
+                    self.state
+                        .append_to_last_log("popd: directory stack empty".into());
+                    self.state.last_exit_code = 1;
+                }
+            },
+            [arg] if let Some(n) = arg.strip_prefix('+').and_then(|n| n.parse().ok()) => {
+                if self.state.dir_stack.take(n).is_some() {
+                    self.print_dirs(false);
+                } else {
+                    let message = format!("popd: {arg}: directory stack index out of range");
+                    self.state.append_to_last_log(message);
+                    self.state.last_exit_code = 1;
+                }
+            }
+            _ => {
+                self.state.append_to_last_log("usage: popd [+N]".into());
+                self.state.last_exit_code = 2;
+            }
+        }
+    }
+
+    /// `dirs` prints the stack on one line, `dirs -v` one per line with its
+    /// index, and `dirs -c` clears it.
+    fn handle_dirs(&mut self, args: &[String]) {
+        match args {
+            [] => self.print_dirs(false),
+            [flag] if flag == "-v" => self.print_dirs(true),
+            [flag] if flag == "-c" => self.state.dir_stack.clear(),
+            _ => {
+                self.state.append_to_last_log("usage: dirs [-v | -c]".into());
+                self.state.last_exit_code = 2;
+            }
+        }
+    }
+
+    /// The cwd (`+0`) and the stack from the top down.
+    fn print_dirs(&mut self, numbered: bool) {
+        let dirs: Vec<String> = std::iter::once(self.state.cwd.as_path())
+            .chain(self.state.dir_stack.iter())
+            .map(state::tilde_path)
+            .collect();
+        if numbered {
+            for (n, dir) in dirs.into_iter().enumerate() {
+                self.state.append_to_last_log(format!("{n:>2}  {dir}"));
+            }
+        } else {
+            self.state.append_to_last_log(dirs.join(" "));
+        }
+    }
+
+    /// `z TERM...` jumps to the best-ranked visited directory matching the
+    /// terms (see `frecency`), `z -l [TERM...]` lists the matches, and a
+    /// lone term that is a directory is simply changed to, as with `cd`.
+    fn handle_z(&mut self, args: &[String]) {
+        if let Some(flag) = args.first().filter(|a| a.starts_with('-') && a.as_str() != "-") {
+            if flag != "-l" {
+                self.state.append_to_last_log("usage: z [-l] [term...]".into());
+                self.state.last_exit_code = 2;
+                return;
+            }
+            for (dir, score) in self.state.frecency.matches(&args[1..]).into_iter().take(20) {
+                let line = format!("{score:>8.1}  {}", state::tilde_path(&dir));
+                self.state.append_to_last_log(line);
+            }
+            return;
+        }
+        if args.len() <= 1 {
+            let target = args.first().map_or("~", |s| s.as_str());
+            let dir = expand_cd_target(target, &self.state.cwd);
+            if target == "-" || dir.is_dir() {
+                self.handle_cd(args);
+                return;
+            }
+        }
+        let mut matches = self.state.frecency.matches(args).into_iter();
+        let found = loop {
+            match matches.next() {
+                Some((dir, _)) if dir == self.state.cwd => continue,
+                Some((dir, _)) if !dir.is_dir() => self.state.forget_dir(&dir),
+                Some((dir, _)) => break Some(dir),
+                None => break None,
+            }
+        };
+        match found {
+            Some(dir) => {
+                if self.change_dir("z", &dir, true) {
+                    self.state.append_to_last_log(state::tilde_path(&self.state.cwd));
+                }
+            }
+            None => {
+                let message = format!("z: no visited directory matches {}", args.join(" "));
+                self.state.append_to_last_log(message);
+                self.state.last_exit_code = 1;
+            }
+        }
+    }
+
+    /// `history [N | clear | <substring>]`
+    fn handle_history(&mut self, args: &[String]) {
+        match args.first().map(|s| s.as_str()) {
+            Some("clear") => {
+                self.state.history.clear();
+                self.state.history_stats = Default::default();
+                self.state.history_index = None;
+                if let Err(e) = self.state.save_history() {
+                    self.state
+                        .append_to_last_log(format!("[history save error] {e}"));
+                    self.state.last_exit_code = 1;
+                } else {
+                    self.state.append_to_last_log("[history cleared]".into());
+                }
+                return;
+            }
+            Some(_) if args.len() > 1 => {
+                self.state
+                    .append_to_last_log("usage: history [N | clear | <text>]".into());
+                self.state.last_exit_code = 2;
+                return;
+            }
+            _ => {}
+        }
+
+        let total = self.state.history.len();
+        let width = total.to_string().len();
+        let (skip, filter) = match args.first() {
+            Some(arg) => match arg.parse::<usize>() {
+                Ok(n) => (total.saturating_sub(n), None),
+                Err(_) => (0, Some(arg.as_str())),
+            },
+            None => (0, None),
+        };
+        let lines: Vec<String> = self
+            .state
+            .history
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .filter(|(_, entry)| filter.is_none_or(|f| entry.contains(f)))
+            .map(|(i, entry)| format!("{:>width$}  {entry}", i + 1))
+            .collect();
+        if lines.is_empty() {
+            self.state.append_to_last_log("(no matching history)".into());
+        }
+        for line in lines {
+            self.state.append_to_last_log(line);
+        }
+    }
+
+    /// Answers the pending confirmation popup.
+    pub fn resolve_confirmation(&mut self, accepted: bool) {
+        let Some(confirmation) = self.state.confirmation.take() else {
+            return;
+        };
+        self.state.needs_redraw = true;
+        match confirmation.action {
+            ConfirmAction::RunLine { line, background } if accepted => {
+                self.run_line(line, background, true);
+            }
+            ConfirmAction::RunLine { .. } => {
+                self.state.append_to_last_log("[cancelled]".into());
+                self.state.last_exit_code = 1;
+                self.state.finish_last_log();
+            }
+            ConfirmAction::DiscardChanges { path, area } if accepted => {
+                if let Some(panel) = &mut self.state.git_panel {
+                    panel.message = Some(match panel.discard(&path, area) {
+                        Ok(done) => done,
+                        Err(e) => e.to_string(),
+                    });
+                    panel.refresh();
+                    self.git.invalidate();
+                }
+            }
+            ConfirmAction::DiscardChanges { .. } => {}
+            ConfirmAction::OpenLink { url } if accepted => self.launch_link(&url),
+            ConfirmAction::OpenLink { .. } => {}
+        }
+    }
+
+    /// Opens a link from the output, or over SSH, where the opener would
+    /// run on the wrong machine, puts it on the clipboard instead. The
+    /// target is always what is shown, since an OSC 8 link's text can
+    /// say anything.
+    pub fn open_link(&mut self, url: &str) {
+        if state::over_ssh() {
+            let message = match clipboard::copy(url, true) {
+                Ok(_) => format!("copied link: {url}"),
+                Err(e) => format!("link not copied: {e}"),
+            };
+            return self.state.show_notice(message);
+        }
+        match links::safety(url) {
+            links::Safety::Open => self.launch_link(url),
+            links::Safety::Confirm => {
+                self.state.confirmation = Some(Confirmation {
+                    prompt: format!("Open {url}?"),
+                    action: ConfirmAction::OpenLink {
+                        url: url.to_string(),
+                    },
+                });
+            }
+            links::Safety::Refuse => self
+                .state
+                .show_notice(format!("not opening {url}: only http(s) and file links open")),
+        }
+    }
+
+    fn launch_link(&mut self, url: &str) {
+        let message = match links::open(url) {
+            Ok(()) => format!("opening {url}"),
+            Err(e) => format!("link not opened: {e}"),
+        };
+        self.state.show_notice(message);
+    }
+
+    /// Alt+L: opens the last link of the previewed or newest entry.
+    pub fn open_last_link(&mut self) {
+        match self.state.copy_source(false).and_then(links::last_link) {
+            Some(url) => self.open_link(&url),
+            None => self.state.show_notice("no links in this entry"),
+        }
+    }
+
+    /// Enter while a placeholder is asked: takes the input line as its
+    /// answer.
+    pub fn answer_placeholder(&mut self) {
+        let answer = std::mem::take(&mut self.state.input_buffer);
+        self.state.cursor_position = 0;
+        if let Some(prompt) = &mut self.state.placeholder_prompt
+            && let Some((name, _)) = prompt.field()
+        {
+            prompt.answers.insert(name.clone(), answer);
+            prompt.current += 1;
+        }
+        self.continue_placeholder_prompt();
+    }
+
+    /// Runs the alias once nothing is left to ask.
+    fn continue_placeholder_prompt(&mut self) {
+        self.state.needs_redraw = true;
+        let Some(prompt) = self.state.placeholder_prompt.take_if(|p| p.field().is_none()) else {
+            return;
+        };
+        let line = prompt.command_line();
+        self.state.append_to_last_log(format!("{} → {line}", prompt.alias));
+        self.run_line(line, prompt.background, false);
+    }
+
+    /// Esc or Ctrl+C while a placeholder is asked.
+    pub fn cancel_placeholder_prompt(&mut self) {
+        if self.state.placeholder_prompt.take().is_none() {
+            return;
+        }
+        self.state.input_buffer.clear();
+        self.state.cursor_position = 0;
+        self.state.append_to_last_log("[cancelled]".into());
+        self.state.last_exit_code = 1;
+        self.state.finish_last_log();
+        self.state.needs_redraw = true;
+    }
+
+    /// `set -C` / `set +C` / `set [-+]o noclobber`.
+    fn handle_set(&mut self, args: &[String]) {
+        let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+        match args.as_slice() {
+            [] => self.state.append_to_last_log(format!(
+                "noclobber {}",
+                if self.state.noclobber { "on" } else { "off" }
+            )),
+            ["-C"] | ["-o", "noclobber"] => self.state.noclobber = true,
+            ["+C"] | ["+o", "noclobber"] => self.state.noclobber = false,
+            _ => {
+                self.state
+                    .append_to_last_log("usage: set [-C | +C | -o noclobber | +o noclobber]".into());
+                self.state.last_exit_code = 2;
+            }
+        }
+    }
+
+    /// `export` lists exported variables; `export NAME=value...` sets them
+    /// for every command started from now on.
+    fn handle_export(&mut self, args: &[String]) {
+        if args.is_empty() {
+            for (name, value) in exports::all() {
+                let line = format!("export {name}={}", parser::quote(&value));
+                self.state.append_to_last_log(line);
+            }
+            return;
+        }
+        for arg in args {
+            match arg.split_once('=') {
+                Some((name, value)) if exports::is_valid_name(name) => exports::set(name, value),
+                _ => {
+                    self.state
+                        .append_to_last_log(format!("export: {arg}: expected NAME=value"));
+                    self.state.last_exit_code = 2;
+                }
+            }
+        }
+    }
+
+    /// `session save|load|rm <name>` and `session list`: named snapshots of
+    /// the directory, exports, aliases and log.
+    fn handle_session(&mut self, args: &[String]) {
+        let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+        match args.as_slice() {
+            [] | ["list"] => {
+                let names = sessions::list();
+                if names.is_empty() {
+                    self.state.append_to_last_log("(no saved sessions)".into());
+                }
+                for name in names {
+                    self.state.append_to_last_log(name);
+                }
+            }
+            ["save", name] => {
+                let everything = RestoreConfig {
+                    entries: usize::MAX,
+                    lines: self.state.restore.lines,
+                };
+                let snapshot = Snapshot {
+                    cwd: self.state.cwd.clone(),
+                    exports: exports::all(),
+                    aliases: self.state.aliases.clone(),
+                    log: restore::save(&self.state.command_log, everything),
+                };
+                match sessions::save(name, &snapshot) {
+                    Ok(path) => {
+                        let message = format!("[session {name} saved to {}]", path.display());
+                        self.state.append_to_last_log(message);
+                    }
+                    Err(e) => {
+                        self.state.append_to_last_log(format!("session: {name}: {e}"));
+                        self.state.last_exit_code = 1;
+                    }
+                }
+            }
+            ["load", name] => match sessions::load(name) {
+                Ok(snapshot) => {
+                    let summary = format!(
+                        "[session {name}: {} entries, {} exports, {} aliases]",
+                        snapshot.log.len(),
+                        snapshot.exports.len(),
+                        snapshot.aliases.len()
+                    );
+                    exports::replace(snapshot.exports);
+                    self.state.aliases = snapshot.aliases;
+                    self.state.replace_log(restore::restore(snapshot.log));
+                    self.handle_cd(&[snapshot.cwd.display().to_string()]);
+                    self.state.append_to_last_log(summary);
+                }
+                Err(e) => {
+                    self.state.append_to_last_log(format!("session: {name}: {e}"));
+                    self.state.last_exit_code = 1;
+                }
+            },
+            ["rm", name] => {
+                if let Err(e) = sessions::remove(name) {
+                    self.state.append_to_last_log(format!("session: {name}: {e}"));
+                    self.state.last_exit_code = 1;
+                }
+            }
+            _ => {
+                self.state.append_to_last_log(
+                    "usage: session [list | save <name> | load <name> | rm <name>]".into(),
+                );
+                self.state.last_exit_code = 2;
+            }
+        }
+    }
+
+    /// `alias` lists aliases and `alias name` shows one; `alias export|import
+    /// <file>` moves them to and from bash/zsh `alias name='value'` files.
+    /// `abbr` does the same for abbreviations, which bash and zsh can only
+    /// take as aliases.
+    fn handle_definitions(&mut self, builtin: &str, args: &[String]) {
+        let (noun, section) = match builtin {
+            "abbr" => ("abbreviations", "abbr"),
+            _ => ("aliases", "aliases"),
+        };
+        let definitions = match builtin {
+            "abbr" => &mut self.state.abbreviations,
+            _ => &mut self.state.aliases,
+        };
+        match (args.first().map(|s| s.as_str()), args.get(1)) {
+            (None, _) => {
+                let text = aliases::format_all(definitions);
+                if text.is_empty() {
+                    self.state.append_to_last_log(format!("(no {noun})"));
+                }
+                for line in text.lines() {
+                    self.state.append_to_last_log(line.to_string());
+                }
+            }
+            (Some(name), None) if name != "export" && name != "import" => {
+                match definitions.get(name) {
+                    Some(value) => {
+                        let line = aliases::format_definition(name, value);
+                        self.state.append_to_last_log(line);
+                    }
+                    None => {
+                        self.state.append_to_last_log(format!("{builtin}: {name}: not found"));
+                        self.state.last_exit_code = 1;
+                    }
+                }
+            }
+            (Some("export"), Some(file)) => {
+                let path = expand_cd_target(file, &self.state.cwd);
+                let count = definitions.len();
+                match std::fs::write(&path, aliases::format_all(definitions)) {
+                    Ok(()) => self.state.append_to_last_log(format!(
+                        "[{count} {noun} written to {}]",
+                        path.display()
+                    )),
+                    Err(e) => {
+                        self.state
+                            .append_to_last_log(format!("{builtin}: {}: {e}", path.display()));
+                        self.state.last_exit_code = 1;
+                    }
+                }
+            }
+            (Some("import"), Some(file)) => {
+                let path = expand_cd_target(file, &self.state.cwd);
+                match std::fs::read_to_string(&path) {
+                    Ok(text) => {
+                        let defs = aliases::parse_definitions(&text);
+                        let count = defs.len();
+                        definitions.extend(defs);
+                        self.state.append_to_last_log(format!(
+                            "[{count} {noun} imported from {} for this session; add them to [{section}] in halo.toml to keep them]",
+                            path.display()
+                        ));
+                    }
+                    Err(e) => {
+                        self.state
+                            .append_to_last_log(format!("{builtin}: {}: {e}", path.display()));
+                        self.state.last_exit_code = 1;
+                    }
+                }
+            }
+            _ => {
+                self.state.append_to_last_log(format!(
+                    "usage: {builtin} [export <file> | import <file>]"
+                ));
+                self.state.last_exit_code = 2;
+            }
+        }
+    }
+
+    /// `alias name=value...` defines aliases and saves them to halo.toml.
+    fn define_aliases(&mut self, args: &[String]) {
+        let mut defs = Vec::new();
+        for arg in args {
+            match arg.split_once('=') {
+                Some((name, value)) if !name.is_empty() && !name.contains(char::is_whitespace) => {
+                    defs.push((name.to_string(), value.to_string()))
+                }
+                _ => {
+                    self.state
+                        .append_to_last_log(format!("alias: `{arg}`: expected name=value"));
+                    self.state.last_exit_code = 2;
+                    return;
+                }
+            }
+        }
+        let changes: Vec<_> = defs.iter().map(|(n, v)| (n.clone(), Some(v.clone()))).collect();
+        self.state.aliases.extend(defs);
+        self.save_aliases(&changes);
+    }
+
+    /// `unalias name...` removes aliases, from halo.toml too.
+    fn handle_unalias(&mut self, args: &[String]) {
+        if args.is_empty() {
+            self.state.append_to_last_log("usage: unalias <name>...".into());
+            self.state.last_exit_code = 2;
+            return;
+        }
+        let mut removed = Vec::new();
+        for name in args {
+            if self.state.aliases.remove(name).is_some() {
+                removed.push((name.clone(), None));
+            } else {
+                self.state
+                    .append_to_last_log(format!("unalias: {name}: not found"));
+                self.state.last_exit_code = 1;
+            }
+        }
+        if !removed.is_empty() {
+            self.save_aliases(&removed);
+        }
+    }
+
+    fn save_aliases(&mut self, changes: &[(String, Option<String>)]) {
+        if let Err(e) = self.state.save_aliases(changes) {
+            self.state
+                .append_to_last_log(format!("alias: halo.toml not updated: {e}"));
+            self.state.last_exit_code = 1;
+        }
+    }
+
+    /// `disk` shows usage of the mounted filesystems as gauges.
+    fn handle_disk(&mut self, args: &[String]) {
+        if !args.is_empty() {
+            self.state.append_to_last_log("usage: disk".into());
+            self.state.last_exit_code = 2;
+            return;
+        }
+        match disk::mounts() {
+            Ok(mounts) => {
+                for line in disk::table(&mounts) {
+                    self.state.append_to_last_log(line);
+                }
+                if let Some(log) = self.state.command_log.last_mut() {
+                    log.rich = Some(RichOutput::Disk(mounts));
+                }
+            }
+            Err(e) => {
+                self.state
+                    .append_to_last_log(format!("disk: cannot read the mount table: {e}"));
+                self.state.last_exit_code = 1;
+            }
+        }
+    }
+
+    /// `note [^N] [text]` annotates the entry N back (default: the previous
+    /// one); without text the note is removed.
+    fn handle_note(&mut self, args: &[String]) {
+        let (back, text) = match args.first().and_then(|a| a.strip_prefix('^')) {
+            Some(n) => match n.parse::<usize>() {
+                Ok(n) if n > 0 => (n, &args[1..]),
+                _ => {
+                    self.state
+                        .append_to_last_log("usage: note [^N] [text]".into());
+                    self.state.last_exit_code = 2;
+                    return;
+                }
+            },
+            None => (1, args),
+        };
+        let note = text.join(" ");
+        let index = self.state.command_log.len().checked_sub(1 + back);
+        let Some(entry) = index.and_then(|i| self.state.command_log.get_mut(i)) else {
+            self.state
+                .append_to_last_log(format!("note: no entry {back} back"));
+            self.state.last_exit_code = 1;
+            return;
+        };
+        let message = if note.is_empty() {
+            entry.note = None;
+            format!("[note removed from `{}`]", entry.command)
+        } else {
+            entry.note = Some(note);
+            format!("[noted `{}`]", entry.command)
+        };
+        self.state.append_to_last_log(message);
+    }
+
+    /// `split [-h | -v]` opens an empty pane beside (the default) or below
+    /// the current one and focuses it. Returns true once focus has moved,
+    /// with this command's entry already closed.
+    fn handle_split(&mut self, args: &[String]) -> bool {
+        let split = match args {
+            [flag] if flag == "-v" => Split::Rows,
+            _ => Split::Columns,
+        };
+        if self.state.panes.len() >= MAX_PANES {
+            self.state
+                .append_to_last_log(format!("split: at most {MAX_PANES} panes"));
+            self.state.last_exit_code = 1;
+            return false;
+        }
+        self.state.finish_last_log();
+        self.state.split_pane(split)
+    }
+
+    /// `pane` lists the panes, `pane N` / `pane next` focus one and
+    /// `pane close` closes the focused one. Returns true once focus has
+    /// moved, with this command's entry already closed.
+    fn handle_pane(&mut self, args: &[String]) -> bool {
+        let count = self.state.panes.len();
+        match args {
+            [] if count == 0 => {
+                self.state
+                    .append_to_last_log("pane: the log is not split (see `split`)".into());
+            }
+            [] => {
+                for index in 0..count {
+                    let (logs, focused) = if index == self.state.focused_pane {
+                        (&self.state.command_log, "*")
+                    } else {
+                        (&self.state.panes[index].command_log, " ")
+                    };
+                    let running = logs.iter().filter(|l| l.is_running && l.job_id.is_some());
+                    let line = format!(
+                        "{focused} pane {}: {} entries, {} running",
+                        index + 1,
+                        logs.len(),
+                        running.count()
+                    );
+                    self.state.append_to_last_log(line);
+                }
+            }
+            [arg] if arg == "close" => {
+                self.state.finish_last_log();
+                if let Err(e) = self.state.close_pane() {
+                    self.state.append_to_last_log(format!("pane: {e}"));
+                    self.state.last_exit_code = 1;
+                    return false;
+                }
+                return true;
+            }
+            [arg] if arg == "next" && count > 0 => {
+                self.state.finish_last_log();
+                self.state.focus_next_pane();
+                return true;
+            }
+            [arg] if let Ok(n) = arg.parse::<usize>() && (1..=count).contains(&n) => {
+                self.state.finish_last_log();
+                self.state.focus_pane(n - 1);
+                return true;
+            }
+            _ => {
+                self.state
+                    .append_to_last_log("usage: pane [N | next | close]".into());
+                self.state.last_exit_code = 2;
+            }
+        }
+        false
+    }
+
+    /// `tab` lists the tabs; `tab new`, `tab N`, `tab next` / `tab prev` and
+    /// `tab close` open, switch and close them. Returns true once another
+    /// tab is active, with this command's entry already closed.
+    fn handle_tab(&mut self, args: &[String]) -> bool {
+        let count = self.state.tabs.len();
+        match args {
+            [] if count == 0 => {
+                self.state
+                    .append_to_last_log("tab: only this one (`tab new` opens another)".into());
+            }
+            [] => {
+                for index in 0..count {
+                    let line = if index == self.state.active_tab {
+                        let entries = self.state.command_log.len();
+                        let jobs = self.state.tab_job_count();
+                        let name = Tab::label(&self.state.cwd);
+                        format!("* tab {}: {name}, {entries} entries, {jobs} jobs", index + 1)
+                    } else {
+                        let tab = &self.state.tabs[index];
+                        let jobs = self.state.job_tabs.values().filter(|t| **t == tab.id);
+                        format!(
+                            "  tab {}: {}, {} entries, {} jobs",
+                            index + 1,
+                            Tab::label(&tab.cwd),
+                            tab.command_log.len(),
+                            jobs.count()
+                        )
+                    };
+                    self.state.append_to_last_log(line);
+                }
+            }
+            [arg] if arg == "new" => {
+                if count >= MAX_TABS {
+                    self.state
+                        .append_to_last_log(format!("tab: at most {MAX_TABS} tabs"));
+                    self.state.last_exit_code = 1;
+                    return false;
+                }
+                self.state.finish_last_log();
+                return self.new_tab();
+            }
+            [arg] if arg == "close" => {
+                self.state.finish_last_log();
+                if let Err(e) = self.close_tab() {
+                    self.state.append_to_last_log(format!("tab: {e}"));
+                    self.state.last_exit_code = 1;
+                    return false;
+                }
+                return true;
+            }
+            [arg] if (arg == "next" || arg == "prev") && count > 0 => {
+                self.state.finish_last_log();
+                self.cycle_tab(arg == "next");
+                return true;
+            }
+            [arg] if let Ok(n) = arg.parse::<usize>() && (1..=count).contains(&n) => {
+                self.state.finish_last_log();
+                self.switch_tab(n - 1);
+                return true;
+            }
+            _ => {
+                self.state
+                    .append_to_last_log("usage: tab [new | N | next | prev | close]".into());
+                self.state.last_exit_code = 2;
+            }
+        }
+        false
+    }
+
+    /// Opens a tab in the current directory and switches to it. False when
+    /// there are already `MAX_TABS`.
+    pub fn new_tab(&mut self) -> bool {
+        if !self.state.open_tab() {
+            return false;
+        }
+        self.switch_tab(self.state.active_tab + 1);
+        true
+    }
+
+    /// Makes tab `index` active, handing it the foreground job it had.
+    pub fn switch_tab(&mut self, index: usize) {
+        let foreground = self.command_manager.jobs.foreground();
+        let next = self.state.switch_tab(index, foreground);
+        self.command_manager.jobs.set_foreground(next);
+    }
+
+    /// Alt+→ / Alt+←: the next or previous tab, wrapping around.
+    pub fn cycle_tab(&mut self, forward: bool) {
+        let count = self.state.tabs.len();
+        if count > 0 {
+            let step = if forward { 1 } else { count - 1 };
+            self.switch_tab((self.state.active_tab + step) % count);
+        }
+    }
+
+    /// Closes the active tab and switches to its neighbour. Refused while
+    /// its jobs are alive, as their output would have nowhere to go.
+    pub fn close_tab(&mut self) -> Result<(), String> {
+        if self.state.tabs.is_empty() {
+            return Err("this is the only tab".to_string());
+        }
+        let jobs = self.state.tab_job_count();
+        if jobs > 0 {
+            return Err(format!("{jobs} job(s) still running or stopped in this tab"));
+        }
+        let closing = self.state.active_tab;
+        let next = if closing + 1 < self.state.tabs.len() { closing + 1 } else { closing - 1 };
+        self.switch_tab(next);
+        self.state.remove_tab(closing);
+        Ok(())
+    }
+
+    /// `share [^N]` posts the entry N back (default: the previous one) to
+    /// the `[share]` endpoint; the link is reported here when it answers.
+    fn handle_share(&mut self, args: &[String]) {
+        let back = match args {
+            [] => 1,
+            [arg] if let Some(n) = arg.strip_prefix('^').and_then(|n| n.parse().ok()) && n > 0 => n,
+            _ => {
+                self.state.append_to_last_log("usage: share [^N]".into());
+                self.state.last_exit_code = 2;
+                return;
+            }
+        };
+        let Some(url) = self.state.share.url.clone() else {
+            let message = "share: no endpoint; set [share] url in halo.toml".to_string();
+            self.state.append_to_last_log(message);
+            self.state.last_exit_code = 1;
+            return;
+        };
+        let logs = &self.state.command_log;
+        let (Some(log), Some(own)) = (
+            logs.len().checked_sub(1 + back).map(|i| &logs[i]),
+            logs.last(),
+        ) else {
+            self.state.append_to_last_log(format!("share: no entry {back} back"));
+            self.state.last_exit_code = 1;
+            return;
+        };
+        let message = format!("[sharing `{}`...]", log.command);
+        let time = self.state.ui.timestamp_format();
+        share::send(&self.state.share, &url, log, time, own.id, self.share_tx.clone());
+        self.state.append_to_last_log(message);
+    }
+
+    /// Reports finished shares in the entries that asked for them, and
+    /// copies the link.
+    fn update_shares(&mut self) {
+        while let Ok(shared) = self.share_rx.try_recv() {
+            let message = match &shared.result {
+                Ok(link) => {
+                    let copied = clipboard::copy(link, state::over_ssh()).is_ok();
+                    let suffix = if copied { " (copied)" } else { "" };
+                    format!("[shared `{}`: {link}{suffix}]", shared.command)
+                }
+                Err(e) => format!("share: {e}"),
+            };
+            let limits = self.state.output_limits;
+            if let Some(log) = self.state.entry_mut(shared.entry) {
+                log.push_line(&message, &limits);
+            } else {
+                self.state.show_notice(message);
+            }
+            self.state.needs_redraw = true;
+        }
+    }
+
+    /// Lists annotated entries, optionally filtered; with a query the newest
+    /// match is also opened in preview.
+    fn handle_notes(&mut self, query: Option<&str>) {
+        let query = query.map(|q| q.to_lowercase());
+        let last = self.state.command_log.len().saturating_sub(1);
+        let matches: Vec<(usize, String)> = self
+            .state
+            .command_log
+            .iter()
+            .enumerate()
+            .filter_map(|(i, log)| {
+                let note = log.note.as_deref()?;
+                let hit = query.as_deref().is_none_or(|q| {
+                    note.to_lowercase().contains(q) || log.command.to_lowercase().contains(q)
+                });
+                hit.then(|| (last - i, format!("✎ {note}  — {}", log.command)))
+            })
+            .collect();
+        if matches.is_empty() {
+            self.state.append_to_last_log("(no notes)".into());
+            return;
+        }
+        for (_, line) in &matches {
+            self.state.append_to_last_log(line.clone());
+        }
+        if query.is_some()
+            && let Some((back, _)) = matches.last()
+        {
+            self.state.scroll_offset = *back;
+        }
+    }
+
+    fn handle_jobs(&mut self) {
+        let jobs = &self.command_manager.jobs;
+        let mine = |id| self.state.owns_job(id);
+        if !jobs.iter().any(|job| mine(job.id)) {
+            self.state.append_to_last_log("(no jobs)".into());
+            return;
+        }
+        let current = jobs.current(mine);
+        let lines: Vec<String> = jobs
+            .iter()
+            .filter(|job| mine(job.id))
+            .map(|job| {
+                let marker = if Some(job.id) == current { '+' } else { ' ' };
+                format!(
+                    "[{}]{} {:<8} {}",
+                    job.id,
+                    marker,
+                    job.status.label(),
+                    job.command
+                )
+            })
+            .collect();
+        for line in lines {
+            self.state.append_to_last_log(line);
+        }
+    }
+
+    /// Implements `fg` / `bg`: resumes the job and moves its output stream into
+    /// the entry for this command. Returns true if the entry stays running.
+    fn handle_resume_job(&mut self, foreground: bool, spec: Option<&str>) -> bool {
+        let name = if foreground { "fg" } else { "bg" };
+        let mine = |id| self.state.owns_job(id);
+        let Some(id) = self.command_manager.jobs.resolve(spec, mine) else {
+            let msg = match spec {
+                Some(spec) => format!("{name}: {spec}: no such job"),
+                None => format!("{name}: no current job"),
+            };
+            self.state.append_to_last_log(msg);
+            self.state.last_exit_code = 1;
+            return false;
+        };
+        if let Err(e) = self.command_manager.continue_job(id, foreground) {
+            self.state.append_to_last_log(format!("{name}: {e}"));
+            self.state.last_exit_code = 1;
+            return false;
+        }
+        let command = self
+            .command_manager
+            .jobs
+            .get(id)
+            .map(|j| j.command.clone())
+            .unwrap_or_default();
+        let where_to = if foreground { "foreground" } else { "background" };
         self.state
-            .append_to_last_log("[Process killed by user]".into());
+            .detach_job_log(id, format!("[{id}] moved to {where_to}"));
+        self.state.attach_job_to_last_log(id);
+        self.state.mark_last_log_started();
+        if foreground {
+            self.state.append_to_last_log(command);
+        } else {
+            self.state.append_to_last_log(format!("[{id}]+ {command} &"));
+        }
+        true
+    }
+
+    /// Suspends the foreground job (Ctrl+Z) and closes its log entry.
+    pub fn stop_foreground_job(&mut self) {
+        if let Some(id) = self.command_manager.stop_foreground() {
+            let command = self
+                .command_manager
+                .jobs
+                .get(id)
+                .map(|j| j.command.clone())
+                .unwrap_or_default();
+            self.state
+                .detach_job_log(id, format!("[{id}]+ Stopped  {command}"));
+        }
+    }
+
+    /// Ctrl+C: kills the foreground job, if there is one.
+    pub fn kill_command(&mut self) -> AppResult<()> {
+        if let Some(id) = self.command_manager.jobs.foreground() {
+            self.command_manager.kill_running_command()?;
+            self.state
+                .append_to_job_log(id, "[Process killed by user]".into());
+        }
         Ok(())
     }
 
     fn process_command_updates(&mut self) {
         while let Ok(update) = self.command_update_rx.try_recv() {
             match update {
-                CommandUpdate::NewLine(line) => self.state.append_to_last_log(line),
-                CommandUpdate::Finished(code) => self.state.finish_last_log_with_result(code),
+                CommandUpdate::NewLine(id, line) => self.state.stream_to_job_log(id, line, false),
+                CommandUpdate::Progress(id, line) => self.state.stream_to_job_log(id, line, true),
+                CommandUpdate::Finished(id, exit) => self.finish_job(id, exit),
             }
             self.state.needs_redraw = true;
         }
     }
+
+    fn finish_job(&mut self, id: usize, exit: JobExit) {
+        // Like other shells, $? only tracks foreground commands.
+        if self.command_manager.jobs.foreground() == Some(id) {
+            self.state.last_exit_code = exit.status();
+        }
+        let job = self.command_manager.reap(id);
+        self.state.forget_job(id, exit.status());
+        self.git.invalidate();
+        self.command_manager.start_queued();
+        if let Some(entry) = self.state.job_entry_id(id) {
+            let duration = self.state.finish_job_log(id, exit);
+            let command = job.map(|job| job.command).unwrap_or_default();
+            self.notify_finished(entry, command, exit, duration);
+        } else if let Some(job) = job {
+            // Stopped or detached jobs have no entry to report into.
+            self.state.command_log.push(CommandLog::new(
+                String::new(),
+                format!("[{id}]+ Done  {}", job.command),
+                false,
+                self.state.cwd.clone(),
+            ));
+        }
+    }
 }
 
-// Helper to get the git branch, returning a clean string for the UI.
-fn get_git_branch(path: &Path) -> Option<String> {
-    let repo = git2::Repository::discover(path).ok()?;
-    let head = repo.head().ok()?;
-    let shorthand = head.shorthand()?;
+/// Plain `>` / `&>` targets that already exist as files; `>>` and `>|` are fine.
+fn clobbered_files(list: &parser::CommandList, cwd: &Path) -> Vec<String> {
+    list.items
+        .iter()
+        .flat_map(|(pipeline, _)| &pipeline.commands)
+        .flat_map(|cmd| &cmd.redirects)
+        .filter(|r| matches!(r.kind, RedirectKind::Write | RedirectKind::WriteBoth))
+        .filter(|r| expand_cd_target(&r.target.text, cwd).is_file())
+        .map(|r| r.target.text.clone())
+        .collect()
+}
+
+/// Substitutes `$?` and, once `tmp` has created it, `$HALO_TMP` outside
+/// single quotes, mirroring POSIX shell quoting. The directory is quoted
+/// so that it stays one word.
+fn expand_special_vars(line: &str, last_exit_code: i32) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' if !in_double => in_single = !in_single,
+            '"' if !in_single => in_double = !in_double,
+            '\\' if !in_single => {
+                out.push(c);
+                if let Some(next) = chars.next() {
+                    out.push(next);
+                }
+                continue;
+            }
+            '$' if !in_single && chars.peek() == Some(&'?') => {
+                chars.next();
+                out.push_str(&last_exit_code.to_string());
+                continue;
+            }
+            '$' if !in_single && let Some(dir) = take_scratch_var(&mut chars) => {
+                if in_double {
+                    out.push_str(&double_quoted(&dir));
+                } else {
+                    out.push_str(&parser::quote(&dir));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        out.push(c);
+    }
+    out
+}
 
-    // Check for dirty status
-    let mut opts = git2::StatusOptions::new();
-    opts.include_untracked(true).recurse_untracked_dirs(true);
-    let statuses = repo.statuses(Some(&mut opts)).ok()?;
+/// `text` escaped to sit inside double quotes.
+fn double_quoted(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
 
-    let is_dirty = statuses.iter().any(|s| s.status() != git2::Status::CURRENT);
+/// An alias's value with the words typed after it.
+fn alias_line(value: &str, args: &[String]) -> String {
+    if args.is_empty() {
+        value.to_string()
+    } else {
+        format!("{value} {}", args.join(" "))
+    }
+}
 
-    let icon = if is_dirty { " " } else { " ✔" }; // nf-fa-warning, nf-fa-check
+/// Consumes the `HALO_TMP` following a `$` and returns the directory, if
+/// it exists and the name is not the start of a longer one.
+fn take_scratch_var(chars: &mut Peekable<Chars>) -> Option<String> {
+    let dir = scratch::current()?;
+    let mut ahead = chars.clone();
+    if !scratch::ENV.chars().all(|c| ahead.next() == Some(c))
+        || ahead.peek().is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_')
+    {
+        return None;
+    }
+    *chars = ahead;
+    Some(dir.display().to_string())
+}
 
-    Some(format!("{shorthand}{icon}"))
+/// A new file only this user can read, holding `text` for the editor.
+/// `create_new` refuses anything already at the path, links included.
+fn create_edit_file(text: &str) -> std::io::Result<PathBuf> {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    let mut attempt = 0;
+    loop {
+        let name = format!("halo-edit-{}-{nanos:x}-{attempt}.sh", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                file.write_all(format!("{text}\n").as_bytes())?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempt < 8 => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
 }
 
 fn expand_cd_target(target: &str, cwd: &Path) -> PathBuf {
     if target == "~" {
         return dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
     }
-    if let Some(rest) = target.strip_prefix("~/") {
-        if let Some(home) = dirs::home_dir() {
-            return home.join(rest);
-        }
+    if let Some(rest) = target.strip_prefix("~/")
+        && let Some(home) = dirs::home_dir()
+    {
+        return home.join(rest);
     }
     let path = Path::new(target);
     if path.is_absolute() {
@@ -269,3 +2403,27 @@ fn expand_cd_target(target: &str, cwd: &Path) -> PathBuf {
         cwd.join(target)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_code_expands_outside_single_quotes() {
+        assert_eq!(expand_special_vars("echo $?", 3), "echo 3");
+        assert_eq!(expand_special_vars("echo '$?'", 3), "echo '$?'");
+        assert_eq!(expand_special_vars("echo \"code $?\"", 3), "echo \"code 3\"");
+        assert_eq!(expand_special_vars(r"echo \$?", 3), r"echo \$?");
+    }
+
+    #[test]
+    fn apostrophe_inside_double_quotes_is_not_a_quote() {
+        assert_eq!(expand_special_vars("echo \"it's $?\"", 3), "echo \"it's 3\"");
+        assert_eq!(expand_special_vars("echo '\"' $?", 1), "echo '\"' 1");
+    }
+
+    #[test]
+    fn double_quoted_escapes_specials() {
+        assert_eq!(double_quoted(r#"a"b\c$d`e"#), r#"a\"b\\c\$d\`e"#);
+    }
+}
@@ -0,0 +1,271 @@
+// src/builder.rs
+
+//! The command builder overlay (`build tar`, `build find`, ...): a form
+//! with one field per option, each explained, that assembles the command
+//! line in the input as it is filled in. Meant as a learning aid for tools
+//! whose flags are hard to remember.
+
+use crate::parser::quote;
+
+pub enum FieldKind {
+    Text,
+    Toggle,
+    /// Options cycled with Space or ←/→; the first is the default.
+    Choice(&'static [&'static str]),
+}
+
+pub struct Field {
+    pub label: &'static str,
+    pub help: &'static str,
+    pub kind: FieldKind,
+    pub default: &'static str,
+}
+
+pub struct Tool {
+    pub name: &'static str,
+    pub about: &'static str,
+    pub fields: &'static [Field],
+    /// The command line for the field values, in field order.
+    assemble: fn(&[String]) -> String,
+}
+
+const fn text(label: &'static str, help: &'static str, default: &'static str) -> Field {
+    Field {
+        label,
+        help,
+        kind: FieldKind::Text,
+        default,
+    }
+}
+
+const fn toggle(label: &'static str, help: &'static str) -> Field {
+    Field {
+        label,
+        help,
+        kind: FieldKind::Toggle,
+        default: "",
+    }
+}
+
+const fn choice(
+    label: &'static str,
+    help: &'static str,
+    options: &'static [&'static str],
+) -> Field {
+    Field {
+        label,
+        help,
+        kind: FieldKind::Choice(options),
+        default: options[0],
+    }
+}
+
+pub const TOOLS: &[Tool] = &[
+    Tool {
+        name: "tar",
+        about: "pack files into an archive, or unpack one",
+        fields: &[
+            choice(
+                "mode",
+                "create (-c) a new archive, extract (-x) one, or list (-t) what is in it",
+                &["create", "extract", "list"],
+            ),
+            text("archive", "The archive file (-f), e.g. backup.tar.gz", ""),
+            choice(
+                "compression",
+                "gzip (-z, .tar.gz), bzip2 (-j, .tar.bz2), xz (-J, .tar.xz) or none (.tar)",
+                &["gzip", "none", "bzip2", "xz"],
+            ),
+            text("files", "Files and directories to pack, separated by spaces (create)", ""),
+            text("into", "Directory to extract into (-C); it must exist", ""),
+            toggle("verbose", "Print each file as it is processed (-v)"),
+        ],
+        assemble: assemble_tar,
+    },
+    Tool {
+        name: "find",
+        about: "search a directory tree for files",
+        fields: &[
+            text("in", "Where to start searching", "."),
+            text("name", "File name pattern (-name); * and ? are wildcards, e.g. *.log", ""),
+            choice(
+                "type",
+                "Only files (-type f), directories (-type d) or symlinks (-type l)",
+                &["any", "file", "directory", "symlink"],
+            ),
+            text("changed", "Modified within this many days (-mtime -N)", ""),
+            text("depth", "How many directory levels to descend at most (-maxdepth)", ""),
+            choice(
+                "action",
+                "print the paths, list them in detail (-ls), or delete them (-delete; try \
+                 print first)",
+                &["print", "ls", "delete"],
+            ),
+        ],
+        assemble: assemble_find,
+    },
+    Tool {
+        name: "grep",
+        about: "search file contents for a pattern",
+        fields: &[
+            text("pattern", "What to look for; a regular expression unless fixed is on", ""),
+            text("in", "Files or directories to search, separated by spaces", "."),
+            toggle("recursive", "Search directories recursively (-r)"),
+            toggle("ignore case", "Match upper and lower case alike (-i)"),
+            toggle("fixed", "Treat the pattern as plain text, not a regex (-F)"),
+            toggle("line numbers", "Show the line number of each match (-n)"),
+            text("include", "Only search files matching this glob (--include), e.g. *.rs", ""),
+        ],
+        assemble: assemble_grep,
+    },
+];
+
+pub fn tool(name: &str) -> Option<&'static Tool> {
+    TOOLS.iter().find(|tool| tool.name == name)
+}
+
+/// Each whitespace-separated word of `value`, quoted.
+fn words(value: &str) -> impl Iterator<Item = String> + '_ {
+    value.split_whitespace().map(quote)
+}
+
+fn assemble_tar(v: &[String]) -> String {
+    let mut flags = match v[0].as_str() {
+        "extract" => "x",
+        "list" => "t",
+        _ => "c",
+    }
+    .to_string();
+    flags.push_str(match v[2].as_str() {
+        "gzip" => "z",
+        "bzip2" => "j",
+        "xz" => "J",
+        _ => "",
+    });
+    if !v[5].is_empty() {
+        flags.push('v');
+    }
+    flags.push('f');
+    let archive = match v[1].trim() {
+        "" => "ARCHIVE".to_string(),
+        path => quote(path),
+    };
+    let mut parts = vec!["tar".to_string(), format!("-{flags}"), archive];
+    match v[0].as_str() {
+        "create" => parts.extend(words(&v[3])),
+        "extract" if !v[4].trim().is_empty() => {
+            parts.push("-C".into());
+            parts.push(quote(v[4].trim()));
+        }
+        _ => {}
+    }
+    parts.join(" ")
+}
+
+fn assemble_find(v: &[String]) -> String {
+    let start = if v[0].trim().is_empty() { "." } else { v[0].trim() };
+    let mut parts = vec!["find".to_string(), quote(start)];
+    if let Ok(depth) = v[4].trim().parse::<u32>() {
+        parts.push(format!("-maxdepth {depth}"));
+    }
+    match v[2].as_str() {
+        "file" => parts.push("-type f".into()),
+        "directory" => parts.push("-type d".into()),
+        "symlink" => parts.push("-type l".into()),
+        _ => {}
+    }
+    if !v[1].trim().is_empty() {
+        parts.push(format!("-name {}", quote(v[1].trim())));
+    }
+    if let Ok(days) = v[3].trim().parse::<u32>() {
+        parts.push(format!("-mtime -{days}"));
+    }
+    match v[5].as_str() {
+        "ls" => parts.push("-ls".into()),
+        "delete" => parts.push("-delete".into()),
+        _ => {}
+    }
+    parts.join(" ")
+}
+
+fn assemble_grep(v: &[String]) -> String {
+    let mut parts = vec!["grep".to_string()];
+    let flags: String = [(2, 'r'), (3, 'i'), (4, 'F'), (5, 'n')]
+        .into_iter()
+        .filter(|(i, _)| !v[*i].is_empty())
+        .map(|(_, flag)| flag)
+        .collect();
+    if !flags.is_empty() {
+        parts.push(format!("-{flags}"));
+    }
+    if !v[6].trim().is_empty() {
+        parts.push(format!("--include={}", quote(v[6].trim())));
+    }
+    let pattern = if v[0].is_empty() { "PATTERN".to_string() } else { quote(&v[0]) };
+    parts.push(pattern);
+    parts.extend(words(&v[1]));
+    parts.join(" ")
+}
+
+/// An open builder form.
+pub struct Builder {
+    pub tool: &'static Tool,
+    pub values: Vec<String>,
+    pub selected: usize,
+}
+
+impl Builder {
+    pub fn new(tool: &'static Tool) -> Self {
+        Self {
+            tool,
+            values: tool.fields.iter().map(|f| f.default.to_string()).collect(),
+            selected: 0,
+        }
+    }
+
+    pub fn command(&self) -> String {
+        (self.tool.assemble)(&self.values)
+    }
+
+    pub fn field(&self) -> &'static Field {
+        &self.tool.fields[self.selected]
+    }
+
+    pub fn select(&mut self, delta: isize) {
+        let count = self.tool.fields.len() as isize;
+        self.selected = (self.selected as isize + delta).rem_euclid(count) as usize;
+    }
+
+    /// Flips a toggle or steps a choice; false for text fields.
+    pub fn change(&mut self, forward: bool) -> bool {
+        let value = &mut self.values[self.selected];
+        match self.tool.fields[self.selected].kind {
+            FieldKind::Text => return false,
+            FieldKind::Toggle => {
+                *value = if value.is_empty() { "on".into() } else { String::new() };
+            }
+            FieldKind::Choice(options) => {
+                let current = options.iter().position(|o| o == value).unwrap_or(0);
+                let next = if forward {
+                    (current + 1) % options.len()
+                } else {
+                    (current + options.len() - 1) % options.len()
+                };
+                *value = options[next].to_string();
+            }
+        }
+        true
+    }
+
+    pub fn type_char(&mut self, c: char) {
+        if matches!(self.field().kind, FieldKind::Text) {
+            self.values[self.selected].push(c);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        if matches!(self.field().kind, FieldKind::Text) {
+            self.values[self.selected].pop();
+        }
+    }
+}
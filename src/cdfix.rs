@@ -0,0 +1,151 @@
+// src/cdfix.rs
+
+//! Spelling correction for `cd` to a directory that doesn't exist, in the
+//! spirit of bash's `cdspell`: each component of the path that isn't there
+//! is matched against the directories beside it, allowing a typo or two.
+//! Failing that, the directories `z` knows of whose name is that close
+//! are tried.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Typos allowed in a name of `len` characters: none but case in one or
+/// two characters (anything would match), one up to four, then two.
+fn allowed(len: usize) -> usize {
+    match len {
+        0..3 => 0,
+        3..5 => 1,
+        _ => 2,
+    }
+}
+
+/// The directory `target` (as typed, relative to `cwd`) most likely meant,
+/// written as it would be typed, or `None` if nothing is close enough.
+pub fn correct(target: &str, cwd: &Path, visited: &[PathBuf]) -> Option<String> {
+    correct_components(target, cwd).or_else(|| closest_visited(target, cwd, visited))
+}
+
+fn correct_components(target: &str, cwd: &Path) -> Option<String> {
+    let (mut dir, prefix, rest) = match target.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => (dirs::home_dir()?, "~/", rest),
+        _ if target.starts_with('/') => (PathBuf::from("/"), "/", target),
+        _ => (cwd.to_path_buf(), "", target),
+    };
+    let mut parts = Vec::new();
+    let mut changed = false;
+    for component in Path::new(rest).components() {
+        let name = match component {
+            Component::Normal(name) => name.to_string_lossy().to_string(),
+            Component::ParentDir => "..".to_string(),
+            Component::CurDir => ".".to_string(),
+            Component::RootDir | Component::Prefix(_) => continue,
+        };
+        let name = if dir.join(&name).is_dir() {
+            name
+        } else {
+            changed = true;
+            closest_name(&name, subdirectories(&dir))?
+        };
+        dir.push(&name);
+        parts.push(name);
+    }
+    (changed && dir.is_dir()).then(|| format!("{prefix}{}", parts.join("/")))
+}
+
+fn subdirectories(dir: &Path) -> impl Iterator<Item = String> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+}
+
+/// The candidate closest to `name` within the allowed typos; ties go to
+/// the first seen.
+fn closest_name(name: &str, candidates: impl Iterator<Item = String>) -> Option<String> {
+    let limit = allowed(name.chars().count());
+    candidates
+        .map(|candidate| (distance(name, &candidate), candidate))
+        .filter(|(d, _)| *d <= limit)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, candidate)| candidate)
+}
+
+/// A visited directory whose last component is closest to the last one
+/// of `target`; ties go to the best-ranked.
+fn closest_visited(target: &str, cwd: &Path, visited: &[PathBuf]) -> Option<String> {
+    let name = Path::new(target).file_name()?.to_string_lossy().to_string();
+    let limit = allowed(name.chars().count());
+    let (_, dir) = visited
+        .iter()
+        .filter(|dir| dir.as_path() != cwd)
+        .filter_map(|dir| {
+            let d = distance(&name, &dir.file_name()?.to_string_lossy());
+            (d <= limit).then_some((d, dir))
+        })
+        .min_by_key(|(d, _)| *d)?;
+    dir.is_dir().then(|| crate::state::tilde_path(dir))
+}
+
+/// Edit distance between `a` and `b`, ignoring case, where swapping two
+/// neighbouring characters counts as one edit (optimal string alignment).
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().flat_map(char::to_lowercase).collect();
+    let b: Vec<char> = b.chars().flat_map(char::to_lowercase).collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut d = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d = d.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = d;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_counts_edits_and_swaps() {
+        assert_eq!(distance("src", "src"), 0);
+        assert_eq!(distance("Docs", "docs"), 0);
+        assert_eq!(distance("dcos", "docs"), 1);
+        assert_eq!(distance("doc", "docs"), 1);
+        assert_eq!(distance("dox", "docs"), 2);
+        assert_eq!(distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn short_names_need_an_exact_match() {
+        assert_eq!(allowed(2), 0);
+        assert_eq!(allowed(3), 1);
+        assert_eq!(allowed(5), 2);
+        let candidates = || ["ab", "src", "target"].into_iter().map(String::from);
+        assert_eq!(closest_name("AB", candidates()), Some("ab".into()));
+        assert_eq!(closest_name("ax", candidates()), None);
+        assert_eq!(closest_name("scr", candidates()), Some("src".into()));
+        assert_eq!(closest_name("trgte", candidates()), Some("target".into()));
+    }
+
+    #[test]
+    fn correct_fixes_each_component() {
+        let root = std::env::temp_dir().join(format!("halo-cdfix-{}", std::process::id()));
+        fs::create_dir_all(root.join("projects/halo")).unwrap();
+        assert_eq!(correct("projcets/hlao", &root, &[]), Some("projects/halo".into()));
+        assert_eq!(correct("projects/halo", &root, &[]), None);
+        assert_eq!(correct("nowhere", &root, &[]), None);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
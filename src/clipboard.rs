@@ -0,0 +1,159 @@
+// src/clipboard.rs
+
+//! Copying to the system clipboard. Locally this goes through the
+//! platform's clipboard tool; over SSH, or when no tool is installed, the
+//! text is sent to the terminal as an OSC 52 sequence, which most modern
+//! terminals (and tmux with `set-clipboard on`) put on the clipboard of the
+//! machine the user is sitting at.
+
+use crate::command::CommandLog;
+use crate::error::AppResult;
+use crate::state;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// How the text reached the clipboard.
+pub enum Via {
+    Tool(&'static str),
+    Osc52,
+}
+
+impl Via {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Via::Tool(name) => name,
+            Via::Osc52 => "OSC 52",
+        }
+    }
+}
+
+/// Copies `text`. `remote` skips the local tools, which would fill the
+/// clipboard of the wrong machine.
+pub fn copy(text: &str, remote: bool) -> AppResult<Via> {
+    if !remote {
+        for (name, args) in tools() {
+            if run_tool(name, args, text) {
+                return Ok(Via::Tool(name));
+            }
+        }
+    }
+    osc52(text)?;
+    Ok(Via::Osc52)
+}
+
+/// Candidate tools for this session, best first.
+fn tools() -> Vec<(&'static str, &'static [&'static str])> {
+    let mut tools: Vec<(&'static str, &'static [&'static str])> = Vec::new();
+    if cfg!(target_os = "macos") {
+        tools.push(("pbcopy", &[]));
+    }
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        tools.push(("wl-copy", &[]));
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        tools.push(("xclip", &["-selection", "clipboard"]));
+        tools.push(("xsel", &["--clipboard", "--input"]));
+    }
+    tools
+}
+
+/// Pipes `text` into the tool; false if it is missing or fails. Its output
+/// is discarded so nothing lands on the TUI.
+fn run_tool(name: &str, args: &[&str], text: &str) -> bool {
+    let Ok(mut child) = Command::new(name)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+    let written = child
+        .stdin
+        .take()
+        .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+    // xclip and wl-copy fork to keep serving the selection; the parent
+    // exits as soon as it has read stdin.
+    child.wait().is_ok_and(|status| status.success()) && written
+}
+
+fn osc52(text: &str) -> AppResult<()> {
+    let sequence = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    // tmux swallows OSC 52 from programs unless it is wrapped in a
+    // passthrough sequence.
+    let sequence = if std::env::var_os("TMUX").is_some() {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    };
+    let mut stdout = std::io::stdout();
+    stdout.write_all(sequence.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | ((b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// What of a log entry to copy.
+#[derive(Clone, Copy)]
+pub enum CopyTarget {
+    Command,
+    Output,
+    /// The command and its output.
+    Block,
+}
+
+impl CopyTarget {
+    pub fn parse(word: &str) -> Option<Self> {
+        match word {
+            "command" => Some(Self::Command),
+            "output" => Some(Self::Output),
+            "block" => Some(Self::Block),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Command => "command",
+            Self::Output => "output",
+            Self::Block => "block",
+        }
+    }
+
+    /// The text as the log shows it, without halo's `[stderr]` markers.
+    pub fn text(self, log: &CommandLog) -> String {
+        let output = || {
+            log.output
+                .lines()
+                .map(state::displayed_line)
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        match self {
+            Self::Command => log.command.clone(),
+            Self::Output => output(),
+            Self::Block if log.output.is_empty() => format!("$ {}", log.command),
+            Self::Block => format!("$ {}\n{}", log.command, output()),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+// src/colorblind.rs
+
+//! Color vision deficiency simulation for the theme gallery (`c` while
+//! choosing a theme), so theme authors can check that error, warning and
+//! success colors stay apart. Uses the full-severity matrices of Machado,
+//! Oliveira and Fernandes (2009), applied in linear RGB.
+
+use ratatui::buffer::Buffer;
+use ratatui::style::Color;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Simulation {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl Simulation {
+    /// The toggle's cycle: off, then each deficiency in turn.
+    pub fn cycle(current: Option<Self>) -> Option<Self> {
+        match current {
+            None => Some(Self::Protanopia),
+            Some(Self::Protanopia) => Some(Self::Deuteranopia),
+            Some(Self::Deuteranopia) => Some(Self::Tritanopia),
+            Some(Self::Tritanopia) => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Protanopia => "protanopia (no red cones)",
+            Self::Deuteranopia => "deuteranopia (no green cones)",
+            Self::Tritanopia => "tritanopia (no blue cones)",
+        }
+    }
+
+    fn matrix(self) -> [[f64; 3]; 3] {
+        match self {
+            Self::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Self::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Self::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+
+    /// `color` as seen with this deficiency. Colors the terminal picks
+    /// (`Reset`, palette indices) are left alone.
+    pub fn apply(self, color: Color) -> Color {
+        let Some([r, g, b]) = rgb(color) else {
+            return color;
+        };
+        let linear = [r, g, b].map(to_linear);
+        let m = self.matrix();
+        let [r, g, b] = m.map(|row| {
+            let value = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+            to_srgb(value.clamp(0.0, 1.0))
+        });
+        Color::Rgb(r, g, b)
+    }
+
+    /// Recolors every cell already drawn into `buf`.
+    pub fn apply_to_buffer(self, buf: &mut Buffer) {
+        for cell in &mut buf.content {
+            cell.fg = self.apply(cell.fg);
+            cell.bg = self.apply(cell.bg);
+        }
+    }
+}
+
+/// RGB of truecolor values and of the named colors halo uses directly,
+/// taken as the xterm defaults.
+fn rgb(color: Color) -> Option<[u8; 3]> {
+    Some(match color {
+        Color::Rgb(r, g, b) => [r, g, b],
+        Color::Black => [0, 0, 0],
+        Color::Red => [205, 0, 0],
+        Color::Green => [0, 205, 0],
+        Color::Yellow => [205, 205, 0],
+        Color::Blue => [0, 0, 238],
+        Color::Magenta => [205, 0, 205],
+        Color::Cyan => [0, 205, 205],
+        Color::Gray => [229, 229, 229],
+        Color::DarkGray => [127, 127, 127],
+        Color::LightRed => [255, 0, 0],
+        Color::LightGreen => [0, 255, 0],
+        Color::LightYellow => [255, 255, 0],
+        Color::LightBlue => [92, 92, 255],
+        Color::LightMagenta => [255, 0, 255],
+        Color::LightCyan => [0, 255, 255],
+        Color::White => [255, 255, 255],
+        _ => return None,
+    })
+}
+
+fn to_linear(channel: u8) -> f64 {
+    let c = channel as f64 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn to_srgb(linear: f64) -> u8 {
+    let c = if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
@@ -1,68 +1,496 @@
 // src/command.rs
 
+//! Jobs: [`CommandManager`] starts external commands, streams their
+//! output and exit back as [`CommandUpdate`]s, and keeps the job table
+//! behind `jobs`, `fg` and `bg`. [`CommandLog`] is one entry of the log.
+
+use crate::disk::Mount;
 use crate::error::AppResult;
+use crate::links::{self, Link};
+use crate::output::{OutputBuffer, OutputLimits};
+use crate::exports;
+use crate::scratch;
+use chrono::{DateTime, Local};
 // no serde types used here anymore
-use anyhow;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, VecDeque};
 use std::path::{Path, PathBuf};
+use std::os::unix::process::ExitStatusExt;
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::process::Command as TokioCommand;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// How long output readers may keep draining after their child exits, e.g.
+/// while a backgrounded grandchild still holds the pipe.
+const READER_GRACE: Duration = Duration::from_millis(500);
 
+/// Output and completion events, tagged with the id of the job they belong to.
 #[derive(Debug)]
 pub enum CommandUpdate {
-    NewLine(String),
-    Finished(Option<i32>),
+    /// A line of output; stderr lines start with `[stderr] `.
+    NewLine(usize, String),
+    /// A line ended by a bare `\r`: it replaces the entry's in-progress line.
+    Progress(usize, String),
+    /// The job is gone: it exited, was killed or never started.
+    Finished(usize, JobExit),
 }
 
+/// How a process ended. Both fields are `None` when that is unknown, e.g.
+/// for a queued job that was cancelled before it started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JobExit {
+    /// Exit status, when the process exited.
+    pub code: Option<i32>,
+    /// Terminating signal, when it was killed by one.
+    pub signal: Option<i32>,
+}
+
+impl JobExit {
+    /// Exited with status `code`.
+    pub fn code(code: i32) -> Self {
+        Self {
+            code: Some(code),
+            signal: None,
+        }
+    }
+
+    /// Killed by `signal`.
+    pub fn signal(signal: i32) -> Self {
+        Self {
+            code: None,
+            signal: Some(signal),
+        }
+    }
+
+    /// The value for `$?`: the exit code, or 128 + N for signal N like
+    /// other shells.
+    pub fn status(self) -> i32 {
+        match (self.code, self.signal) {
+            (Some(code), _) => code,
+            (None, Some(signal)) => 128 + signal,
+            (None, None) => 1,
+        }
+    }
+}
+
+/// `SIGSEGV` and friends; `None` for signals without a portable name.
+pub fn signal_name(signal: i32) -> Option<&'static str> {
+    let name = match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGCHLD => "SIGCHLD",
+        libc::SIGCONT => "SIGCONT",
+        libc::SIGSTOP => "SIGSTOP",
+        libc::SIGTSTP => "SIGTSTP",
+        libc::SIGTTIN => "SIGTTIN",
+        libc::SIGTTOU => "SIGTTOU",
+        libc::SIGURG => "SIGURG",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        libc::SIGVTALRM => "SIGVTALRM",
+        libc::SIGPROF => "SIGPROF",
+        libc::SIGWINCH => "SIGWINCH",
+        libc::SIGIO => "SIGIO",
+        libc::SIGSYS => "SIGSYS",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// `killed (SIGSEGV)`, or `killed (signal 42)` for unnamed signals.
+pub fn describe_signal(signal: i32) -> String {
+    match signal_name(signal) {
+        Some(name) => format!("killed ({name})"),
+        None => format!("killed (signal {signal})"),
+    }
+}
+
+impl From<std::process::ExitStatus> for JobExit {
+    fn from(status: std::process::ExitStatus) -> Self {
+        Self {
+            code: status.code(),
+            signal: status.signal(),
+        }
+    }
+}
+
+/// Output a builtin draws with widgets. The entry's `output` keeps a
+/// plain-text copy.
+#[derive(Clone, Debug)]
+pub enum RichOutput {
+    /// The `disk` builtin's usage bars.
+    Disk(Vec<Mount>),
+}
+
+/// Source of `CommandLog::id`.
+static NEXT_LOG_ID: AtomicU64 = AtomicU64::new(0);
+
+/// One entry of the log: a command line and what came of it.
 #[derive(Clone, Debug)]
 pub struct CommandLog {
+    /// Stable identity while entries are added and trimmed around it.
+    pub id: u64,
+    /// The line as typed; empty for a bare prompt.
     pub command: String,
-    pub output: String,
+    /// Output lines, bounded by `[performance] max_output_lines`.
+    pub output: OutputBuffer,
+    /// Still waiting for its command to finish.
     pub is_running: bool,
+    /// Directory the command ran in.
     pub cwd: PathBuf,
+    /// Git branch `cwd` was on when the command ran.
+    pub branch: Option<String>,
+    /// Exit status, once finished.
     pub exit_code: Option<i32>,
+    /// Signal that terminated the process, if it did not exit normally.
+    pub signal: Option<i32>,
+    /// How long the command ran, once finished.
     pub duration_ms: Option<u128>,
+    /// Job currently streaming its output into this entry.
+    pub job_id: Option<usize>,
+    /// When the command started, for its duration.
+    pub started_at: Option<Instant>,
+    /// Wall-clock start, for the timestamps in the log.
+    pub started_wall: Option<DateTime<Local>>,
+    /// User annotation shown in the entry header (`note` builtin).
+    pub note: Option<String>,
+    /// Widgets drawn instead of the plain output.
+    pub rich: Option<RichOutput>,
+    /// OSC 8 links lifted out of the output.
+    pub links: Vec<Link>,
+    /// Finished while the user was away: scrolled up or in another window.
+    /// The header is marked until they are back at the bottom.
+    pub unseen: bool,
+    /// The last line is a progress line, from stderr if true, that the
+    /// stream's next line replaces.
+    progress: Option<bool>,
 }
 
 impl CommandLog {
+    /// A new entry with a fresh id; `output` may hold several lines.
     pub fn new(command: String, output: String, is_running: bool, cwd: PathBuf) -> Self {
         Self {
+            id: NEXT_LOG_ID.fetch_add(1, Ordering::Relaxed),
             command,
-            output,
+            output: OutputBuffer::from_text(&output),
             is_running,
             cwd,
+            branch: None,
             exit_code: None,
+            signal: None,
             duration_ms: None,
+            job_id: None,
+            started_at: None,
+            started_wall: None,
+            note: None,
+            rich: None,
+            links: Vec::new(),
+            unseen: false,
+            progress: None,
+        }
+    }
+
+    /// Appends output, one line per `\n`, lifting OSC 8 links out of it.
+    pub fn push_line(&mut self, text: &str, limits: &OutputLimits) {
+        self.progress = None;
+        for line in text.split('\n') {
+            self.push_one(line, limits);
+        }
+    }
+
+    /// Appends a line from a job's output stream, replacing that stream's
+    /// progress line if it left one. `progress` lines are replaced in turn.
+    pub fn push_stream_line(&mut self, line: &str, progress: bool, limits: &OutputLimits) {
+        let stderr = line.starts_with("[stderr] ");
+        if self.progress == Some(stderr) && self.output.pop().is_some() {
+            let last = self.output.len();
+            self.links.retain(|link| link.line != last);
+        }
+        self.push_one(line, limits);
+        self.progress = progress.then_some(stderr);
+    }
+
+    fn push_one(&mut self, line: &str, limits: &OutputLimits) {
+        let line = if line.contains("\x1b]8;") {
+            let (text, found) = links::strip_osc8(line);
+            let index = self.output.len();
+            // Ranges are kept for the line as displayed.
+            let shift = if text.starts_with("[stderr] ") { "[stderr] ".len() } else { 0 };
+            self.links.extend(found.into_iter().map(|(range, url)| Link {
+                line: index,
+                range: range.start.saturating_sub(shift)..range.end.saturating_sub(shift),
+                url,
+            }));
+            Cow::Owned(text)
+        } else {
+            Cow::Borrowed(line)
+        };
+        let dropped = self.output.push(line.into_owned(), limits, self.id);
+        if dropped > 0 {
+            // Link lines count from the first line still held.
+            self.links.retain(|link| link.line >= dropped);
+            for link in &mut self.links {
+                link.line -= dropped;
+            }
+        }
+    }
+}
+
+/// Where a job is in its life, as `jobs` lists it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobStatus {
+    /// Waiting for a slot under `[jobs] max_parallel`.
+    Queued,
+    /// Started and not stopped.
+    Running,
+    /// Suspended with Ctrl+Z until `fg` or `bg`.
+    Stopped,
+}
+
+impl JobStatus {
+    /// The word `jobs` shows for it.
+    pub fn label(self) -> &'static str {
+        match self {
+            JobStatus::Queued => "Queued",
+            JobStatus::Running => "Running",
+            JobStatus::Stopped => "Stopped",
         }
     }
 }
 
+/// A command halo started, from launch until it is reaped.
+pub struct Job {
+    /// The job id `%N` refers to.
+    pub id: usize,
+    /// The command line it runs.
+    pub command: String,
+    /// Process id, once started.
+    pub pid: Option<u32>,
+    /// Queued, running or stopped.
+    pub status: JobStatus,
+    kill_sender: Option<oneshot::Sender<()>>,
+}
+
+/// Children spawned by halo, keyed by their shell-visible job id (`%1`, `%2`, ...).
+#[derive(Default)]
+pub struct JobTable {
+    jobs: BTreeMap<usize, Job>,
+    foreground: Option<usize>,
+}
+
+impl JobTable {
+    fn next_id(&self) -> usize {
+        self.jobs.keys().next_back().map_or(1, |id| id + 1)
+    }
+
+    /// The job with id `id`.
+    pub fn get(&self, id: usize) -> Option<&Job> {
+        self.jobs.get(&id)
+    }
+
+    /// All jobs, by id.
+    pub fn iter(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.values()
+    }
+
+    /// True when no job is alive.
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// The job Ctrl+C and Ctrl+Z act on.
+    pub fn foreground(&self) -> Option<usize> {
+        self.foreground
+    }
+
+    /// Hands the foreground to another job (or none), as when switching tabs.
+    pub fn set_foreground(&mut self, id: Option<usize>) {
+        self.foreground = id.filter(|id| self.jobs.contains_key(id));
+    }
+
+    /// The job `%+` refers to: the most recently started one of those
+    /// `visible` accepts (jobs belong to the tab that started them).
+    pub fn current(&self, visible: impl Fn(usize) -> bool) -> Option<usize> {
+        self.jobs.keys().rev().copied().find(|id| visible(*id))
+    }
+
+    /// Resolves a job spec such as `%1`, `1`, `%+` or `%%`; no spec means the current job.
+    pub fn resolve(&self, spec: Option<&str>, visible: impl Fn(usize) -> bool) -> Option<usize> {
+        let spec = spec.map(|s| s.strip_prefix('%').unwrap_or(s));
+        match spec {
+            None | Some("") | Some("+") | Some("%") => self.current(visible),
+            Some(n) => n
+                .parse()
+                .ok()
+                .filter(|id| self.jobs.contains_key(id) && visible(*id)),
+        }
+    }
+
+    fn remove(&mut self, id: usize) -> Option<Job> {
+        if self.foreground == Some(id) {
+            self.foreground = None;
+        }
+        self.jobs.remove(&id)
+    }
+}
+
+/// Everything needed to start a job later, once a slot frees up.
+struct JobSpec {
+    cmd: String,
+    args: Vec<String>,
+    cwd: PathBuf,
+    tx: UnboundedSender<CommandUpdate>,
+}
+
+/// Starts commands as jobs and reports on them over a channel.
 #[derive(Default)]
 pub struct CommandManager {
-    kill_sender: Option<oneshot::Sender<()>>,
+    /// Every live job.
+    pub jobs: JobTable,
+    /// Upper bound on live (running or stopped) jobs; None means unlimited.
+    pub max_parallel: Option<usize>,
+    queue: VecDeque<(usize, JobSpec)>,
+    /// One per launched job; each owns its child and output readers and
+    /// finishes once the child has been reaped.
+    tasks: Vec<JoinHandle<()>>,
 }
 
 impl CommandManager {
+    /// A manager with no jobs and no parallel limit.
     pub fn new() -> Self {
         Self::default()
     }
 
+    fn live_jobs(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|j| j.status != JobStatus::Queued)
+            .count()
+    }
+
+    fn has_free_slot(&self) -> bool {
+        self.max_parallel.is_none_or(|max| self.live_jobs() < max)
+    }
+
+    /// Spawns a child as a new job and returns its id, or queues it when
+    /// `max_parallel` jobs are already alive. Foreground jobs become the
+    /// target of Ctrl+C / Ctrl+Z.
     pub fn spawn_command(
         &mut self,
         cmd: &str,
         args: &[String],
         cwd: &Path,
+        command_line: &str,
+        background: bool,
         tx: UnboundedSender<CommandUpdate>,
-    ) -> AppResult<()> {
-        let mut child = TokioCommand::new(cmd)
-            .args(args)
-            .current_dir(cwd)
+    ) -> AppResult<usize> {
+        let id = self.jobs.next_id();
+        let spec = JobSpec {
+            cmd: cmd.to_string(),
+            args: args.to_vec(),
+            cwd: cwd.to_path_buf(),
+            tx,
+        };
+        if self.has_free_slot() {
+            let (pid, kill_sender) = self.launch(id, &spec)?;
+            self.jobs.jobs.insert(
+                id,
+                Job {
+                    id,
+                    command: command_line.to_string(),
+                    pid,
+                    status: JobStatus::Running,
+                    kill_sender: Some(kill_sender),
+                },
+            );
+        } else {
+            self.jobs.jobs.insert(
+                id,
+                Job {
+                    id,
+                    command: command_line.to_string(),
+                    pid: None,
+                    status: JobStatus::Queued,
+                    kill_sender: None,
+                },
+            );
+            self.queue.push_back((id, spec));
+        }
+        if !background {
+            self.jobs.foreground = Some(id);
+        }
+        Ok(id)
+    }
+
+    /// Starts queued jobs while slots are free. Launch failures are reported
+    /// through the job's own update channel like any other exit.
+    pub fn start_queued(&mut self) {
+        while self.has_free_slot() {
+            let Some((id, spec)) = self.queue.pop_front() else {
+                break;
+            };
+            match self.launch(id, &spec) {
+                Ok((pid, kill_sender)) => {
+                    if let Some(job) = self.jobs.jobs.get_mut(&id) {
+                        job.pid = pid;
+                        job.status = JobStatus::Running;
+                        job.kill_sender = Some(kill_sender);
+                    }
+                }
+                Err(e) => {
+                    let _ = spec
+                        .tx
+                        .send(CommandUpdate::NewLine(id, format!("{}: {e}", spec.cmd)));
+                    let _ = spec.tx.send(CommandUpdate::Finished(id, JobExit::code(127)));
+                }
+            }
+        }
+    }
+
+    /// Jobs waiting for a slot.
+    pub fn queued_jobs(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn launch(
+        &mut self,
+        id: usize,
+        spec: &JobSpec,
+    ) -> AppResult<(Option<u32>, oneshot::Sender<()>)> {
+        let cmd = spec.cmd.as_str();
+        let mut command = TokioCommand::new(cmd);
+        command
+            .args(&spec.args)
+            .current_dir(&spec.cwd)
+            .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .kill_on_drop(true)
-            .spawn()?;
+            // Own process group so job control signals reach the whole pipeline.
+            .process_group(0)
+            .kill_on_drop(true);
+        if let Some(dir) = scratch::current() {
+            command.env(scratch::ENV, dir);
+        }
+        command.envs(exports::all());
+        let mut child = command.spawn()?;
 
         let stdout = child
             .stdout
@@ -73,62 +501,218 @@ impl CommandManager {
             .take()
             .ok_or_else(|| anyhow::anyhow!("Failed to capture stderr for command: {cmd}"))?;
 
+        let pid = child.id();
         let (kill_tx, mut kill_rx) = oneshot::channel();
-        self.kill_sender = Some(kill_tx);
-
-        let tx_out = tx.clone();
-        tokio::spawn(async move {
-            let mut reader = BufReader::new(stdout).lines();
-            while let Ok(Some(line)) = reader.next_line().await {
-                if tx_out.send(CommandUpdate::NewLine(line)).is_err() {
-                    break;
-                }
-            }
-        });
 
-        let tx_err = tx.clone();
-        tokio::spawn(async move {
-            let mut reader = BufReader::new(stderr).lines();
-            while let Ok(Some(line)) = reader.next_line().await {
-                if tx_err
-                    .send(CommandUpdate::NewLine(format!("[stderr] {line}")))
-                    .is_err()
-                {
-                    break;
-                }
-            }
-        });
+        let mut out_reader = tokio::spawn(stream_output(stdout, id, "", spec.tx.clone()));
+        let mut err_reader = tokio::spawn(stream_output(stderr, id, "[stderr] ", spec.tx.clone()));
 
-        let tx_finish = tx;
-        #[allow(unused_mut)]
-        tokio::spawn(async move {
-            tokio::select! {
-                status = child.wait() => {
-                    // Command finished on its own
-                    let code = status.ok().and_then(|s| s.code());
-                    let _ = tx_finish.send(CommandUpdate::Finished(code));
-                    return;
-                }
+        let tx_finish = spec.tx.clone();
+        let waiter = tokio::spawn(async move {
+            let status = tokio::select! {
+                status = child.wait() => status,
                 _ = &mut kill_rx => {
-                    // Kill signal received
-                    let _ = child.kill().await;
-                    let _ = tx_finish.send(CommandUpdate::Finished(None));
-                    return;
+                    let _ = child.start_kill();
+                    child.wait().await
                 }
+            };
+            // Let the readers flush what the child wrote, so every line lands
+            // before Finished; give up on them if something else holds the pipe.
+            let drained = tokio::time::timeout(READER_GRACE, async {
+                let _ = (&mut out_reader).await;
+                let _ = (&mut err_reader).await;
+            })
+            .await;
+            if drained.is_err() {
+                out_reader.abort();
+                err_reader.abort();
             }
+            let exit = status.map(JobExit::from).unwrap_or_default();
+            let _ = tx_finish.send(CommandUpdate::Finished(id, exit));
         });
+        self.tasks.retain(|task| !task.is_finished());
+        self.tasks.push(waiter);
 
-        Ok(())
+        Ok((pid, kill_tx))
     }
 
+    /// Ends every job when halo exits: stopped jobs are continued and all
+    /// get SIGHUP, as a login shell would do. Jobs still alive after
+    /// `timeout` are killed, and their tasks get the same time again to reap
+    /// them.
+    pub async fn shutdown(&mut self, timeout: Duration) {
+        self.queue.clear();
+        for job in self.jobs.iter() {
+            signal_job(job, libc::SIGHUP);
+            if job.status == JobStatus::Stopped {
+                signal_job(job, libc::SIGCONT);
+            }
+        }
+        if self.join_tasks(timeout).await {
+            return;
+        }
+        for job in self.jobs.jobs.values_mut() {
+            signal_job(job, libc::SIGKILL);
+            if let Some(sender) = job.kill_sender.take() {
+                let _ = sender.send(());
+            }
+        }
+        self.join_tasks(timeout).await;
+    }
+
+    /// Waits for the job tasks; true if they all finished within `timeout`.
+    async fn join_tasks(&mut self, timeout: Duration) -> bool {
+        let joined = tokio::time::timeout(timeout, async {
+            for task in &mut self.tasks {
+                let _ = task.await;
+            }
+        })
+        .await;
+        self.tasks.retain(|task| !task.is_finished());
+        joined.is_ok()
+    }
+
+    /// Kills the foreground job, or drops it from the queue.
     pub fn kill_running_command(&mut self) -> AppResult<()> {
-        if let Some(sender) = self.kill_sender.take() {
+        let Some(id) = self.jobs.foreground else {
+            return Ok(());
+        };
+        // A queued job has no process yet; just drop it from the queue.
+        if let Some(pos) = self.queue.iter().position(|(qid, _)| *qid == id) {
+            if let Some((_, spec)) = self.queue.remove(pos) {
+                let _ = spec.tx.send(CommandUpdate::Finished(id, JobExit::default()));
+            }
+            return Ok(());
+        }
+        if let Some(job) = self.jobs.jobs.get_mut(&id)
+            && let Some(sender) = job.kill_sender.take()
+        {
             // Send the kill signal. We don't care if it fails,
             // as that means the process already finished.
             let _ = sender.send(());
         }
         Ok(())
     }
+
+    /// Suspends the foreground job (Ctrl+Z), returning its id.
+    pub fn stop_foreground(&mut self) -> Option<usize> {
+        let id = self.jobs.foreground?;
+        let job = self.jobs.jobs.get_mut(&id)?;
+        if job.status != JobStatus::Running {
+            return None;
+        }
+        self.jobs.foreground = None;
+        signal_job(job, libc::SIGSTOP);
+        job.status = JobStatus::Stopped;
+        Some(id)
+    }
+
+    /// Resumes a job, optionally making it the foreground job.
+    pub fn continue_job(&mut self, id: usize, foreground: bool) -> AppResult<()> {
+        let job = self
+            .jobs
+            .jobs
+            .get_mut(&id)
+            .ok_or_else(|| anyhow::anyhow!("%{id}: no such job"))?;
+        if job.status == JobStatus::Stopped {
+            signal_job(job, libc::SIGCONT);
+            job.status = JobStatus::Running;
+        }
+        if foreground {
+            self.jobs.foreground = Some(id);
+        } else if self.jobs.foreground == Some(id) {
+            self.jobs.foreground = None;
+        }
+        Ok(())
+    }
+
+    /// Sends `signal` to a job's process group. A stopped job is continued
+    /// too, so it can act on the signal.
+    pub fn signal(&self, id: usize, signal: libc::c_int) -> AppResult<()> {
+        let job = self
+            .jobs
+            .get(id)
+            .ok_or_else(|| anyhow::anyhow!("%{id}: no such job"))?;
+        if job.pid.is_none() {
+            anyhow::bail!("%{id} is still queued");
+        }
+        signal_job(job, signal);
+        if job.status == JobStatus::Stopped && signal != libc::SIGSTOP {
+            signal_job(job, libc::SIGCONT);
+        }
+        Ok(())
+    }
+
+    /// Drops a finished job from the table.
+    pub fn reap(&mut self, id: usize) -> Option<Job> {
+        self.jobs.remove(id)
+    }
+}
+
+/// Sends `pipe` to `tx` line by line, each prefixed with `prefix`. A bare
+/// `\r` ends a progress update rather than a line, the way curl, pip and
+/// cargo redraw their progress bars; `\r\n` is an ordinary line end.
+async fn stream_output<R: AsyncRead + Unpin>(
+    pipe: R,
+    id: usize,
+    prefix: &'static str,
+    tx: UnboundedSender<CommandUpdate>,
+) {
+    let mut reader = BufReader::new(pipe);
+    let mut line = Vec::new();
+    let mut after_cr = false;
+    let send = |line: &mut Vec<u8>, progress: bool| {
+        let text = format!("{prefix}{}", String::from_utf8_lossy(line));
+        line.clear();
+        let update = if progress {
+            CommandUpdate::Progress(id, text)
+        } else {
+            CommandUpdate::NewLine(id, text)
+        };
+        tx.send(update).is_ok()
+    };
+    while let Ok(buf) = reader.fill_buf().await
+        && !buf.is_empty()
+    {
+        let len = buf.len();
+        for &byte in buf {
+            if std::mem::take(&mut after_cr) {
+                if byte == b'\n' {
+                    if !send(&mut line, false) {
+                        return;
+                    }
+                    continue;
+                }
+                // Tools often start with `\r`; there is nothing to show yet.
+                if !line.is_empty() && !send(&mut line, true) {
+                    return;
+                }
+            }
+            match byte {
+                b'\n' => {
+                    if !send(&mut line, false) {
+                        return;
+                    }
+                }
+                b'\r' => after_cr = true,
+                _ => line.push(byte),
+            }
+        }
+        reader.consume(len);
+    }
+    // The final state of a progress line stays as an ordinary line.
+    if !line.is_empty() {
+        send(&mut line, false);
+    }
+}
+
+fn signal_job(job: &Job, signal: libc::c_int) {
+    if let Some(pid) = job.pid {
+        // Negative pid targets the process group created at spawn time.
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), signal);
+        }
+    }
 }
 
 // Removed duplicate CompletionState. The canonical implementation lives in crate::completion.
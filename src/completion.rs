@@ -1,58 +1,468 @@
 // src/completion.rs
 
+//! Tab completion and suggestions: commands, paths, flags, history,
+//! variables, hosts, and user-defined `[completions]` and `[templates]`.
+
+use crate::exports;
+use crate::flags::FlagCache;
+use crate::parser::{self, Token};
+use crate::pathindex::PathIndex;
+use crate::specs;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
 use std::fs;
-use std::os::unix::fs::PermissionsExt; // For checking executable permission on Unix-like systems
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
 
-// An enum to determine what kind of paths we should suggest.
+/// Which paths to suggest.
 #[derive(PartialEq)]
-enum PathFilter {
+pub enum PathFilter {
+    /// Files and directories.
     All,
+    /// Directories only, as for `cd`.
     DirectoriesOnly,
 }
 
+/// Where a suggestion came from, which decides its icon and how it applies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SuggestionSource {
+    /// Paths, commands, flags and other live lookups.
+    Completion,
+    /// An argument used with the same command earlier.
+    History,
+    /// A `[templates]` command for the file on the line; replaces the line.
+    Template,
+    /// A frequently visited directory, for `cd` and `pushd`.
+    Frecent,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+/// One entry of the completion popup.
+pub struct Suggestion {
+    /// The word (or, for templates, the line) it inserts.
+    pub text: String,
+    /// Where it came from.
+    pub source: SuggestionSource,
+}
+
+/// Suggestions computed off the UI thread for a given input line.
+pub struct CompletionResult {
+    /// The input line they were computed for.
+    pub input: String,
+    /// Directory relative paths were completed against.
+    pub cwd: PathBuf,
+    /// Best first.
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// How long a `[completions]` command may run before it is killed.
+const USER_COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// One `[completions]` entry.
+#[derive(Clone, Debug)]
+pub enum UserCompletion {
+    /// `cmd = ["a", "b"]`: a fixed word list.
+    Words(Vec<String>),
+    /// `cmd = "shell command"`: run with `sh -c`, one suggestion per output
+    /// line. Like bash's `complete -C`, it gets the command, the word being
+    /// completed and the previous word as `$1 $2 $3`, and the line as $COMP_LINE.
+    Command(String),
+}
+
+/// Commands offered for files by extension, from the `[templates]` table.
+/// `{}` stands for the path; without it the path is appended.
+#[derive(Clone, Debug, Default)]
+pub struct FileTemplates(Vec<(String, Vec<String>)>);
+
+impl FileTemplates {
+    /// Reads the `[templates]` table; bad entries are skipped.
+    pub fn from_table(tbl: &toml::value::Table) -> Self {
+        let mut entries: Vec<(String, Vec<String>)> = tbl
+            .iter()
+            .filter_map(|(ext, v)| {
+                let commands = match v {
+                    toml::Value::String(command) => vec![command.clone()],
+                    toml::Value::Array(items) => items
+                        .iter()
+                        .filter_map(|i| i.as_str().map(str::to_string))
+                        .collect(),
+                    _ => return None,
+                };
+                let ext = format!(".{}", ext.trim_start_matches('.'));
+                Some((ext, commands))
+            })
+            .collect();
+        // Longest first, so `.tar.gz` wins over `.gz`.
+        entries.sort_by_key(|(ext, _)| std::cmp::Reverse(ext.len()));
+        Self(entries)
+    }
+
+    /// The template commands for `path`, filled in.
+    pub fn for_path(&self, path: &str) -> Vec<String> {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let Some((_, commands)) = self.0.iter().find(|(ext, _)| name.ends_with(ext.as_str()))
+        else {
+            return Vec::new();
+        };
+        let quoted = parser::quote(path);
+        commands
+            .iter()
+            .map(|command| {
+                if command.contains("{}") {
+                    command.replace("{}", &quoted)
+                } else {
+                    format!("{command} {quoted}")
+                }
+            })
+            .collect()
+    }
+}
+
+/// User-defined completions from the `[completions]` table, keyed by command.
+#[derive(Clone, Debug, Default)]
+pub struct UserCompletions(HashMap<String, UserCompletion>);
+
+impl UserCompletions {
+    /// Reads the `[completions]` table; bad entries are skipped.
+    pub fn from_table(tbl: &toml::value::Table) -> Self {
+        let entries = tbl
+            .iter()
+            .filter_map(|(cmd, v)| {
+                let completion = match v {
+                    toml::Value::String(command) => UserCompletion::Command(command.clone()),
+                    toml::Value::Array(items) => UserCompletion::Words(
+                        items
+                            .iter()
+                            .filter_map(|i| i.as_str().map(str::to_string))
+                            .collect(),
+                    ),
+                    _ => return None,
+                };
+                Some((cmd.clone(), completion))
+            })
+            .collect();
+        Self(entries)
+    }
+
+    fn suggest(
+        &self,
+        input_buffer: &str,
+        preceding: &[&str],
+        token: &str,
+        cwd: &Path,
+    ) -> Option<Vec<String>> {
+        let command = preceding.first()?;
+        let words = match self.0.get(*command)? {
+            UserCompletion::Words(words) => words.clone(),
+            UserCompletion::Command(script) => {
+                let previous = preceding.last().copied().unwrap_or_default();
+                run_completion_command(script, [command, token, previous], input_buffer, cwd)
+            }
+        };
+        Some(words.into_iter().filter(|w| w.starts_with(token)).collect())
+    }
+}
+
+fn run_completion_command(script: &str, args: [&str; 3], line: &str, cwd: &Path) -> Vec<String> {
+    let child = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .arg("sh")
+        .args(args)
+        .env("COMP_LINE", line)
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else {
+        return Vec::new();
+    };
+    // Read while it runs: a command with more output than the pipe holds
+    // would otherwise block on write until the timeout.
+    let (tx, rx) = mpsc::channel();
+    if let Some(mut stdout) = child.stdout.take() {
+        std::thread::spawn(move || {
+            let mut out = String::new();
+            let _ = stdout.read_to_string(&mut out);
+            let _ = tx.send(out);
+        });
+    }
+    let out = rx.recv_timeout(USER_COMMAND_TIMEOUT).unwrap_or_default();
+    // Done writing is as good as done; don't wait on anything it left behind.
+    let _ = child.kill();
+    let _ = child.wait();
+    out.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Most history-derived arguments offered at once.
+const HISTORY_ARGUMENT_LIMIT: usize = 10;
+/// Most visited directories offered to `cd` besides the local ones.
+const FRECENT_LIMIT: usize = 8;
+
+/// The completion popup and what feeds it.
 #[derive(Default, Serialize, Deserialize)]
 pub struct CompletionState {
+    /// The popup is open.
     pub active: bool,
-    pub suggestions: Vec<String>,
+    /// What the popup lists.
+    pub suggestions: Vec<Suggestion>,
+    /// The highlighted suggestion.
     pub selected_index: usize,
+    #[serde(skip)]
+    flags: FlagCache,
+    /// Input whose flag completion is waiting on a `--help` fetch.
+    #[serde(skip)]
+    pending_flags: Option<String>,
+    /// Input whose suggestions are still being generated.
+    #[serde(skip)]
+    requested: Option<String>,
+    /// `[completions]`.
+    #[serde(skip)]
+    pub user: UserCompletions,
+    /// Commands on `$PATH`.
+    #[serde(skip)]
+    pub path_index: PathIndex,
+    /// `[templates]`.
+    #[serde(skip)]
+    pub templates: FileTemplates,
+    /// `[shell.words] completion`: characters after which a path argument
+    /// is completed on its own, e.g. `=` in `--out=bu`.
+    #[serde(skip)]
+    pub separators: String,
+    /// Directory of the shown or requested suggestions; they go stale when
+    /// the shell's cwd moves on.
+    #[serde(skip)]
+    pub cwd: Option<PathBuf>,
+    /// Visited directories, best first (see `frecency`).
+    #[serde(skip)]
+    pub directories: Vec<PathBuf>,
+}
+
+/// The command and the `-`-prefixed token being completed, if any.
+fn flag_context(input_buffer: &str) -> Option<(&str, &str)> {
+    if input_buffer.ends_with(' ') {
+        return None;
+    }
+    let mut words = input_buffer.split_whitespace();
+    let command = words.next()?;
+    let token = words.next_back().filter(|t| t.starts_with('-'))?;
+    Some((command, token))
 }
 
 impl CompletionState {
+    /// Closed, with nothing configured.
     pub fn new() -> Self {
         Self::default()
     }
 
-    /// Triggers the new, context-aware completion logic.
-    pub fn start_completion(&mut self, input_buffer: &str, cwd: &Path) {
+    /// Starts generating suggestions on the blocking pool; directory walks on
+    /// slow filesystems must never stall the UI. Results come back through
+    /// `tx` and are applied by `receive`.
+    pub fn start_completion(
+        &mut self,
+        input_buffer: &str,
+        cwd: &Path,
+        history: &[String],
+        tx: UnboundedSender<CompletionResult>,
+    ) {
+        self.pending_flags = None;
+        self.requested = Some(input_buffer.to_string());
+        self.cwd = Some(cwd.to_path_buf());
+        let flags = self.flags.clone();
+        let user = self.user.clone();
+        let path_index = self.path_index.clone();
+        let templates = self.templates.clone();
+        let separators = self.separators.clone();
+        let directories = self.directories.clone();
+        let input = input_buffer.to_string();
+        let cwd = cwd.to_path_buf();
+        // Only lines run with the same command can contribute arguments.
+        let related: Vec<String> = input_buffer
+            .split_whitespace()
+            .next()
+            .map(|command| {
+                let prefix = format!("{command} ");
+                history
+                    .iter()
+                    .filter(|line| line.starts_with(&prefix))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        tokio::task::spawn_blocking(move || {
+            let mut suggestions: Vec<Suggestion> =
+                generate_suggestions(&flags, &user, &path_index, &separators, &input, &cwd)
+                    .into_iter()
+                    .map(|text| Suggestion {
+                        text,
+                        source: SuggestionSource::Completion,
+                    })
+                    .collect();
+            for text in history_arguments(&related, &input) {
+                if !suggestions.iter().any(|s| s.text == text) {
+                    suggestions.push(Suggestion {
+                        text,
+                        source: SuggestionSource::History,
+                    });
+                }
+            }
+            for text in frecent_directories(&directories, &input, &cwd) {
+                if !suggestions.iter().any(|s| s.text == text) {
+                    suggestions.push(Suggestion {
+                        text,
+                        source: SuggestionSource::Frecent,
+                    });
+                }
+            }
+            // A lone, already complete file name also gets its templates.
+            let path = input.trim_end();
+            if !path.is_empty() && !path.contains(char::is_whitespace) && cwd.join(path).is_file()
+            {
+                suggestions.extend(templates.for_path(path).into_iter().map(|text| Suggestion {
+                    text,
+                    source: SuggestionSource::Template,
+                }));
+            }
+            let _ = tx.send(CompletionResult {
+                input,
+                cwd,
+                suggestions,
+            });
+        });
+    }
+
+    /// Opens the menu with the templates for the last word of the line.
+    /// Returns false if its extension has none.
+    pub fn offer_templates(&mut self, input_buffer: &str) -> bool {
+        let Some(path) = input_buffer.split_whitespace().last() else {
+            return false;
+        };
+        let suggestions: Vec<Suggestion> = self
+            .templates
+            .for_path(path)
+            .into_iter()
+            .map(|text| Suggestion {
+                text,
+                source: SuggestionSource::Template,
+            })
+            .collect();
+        if suggestions.is_empty() {
+            return false;
+        }
+        self.requested = None;
+        self.cwd = None;
+        self.selected_index = 0;
+        self.suggestions = suggestions;
         self.active = true;
+        true
+    }
+
+    /// Opens the menu with finished suggestions, unless the user has typed
+    /// on or changed directory since asking. Returns true if the state changed.
+    pub fn receive(&mut self, result: CompletionResult, input_buffer: &str, cwd: &Path) -> bool {
+        if self.requested.as_deref() != Some(result.input.as_str())
+            || result.input != input_buffer
+            || result.cwd != cwd
+        {
+            return false;
+        }
+        self.requested = None;
         self.selected_index = 0;
-        self.suggestions = self.generate_suggestions(input_buffer, cwd);
+        self.suggestions = result.suggestions;
+        self.active = !self.suggestions.is_empty();
+        if !self.active
+            && let Some((command, _)) = flag_context(input_buffer)
+            && !self.flags.is_ready(command)
+        {
+            self.pending_flags = Some(input_buffer.to_string());
+        }
+        true
+    }
 
-        // If there's only one suggestion, apply it immediately.
-        if self.suggestions.len() == 1 {
-            // We need a mutable buffer to apply, but we can't get one here.
-            // This is a candidate for a future enhancement. For now, show the menu.
+    /// Asks again once a background flag fetch for `input_buffer` finishes.
+    pub fn poll_pending(
+        &mut self,
+        input_buffer: &str,
+        cwd: &Path,
+        history: &[String],
+        tx: &UnboundedSender<CompletionResult>,
+    ) {
+        let Some(pending) = &self.pending_flags else {
+            return;
+        };
+        if pending != input_buffer {
+            // The user kept typing; the old request no longer applies.
+            self.pending_flags = None;
+            return;
+        }
+        if let Some((command, _)) = flag_context(input_buffer)
+            && self.flags.is_ready(command)
+        {
+            self.start_completion(input_buffer, cwd, history, tx.clone());
         }
+    }
 
-        if self.suggestions.is_empty() {
-            self.active = false;
+    /// Recomputes open or pending suggestions once `cwd` is no longer the
+    /// directory they were made for. Returns true if it did.
+    pub fn follow_cwd(
+        &mut self,
+        input_buffer: &str,
+        cwd: &Path,
+        history: &[String],
+        tx: &UnboundedSender<CompletionResult>,
+    ) -> bool {
+        let open = self.active || self.requested.is_some();
+        if !open || self.cwd.as_deref().is_none_or(|c| c == cwd) {
+            return false;
         }
+        self.stop_completion();
+        self.start_completion(input_buffer, cwd, history, tx.clone());
+        true
     }
 
+    /// Closes the popup.
     pub fn stop_completion(&mut self) {
         self.active = false;
         self.suggestions.clear();
     }
 
+    /// The selection in words, e.g. `3/14: src/ (directory)`, for
+    /// `[ui.completion] announce`.
+    pub fn announcement(&self) -> Option<String> {
+        let selected = self.suggestions.get(self.selected_index)?;
+        let kind = match selected.source {
+            SuggestionSource::History => " (from history)",
+            SuggestionSource::Template => " (template)",
+            SuggestionSource::Frecent => " (frequent directory)",
+            _ if selected.text.ends_with('/') => " (directory)",
+            _ => "",
+        };
+        Some(format!(
+            "{}/{}: {}{kind}",
+            self.selected_index + 1,
+            self.suggestions.len(),
+            selected.text
+        ))
+    }
+
+    /// Selects the next suggestion, wrapping around.
     pub fn next_suggestion(&mut self) {
         if !self.suggestions.is_empty() {
             self.selected_index = (self.selected_index + 1) % self.suggestions.len();
         }
     }
 
+    /// Selects the previous suggestion, wrapping around.
     pub fn previous_suggestion(&mut self) {
         if !self.suggestions.is_empty() {
             self.selected_index =
@@ -62,7 +472,11 @@ impl CompletionState {
 
     /// Applies the selected suggestion to the input buffer.
     pub fn apply_completion(&self, current_input: &str) -> Option<(String, usize)> {
-        let suggestion = self.suggestions.get(self.selected_index)?;
+        let selected = self.suggestions.get(self.selected_index)?;
+        let suggestion = &selected.text;
+        if selected.source == SuggestionSource::Template {
+            return Some((suggestion.clone(), suggestion.len()));
+        }
 
         // Find the start of the word being completed.
         let mut last_word_start = current_input
@@ -76,8 +490,9 @@ impl CompletionState {
         let mut new_input = current_input[..last_word_start].to_string();
         new_input.push_str(suggestion);
 
-        // Add a space after the completion unless it's a directory.
-        if !suggestion.ends_with('/') {
+        // Add a space after the completion unless more of the word follows
+        // (a directory, or a `host:` waiting for its remote path).
+        if !suggestion.ends_with(['/', ':']) {
             new_input.push(' ');
         }
 
@@ -85,134 +500,323 @@ impl CompletionState {
 
         Some((new_input, new_cursor_pos))
     }
+}
 
-    /// The new context-aware suggestion generation engine.
-    fn generate_suggestions(&self, input_buffer: &str, cwd: &Path) -> Vec<String> {
-        let words: Vec<&str> = input_buffer.split_whitespace().collect();
+/// The context-aware suggestion generation engine. Runs on the blocking pool.
+fn generate_suggestions(
+    flags: &FlagCache,
+    user: &UserCompletions,
+    path_index: &PathIndex,
+    separators: &str,
+    input_buffer: &str,
+    cwd: &Path,
+) -> Vec<String> {
+    let words: Vec<&str> = input_buffer.split_whitespace().collect();
 
-        // The token to complete is the last "word", unless the line ends with a space.
-        let token_to_complete = if input_buffer.ends_with(' ') {
-            ""
-        } else {
-            words.last().unwrap_or(&"")
-        };
+    // The token to complete is the last "word", unless the line ends with a space.
+    let token_to_complete = if input_buffer.ends_with(' ') {
+        ""
+    } else {
+        words.last().unwrap_or(&"")
+    };
 
-        // Determine if we are typing the very first word (the command).
-        let is_completing_command =
-            words.is_empty() || (words.len() == 1 && !input_buffer.ends_with(' '));
-
-        if is_completing_command {
-            self.suggest_executables(token_to_complete)
-        } else {
-            // It's an argument, so complete a path.
-            let command = words.first().unwrap_or(&"");
-            let filter = match *command {
-                "cd" => PathFilter::DirectoriesOnly,
-                _ => PathFilter::All, // Most commands take files or directories
-            };
-            self.suggest_paths(token_to_complete, cwd, filter)
-        }
+    // Words before the token being completed.
+    let preceding = &words[..words.len() - usize::from(!token_to_complete.is_empty())];
+
+    // Determine if we are typing the very first word (the command).
+    let is_completing_command =
+        words.is_empty() || (words.len() == 1 && !input_buffer.ends_with(' '));
+
+    if let Some(suggestions) = suggest_variables(token_to_complete) {
+        suggestions
+    } else if is_completing_command {
+        suggest_executables(path_index, token_to_complete)
+    } else if let Some(at) = token_to_complete.rfind(|c| separators.contains(c)) {
+        // The part after the separator is a path; keep what precedes it.
+        let sep_len = token_to_complete[at..].chars().next().map_or(1, char::len_utf8);
+        let (head, tail) = token_to_complete.split_at(at + sep_len);
+        suggest_paths(tail, cwd, PathFilter::All)
+            .into_iter()
+            .map(|path| format!("{head}{path}"))
+            .collect()
+    } else if let Some((command, token)) = flag_context(input_buffer) {
+        suggest_flags(flags, command, token)
+    } else if let Some(suggestions) = user.suggest(input_buffer, preceding, token_to_complete, cwd)
+    {
+        suggestions
+    } else if let Some(suggestions) = suggest_remote(preceding, token_to_complete, cwd) {
+        suggestions
+    } else if let Some(suggestions) = specs::suggest(preceding, token_to_complete, cwd) {
+        suggestions
+    } else {
+        // It's an argument, so complete a path.
+        let command = words.first().unwrap_or(&"");
+        let filter = match *command {
+            "cd" | "pushd" => PathFilter::DirectoriesOnly,
+            _ => PathFilter::All, // Most commands take files or directories
+        };
+        suggest_paths(token_to_complete, cwd, filter)
     }
+}
 
-    /// Suggests executables from the system's $PATH.
-    fn suggest_executables(&self, partial_cmd: &str) -> Vec<String> {
-        let mut commands = std::collections::HashSet::new();
-        // Add built-ins
-        for cmd in ["cd", "pwd", "exit"] {
-            if cmd.starts_with(partial_cmd) {
-                commands.insert(cmd.to_string());
-            }
-        }
+/// For `cd` and `pushd`, the best visited directories whose path contains
+/// the word being typed, written as `cd` accepts them (`~/` for home).
+fn frecent_directories(directories: &[PathBuf], input_buffer: &str, cwd: &Path) -> Vec<String> {
+    let words: Vec<&str> = input_buffer.split_whitespace().collect();
+    let token = match words.as_slice() {
+        ["cd" | "pushd"] if input_buffer.ends_with(' ') => "",
+        ["cd" | "pushd", token] if !input_buffer.ends_with(' ') => token,
+        _ => return Vec::new(),
+    };
+    let token = token.to_lowercase();
+    directories
+        .iter()
+        .filter(|dir| dir.as_path() != cwd)
+        .map(|dir| format!("{}/", crate::state::tilde_path(dir).trim_end_matches('/')))
+        .filter(|text| text.to_lowercase().contains(&token))
+        .take(FRECENT_LIMIT)
+        .collect()
+}
 
-        if let Ok(path_var) = env::var("PATH") {
-            for path in env::split_paths(&path_var) {
-                if let Ok(entries) = fs::read_dir(path) {
-                    for entry in entries.filter_map(Result::ok) {
-                        if let Ok(metadata) = entry.metadata() {
-                            // On Unix, check the executable permission bit.
-                            let is_executable = metadata.permissions().mode() & 0o111 != 0;
-                            if metadata.is_file()
-                                && is_executable
-                                && let Some(name) = entry.file_name().to_str()
-                                && name.starts_with(partial_cmd)
-                            {
-                                commands.insert(name.to_string());
-                            }
-                        }
-                    }
+/// Arguments previously passed to the input's command that extend the
+/// token being completed, newest first. `related` holds history lines that
+/// start with that command.
+fn history_arguments(related: &[String], input_buffer: &str) -> Vec<String> {
+    let words: Vec<&str> = input_buffer.split_whitespace().collect();
+    let token = if input_buffer.ends_with(' ') {
+        ""
+    } else {
+        words.last().copied().unwrap_or("")
+    };
+    // Arguments only: not the command itself, and not `$VAR`s.
+    let completing_command = words.is_empty() || (words.len() == 1 && !input_buffer.ends_with(' '));
+    if completing_command || token.contains('$') {
+        return Vec::new();
+    }
+    let mut found: Vec<String> = Vec::new();
+    for line in related.iter().rev() {
+        let Ok(tokens) = parser::tokenize(line) else {
+            continue;
+        };
+        let args = tokens.into_iter().skip(1).filter_map(|t| match t {
+            Token::Word(w) => Some(w.text),
+            _ => None,
+        });
+        for arg in args {
+            if arg.starts_with(token)
+                && arg != token
+                && (token.starts_with('-') || !arg.starts_with('-'))
+                && !found.contains(&arg)
+            {
+                found.push(arg);
+                if found.len() == HISTORY_ARGUMENT_LIMIT {
+                    return found;
                 }
             }
         }
+    }
+    found
+}
+
+/// Suggests flags scraped from the command's help; empty while fetching.
+fn suggest_flags(flags: &FlagCache, command: &str, partial_flag: &str) -> Vec<String> {
+    flags
+        .lookup(command)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|flag| flag.starts_with(partial_flag))
+        .collect()
+}
+
+/// Variables halo expands itself, on top of the process environment.
+const HALO_VARIABLES: &[&str] = &["?", "HALO_TMP"];
+
+/// Completes `$NAME` / `${NAME}` at the end of the token, keeping whatever
+/// precedes the `$` (e.g. `--prefix=$HO` → `--prefix=$HOME`). Names come
+/// from the environment, the `export` builtin and halo's own variables.
+fn suggest_variables(token: &str) -> Option<Vec<String>> {
+    let dollar = token.rfind('$')?;
+    let (head, rest) = token.split_at(dollar + 1);
+    let (open, partial) = match rest.strip_prefix('{') {
+        Some(partial) => ("{", partial),
+        None => ("", rest),
+    };
+    if !partial.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    let close = if open.is_empty() { "" } else { "}" };
+    let mut names: Vec<String> = env::vars_os()
+        .filter_map(|(name, _)| name.into_string().ok())
+        .chain(exports::all().into_keys())
+        .chain(HALO_VARIABLES.iter().map(|s| s.to_string()))
+        .filter(|name| name.starts_with(partial))
+        .collect();
+    names.sort();
+    names.dedup();
+    Some(
+        names
+            .into_iter()
+            .map(|name| format!("{head}{open}{name}{close}"))
+            .collect(),
+    )
+}
 
-        let mut sorted_commands: Vec<String> = commands.into_iter().collect();
-        sorted_commands.sort();
-        sorted_commands
+/// Host completion for ssh-style commands. `ssh` takes only a host; `scp`
+/// and `rsync` mix `host:` targets with local paths. The token may carry a
+/// `user@` prefix, which is kept.
+fn suggest_remote(preceding: &[&str], token: &str, cwd: &Path) -> Option<Vec<String>> {
+    let remote_paths = match preceding.first()? {
+        &"ssh" => false,
+        &"scp" | &"rsync" => true,
+        _ => return None,
+    };
+    // Past the host already (`host:path`), or plainly a local path.
+    if token.contains(':') || token.contains('/') || token.starts_with('-') {
+        return None;
+    }
+    let (user, partial) = match token.split_once('@') {
+        Some((user, host)) => (format!("{user}@"), host),
+        None => (String::new(), token),
+    };
+    let suffix = if remote_paths { ":" } else { "" };
+    let mut results: Vec<String> = ssh_hosts()
+        .into_iter()
+        .filter(|host| host.starts_with(partial))
+        .map(|host| format!("{user}{host}{suffix}"))
+        .collect();
+    if remote_paths && user.is_empty() {
+        results.extend(suggest_paths(token, cwd, PathFilter::All));
     }
+    Some(results)
+}
 
-    /// Suggests file or directory paths.
-    fn suggest_paths(&self, partial_path: &str, cwd: &Path, filter: PathFilter) -> Vec<String> {
-        // Handle home directory expansion
-        let mut path_to_complete = PathBuf::new();
-        if let Some(after_home) = partial_path.strip_prefix('~') {
-            if let Some(home) = dirs::home_dir() {
-                path_to_complete.push(home);
-                // Add the rest of the path, skipping the tilde
-                path_to_complete.push(after_home);
+/// Host aliases from ~/.ssh/config plus hosts from ~/.ssh/known_hosts.
+fn ssh_hosts() -> Vec<String> {
+    let Some(ssh_dir) = dirs::home_dir().map(|home| home.join(".ssh")) else {
+        return Vec::new();
+    };
+    let mut hosts = std::collections::BTreeSet::new();
+    if let Ok(config) = fs::read_to_string(ssh_dir.join("config")) {
+        for line in config.lines() {
+            let mut fields = line.split_whitespace();
+            if fields
+                .next()
+                .is_some_and(|key| key.eq_ignore_ascii_case("host"))
+            {
+                hosts.extend(
+                    fields
+                        .filter(|h| !h.contains(['*', '?', '!']))
+                        .map(str::to_string),
+                );
             }
-        } else {
-            path_to_complete.push(partial_path);
         }
+    }
+    if let Ok(known) = fs::read_to_string(ssh_dir.join("known_hosts")) {
+        for line in known.lines() {
+            // Hashed entries (`|1|...`) can't be completed; markers like @revoked are skipped.
+            let Some(field) = line.split_whitespace().next() else {
+                continue;
+            };
+            if field.starts_with(['|', '@', '#']) {
+                continue;
+            }
+            for host in field.split(',') {
+                // `[host]:port` for non-standard ports.
+                let host = host
+                    .strip_prefix('[')
+                    .and_then(|h| h.split_once(']'))
+                    .map_or(host, |(h, _)| h);
+                hosts.insert(host.to_string());
+            }
+        }
+    }
+    hosts.into_iter().collect()
+}
 
-        let (search_dir, partial_name) = if partial_path.ends_with('/') || partial_path == "~" {
-            (cwd.join(&path_to_complete), "")
-        } else {
-            (
-                cwd.join(&path_to_complete)
-                    .parent()
-                    .unwrap_or(cwd)
-                    .to_path_buf(),
-                path_to_complete
-                    .file_name()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or(""),
-            )
-        };
+/// Commands handled by halo itself rather than looked up on $PATH.
+pub const BUILTINS: &[&str] = &[
+    "cd", "pwd", "exit", "jobs", "fg", "bg", "present", "history", "note", "notes", "set",
+    "alias", "unalias", "abbr", "theme", ":reload", "summary", "disk", "clear", "procs", "copy",
+    "title", ":perf", ":git", "log", "build", "tmp", "share", "split", "pane", "tab", "lock",
+    "session", "export", "unset", "pushd", "popd", "dirs", "z", "doctor", "diff", "handoff",
+];
 
-        if let Ok(entries) = fs::read_dir(&search_dir) {
-            let mut results: Vec<String> = entries
-                .filter_map(Result::ok)
-                .filter_map(|entry| {
-                    let file_name = entry.file_name().to_string_lossy().to_string();
-                    if file_name.starts_with(partial_name) {
-                        // Check if the entry matches the filter (All or Dirs only)
-                        let file_type = entry.file_type().ok()?;
-                        let is_dir = file_type.is_dir();
-                        if filter == PathFilter::DirectoriesOnly && !is_dir {
-                            return None;
-                        }
-
-                        // Determine the base path of the token being completed
-                        let mut suggestion_base = PathBuf::from(partial_path);
-                        if suggestion_base.file_name().is_some() {
-                            suggestion_base.pop();
-                        }
-
-                        let mut final_suggestion = suggestion_base.join(file_name);
-
-                        if is_dir {
-                            final_suggestion.push(""); // Appends a trailing slash
-                        }
-
-                        Some(final_suggestion.to_string_lossy().to_string())
-                    } else {
-                        None
-                    }
-                })
-                .collect();
-            results.sort();
-            return results;
+/// Suggests builtins and executables from the system's $PATH.
+fn suggest_executables(path_index: &PathIndex, partial_cmd: &str) -> Vec<String> {
+    let mut commands: Vec<String> = BUILTINS
+        .iter()
+        .filter(|cmd| cmd.starts_with(partial_cmd))
+        .map(|cmd| cmd.to_string())
+        .chain(path_index.starting_with(partial_cmd))
+        .collect();
+    commands.sort();
+    commands.dedup();
+    commands
+}
+
+/// Suggests file or directory paths.
+pub fn suggest_paths(partial_path: &str, cwd: &Path, filter: PathFilter) -> Vec<String> {
+    // Handle home directory expansion
+    let mut path_to_complete = PathBuf::new();
+    if let Some(after_home) = partial_path.strip_prefix('~') {
+        if let Some(home) = dirs::home_dir() {
+            path_to_complete.push(home);
+            // Add the rest of the path, skipping the tilde
+            path_to_complete.push(after_home);
         }
+    } else {
+        path_to_complete.push(partial_path);
+    }
+
+    let (search_dir, partial_name) = if partial_path.ends_with('/') || partial_path == "~" {
+        (cwd.join(&path_to_complete), "")
+    } else {
+        (
+            cwd.join(&path_to_complete)
+                .parent()
+                .unwrap_or(cwd)
+                .to_path_buf(),
+            path_to_complete
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or(""),
+        )
+    };
+
+    if let Ok(entries) = fs::read_dir(&search_dir) {
+        let mut results: Vec<String> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                if file_name.starts_with(partial_name) {
+                    // Check if the entry matches the filter (All or Dirs only)
+                    let file_type = entry.file_type().ok()?;
+                    let is_dir = file_type.is_dir();
+                    if filter == PathFilter::DirectoriesOnly && !is_dir {
+                        return None;
+                    }
+
+                    // Determine the base path of the token being completed
+                    let mut suggestion_base = PathBuf::from(partial_path);
+                    if suggestion_base.file_name().is_some() {
+                        suggestion_base.pop();
+                    }
+
+                    let mut final_suggestion = suggestion_base.join(file_name);
+
+                    if is_dir {
+                        final_suggestion.push(""); // Appends a trailing slash
+                    }
 
-        Vec::new()
+                    Some(final_suggestion.to_string_lossy().to_string())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        results.sort();
+        return results;
     }
+
+    Vec::new()
 }
@@ -0,0 +1,110 @@
+// src/crash.rs
+
+//! Crash reports. The panic hook cannot reach the app's state, so the last
+//! few commands are mirrored here as they start and finish; on a panic or
+//! a fatal error they are written with the version, OS and a backtrace to a
+//! new file under ~/.local/share/halo/crash/ for attaching to bug reports.
+
+use crate::command::CommandLog;
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Commands included in a report.
+const KEPT_COMMANDS: usize = 20;
+/// Trailing output lines kept per command.
+const KEPT_LINES: usize = 10;
+/// Longest output line kept, in characters.
+const MAX_LINE: usize = 200;
+
+struct Recorded {
+    started: Option<Instant>,
+    summary: String,
+}
+
+static RECENT: Mutex<Vec<Recorded>> = Mutex::new(Vec::new());
+
+/// Mirrors `log` into the report, replacing its earlier copy.
+pub fn record(log: &CommandLog) {
+    let Ok(mut recent) = RECENT.lock() else {
+        return;
+    };
+    let summary = summarize(log);
+    if log.started_at.is_some()
+        && let Some(entry) = recent.iter_mut().find(|r| r.started == log.started_at)
+    {
+        entry.summary = summary;
+        return;
+    }
+    if recent.len() == KEPT_COMMANDS {
+        recent.remove(0);
+    }
+    recent.push(Recorded {
+        started: log.started_at,
+        summary,
+    });
+}
+
+fn summarize(log: &CommandLog) -> String {
+    let status = if log.is_running {
+        "running".to_string()
+    } else {
+        match (log.exit_code, log.signal) {
+            (Some(code), _) => format!("exit {code}"),
+            (None, Some(signal)) => format!("signal {signal}"),
+            (None, None) => "exit ?".to_string(),
+        }
+    };
+    let mut out = format!("$ {}  ({}, in {})\n", log.command, status, log.cwd.display());
+    let lines: Vec<&str> = log.output.lines().collect();
+    let skipped = lines.len().saturating_sub(KEPT_LINES);
+    if skipped > 0 {
+        let _ = writeln!(out, "  [... {skipped} lines]");
+    }
+    for line in &lines[skipped..] {
+        let mut kept: String = line.chars().take(MAX_LINE).collect();
+        if kept.len() < line.len() {
+            kept.push('…');
+        }
+        let _ = writeln!(out, "  {kept}");
+    }
+    out
+}
+
+/// Writes a report for `what` (the panic message or error) and returns
+/// its path. Reports are never overwritten; each crash gets its own file.
+pub fn write_report(what: &str) -> Option<PathBuf> {
+    let dir = dirs::data_dir()?.join("halo/crash");
+    std::fs::create_dir_all(&dir).ok()?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let path = dir.join(format!("crash-{stamp}-{}.txt", std::process::id()));
+    let mut file = OpenOptions::new()
+        .append(true)
+        .create_new(true)
+        .open(&path)
+        .ok()?;
+
+    let mut report = format!(
+        "halo {} crash report, {}\nos: {} {}\n\n{what}\n\n",
+        env!("CARGO_PKG_VERSION"),
+        chrono::Local::now().to_rfc3339(),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+    );
+    report.push_str("backtrace:\n");
+    let _ = writeln!(report, "{}", std::backtrace::Backtrace::force_capture());
+    report.push_str("\nrecent commands (oldest first):\n");
+    // A panic while the lock was held must not lose the report.
+    let recent = RECENT.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if recent.is_empty() {
+        report.push_str("  none\n");
+    }
+    for entry in recent.iter() {
+        report.push_str(&entry.summary);
+    }
+    file.write_all(report.as_bytes()).ok()?;
+    Some(path)
+}
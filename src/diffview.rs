@@ -0,0 +1,151 @@
+// src/diffview.rs
+
+//! A scrollable popup with the unified diff of one file, made with git2:
+//! `diff <file>` shows everything since HEAD (staged or not), Enter in the
+//! git panel the selected row's own changes.
+
+use crate::error::AppResult;
+use crate::gitpanel::{Area, FileRow};
+use anyhow::anyhow;
+use git2::{Diff, DiffFormat, DiffOptions, Repository};
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineKind {
+    /// `diff --git`, `---`, `+++` and the like.
+    Header,
+    /// `@@ -a,b +c,d @@`
+    Hunk,
+    Added,
+    Removed,
+    Context,
+}
+
+pub struct DiffView {
+    pub title: String,
+    pub lines: Vec<(LineKind, String)>,
+    /// First line on screen.
+    pub top: usize,
+    /// Rows the last frame had for text.
+    pub height: usize,
+}
+
+impl DiffView {
+    /// `diff <file>`: HEAD against the work tree for `path` (relative to
+    /// `cwd`), untracked files included as all added.
+    pub fn for_file(cwd: &Path, path: &str) -> AppResult<Self> {
+        let (repo, spec) = locate(cwd, path)?;
+        let mut opts = options(&spec);
+        let head = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        let diff = repo.diff_tree_to_workdir_with_index(head.as_ref(), Some(&mut opts))?;
+        Self::from_diff(format!("diff {spec}"), &diff)
+    }
+
+    /// Whether `diff <path>` is one for the viewer: an existing file or
+    /// directory inside a git work tree, rather than an option or `-`.
+    pub fn applies(cwd: &Path, path: &str) -> bool {
+        !path.starts_with('-') && locate(cwd, path).is_ok()
+    }
+
+    /// The changes behind one row of the git panel: staged ones against
+    /// HEAD, the rest against the index.
+    pub fn for_row(root: &Path, row: &FileRow) -> AppResult<Self> {
+        let repo = Repository::open(root)?;
+        let mut opts = options(&row.path);
+        let diff = match row.area {
+            Area::Staged => {
+                let head = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+                repo.diff_tree_to_index(head.as_ref(), None, Some(&mut opts))?
+            }
+            Area::Unstaged | Area::Untracked | Area::Conflicted => {
+                repo.diff_index_to_workdir(None, Some(&mut opts))?
+            }
+        };
+        let title = format!("{} {}", row.area.label().to_lowercase(), row.path);
+        Self::from_diff(title, &diff)
+    }
+
+    fn from_diff(title: String, diff: &Diff) -> AppResult<Self> {
+        let mut lines = Vec::new();
+        diff.print(DiffFormat::Patch, |_, _, line| {
+            let kind = match line.origin() {
+                '+' | '>' => LineKind::Added,
+                '-' | '<' => LineKind::Removed,
+                'H' => LineKind::Hunk,
+                'F' | 'B' => LineKind::Header,
+                _ => LineKind::Context,
+            };
+            let prefix = match line.origin() {
+                c @ ('+' | '-' | ' ') => c.to_string(),
+                _ => String::new(),
+            };
+            let content = String::from_utf8_lossy(line.content());
+            for text in content.lines() {
+                lines.push((kind, format!("{prefix}{}", text.replace('\t', "    "))));
+            }
+            true
+        })?;
+        if lines.is_empty() {
+            lines.push((LineKind::Context, "no changes".to_string()));
+        }
+        Ok(Self {
+            title,
+            lines,
+            top: 0,
+            height: 1,
+        })
+    }
+
+    fn max_top(&self) -> usize {
+        self.lines.len().saturating_sub(self.height.max(1))
+    }
+
+    /// Moves the view `delta` lines down (negative: up).
+    pub fn scroll(&mut self, delta: isize) {
+        self.top = self.top.saturating_add_signed(delta).min(self.max_top());
+    }
+
+    pub fn page(&mut self, down: bool) {
+        let rows = self.height.max(1) as isize;
+        self.scroll(if down { rows } else { -rows });
+    }
+
+    pub fn go_to_start(&mut self) {
+        self.top = 0;
+    }
+
+    pub fn go_to_end(&mut self) {
+        self.top = self.max_top();
+    }
+
+    pub fn set_height(&mut self, height: usize) {
+        self.height = height;
+        self.top = self.top.min(self.max_top());
+    }
+}
+
+/// The repository around `path` and `path` relative to its work tree.
+fn locate(cwd: &Path, path: &str) -> AppResult<(Repository, String)> {
+    let full = cwd.join(path);
+    let repo = Repository::discover(full.parent().unwrap_or(cwd))
+        .map_err(|_| anyhow!("{path}: not in a git repository"))?;
+    let root = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("bare repository"))?
+        .canonicalize()?;
+    let full = full.canonicalize().map_err(|e| anyhow!("{path}: {e}"))?;
+    let relative = full
+        .strip_prefix(&root)
+        .map_err(|_| anyhow!("{path}: outside the repository"))?;
+    Ok((repo, relative.to_string_lossy().to_string()))
+}
+
+fn options(path: &str) -> DiffOptions {
+    let mut opts = DiffOptions::new();
+    opts.pathspec(path)
+        .disable_pathspec_match(!path.ends_with('/'))
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    opts
+}
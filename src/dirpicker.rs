@@ -0,0 +1,88 @@
+// src/dirpicker.rs
+
+//! Ctrl+G: a popup of recently visited directories, best-ranked first,
+//! narrowed by typing. The query matches fuzzily, as a subsequence of the
+//! path; Enter changes to the selected directory.
+
+use std::path::{Path, PathBuf};
+
+pub struct DirPicker {
+    pub query: String,
+    /// Index into the current matches.
+    pub selected: usize,
+    /// Candidates, best first.
+    dirs: Vec<PathBuf>,
+}
+
+impl DirPicker {
+    pub fn new(dirs: Vec<PathBuf>) -> Self {
+        Self {
+            query: String::new(),
+            selected: 0,
+            dirs,
+        }
+    }
+
+    /// Directories matching the query, best first. Those matching within
+    /// their last component come before those that only match across the
+    /// path, and tighter matches before looser ones; ties keep the rank.
+    pub fn matches(&self) -> Vec<&Path> {
+        if self.query.is_empty() {
+            return self.dirs.iter().map(PathBuf::as_path).collect();
+        }
+        let query = self.query.to_lowercase();
+        let mut found: Vec<(bool, usize, &Path)> = self
+            .dirs
+            .iter()
+            .filter_map(|dir| {
+                let name = dir.file_name().map(|n| n.to_string_lossy().to_lowercase());
+                let in_name = name.is_some_and(|name| spread(&name, &query).is_some());
+                let spread = spread(&dir.to_string_lossy().to_lowercase(), &query)?;
+                Some((!in_name, spread, dir.as_path()))
+            })
+            .collect();
+        found.sort_by_key(|&(outside_name, spread, _)| (outside_name, spread));
+        found.into_iter().map(|(_, _, dir)| dir).collect()
+    }
+
+    pub fn select_next(&mut self, matches: usize) {
+        if self.selected + 1 < matches {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn selected_dir(&self) -> Option<PathBuf> {
+        self.matches().get(self.selected).map(|dir| dir.to_path_buf())
+    }
+}
+
+/// The length of the shortest stretch of `text` holding `query` as a
+/// subsequence (matching greedily from each start), or `None`.
+fn spread(text: &str, query: &str) -> Option<usize> {
+    let text: Vec<char> = text.chars().collect();
+    let query: Vec<char> = query.chars().collect();
+    let first = *query.first()?;
+    let mut best: Option<usize> = None;
+    for start in (0..text.len()).filter(|&i| text[i] == first) {
+        let mut next = 1;
+        let mut end = start;
+        for (i, &c) in text.iter().enumerate().skip(start + 1) {
+            if next == query.len() {
+                break;
+            }
+            if c == query[next] {
+                next += 1;
+                end = i;
+            }
+        }
+        if next == query.len() {
+            let len = end - start + 1;
+            best = Some(best.map_or(len, |b| b.min(len)));
+        }
+    }
+    best
+}
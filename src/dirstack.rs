@@ -0,0 +1,50 @@
+// src/dirstack.rs
+
+//! The directory stack behind `pushd`, `popd`, `dirs` and `cd -`. Every
+//! `cd` pushes the directory it leaves, as zsh's `AUTO_PUSHD` does, so the
+//! way back is always there without planning ahead with `pushd`.
+
+use std::path::{Path, PathBuf};
+
+/// Older entries fall off the bottom.
+const LIMIT: usize = 20;
+
+#[derive(Default)]
+pub struct DirStack {
+    /// Bottom first; the top is `dirs +1` (`+0` being the cwd itself).
+    dirs: Vec<PathBuf>,
+}
+
+impl DirStack {
+    /// Pushes `dir`, moving it to the top if it is already further down.
+    pub fn push(&mut self, dir: &Path) {
+        self.dirs.retain(|d| d != dir);
+        self.dirs.push(dir.to_path_buf());
+        if self.dirs.len() > LIMIT {
+            self.dirs.remove(0);
+        }
+    }
+
+    pub fn top(&self) -> Option<&Path> {
+        self.dirs.last().map(PathBuf::as_path)
+    }
+
+    pub fn pop(&mut self) -> Option<PathBuf> {
+        self.dirs.pop()
+    }
+
+    /// Removes entry `n` (1 is the top), as in `popd +N`.
+    pub fn take(&mut self, n: usize) -> Option<PathBuf> {
+        let index = self.dirs.len().checked_sub(n).filter(|_| n > 0)?;
+        Some(self.dirs.remove(index))
+    }
+
+    pub fn clear(&mut self) {
+        self.dirs.clear();
+    }
+
+    /// Entries from the top down.
+    pub fn iter(&self) -> impl Iterator<Item = &Path> {
+        self.dirs.iter().rev().map(PathBuf::as_path)
+    }
+}
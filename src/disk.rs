@@ -0,0 +1,176 @@
+// src/disk.rs
+
+//! Mounted filesystems and their usage, for the `disk` builtin.
+
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Kernel and virtual filesystems that say nothing about disk space.
+const PSEUDO_FS: &[&str] = &[
+    "autofs", "binfmt_misc", "bpf", "cgroup", "cgroup2", "configfs", "debugfs", "devpts",
+    "devtmpfs", "efivarfs", "fusectl", "hugetlbfs", "mqueue", "nsfs", "proc", "pstore",
+    "ramfs", "rpc_pipefs", "securityfs", "selinuxfs", "squashfs", "sysfs", "tracefs",
+];
+
+#[derive(Clone, Debug)]
+pub struct Mount {
+    pub mount_point: String,
+    pub fs_type: String,
+    pub total: u64,
+    pub used: u64,
+    pub avail: u64,
+}
+
+impl Mount {
+    /// Share of the space usable by ordinary users that is taken, like
+    /// `df`'s Use% (reserved blocks count as neither).
+    pub fn ratio(&self) -> f64 {
+        let usable = self.used + self.avail;
+        if usable == 0 {
+            0.0
+        } else {
+            self.used as f64 / usable as f64
+        }
+    }
+}
+
+/// Real filesystems from the mount table, one per device.
+pub fn mounts() -> std::io::Result<Vec<Mount>> {
+    let table = fs::read_to_string("/proc/self/mounts")?;
+    let mut devices = HashSet::new();
+    let mut mounts = Vec::new();
+    for line in table.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(device), Some(mount_point), Some(fs_type)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        if PSEUDO_FS.contains(&fs_type) {
+            continue;
+        }
+        let mount_point = unescape(mount_point);
+        let Some((total, used, avail)) = usage(Path::new(&mount_point)) else {
+            continue;
+        };
+        // Bind mounts repeat their device; the first mount point is enough.
+        if total == 0 || !devices.insert(device.to_string()) {
+            continue;
+        }
+        mounts.push(Mount {
+            mount_point,
+            fs_type: fs_type.to_string(),
+            total,
+            used,
+            avail,
+        });
+    }
+    Ok(mounts)
+}
+
+/// Total, used and available bytes of the filesystem holding `path`.
+fn usage(path: &Path) -> Option<(u64, u64, u64)> {
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs only writes into the zeroed struct we own.
+    let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut st) } != 0 {
+        return None;
+    }
+    let frsize = st.f_frsize as u64;
+    let total = st.f_blocks as u64 * frsize;
+    let free = st.f_bfree as u64 * frsize;
+    let avail = st.f_bavail as u64 * frsize;
+    Some((total, total.saturating_sub(free), avail))
+}
+
+/// The mount table writes spaces and tabs in paths as `\040`, `\011`.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && let Some(octal) = field.get(i + 1..i + 4)
+            && let Ok(byte) = u8::from_str_radix(octal, 8)
+        {
+            out.push(byte);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// `512B`, `4.0K`, `45.2G`: powers of 1024, like `df -h`.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["K", "M", "G", "T", "P"];
+    if bytes < 1024 {
+        return format!("{bytes}B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1}{}", UNITS[unit])
+}
+
+/// Columns of the usage gauge: a `100%` label and the bar.
+pub const GAUGE_WIDTH: usize = 26;
+const MAX_MOUNT_WIDTH: usize = 30;
+
+/// Width of the mount point column for `mounts`.
+pub fn mount_width(mounts: &[Mount]) -> usize {
+    mounts
+        .iter()
+        .map(|m| m.mount_point.chars().count())
+        .max()
+        .unwrap_or(0)
+        .clamp("Mounted on".len(), MAX_MOUNT_WIDTH)
+}
+
+pub fn header(mount_width: usize) -> String {
+    format!(
+        "{:<mount_width$} {:<GAUGE_WIDTH$} {:>7}   {:<7} Type",
+        "Mounted on", "Use", "Used", "Size"
+    )
+}
+
+/// One table row with `gauge` (GAUGE_WIDTH columns) in the usage column.
+pub fn row(mount: &Mount, mount_width: usize, gauge: &str) -> String {
+    let mut name: String = mount.mount_point.chars().take(mount_width).collect();
+    if mount.mount_point.chars().count() > mount_width {
+        name.pop();
+        name.push('…');
+    }
+    format!(
+        "{name:<mount_width$} {gauge:<GAUGE_WIDTH$} {:>7} / {:<7} {}",
+        human_size(mount.used),
+        human_size(mount.total),
+        mount.fs_type
+    )
+}
+
+/// The table as plain text, with `#` bars, for the log's text copy.
+pub fn table(mounts: &[Mount]) -> Vec<String> {
+    let width = mount_width(mounts);
+    let bar_width = GAUGE_WIDTH - "100% []".len();
+    let mut lines = vec![header(width)];
+    for mount in mounts {
+        let filled = (mount.ratio() * bar_width as f64).round() as usize;
+        let gauge = format!(
+            "{:>3.0}% [{}{}]",
+            mount.ratio() * 100.0,
+            "#".repeat(filled),
+            " ".repeat(bar_width - filled)
+        );
+        lines.push(row(mount, width, &gauge));
+    }
+    lines
+}
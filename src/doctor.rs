@@ -0,0 +1,362 @@
+// src/doctor.rs
+
+//! `halo doctor` (and the `doctor` builtin): a plain-text report on what
+//! halo depends on around it — the terminal, halo.toml, themes, $PATH and
+//! git2 — with each check marked ok, warn or fail. It runs before the TUI
+//! starts, so it also works when halo itself won't; paste it into bug
+//! reports.
+
+use crate::prompt::Template;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Top-level halo.toml keys halo reads; anything else is likely a typo.
+const KNOWN_SECTIONS: &[&str] = &[
+    "abbr",
+    "aliases",
+    "completions",
+    "handoff",
+    "history",
+    "hooks",
+    "jobs",
+    "lock",
+    "notify",
+    "performance",
+    "session",
+    "share",
+    "shell",
+    "templates",
+    "theme",
+    "ui",
+];
+/// Names `theme = "..."` falls back to when there is no theme file.
+const BUILTIN_THEMES: &[&str] = &["cyber-nord", "dracula", "gruvbox-dark", "one-dark"];
+/// A repository on a hung network mount shouldn't hang the report.
+const GIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+struct Report {
+    lines: Vec<String>,
+    warnings: usize,
+    failures: usize,
+}
+
+impl Report {
+    fn section(&mut self, title: &str) {
+        if !self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.lines.push(title.to_string());
+    }
+
+    fn check(&mut self, status: Status, text: impl Into<String>) {
+        let mark = match status {
+            Status::Ok => "[ok]  ",
+            Status::Warn => {
+                self.warnings += 1;
+                "[warn]"
+            }
+            Status::Fail => {
+                self.failures += 1;
+                "[fail]"
+            }
+        };
+        self.lines.push(format!("  {mark} {}", text.into()));
+    }
+
+    fn note(&mut self, text: impl Into<String>) {
+        self.lines.push(format!("         {}", text.into()));
+    }
+}
+
+/// The whole report, one line per element.
+pub fn report(cwd: &Path) -> Vec<String> {
+    let mut report = Report {
+        lines: Vec::new(),
+        warnings: 0,
+        failures: 0,
+    };
+    report.lines.push(format!(
+        "halo {} on {} {}",
+        env!("CARGO_PKG_VERSION"),
+        env::consts::OS,
+        env::consts::ARCH
+    ));
+    terminal(&mut report);
+    let config = config(&mut report);
+    themes(&mut report, config.as_ref());
+    path(&mut report);
+    git(&mut report, cwd);
+    report.lines.push(String::new());
+    report.lines.push(format!(
+        "{} warning{}, {} failure{}",
+        report.warnings,
+        if report.warnings == 1 { "" } else { "s" },
+        report.failures,
+        if report.failures == 1 { "" } else { "s" }
+    ));
+    report.lines
+}
+
+fn var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn terminal(report: &mut Report) {
+    report.section("Terminal");
+    let term = var("TERM");
+    match term.as_deref() {
+        None => report.check(Status::Fail, "TERM is not set"),
+        Some("dumb") => report.check(Status::Fail, "TERM=dumb: no cursor movement or colors"),
+        Some(term) => report.check(Status::Ok, format!("TERM={term}")),
+    }
+    if let Some(program) = var("TERM_PROGRAM") {
+        let version = var("TERM_PROGRAM_VERSION").map(|v| format!(" {v}")).unwrap_or_default();
+        report.check(Status::Ok, format!("terminal: {program}{version}"));
+    }
+
+    match var("COLORTERM").as_deref() {
+        Some("truecolor" | "24bit") => report.check(Status::Ok, "truecolor (COLORTERM)"),
+        other => {
+            report.check(
+                Status::Warn,
+                format!("no truecolor: COLORTERM={}", other.unwrap_or("(unset)")),
+            );
+            report.note("themes use RGB colors; they are approximated or wrong without it");
+        }
+    }
+
+    // The first of these that is set decides, as in setlocale(3).
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|name| var(name).map(|v| (name, v)));
+    match locale {
+        Some((name, value)) => {
+            let lower = value.to_lowercase();
+            if lower.contains("utf-8") || lower.contains("utf8") {
+                report.check(Status::Ok, format!("UTF-8 locale ({name}={value})"));
+            } else {
+                report.check(Status::Warn, format!("{name}={value} is not UTF-8"));
+                report.note("box drawing, icons and wide characters may be garbled or misaligned");
+            }
+        }
+        None => report.check(Status::Warn, "no locale set (LC_ALL, LC_CTYPE, LANG)"),
+    }
+    let width = unicode_width::UnicodeWidthStr::width("界");
+    if width == 2 {
+        report.check(Status::Ok, "wide characters measured as two columns");
+    } else {
+        report.check(Status::Fail, format!("wide characters measured as {width} columns"));
+    }
+
+    match term.as_deref() {
+        Some("linux" | "dumb") | None => {
+            report.check(Status::Warn, "mouse reporting is unlikely on this terminal")
+        }
+        Some(_) => report.check(Status::Ok, "mouse reporting (SGR) expected"),
+    }
+
+    let tmux = var("TMUX").is_some();
+    let screen = term.as_deref().is_some_and(|t| t.starts_with("screen")) && !tmux;
+    if tmux {
+        report.check(Status::Warn, "inside tmux: OSC 8 links, 9 and 777 need passthrough");
+        report.note("set -g allow-passthrough on; OSC 52 copy needs set -g set-clipboard on");
+    } else if screen {
+        report.check(Status::Warn, "inside GNU screen: most OSC sequences are dropped");
+    } else if term.as_deref() == Some("linux") {
+        report.check(Status::Warn, "Linux console: no OSC titles, links or notifications");
+    } else {
+        report.check(Status::Ok, "OSC titles, links (8), notifications (9/777), clipboard (52)");
+        report.note("assumed from TERM; terminals ignore the ones they don't support");
+    }
+    if crate::state::over_ssh() {
+        report.check(Status::Ok, "over SSH: low-bandwidth mode is on by default");
+    }
+
+    match crossterm::terminal::size() {
+        Ok((cols, rows)) if cols < 40 || rows < 10 => {
+            report.check(Status::Warn, format!("{cols}x{rows} is too small for the layout"))
+        }
+        Ok((cols, rows)) => report.check(Status::Ok, format!("size {cols}x{rows}")),
+        Err(e) => report.check(Status::Warn, format!("size unknown: {e}")),
+    }
+}
+
+/// Checks halo.toml; returns it parsed, if it was.
+fn config(report: &mut Report) -> Option<toml::Value> {
+    report.section("Config");
+    let Some(path) = dirs::config_dir().map(|dir| dir.join("halo/halo.toml")) else {
+        report.check(Status::Fail, "no config directory ($XDG_CONFIG_HOME / $HOME)");
+        return None;
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            report.check(Status::Ok, format!("{} (not created yet)", path.display()));
+            return None;
+        }
+        Err(e) => {
+            report.check(Status::Fail, format!("{}: {e}", path.display()));
+            return None;
+        }
+    };
+    let value = match text.parse::<toml::Value>() {
+        Ok(value) => value,
+        Err(e) => {
+            report.check(Status::Fail, format!("{} does not parse", path.display()));
+            for line in e.to_string().lines().filter(|line| !line.trim().is_empty()) {
+                report.note(line);
+            }
+            return None;
+        }
+    };
+    report.check(Status::Ok, format!("{} parses", path.display()));
+    if let Some(table) = value.as_table() {
+        for key in table.keys().filter(|key| !KNOWN_SECTIONS.contains(&key.as_str())) {
+            report.check(Status::Warn, format!("unknown section or key `{key}` is ignored"));
+        }
+    }
+    for key in ["prompt_format", "rprompt_format", "title_format", "status_format"] {
+        let source = value.get("ui").and_then(|ui| ui.get(key)).and_then(|v| v.as_str());
+        if let Some(source) = source
+            && let Err(e) = Template::parse(source)
+        {
+            report.check(Status::Fail, format!("[ui] {key}: {e}; the default is used"));
+        }
+    }
+    let lock_key = |key| value.get("lock").and_then(|lock| lock.get(key)).is_some();
+    let (plain, hashed) = (lock_key("passphrase"), lock_key("passphrase_hash"));
+    if plain && !hashed {
+        report.check(Status::Warn, "[lock] passphrase is plain text (see `halo passphrase`)");
+    }
+    if (plain || hashed) && fs::metadata(&path).is_ok_and(|m| m.permissions().mode() & 0o077 != 0) {
+        report.check(Status::Warn, "holds the lock passphrase but others can read it (chmod 600)");
+    }
+    Some(value)
+}
+
+fn themes(report: &mut Report, config: Option<&toml::Value>) {
+    report.section("Themes");
+    let Some(dir) = dirs::config_dir().map(|dir| dir.join("halo/themes")) else {
+        return;
+    };
+    let installed: Vec<String> = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+                .filter_map(|path| path.file_stem()?.to_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    if installed.is_empty() {
+        report.check(Status::Warn, format!("no themes in {}", dir.display()));
+        report.note("they are unpacked on first use; `theme refresh` reinstalls them");
+    } else {
+        report.check(Status::Ok, format!("{} themes in {}", installed.len(), dir.display()));
+    }
+
+    match config.and_then(|value| value.get("theme")) {
+        Some(toml::Value::String(name)) if installed.contains(name) => {
+            report.check(Status::Ok, format!("theme `{name}` found"))
+        }
+        Some(toml::Value::String(name)) if BUILTIN_THEMES.contains(&name.as_str()) => {
+            report.check(Status::Ok, format!("theme `{name}` (built in)"))
+        }
+        Some(toml::Value::String(name)) => {
+            report.check(Status::Fail, format!("theme `{name}` not found; the default is used"))
+        }
+        Some(toml::Value::Table(_)) => report.check(Status::Ok, "custom [theme] colors"),
+        Some(_) => report.check(Status::Fail, "`theme` is neither a name nor a table"),
+        None => report.check(Status::Ok, "default theme"),
+    }
+}
+
+fn path(report: &mut Report) {
+    report.section("PATH");
+    let Some(path) = env::var_os("PATH").filter(|path| !path.is_empty()) else {
+        report.check(Status::Fail, "PATH is not set; only commands given by path will run");
+        return;
+    };
+    let dirs: Vec<PathBuf> = env::split_paths(&path).collect();
+    report.check(Status::Ok, format!("{} entries", dirs.len()));
+    let mut seen = HashSet::new();
+    for dir in &dirs {
+        if dir.as_os_str().is_empty() {
+            report.check(Status::Warn, "empty entry (searches the current directory)");
+        } else if dir.is_relative() {
+            report.check(Status::Warn, format!("relative entry {}", dir.display()));
+        } else if !dir.is_dir() {
+            report.check(Status::Warn, format!("{} does not exist", dir.display()));
+        } else if !seen.insert(dir) {
+            report.check(Status::Warn, format!("{} is listed twice", dir.display()));
+        }
+    }
+    for (program, why) in [("sh", "command lines run with sh -c"), ("git", "git builtins")] {
+        let executable = |path: &Path| {
+            path.metadata()
+                .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        };
+        match dirs.iter().map(|dir| dir.join(program)).find(|path| executable(path)) {
+            Some(found) => report.check(Status::Ok, format!("{program}: {}", found.display())),
+            None if program == "sh" => {
+                report.check(Status::Fail, format!("{program} not found ({why})"))
+            }
+            None => report.check(Status::Warn, format!("{program} not found ({why})")),
+        }
+    }
+}
+
+fn git(report: &mut Report, cwd: &Path) {
+    report.section("git2");
+    let version = git2::Version::get();
+    let (major, minor, patch) = version.libgit2_version();
+    report.check(Status::Ok, format!("libgit2 {major}.{minor}.{patch}"));
+    if !version.https() {
+        report.check(Status::Warn, "built without HTTPS support");
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let dir = cwd.to_path_buf();
+    std::thread::spawn(move || {
+        let result = git2::Repository::discover(&dir).map(|repo| {
+            let head = repo.head().ok().and_then(|h| h.shorthand().map(str::to_string));
+            let statuses = repo.statuses(None).map(|s| s.len());
+            (repo.workdir().map(Path::to_path_buf), head, statuses)
+        });
+        let _ = tx.send(result);
+    });
+    match rx.recv_timeout(GIT_TIMEOUT) {
+        Ok(Ok((workdir, head, statuses))) => {
+            let root = workdir.map(|w| w.display().to_string()).unwrap_or("bare".into());
+            let head = head.unwrap_or_else(|| "no commits".into());
+            report.check(Status::Ok, format!("repository {root} on {head}"));
+            match statuses {
+                Ok(n) => report.check(Status::Ok, format!("status read ({n} changed)")),
+                Err(e) => report.check(Status::Fail, format!("status failed: {}", e.message())),
+            }
+        }
+        Ok(Err(e)) if e.code() == git2::ErrorCode::NotFound => {
+            report.check(Status::Ok, format!("{} is not in a repository", cwd.display()))
+        }
+        Ok(Err(e)) => {
+            report.check(Status::Fail, format!("opening the repository: {}", e.message()))
+        }
+        Err(_) => report.check(
+            Status::Fail,
+            format!("no answer within {}s; the prompt will lag here", GIT_TIMEOUT.as_secs()),
+        ),
+    }
+}
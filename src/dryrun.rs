@@ -0,0 +1,152 @@
+// src/dryrun.rs
+
+//! Alt+Enter: what the input line would run, one expansion at a time, with
+//! nothing executed. Worth a look before an `rm` built from an alias and
+//! `$?`, or a line whose globs sh will expand.
+
+use std::env;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// One line of the preview, e.g. `alias` / `ll → ls -alF`.
+pub struct Step {
+    pub label: &'static str,
+    pub text: String,
+}
+
+impl Step {
+    pub fn new(label: &'static str, text: impl Into<String>) -> Self {
+        Self {
+            label,
+            text: text.into(),
+        }
+    }
+}
+
+/// Where `name` would be found on $PATH (or directly, for a path).
+pub fn program_path(name: &str, cwd: &Path) -> Option<PathBuf> {
+    let executable = |path: &Path| {
+        path.metadata()
+            .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    };
+    if name.contains('/') {
+        let path = cwd.join(name);
+        return executable(&path).then_some(path);
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| executable(path))
+}
+
+/// What sh will still expand in a line halo hands to `sh -c`: variables
+/// (with their current values), command substitutions, globs and braces.
+/// Single quotes are respected; nothing is run to find out.
+pub fn shell_expansions(line: &str, cwd: &Path) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' if !in_double => in_single = !in_single,
+            '"' if !in_single => in_double = !in_double,
+            '\\' if !in_single => {
+                chars.next();
+            }
+            '$' if !in_single => match chars.peek().map(|(_, c)| *c) {
+                Some('(') => steps.push(Step::new("runs", "$(…) is run by sh, not here")),
+                Some('{') => {
+                    let rest = &line[i + 2..];
+                    let len = rest.find('}').unwrap_or(rest.len());
+                    steps.push(variable(&format!("${{{}}}", &rest[..len]), &rest[..len]));
+                    while chars.next_if(|(j, _)| *j <= i + 2 + len).is_some() {}
+                }
+                Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                    let rest = &line[i + 1..];
+                    let len = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+                    steps.push(variable(&line[i..=i + len], &rest[..len]));
+                    while chars.next_if(|(j, _)| *j <= i + len).is_some() {}
+                }
+                _ => {}
+            },
+            '`' if !in_single => steps.push(Step::new("runs", "`…` is run by sh, not here")),
+            '*' | '?' | '[' if !in_single && !in_double && word_at(line, i) != "[" => {
+                let word = word_at(line, i);
+                steps.push(Step::new("glob", format!("{word} ({})", matches(word, cwd))));
+            }
+            '{' if !in_single && !in_double && word_at(line, i).contains(',') => {
+                steps.push(Step::new(
+                    "braces",
+                    format!("{} stays as is: sh has no brace expansion", word_at(line, i)),
+                ));
+            }
+            _ => {}
+        }
+    }
+    // One line per glob or brace word, however many wildcards it has.
+    steps.dedup_by(|a, b| a.label == b.label && a.text == b.text);
+    steps
+}
+
+/// Words of a plain command that look like they want a shell. Halo runs
+/// those without one, so they reach the program exactly as typed.
+pub fn literal_words(argv: &[String]) -> Vec<&str> {
+    argv.iter()
+        .skip(1)
+        .map(String::as_str)
+        .filter(|word| word.contains(['$', '*', '?', '`']) || word.starts_with('~'))
+        .collect()
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// `shown` as written (`$HOME`, `${HOME:-/}`) with the value of `name`'s
+/// leading variable name.
+fn variable(shown: &str, name: &str) -> Step {
+    let name = name.split(|c| !is_name_char(c)).next().unwrap_or(name);
+    let value = env::var(name).unwrap_or_else(|_| "(unset)".to_string());
+    Step::new("variable", format!("{shown} → {value}"))
+}
+
+/// The whitespace-delimited word around byte `i`.
+fn word_at(line: &str, i: usize) -> &str {
+    let start = line[..i].rfind(char::is_whitespace).map_or(0, |s| s + 1);
+    let end = line[i..].find(char::is_whitespace).map_or(line.len(), |e| i + e);
+    &line[start..end]
+}
+
+/// How many entries of `cwd` a simple `*`/`?` pattern matches, as a hint;
+/// patterns with directories or classes are left to sh.
+fn matches(pattern: &str, cwd: &Path) -> String {
+    if pattern.contains(['/', '[']) {
+        return "expanded by sh".to_string();
+    }
+    let Ok(entries) = std::fs::read_dir(cwd) else {
+        return "expanded by sh".to_string();
+    };
+    let count = entries
+        .filter_map(Result::ok)
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|name| !name.starts_with('.') || pattern.starts_with('.'))
+        .filter(|name| wildcard(pattern.as_bytes(), name.as_bytes()))
+        .count();
+    match count {
+        0 => "no match; sh passes it on as is".to_string(),
+        1 => "1 match".to_string(),
+        n => format!("{n} matches"),
+    }
+}
+
+fn wildcard(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => {
+            wildcard(rest, name) || (!name.is_empty() && wildcard(pattern, &name[1..]))
+        }
+        (Some((b'?', rest)), Some((_, name))) => wildcard(rest, name),
+        (Some((p, rest)), Some((n, name))) if p == n => wildcard(rest, name),
+        _ => false,
+    }
+}
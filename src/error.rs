@@ -1,5 +1,7 @@
 // src/error.rs
 
+//! Error handling shared by the crate.
+
 /// A common Result type for the application.
 /// It uses anyhow::Error to allow for flexible error handling.
 pub type AppResult<T> = Result<T, anyhow::Error>;
@@ -1,18 +1,49 @@
 // src/event.rs
 
 use crate::app::App;
-use crate::error::AppResult;
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use halo_core::clipboard::CopyTarget;
+use halo_core::colorblind::Simulation;
+use halo_core::error::AppResult;
+use halo_core::panes::Split;
+use halo_core::tabs::MAX_TABS;
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::layout::Position;
+use std::time::Instant;
 
 pub struct EventHandler;
 
 impl EventHandler {
     pub async fn handle_event(&self, event: Event, app: &mut App) -> AppResult<()> {
-        app.state.needs_redraw = true;
+        // Bare pointer motion only redraws when the hovered block changes.
+        if !matches!(event, Event::Mouse(m) if m.kind == MouseEventKind::Moved) {
+            app.state.needs_redraw = true;
+        }
+
+        if matches!(event, Event::Key(_) | Event::Mouse(_)) {
+            app.state.last_input = Instant::now();
+        }
+        // Locked, keys only go to the lock screen and the mouse does nothing.
+        if let Some(lock) = &mut app.state.locked {
+            if let Event::Key(key) = event
+                && lock.key(key, app.state.lock.passphrase_hash.as_deref())
+            {
+                app.state.locked = None;
+            }
+            return Ok(());
+        }
 
         match event {
-            Event::Key(key_event) => self.handle_key_press(key_event, app).await?,
+            Event::Key(key_event) => {
+                self.handle_key_press(key_event, app).await?;
+                // Marks stay until the user is back and acts, so coming
+                // back to the window does not clear them unread.
+                app.state.mark_seen();
+            }
             Event::Mouse(mouse_event) => self.handle_mouse_event(mouse_event, app),
+            Event::FocusGained => app.state.focused = true,
+            Event::FocusLost => app.state.focused = false,
             _ => {}
         }
 
@@ -20,6 +51,44 @@ impl EventHandler {
     }
 
     async fn handle_key_press(&self, key: KeyEvent, app: &mut App) -> AppResult<()> {
+        if key.code == KeyCode::F(2) {
+            app.state.toggle_presentation_mode();
+            return Ok(());
+        }
+
+        if app.state.pager.is_some() {
+            self.handle_pager_key(key, app);
+            return Ok(());
+        }
+
+        if self.handle_log_search_key(key, app) {
+            return Ok(());
+        }
+
+        if app.state.presentation_mode {
+            self.handle_presentation_key(key, app);
+            return Ok(());
+        }
+
+        if app.state.placeholder_prompt.is_some() && self.handle_placeholder_key(key, app) {
+            return Ok(());
+        }
+
+        if app.state.builder.is_some() {
+            self.handle_builder_key(key, app);
+            return Ok(());
+        }
+
+        if key.code == KeyCode::F(3) {
+            app.toggle_procs();
+            return Ok(());
+        }
+
+        if key.code == KeyCode::F(4) {
+            app.toggle_git_panel();
+            return Ok(());
+        }
+
         if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
             if app.state.completion_state.active {
                 app.state.completion_state.stop_completion();
@@ -29,11 +98,89 @@ impl EventHandler {
             return Ok(());
         }
 
+        if key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            app.stop_foreground_job();
+            return Ok(());
+        }
+
+        // Ctrl+N on a previewed entry starts a `note` for it.
+        if key.code == KeyCode::Char('n')
+            && key.modifiers.contains(KeyModifiers::CONTROL)
+            && app.state.scroll_offset > 0
+        {
+            app.state.start_note_for_preview();
+            return Ok(());
+        }
+
+        // Keys end a mouse selection; Alt+W copies it first.
+        if app.state.selection.is_some() {
+            let copy = key.code == KeyCode::Char('w') && key.modifiers.contains(KeyModifiers::ALT);
+            if copy {
+                app.copy_selection();
+            }
+            app.state.selection = None;
+            if copy {
+                return Ok(());
+            }
+        }
+
+        // Before any typing leaves preview, so they copy the previewed entry.
+        if key.modifiers.contains(KeyModifiers::ALT) {
+            let target = match key.code {
+                KeyCode::Char('c') => Some(CopyTarget::Command),
+                KeyCode::Char('o') => Some(CopyTarget::Output),
+                KeyCode::Char('w') => Some(CopyTarget::Block),
+                _ => None,
+            };
+            if let Some(target) = target {
+                app.copy_to_clipboard(target, false);
+                return Ok(());
+            }
+            if key.code == KeyCode::Char('p') {
+                app.state.open_pager();
+                return Ok(());
+            }
+            if key.code == KeyCode::Char('l') {
+                app.open_last_link();
+                return Ok(());
+            }
+        }
+
+        // While previewing, `/` searches the log rather than typing a path,
+        // and `[` / `]` hop between command blocks.
+        if app.state.scroll_offset > 0
+            && let KeyCode::Char(c @ ('/' | '[' | ']')) = key.code
+        {
+            match c {
+                '/' => app.state.start_log_search(),
+                c => app.state.jump_to_block(c == '['),
+            }
+            return Ok(());
+        }
+
         if matches!(key.code, KeyCode::Char(_) | KeyCode::Backspace) {
             app.state.exit_preview_mode();
         }
 
-        if app.state.theme_selection_mode {
+        if app.state.confirmation.is_some() {
+            match key.code {
+                KeyCode::Char('y' | 'Y') => app.resolve_confirmation(true),
+                KeyCode::Char('n' | 'N') | KeyCode::Esc | KeyCode::Enter => {
+                    app.resolve_confirmation(false)
+                }
+                _ => {}
+            }
+        } else if app.state.procs.is_some() {
+            self.handle_procs_key(key, app);
+        } else if app.state.diff_view.is_some() {
+            self.handle_diff_view_key(key, app);
+        } else if app.state.git_panel.is_some() {
+            self.handle_git_panel_key(key, app);
+        } else if app.state.history_search.is_some() {
+            self.handle_history_search_key(key, app);
+        } else if app.state.dir_picker.is_some() {
+            self.handle_dir_picker_key(key, app);
+        } else if app.state.theme_selection_mode {
             self.handle_theme_selection_key(key, app);
         } else if app.state.completion_state.active {
             self.handle_completion_mode_key(key, app);
@@ -66,17 +213,261 @@ impl EventHandler {
         }
     }
 
+    fn handle_history_search_key(&self, key: KeyEvent, app: &mut App) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let state = &mut app.state;
+        let matches = state.history_matches().len();
+        let Some(search) = state.history_search.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('r') if ctrl => search.select_older(matches),
+            KeyCode::Up => search.select_older(matches),
+            KeyCode::Char('g') if ctrl => state.history_search = None,
+            KeyCode::Esc => state.history_search = None,
+            KeyCode::Enter | KeyCode::Right | KeyCode::End => state.accept_history_search(),
+            KeyCode::Down => search.selected = search.selected.saturating_sub(1),
+            KeyCode::Backspace => {
+                search.query.pop();
+                search.selected = 0;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                search.query.push(c);
+                search.selected = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_dir_picker_key(&self, key: KeyEvent, app: &mut App) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let Some(picker) = app.state.dir_picker.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Down | KeyCode::Tab => {
+                let matches = picker.matches().len();
+                picker.select_next(matches);
+            }
+            KeyCode::Char('n') if ctrl => {
+                let matches = picker.matches().len();
+                picker.select_next(matches);
+            }
+            KeyCode::Up | KeyCode::BackTab => picker.select_previous(),
+            KeyCode::Char('p') if ctrl => picker.select_previous(),
+            KeyCode::Esc => app.state.dir_picker = None,
+            KeyCode::Char('g') if ctrl => app.state.dir_picker = None,
+            KeyCode::Enter => app.accept_dir_picker(),
+            KeyCode::Backspace => {
+                picker.query.pop();
+                picker.selected = 0;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                picker.query.push(c);
+                picker.selected = 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Keys for an open log search. Returns false for keys it leaves to the
+    /// normal handlers; those also end the search, keeping the scroll.
+    fn handle_log_search_key(&self, key: KeyEvent, app: &mut App) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let state = &mut app.state;
+        let Some(search) = state.log_search.as_mut() else {
+            return false;
+        };
+        if search.editing {
+            match key.code {
+                KeyCode::Enter if search.matches.is_empty() => state.close_log_search(),
+                KeyCode::Enter => search.editing = false,
+                KeyCode::Esc => state.close_log_search(),
+                KeyCode::Backspace => {
+                    search.query.pop();
+                    state.update_log_search();
+                }
+                KeyCode::Char(c) if !ctrl => {
+                    search.query.push(c);
+                    state.update_log_search();
+                }
+                _ => {}
+            }
+            return true;
+        }
+        match key.code {
+            KeyCode::Char('n') if !ctrl => state.step_log_search(true),
+            KeyCode::Char('N') => state.step_log_search(false),
+            KeyCode::Char('/') => search.editing = true,
+            KeyCode::Esc => state.close_log_search(),
+            _ => {
+                state.close_log_search();
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Keys while an alias placeholder is asked in the input line. Returns
+    /// false for line editing keys, which work as usual.
+    fn handle_placeholder_key(&self, key: KeyEvent, app: &mut App) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => app.answer_placeholder(),
+            KeyCode::Esc => app.cancel_placeholder_prompt(),
+            KeyCode::Char('c') if ctrl => app.cancel_placeholder_prompt(),
+            KeyCode::Tab => app.state.complete_placeholder_path(),
+            KeyCode::Char('a' | 'e' | 'k' | 'u' | 'w' | 'y') if ctrl => return false,
+            KeyCode::Char(_) if ctrl => {}
+            // Not through normal mode, which would expand abbreviations.
+            KeyCode::Char(' ') => app.state.insert_char(' '),
+            KeyCode::Char(_)
+            | KeyCode::Backspace
+            | KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Home
+            | KeyCode::End => return false,
+            _ => {}
+        }
+        true
+    }
+
+    /// Keys in the command builder form. Each change rewrites the input line.
+    fn handle_builder_key(&self, key: KeyEvent, app: &mut App) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let Some(form) = app.state.builder.as_mut() else {
+            return;
+        };
+        let cancel = key.code == KeyCode::Esc || (ctrl && key.code == KeyCode::Char('c'));
+        // Enter leaves the command in the input to be checked and run.
+        if cancel || key.code == KeyCode::Enter {
+            app.state.builder = None;
+            if cancel {
+                app.state.set_input(String::new());
+            }
+            return;
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::BackTab => form.select(-1),
+            KeyCode::Down | KeyCode::Tab => form.select(1),
+            KeyCode::Left => {
+                form.change(false);
+            }
+            KeyCode::Right => {
+                form.change(true);
+            }
+            KeyCode::Char(' ') if form.change(true) => {}
+            KeyCode::Char(c) if !ctrl => form.type_char(c),
+            KeyCode::Backspace => form.backspace(),
+            _ => {}
+        }
+        let command = form.command();
+        app.state.set_input(command);
+    }
+
+    /// Keys for the output pager, roughly those of `less`.
+    fn handle_pager_key(&self, key: KeyEvent, app: &mut App) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let Some(pager) = app.state.pager.as_mut() else {
+            return;
+        };
+        if let Some(search) = pager.search.as_mut()
+            && search.editing
+        {
+            match key.code {
+                KeyCode::Enter if search.matches.is_empty() => pager.search = None,
+                KeyCode::Enter => search.editing = false,
+                KeyCode::Esc => pager.search = None,
+                KeyCode::Backspace => {
+                    search.query.pop();
+                    pager.update_search();
+                }
+                KeyCode::Char(c) if !ctrl => {
+                    search.query.push(c);
+                    pager.update_search();
+                }
+                _ => {}
+            }
+            return;
+        }
+        match key.code {
+            KeyCode::Char('q') => app.state.pager = None,
+            KeyCode::Esc if pager.search.is_some() => pager.search = None,
+            KeyCode::Esc => app.state.pager = None,
+            KeyCode::Up | KeyCode::Char('k') => pager.scroll(-1),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Enter => pager.scroll(1),
+            KeyCode::PageUp | KeyCode::Char('b') => pager.page(false),
+            KeyCode::PageDown | KeyCode::Char(' ' | 'f') => pager.page(true),
+            KeyCode::Left => pager.scroll_side(false),
+            KeyCode::Right => pager.scroll_side(true),
+            KeyCode::Home | KeyCode::Char('g') => pager.go_to_start(),
+            KeyCode::End | KeyCode::Char('G') => pager.go_to_end(),
+            KeyCode::Char('/') => pager.start_search(),
+            KeyCode::Char('n') if !ctrl => pager.step_search(true),
+            KeyCode::Char('N') => pager.step_search(false),
+            _ => {}
+        }
+    }
+
+    fn handle_procs_key(&self, key: KeyEvent, app: &mut App) {
+        let Some(monitor) = app.state.procs.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Up => monitor.select_up(),
+            KeyCode::Down => monitor.select_down(),
+            KeyCode::Char('a') => monitor.toggle_system(),
+            KeyCode::Char('k') => app.signal_selected_proc(libc::SIGTERM),
+            KeyCode::Char('K') => app.signal_selected_proc(libc::SIGKILL),
+            KeyCode::Esc | KeyCode::Char('q') => app.state.procs = None,
+            _ => {}
+        }
+    }
+
+    fn handle_diff_view_key(&self, key: KeyEvent, app: &mut App) {
+        let Some(view) = app.state.diff_view.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => view.scroll(-1),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Enter => view.scroll(1),
+            KeyCode::PageUp | KeyCode::Char('b') => view.page(false),
+            KeyCode::PageDown | KeyCode::Char(' ' | 'f') => view.page(true),
+            KeyCode::Home | KeyCode::Char('g') => view.go_to_start(),
+            KeyCode::End | KeyCode::Char('G') => view.go_to_end(),
+            KeyCode::Esc | KeyCode::Char('q') => app.state.diff_view = None,
+            _ => {}
+        }
+    }
+
+    fn handle_git_panel_key(&self, key: KeyEvent, app: &mut App) {
+        let Some(panel) = app.state.git_panel.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => panel.select_up(),
+            KeyCode::Down | KeyCode::Char('j') => panel.select_down(),
+            KeyCode::Char(' ' | 's') => app.git_panel_toggle_stage(),
+            KeyCode::Char('d') => app.git_panel_discard(),
+            KeyCode::Enter => app.git_panel_show_diff(),
+            KeyCode::Char('r') => panel.refresh(),
+            KeyCode::Esc | KeyCode::Char('q') => app.state.git_panel = None,
+            _ => {}
+        }
+    }
+
     fn handle_theme_selection_key(&self, key: KeyEvent, app: &mut App) {
         match key.code {
             KeyCode::Up => app.state.select_theme_up(),
             KeyCode::Down => app.state.select_theme_down(),
-            KeyCode::Enter => {
-                if app.state.confirm_theme_selection() {
-                    app.state.append_to_last_log(format!("[theme set to {}]", app.state.theme_name));
-                }
+            KeyCode::Char('c') => {
+                app.state.theme_simulation = Simulation::cycle(app.state.theme_simulation);
+            }
+            KeyCode::Enter if app.state.confirm_theme_selection() => {
+                app.state.append_to_last_log(format!("[theme set to {}]", app.state.theme_name));
             }
             KeyCode::Esc => {
-                app.state.exit_theme_selection_mode();
+                app.state.cancel_theme_selection();
                 app.state.append_to_last_log("[theme selection cancelled]".into());
             }
             _ => {}
@@ -86,18 +477,110 @@ impl EventHandler {
     fn handle_normal_mode_key(&self, key: KeyEvent, app: &mut App) {
         // Allow scrolling up to the very first command (index 0)
         let max_scroll = app.state.command_log.len().saturating_sub(1);
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        // Alt-Y only cycles straight after a yank.
+        let last_yank = app.state.last_yank.take();
+        app.state.dry_run = None;
+        // Ctrl-X chords: Ctrl-X Ctrl-E, the pane keys and the tab keys.
+        if std::mem::take(&mut app.state.ctrl_x_pending) {
+            match key.code {
+                KeyCode::Char('e') if ctrl => app.request_edit(),
+                // Emacs window keys: other, split below, split right, close.
+                KeyCode::Char('o') => app.state.focus_next_pane(),
+                KeyCode::Char('2') => {
+                    app.state.split_pane(Split::Rows);
+                }
+                KeyCode::Char('3') => {
+                    app.state.split_pane(Split::Columns);
+                }
+                KeyCode::Char('0') => {
+                    if let Err(e) = app.state.close_pane() {
+                        app.state.show_notice(format!("pane: {e}"));
+                    }
+                }
+                // New tab, kill tab.
+                KeyCode::Char('t') if !app.new_tab() => {
+                    app.state.show_notice(format!("tab: at most {MAX_TABS} tabs"));
+                }
+                KeyCode::Char('k') => {
+                    if let Err(e) = app.close_tab() {
+                        app.state.show_notice(format!("tab: {e}"));
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
         match key.code {
+            KeyCode::Char('x') if ctrl => app.state.ctrl_x_pending = true,
+            // Readline editing.
+            KeyCode::Char('a') if ctrl => app.state.cursor_position = 0,
+            KeyCode::Char('e') if ctrl && app.state.accept_suggestion() => {}
+            KeyCode::Char('e') if ctrl => app.state.cursor_position = app.state.input_buffer.len(),
+            KeyCode::Char('k') if ctrl => app.state.kill_to_end(),
+            KeyCode::Char('u') if ctrl => app.state.kill_to_start(),
+            KeyCode::Char('w') if ctrl => app.state.delete_word_back(),
+            KeyCode::Char('d') if alt => app.state.delete_word_forward(),
+            KeyCode::Char('b') if alt => app.state.move_word_left(),
+            KeyCode::Char('f') if alt => app.state.move_word_right(),
+            KeyCode::Char(c @ '1'..='9') if alt => {
+                app.switch_tab(c as usize - '1' as usize);
+            }
+            KeyCode::Left if alt => app.cycle_tab(false),
+            KeyCode::Right if alt => app.cycle_tab(true),
+            KeyCode::Left if ctrl => app.state.move_word_left(),
+            KeyCode::Right if ctrl => app.state.move_word_right(),
+            KeyCode::Char('y') if ctrl => app.state.yank(),
+            KeyCode::Char('y') if alt => {
+                app.state.last_yank = last_yank;
+                app.state.yank_pop();
+            }
+            KeyCode::Char('r') if ctrl => app.state.start_history_search(),
+            KeyCode::Char('g') if ctrl => app.state.open_dir_picker(),
+            KeyCode::Char('l') if ctrl => app.state.clear_log(),
+            KeyCode::Char('f') if ctrl => app.state.start_log_search(),
+            KeyCode::Char('t') if ctrl => app.state.enter_theme_selection_mode(),
+            KeyCode::Char('o') if ctrl => {
+                app.state
+                    .completion_state
+                    .offer_templates(&app.state.input_buffer);
+            }
+            KeyCode::Char(' ') => {
+                app.state.expand_abbreviation();
+                app.state.insert_char(' ');
+            }
             KeyCode::Char(c) => app.state.insert_char(c),
             KeyCode::Backspace => app.state.backspace(),
             KeyCode::Left => app.state.move_cursor_left(),
+            // Right/End take the autosuggestion when one is showing.
+            KeyCode::Right | KeyCode::End if app.state.accept_suggestion() => {}
             KeyCode::Right => app.state.move_cursor_right(),
+            KeyCode::End => app.state.cursor_position = app.state.input_buffer.len(),
+            KeyCode::Up if ctrl => app.state.jump_to_block(true),
+            KeyCode::Down if ctrl => app.state.jump_to_block(false),
             KeyCode::Up => self.navigate_history_up(app),
             KeyCode::Down => self.navigate_history_down(app),
-            KeyCode::Enter => app.submit_command(),
-            KeyCode::Tab => {
-                let (input, cwd) = (app.state.input_buffer.clone(), app.state.cwd.clone());
-                app.state.completion_state.start_completion(&input, &cwd);
+            // In history preview, Enter runs the highlighted command again;
+            // Ctrl+Enter (Alt+Enter where the terminal can't tell Ctrl apart)
+            // puts it in the input line for editing instead.
+            KeyCode::Enter if app.state.scroll_offset > 0 => {
+                let command = app.state.previewed_command();
+                app.state.exit_preview_mode();
+                if let Some(command) = command {
+                    app.state.set_input(command);
+                    if !ctrl && !alt {
+                        app.submit_command();
+                    }
+                }
             }
+            // Alt+Enter shows what Enter would run, without running it.
+            KeyCode::Enter if alt => app.show_dry_run(),
+            KeyCode::Enter => {
+                app.state.expand_abbreviation();
+                app.submit_command();
+            }
+            KeyCode::Tab => app.start_completion(),
             KeyCode::PageUp => {
                 app.state.scroll_offset = (app.state.scroll_offset + 5).min(max_scroll);
             }
@@ -108,16 +591,110 @@ impl EventHandler {
         }
     }
 
+    /// Only navigation keys work while presenting; everything else is swallowed.
+    fn handle_presentation_key(&self, key: KeyEvent, app: &mut App) {
+        let max_scroll = app.state.command_log.len().saturating_sub(1);
+        let offset = &mut app.state.scroll_offset;
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => *offset = (*offset + 1).min(max_scroll),
+            KeyCode::Down | KeyCode::Char('j') => *offset = offset.saturating_sub(1),
+            KeyCode::PageUp => *offset = (*offset + 5).min(max_scroll),
+            KeyCode::PageDown => *offset = offset.saturating_sub(5),
+            KeyCode::Home | KeyCode::Char('g') => *offset = max_scroll,
+            KeyCode::End | KeyCode::Char('G') => *offset = 0,
+            KeyCode::Char('[') => app.state.jump_to_block(true),
+            KeyCode::Char(']') => app.state.jump_to_block(false),
+            KeyCode::Char('/') => app.state.start_log_search(),
+            KeyCode::Char('p') => app.state.open_pager(),
+            _ => {}
+        }
+    }
+
     fn handle_mouse_event(&self, mouse: MouseEvent, app: &mut App) {
         // Allow scrolling up to the very first command (index 0)
         let max_scroll = app.state.command_log.len().saturating_sub(1);
+        if let Some(pager) = app.state.pager.as_mut() {
+            match mouse.kind {
+                MouseEventKind::ScrollUp => pager.scroll(-3),
+                MouseEventKind::ScrollDown => pager.scroll(3),
+                _ => {}
+            }
+            return;
+        }
         match mouse.kind {
             MouseEventKind::ScrollUp => {
+                app.state.selection = None;
                 app.state.scroll_offset = (app.state.scroll_offset + 1).min(max_scroll);
             }
             MouseEventKind::ScrollDown => {
+                app.state.selection = None;
                 app.state.scroll_offset = app.state.scroll_offset.saturating_sub(1);
             }
+            MouseEventKind::Moved => {
+                let hovered = if app.state.low_bandwidth {
+                    None
+                } else {
+                    app.state
+                        .log_areas
+                        .iter()
+                        .find(|(area, _)| area.contains(Position::new(mouse.column, mouse.row)))
+                        .map(|(_, i)| *i)
+                };
+                if hovered != app.state.hovered_log {
+                    app.state.hovered_log = hovered;
+                    app.state.needs_redraw = true;
+                }
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                app.state.selection = None;
+                let position = Position::new(mouse.column, mouse.row);
+                // A click on the exit status strip jumps to that command.
+                if let Some((area, indices)) = &app.state.status_strip
+                    && mouse.row == area.y
+                    && let Some(&index) = mouse
+                        .column
+                        .checked_sub(area.x)
+                        .and_then(|col| indices.get(col as usize))
+                {
+                    app.state.jump_to_entry(index);
+                } else if app.state.log_screen.area.contains(position) {
+                    // Selection or click; which one is decided on release.
+                    app.state.drag_start = Some(position);
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let Some(anchor) = app.state.drag_start {
+                    let area = app.state.log_screen.area;
+                    // Not `clamp`: the area may have collapsed in a resize.
+                    let head = Position::new(
+                        mouse.column.min(area.right().saturating_sub(1)).max(area.left()),
+                        mouse.row.min(area.bottom().saturating_sub(1)).max(area.top()),
+                    );
+                    app.state.selection = Some((anchor, head));
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                if app.state.drag_start.take().is_none() {
+                    return;
+                }
+                let position = Position::new(mouse.column, mouse.row);
+                let link = app
+                    .state
+                    .link_targets
+                    .iter()
+                    .find(|(area, _)| area.contains(position))
+                    .map(|(_, url)| url.clone());
+                if app.state.selection.is_some_and(|(anchor, head)| anchor != head) {
+                    app.copy_selection();
+                } else if let Some(url) = link {
+                    app.state.selection = None;
+                    app.open_link(&url);
+                } else if let Some(index) = app.state.hovered_log {
+                    // A plain click on a log block recalls it into the preview.
+                    app.state.selection = None;
+                    app.state.jump_to_entry(index);
+                }
+            }
             _ => {}
         }
     }
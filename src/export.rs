@@ -0,0 +1,148 @@
+// src/export.rs
+
+//! Session transcripts for `log export`: the log's commands with their
+//! directory, output, exit status and duration, as JSON, Markdown or text.
+
+use crate::command::{self, CommandLog};
+use crate::state;
+use crate::timefmt::TimeFormat;
+use std::path::Path;
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Json,
+    Markdown,
+    Text,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Self::Json),
+            "md" | "markdown" => Some(Self::Markdown),
+            "txt" | "text" => Some(Self::Text),
+            _ => None,
+        }
+    }
+
+    /// Guessed from the extension; text for anything unknown.
+    pub fn for_path(path: &Path) -> Self {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::parse)
+            .unwrap_or(Self::Text)
+    }
+}
+
+/// Empty prompt entries are left out.
+pub fn is_exported(log: &CommandLog) -> bool {
+    !log.command.is_empty() || !log.output.is_empty()
+}
+
+/// `time` formats when each command started, as the log shows it.
+pub fn render(logs: &[CommandLog], format: Format, time: &TimeFormat) -> String {
+    let logs = logs.iter().filter(|log| is_exported(log));
+    match format {
+        Format::Json => {
+            let entries: Vec<serde_json::Value> = logs.map(json_entry).collect();
+            let mut out = serde_json::to_string_pretty(&entries).unwrap_or_default();
+            out.push('\n');
+            out
+        }
+        Format::Markdown => logs
+            .map(|log| markdown_entry(log, time))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Format::Text => logs.map(|log| text_entry(log, time)).collect::<Vec<_>>().join("\n"),
+    }
+}
+
+/// The output as the log shows it, without halo's `[stderr]` markers.
+fn output(log: &CommandLog) -> String {
+    log.output
+        .lines()
+        .map(state::displayed_line)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `exit=0  1.2s  14:03:22`, as much as is known; the start time is in
+/// `[ui] timestamp_format` (`time_format` when unset).
+fn status_line(log: &CommandLog, time: &TimeFormat) -> String {
+    let mut parts = Vec::new();
+    if log.is_running {
+        parts.push("running".to_string());
+    }
+    match (log.exit_code, log.signal) {
+        (Some(code), _) => parts.push(format!("exit={code}")),
+        (None, Some(signal)) => parts.push(command::describe_signal(signal)),
+        (None, None) => {}
+    }
+    if let Some(ms) = log.duration_ms {
+        parts.push(crate::summary::format_ms(ms));
+    }
+    if let Some(started) = &log.started_wall {
+        parts.push(time.format(started));
+    }
+    parts.join("  ")
+}
+
+pub fn json_entry(log: &CommandLog) -> serde_json::Value {
+    serde_json::json!({
+        "command": log.command,
+        "cwd": log.cwd.display().to_string(),
+        "branch": log.branch,
+        "started": log.started_wall.map(|t| t.to_rfc3339()),
+        "exit_code": log.exit_code,
+        "signal": log.signal,
+        "duration_ms": log.duration_ms.map(|ms| ms as u64),
+        "running": log.is_running,
+        "note": log.note,
+        "output": output(log),
+        "dropped_lines": log.output.dropped(),
+    })
+}
+
+pub fn markdown_entry(log: &CommandLog, time: &TimeFormat) -> String {
+    let mut out = format!("### `{}`\n\n", log.command.replace('`', "'"));
+    out.push_str(&format!("- cwd: `{}`\n", log.cwd.display()));
+    if let Some(branch) = &log.branch {
+        out.push_str(&format!("- branch: `{branch}`\n"));
+    }
+    let status = status_line(log, time);
+    if !status.is_empty() {
+        out.push_str(&format!("- {status}\n"));
+    }
+    if let Some(note) = &log.note {
+        out.push_str(&format!("- note: {note}\n"));
+    }
+    let output = output(log);
+    if !output.is_empty() {
+        // A fence longer than any backtick run in the output.
+        let longest = output
+            .split(|c| c != '`')
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(longest.max(2) + 1);
+        out.push_str(&format!("\n{fence}\n{output}\n{fence}\n"));
+    }
+    out
+}
+
+fn text_entry(log: &CommandLog, time: &TimeFormat) -> String {
+    let mut out = format!("$ {}\n# cwd: {}\n", log.command, log.cwd.display());
+    let status = status_line(log, time);
+    if !status.is_empty() {
+        out.push_str(&format!("# {status}\n"));
+    }
+    if let Some(note) = &log.note {
+        out.push_str(&format!("# note: {note}\n"));
+    }
+    let output = output(log);
+    if !output.is_empty() {
+        out.push_str(&output);
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,44 @@
+// src/exports.rs
+
+//! Variables set with the `export` builtin. Halo never changes its own
+//! environment (that races with the runtime's threads); these are added to
+//! every command it starts instead, including `sh -c` lines, so `$NAME`
+//! expands there as usual.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+static VARS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+pub fn set(name: &str, value: &str) {
+    if let Ok(mut vars) = VARS.lock() {
+        vars.insert(name.to_string(), value.to_string());
+    }
+}
+
+/// True if `name` was exported.
+pub fn remove(name: &str) -> bool {
+    VARS.lock().is_ok_and(|mut vars| vars.remove(name).is_some())
+}
+
+/// Everything exported, by name.
+pub fn all() -> BTreeMap<String, String> {
+    VARS.lock().map(|vars| vars.clone()).unwrap_or_default()
+}
+
+/// Replaces the whole set, as when loading a named session.
+pub fn replace(new: BTreeMap<String, String>) {
+    if let Ok(mut vars) = VARS.lock() {
+        *vars = new;
+    }
+}
+
+/// Whether `name` can be a variable name: a letter or `_`, then letters,
+/// digits and `_`.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
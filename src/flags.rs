@@ -0,0 +1,130 @@
+// src/flags.rs
+
+use std::collections::HashMap;
+use std::env;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::process::Command as TokioCommand;
+
+/// Commands that take longer than this to print their help get no flags.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Per-command flag lists scraped from `--help` or the man page. Fetching runs
+/// on a tokio task; until it finishes the command maps to `None`.
+#[derive(Clone, Default)]
+pub struct FlagCache {
+    entries: Arc<Mutex<HashMap<String, Option<Vec<String>>>>>,
+}
+
+impl FlagCache {
+    /// Flags for `cmd` if they are known. The first call for a command starts
+    /// the fetch in the background and returns `None`.
+    pub fn lookup(&self, cmd: &str) -> Option<Vec<String>> {
+        let mut entries = self.entries.lock().ok()?;
+        if let Some(entry) = entries.get(cmd) {
+            return entry.clone();
+        }
+        entries.insert(cmd.to_string(), None);
+        // Only ask programs found on $PATH; running `./script --help` could do anything.
+        if is_on_path(cmd) {
+            let cache = self.clone();
+            let cmd = cmd.to_string();
+            tokio::spawn(async move {
+                let flags = fetch_flags(&cmd).await;
+                if let Ok(mut entries) = cache.entries.lock() {
+                    entries.insert(cmd, Some(flags));
+                }
+            });
+        } else {
+            entries.insert(cmd.to_string(), Some(Vec::new()));
+        }
+        None
+    }
+
+    /// True once the fetch for `cmd` has finished.
+    pub fn is_ready(&self, cmd: &str) -> bool {
+        self.entries
+            .lock()
+            .is_ok_and(|entries| matches!(entries.get(cmd), Some(Some(_))))
+    }
+}
+
+fn is_on_path(cmd: &str) -> bool {
+    if cmd.contains('/') {
+        return false;
+    }
+    env::var_os("PATH")
+        .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(cmd).is_file()))
+}
+
+async fn fetch_flags(cmd: &str) -> Vec<String> {
+    let help = run_for_text(TokioCommand::new(cmd).arg("--help")).await;
+    let flags = parse_flags(&help);
+    if !flags.is_empty() {
+        return flags;
+    }
+    let mut man = TokioCommand::new("man");
+    man.arg(cmd).env("MANPAGER", "cat").env("MANWIDTH", "120");
+    parse_flags(&strip_overstrike(&run_for_text(&mut man).await))
+}
+
+/// Stdout and stderr together; plenty of tools print usage to stderr.
+async fn run_for_text(cmd: &mut TokioCommand) -> String {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    match tokio::time::timeout(FETCH_TIMEOUT, cmd.output()).await {
+        Ok(Ok(out)) => {
+            let mut text = String::from_utf8_lossy(&out.stdout).into_owned();
+            text.push_str(&String::from_utf8_lossy(&out.stderr));
+            text
+        }
+        _ => String::new(),
+    }
+}
+
+/// Drops the `x\bx` bold/underline sequences man uses for emphasis.
+fn strip_overstrike(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\u{8}' {
+            out.pop();
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Collects option names from lines that start with one, e.g.
+/// `  -a, --all   do not ignore entries` or `-o, --output FILE`.
+fn parse_flags(text: &str) -> Vec<String> {
+    let mut flags = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_start();
+        if !line.starts_with('-') {
+            continue;
+        }
+        // The option column ends where the description starts.
+        let options = line.split("  ").next().unwrap_or(line);
+        for token in options.split([',', ' ', '\t']) {
+            if !token.starts_with('-') {
+                continue;
+            }
+            let name: String = token
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+                .collect();
+            if name.trim_start_matches('-').is_empty() {
+                continue;
+            }
+            if !flags.contains(&name) {
+                flags.push(name);
+            }
+        }
+    }
+    flags.sort();
+    flags
+}
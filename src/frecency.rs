@@ -0,0 +1,137 @@
+// src/frecency.rs
+
+//! Visited directories ranked by frecency, as zoxide does, for `z` and
+//! `cd` completion. Each visit adds one to a directory's rank; its score
+//! is that rank weighted by how long ago the last visit was. Once the
+//! ranks add up to more than `MAX_TOTAL` they are all scaled down, so old
+//! favourites fade and the file stays small.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_TOTAL: f64 = 10_000.0;
+/// Entries whose rank drops below this after aging are forgotten.
+const MIN_RANK: f64 = 1.0;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct Visits {
+    rank: f64,
+    /// Seconds since the epoch.
+    last: u64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Frecency {
+    dirs: HashMap<PathBuf, Visits>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+impl Visits {
+    fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last);
+        let weight = match age {
+            0..3_600 => 4.0,
+            3_600..86_400 => 2.0,
+            86_400..604_800 => 0.5,
+            _ => 0.25,
+        };
+        self.rank * weight
+    }
+}
+
+impl Frecency {
+    pub fn visit(&mut self, dir: &Path) {
+        let visits = self.dirs.entry(dir.to_path_buf()).or_insert(Visits {
+            rank: 0.0,
+            last: 0,
+        });
+        visits.rank += 1.0;
+        visits.last = now();
+        let total: f64 = self.dirs.values().map(|v| v.rank).sum();
+        if total > MAX_TOTAL {
+            for visits in self.dirs.values_mut() {
+                visits.rank *= 0.9;
+            }
+            self.dirs.retain(|_, v| v.rank >= MIN_RANK);
+        }
+    }
+
+    /// Forgets `dir`, e.g. once it turns out to be gone.
+    pub fn remove(&mut self, dir: &Path) {
+        self.dirs.remove(dir);
+    }
+
+    /// Every directory, best first.
+    pub fn ranked(&self) -> Vec<PathBuf> {
+        let now = now();
+        let mut dirs: Vec<(&PathBuf, f64)> =
+            self.dirs.iter().map(|(dir, v)| (dir, v.score(now))).collect();
+        dirs.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        dirs.into_iter().map(|(dir, _)| dir.clone()).collect()
+    }
+
+    /// Directories matching `terms`, best first, with their scores. As in
+    /// zoxide, the terms must appear in the path in order (ignoring case)
+    /// and the last one in its final component.
+    pub fn matches(&self, terms: &[String]) -> Vec<(PathBuf, f64)> {
+        let now = now();
+        let terms: Vec<String> = terms.iter().map(|t| t.to_lowercase()).collect();
+        let mut found: Vec<(PathBuf, f64)> = self
+            .dirs
+            .iter()
+            .filter(|(dir, _)| matches_terms(dir, &terms))
+            .map(|(dir, v)| (dir.clone(), v.score(now)))
+            .collect();
+        found.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        found
+    }
+}
+
+fn matches_terms(dir: &Path, terms: &[String]) -> bool {
+    let path = dir.to_string_lossy().to_lowercase();
+    let mut from = 0;
+    for term in terms {
+        match path[from..].find(term.as_str()) {
+            Some(at) => from += at + term.len(),
+            None => return false,
+        }
+    }
+    let Some(last) = terms.last() else {
+        return true;
+    };
+    dir.file_name()
+        .is_some_and(|name| name.to_string_lossy().to_lowercase().contains(last.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(terms: &[&str]) -> Vec<String> {
+        terms.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn terms_match_in_order() {
+        let dir = Path::new("/home/me/src/halo-shell");
+        assert!(matches_terms(dir, &terms(&[])));
+        assert!(matches_terms(dir, &terms(&["src", "halo"])));
+        assert!(matches_terms(dir, &terms(&["me", "shell"])));
+        assert!(!matches_terms(dir, &terms(&["halo", "src"])));
+        assert!(!matches_terms(dir, &terms(&["nothing"])));
+    }
+
+    #[test]
+    fn last_term_must_be_in_the_last_component() {
+        let dir = Path::new("/home/me/src/halo-shell");
+        assert!(!matches_terms(dir, &terms(&["src"])));
+        assert!(matches_terms(dir, &terms(&["src", "sh"])));
+    }
+}
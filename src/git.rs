@@ -0,0 +1,326 @@
+// src/git.rs
+
+//! Branch and repository root for the status bar, read on the blocking
+//! pool. `Repository::discover` and a status walk can hang for minutes on a
+//! dead network mount; the UI loop only ever polls for the result, gives
+//! up after `TIMEOUT`, and stops asking about a directory that keeps timing
+//! out.
+//!
+//! The status walk is the expensive part in a large repository, so results
+//! are cached per directory along with the HEAD they were read at. Between
+//! walks only HEAD is looked at, every `HEAD_INTERVAL`; a walk runs when
+//! HEAD moved, after a command finishes (it may have touched the tree), or
+//! every `FULL_INTERVAL` for edits made outside halo.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot::{self, error::TryRecvError};
+
+/// How long one lookup may take before the segment shows as unavailable.
+const TIMEOUT: Duration = Duration::from_secs(2);
+/// Timeouts after which a directory is not probed again this session.
+/// Each abandoned lookup may leave a thread stuck in the kernel, so this
+/// also bounds how many pile up.
+const MAX_FAILURES: u32 = 3;
+/// How often HEAD is compared with the cached one.
+const HEAD_INTERVAL: Duration = Duration::from_secs(2);
+/// How long a status walk is trusted when nothing in halo changed anything.
+const FULL_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Default, PartialEq)]
+pub struct GitInfo {
+    /// Branch with a dirty/clean marker, e.g. `main ✔`.
+    pub branch: Option<String>,
+    pub root: Option<PathBuf>,
+    pub status: GitStatus,
+}
+
+/// Counts behind the status bar's indicators after the branch.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct GitStatus {
+    /// Commits not on / only on the upstream branch.
+    pub ahead: usize,
+    pub behind: usize,
+    /// Files with changes in the index.
+    pub staged: usize,
+    /// Tracked files with changes not yet staged.
+    pub unstaged: usize,
+    pub untracked: usize,
+    pub stashes: usize,
+}
+
+impl GitStatus {
+    fn is_dirty(&self) -> bool {
+        self.staged + self.unstaged + self.untracked > 0
+    }
+}
+
+/// `[ui.git]`: which indicators follow the branch in the status bar.
+#[derive(Clone, Copy)]
+pub struct GitSegmentConfig {
+    pub ahead_behind: bool,
+    pub staged: bool,
+    pub unstaged: bool,
+    pub untracked: bool,
+    pub stash: bool,
+}
+
+impl Default for GitSegmentConfig {
+    fn default() -> Self {
+        Self {
+            ahead_behind: true,
+            staged: true,
+            unstaged: true,
+            untracked: true,
+            stash: true,
+        }
+    }
+}
+
+impl GitSegmentConfig {
+    pub fn from_table(tbl: &toml::value::Table) -> Self {
+        let mut config = Self::default();
+        for (key, on) in [
+            ("ahead_behind", &mut config.ahead_behind),
+            ("staged", &mut config.staged),
+            ("unstaged", &mut config.unstaged),
+            ("untracked", &mut config.untracked),
+            ("stash", &mut config.stash),
+        ] {
+            if let Some(value) = tbl.get(key).and_then(|v| v.as_bool()) {
+                *on = value;
+            }
+        }
+        config
+    }
+}
+
+pub enum GitUpdate {
+    Info(GitInfo),
+    /// The lookup timed out or failed outright.
+    Unavailable,
+}
+
+/// What a lookup found.
+enum Reading {
+    /// A full read, with the HEAD it was made at.
+    Fresh(GitInfo, Option<String>),
+    /// HEAD is where the cached read saw it; the rest was skipped.
+    Unchanged,
+}
+
+struct Probe {
+    cwd: PathBuf,
+    started: Instant,
+    rx: oneshot::Receiver<Reading>,
+}
+
+struct Cached {
+    info: GitInfo,
+    head: Option<String>,
+    /// When HEAD was last compared.
+    checked: Instant,
+    /// When the status was last walked.
+    walked: Instant,
+    /// A command finished since the walk.
+    stale: bool,
+}
+
+#[derive(Default)]
+pub struct GitMonitor {
+    probe: Option<Probe>,
+    failures: HashMap<PathBuf, u32>,
+    cache: HashMap<PathBuf, Cached>,
+    /// The directory the last update was for.
+    shown: Option<PathBuf>,
+}
+
+impl GitMonitor {
+    /// Collects a finished lookup and starts the next one for `cwd` if one
+    /// is due. Returns what the status bar should show now, or None to keep
+    /// it; a directory seen before shows its cached result at once.
+    pub fn poll(&mut self, cwd: &Path) -> Option<GitUpdate> {
+        if self.probe.is_some() {
+            if let Some(update) = self.collect(cwd) {
+                return Some(update);
+            }
+        } else if self.failures.get(cwd).is_some_and(|n| *n >= MAX_FAILURES) {
+            return Some(GitUpdate::Unavailable);
+        } else {
+            self.start_if_due(cwd);
+        }
+        if self.shown.as_deref() != Some(cwd)
+            && let Some(cached) = self.cache.get(cwd)
+        {
+            self.shown = Some(cwd.to_path_buf());
+            return Some(GitUpdate::Info(cached.info.clone()));
+        }
+        None
+    }
+
+    /// Marks every cached status as possibly out of date, e.g. when a
+    /// command finishes.
+    pub fn invalidate(&mut self) {
+        for cached in self.cache.values_mut() {
+            cached.stale = true;
+        }
+    }
+
+    fn collect(&mut self, cwd: &Path) -> Option<GitUpdate> {
+        let probe = self.probe.as_mut()?;
+        let result = match probe.rx.try_recv() {
+            Ok(reading) => Ok(reading),
+            Err(TryRecvError::Empty) if probe.started.elapsed() < TIMEOUT => return None,
+            Err(_) => Err(()),
+        };
+        let probe = self.probe.take()?;
+        let update = match result {
+            Ok(reading) => {
+                self.failures.remove(&probe.cwd);
+                let now = Instant::now();
+                match (reading, self.cache.get_mut(&probe.cwd)) {
+                    (Reading::Unchanged, Some(cached)) => cached.checked = now,
+                    (Reading::Unchanged, None) => return None,
+                    (Reading::Fresh(info, head), _) => {
+                        let cached = Cached {
+                            info,
+                            head,
+                            checked: now,
+                            walked: now,
+                            stale: false,
+                        };
+                        self.cache.insert(probe.cwd.clone(), cached);
+                    }
+                }
+                GitUpdate::Info(self.cache.get(&probe.cwd)?.info.clone())
+            }
+            Err(()) => {
+                *self.failures.entry(probe.cwd.clone()).or_default() += 1;
+                GitUpdate::Unavailable
+            }
+        };
+        if probe.cwd != cwd {
+            return None;
+        }
+        self.shown = Some(probe.cwd);
+        Some(update)
+    }
+
+    /// Starts a lookup for `cwd`: a full one when nothing is cached or the
+    /// cache is stale or old, a HEAD check when one is due or the directory
+    /// just changed, else nothing.
+    fn start_if_due(&mut self, cwd: &Path) {
+        let moved = self.shown.as_deref() != Some(cwd);
+        let known_head = match self.cache.get(cwd) {
+            None => None,
+            Some(c) if c.stale || c.walked.elapsed() >= FULL_INTERVAL => None,
+            Some(c) if moved || c.checked.elapsed() >= HEAD_INTERVAL => Some(c.head.clone()),
+            Some(_) => return,
+        };
+        let (tx, rx) = oneshot::channel();
+        let dir = cwd.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(read(&dir, known_head));
+        });
+        self.probe = Some(Probe {
+            cwd: cwd.to_path_buf(),
+            started: Instant::now(),
+            rx,
+        });
+    }
+}
+
+/// The branch name in a `GitInfo::branch`, without the dirty/clean marker.
+/// Branch names cannot contain spaces, so the marker is what follows the
+/// last one.
+pub fn branch_name(branch: &str) -> &str {
+    branch.rsplit_once(' ').map_or(branch, |(name, _)| name)
+}
+
+/// Reads the repository at `path`, skipping the status walk if HEAD is
+/// still `known_head` (given as `Some` only when a cached read exists).
+fn read(path: &Path, known_head: Option<Option<String>>) -> Reading {
+    let Ok(mut repo) = git2::Repository::discover(path) else {
+        return Reading::Fresh(GitInfo::default(), None);
+    };
+    let head = head_key(&repo);
+    if known_head.is_some_and(|known| known == head) {
+        return Reading::Unchanged;
+    }
+    let status = status(&mut repo);
+    let info = GitInfo {
+        branch: status.and_then(|status| branch(&repo, status.is_dirty())),
+        root: repo.workdir().map(Path::to_path_buf),
+        status: status.unwrap_or_default(),
+    };
+    Reading::Fresh(info, head)
+}
+
+/// Change counts from one status walk, plus upstream and stash counts.
+fn status(repo: &mut git2::Repository) -> Option<GitStatus> {
+    let mut status = GitStatus::default();
+    let mut opts = git2::StatusOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true);
+    for entry in repo.statuses(Some(&mut opts)).ok()?.iter() {
+        let flags = entry.status();
+        if flags.is_wt_new() {
+            status.untracked += 1;
+            continue;
+        }
+        if flags.intersects(
+            git2::Status::INDEX_NEW
+                | git2::Status::INDEX_MODIFIED
+                | git2::Status::INDEX_DELETED
+                | git2::Status::INDEX_RENAMED
+                | git2::Status::INDEX_TYPECHANGE,
+        ) {
+            status.staged += 1;
+        }
+        if flags.intersects(
+            git2::Status::WT_MODIFIED
+                | git2::Status::WT_DELETED
+                | git2::Status::WT_RENAMED
+                | git2::Status::WT_TYPECHANGE
+                | git2::Status::CONFLICTED,
+        ) {
+            status.unstaged += 1;
+        }
+    }
+    if let Some((ahead, behind)) = ahead_behind(repo) {
+        status.ahead = ahead;
+        status.behind = behind;
+    }
+    let _ = repo.stash_foreach(|_, _, _| {
+        status.stashes += 1;
+        true
+    });
+    Some(status)
+}
+
+/// Commits ahead of and behind the branch's upstream, if it has one.
+fn ahead_behind(repo: &git2::Repository) -> Option<(usize, usize)> {
+    let head = repo.head().ok()?;
+    let local = head.target()?;
+    let upstream = repo.branch_upstream_name(head.name()?).ok()?;
+    let upstream = repo.refname_to_id(upstream.as_str()?).ok()?;
+    repo.graph_ahead_behind(local, upstream).ok()
+}
+
+/// Where HEAD points, as the reference name and commit: a checkout, commit
+/// or reset changes it.
+fn head_key(repo: &git2::Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    let target = head.target().map(|oid| oid.to_string()).unwrap_or_default();
+    Some(format!("{}@{target}", head.name().unwrap_or_default()))
+}
+
+// The branch as a clean string for the UI.
+fn branch(repo: &git2::Repository, is_dirty: bool) -> Option<String> {
+    let head = repo.head().ok()?;
+    let shorthand = head.shorthand()?;
+
+    let icon = if is_dirty { " " } else { " ✔" }; // nf-fa-warning, nf-fa-check
+
+    Some(format!("{shorthand}{icon}"))
+}
@@ -0,0 +1,297 @@
+// src/gitpanel.rs
+
+//! The git panel (`:git` / F4): the repository's changed files, grouped as
+//! `git status` groups them, with stage, unstage and discard on the
+//! selected one. Reading the status runs on the blocking pool like the
+//! status bar's lookups; the actions are single index or checkout calls
+//! and run in place.
+
+use crate::error::AppResult;
+use anyhow::{anyhow, bail};
+use git2::{IndexAddOption, Repository, Status, StatusOptions, build::CheckoutBuilder};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::sync::oneshot::{self, error::TryRecvError};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Area {
+    Conflicted,
+    Staged,
+    Unstaged,
+    Untracked,
+}
+
+impl Area {
+    pub fn label(self) -> &'static str {
+        match self {
+            Area::Conflicted => "Unmerged",
+            Area::Staged => "Staged",
+            Area::Unstaged => "Not staged",
+            Area::Untracked => "Untracked",
+        }
+    }
+}
+
+pub struct FileRow {
+    /// Relative to the work tree root; untracked directories end in `/`.
+    pub path: String,
+    pub area: Area,
+    /// `M`odified, `A`dded, `D`eleted, `R`enamed, `T`ype change, `?`, `U`.
+    pub kind: char,
+}
+
+struct Snapshot {
+    root: PathBuf,
+    branch: Option<String>,
+    rows: Vec<FileRow>,
+}
+
+pub struct GitPanel {
+    cwd: PathBuf,
+    /// Work tree root, once read.
+    pub root: Option<PathBuf>,
+    pub branch: Option<String>,
+    pub rows: Vec<FileRow>,
+    pub selected: usize,
+    /// Result of the last action or read, shown in the footer.
+    pub message: Option<String>,
+    pending: Option<oneshot::Receiver<AppResult<Snapshot>>>,
+}
+
+impl GitPanel {
+    /// Opens the panel for the repository containing `cwd`; the files
+    /// arrive through `poll`.
+    pub fn open(cwd: &Path) -> Self {
+        let mut panel = Self {
+            cwd: cwd.to_path_buf(),
+            root: None,
+            branch: None,
+            rows: Vec::new(),
+            selected: 0,
+            message: None,
+            pending: None,
+        };
+        panel.refresh();
+        panel
+    }
+
+    pub fn loading(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Reads the status again in the background.
+    pub fn refresh(&mut self) {
+        let (tx, rx) = oneshot::channel();
+        let cwd = self.cwd.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(read(&cwd));
+        });
+        self.pending = Some(rx);
+    }
+
+    /// Takes a finished read. Returns true if the panel changed.
+    pub fn poll(&mut self) -> bool {
+        let Some(rx) = &mut self.pending else {
+            return false;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Closed) => Err(anyhow!("status read failed")),
+        };
+        self.pending = None;
+        match result {
+            Ok(snapshot) => {
+                // Stay on the same file, or the row where it was.
+                let selected = self.selected_row().map(|row| (row.path.clone(), row.area));
+                self.root = Some(snapshot.root);
+                self.branch = snapshot.branch;
+                self.rows = snapshot.rows;
+                self.selected = selected
+                    .and_then(|(path, area)| {
+                        self.rows.iter().position(|r| r.path == path && r.area == area)
+                    })
+                    .unwrap_or(self.selected)
+                    .min(self.rows.len().saturating_sub(1));
+            }
+            Err(e) => self.message = Some(e.to_string()),
+        }
+        true
+    }
+
+    pub fn selected_row(&self) -> Option<&FileRow> {
+        self.rows.get(self.selected)
+    }
+
+    pub fn select_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_down(&mut self) {
+        if self.selected + 1 < self.rows.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Stages the selected file, or unstages it if it is in the Staged
+    /// group. Returns what was done, for the footer.
+    pub fn toggle_stage(&mut self) -> AppResult<String> {
+        let (repo, row) = self.selected_repo()?;
+        if row.area == Area::Staged {
+            unstage(&repo, &row.path)?;
+            Ok(format!("unstaged {}", row.path))
+        } else {
+            stage(&repo, &row.path)?;
+            Ok(format!("staged {}", row.path))
+        }
+    }
+
+    /// Throws away `path`'s unstaged changes, or deletes it if it is
+    /// untracked, as long as the panel still lists it in `area`. Staged
+    /// changes are left alone.
+    pub fn discard(&self, path: &str, area: Area) -> AppResult<String> {
+        let row = self
+            .rows
+            .iter()
+            .find(|row| row.path == path && row.area == area)
+            .ok_or_else(|| anyhow!("{path} has changed since; nothing discarded"))?;
+        let repo = self.repo()?;
+        let root = repo.workdir().ok_or_else(|| anyhow!("bare repository"))?;
+        match row.area {
+            Area::Untracked => {
+                let path = root.join(&row.path);
+                if path.is_dir() {
+                    fs::remove_dir_all(&path)?;
+                } else {
+                    fs::remove_file(&path)?;
+                }
+                Ok(format!("deleted {}", row.path))
+            }
+            Area::Unstaged => {
+                let mut checkout = CheckoutBuilder::new();
+                checkout.force().path(literal(&row.path));
+                repo.checkout_index(None, Some(&mut checkout))?;
+                Ok(format!("discarded changes to {}", row.path))
+            }
+            Area::Staged | Area::Conflicted => {
+                bail!("{} has staged changes; unstage it first", row.path)
+            }
+        }
+    }
+
+    fn selected_repo(&self) -> AppResult<(Repository, &FileRow)> {
+        let row = self.selected_row().ok_or_else(|| anyhow!("no file selected"))?;
+        Ok((self.repo()?, row))
+    }
+
+    fn repo(&self) -> AppResult<Repository> {
+        let root = self.root.as_deref().ok_or_else(|| anyhow!("status not read yet"))?;
+        Ok(Repository::open(root)?)
+    }
+}
+
+fn read(cwd: &Path) -> AppResult<Snapshot> {
+    let repo = Repository::discover(cwd).map_err(|_| anyhow!("not in a git repository"))?;
+    let root = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("bare repository"))?
+        .to_path_buf();
+    let branch = repo.head().ok().and_then(|h| h.shorthand().map(str::to_string));
+    let mut opts = StatusOptions::new();
+    // Untracked directories stay one row, as `git status` shows them.
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .renames_head_to_index(true);
+    let mut rows = Vec::new();
+    for entry in repo.statuses(Some(&mut opts))?.iter() {
+        let path = String::from_utf8_lossy(entry.path_bytes()).to_string();
+        let status = entry.status();
+        let mut push = |area, kind| {
+            rows.push(FileRow {
+                path: path.clone(),
+                area,
+                kind,
+            })
+        };
+        if status.is_conflicted() {
+            push(Area::Conflicted, 'U');
+            continue;
+        }
+        if status.is_wt_new() {
+            push(Area::Untracked, '?');
+            continue;
+        }
+        if let Some(kind) = index_kind(status) {
+            push(Area::Staged, kind);
+        }
+        if let Some(kind) = worktree_kind(status) {
+            push(Area::Unstaged, kind);
+        }
+    }
+    rows.sort_by(|a, b| a.area.cmp(&b.area).then_with(|| a.path.cmp(&b.path)));
+    Ok(Snapshot { root, branch, rows })
+}
+
+fn index_kind(status: Status) -> Option<char> {
+    Some(match status {
+        s if s.is_index_new() => 'A',
+        s if s.is_index_modified() => 'M',
+        s if s.is_index_deleted() => 'D',
+        s if s.is_index_renamed() => 'R',
+        s if s.is_index_typechange() => 'T',
+        _ => return None,
+    })
+}
+
+fn worktree_kind(status: Status) -> Option<char> {
+    Some(match status {
+        s if s.is_wt_modified() => 'M',
+        s if s.is_wt_deleted() => 'D',
+        s if s.is_wt_renamed() => 'R',
+        s if s.is_wt_typechange() => 'T',
+        _ => return None,
+    })
+}
+
+/// `git add`, or `git rm --cached` for a file deleted from the work tree.
+fn stage(repo: &Repository, path: &str) -> AppResult<()> {
+    let root = repo.workdir().ok_or_else(|| anyhow!("bare repository"))?;
+    let mut index = repo.index()?;
+    if path.ends_with('/') {
+        index.add_all([path], IndexAddOption::DEFAULT, None)?;
+    } else if root.join(path).symlink_metadata().is_ok() {
+        index.add_path(Path::new(path))?;
+    } else {
+        index.remove_path(Path::new(path))?;
+    }
+    index.write()?;
+    Ok(())
+}
+
+/// `git restore --staged`: the index entry goes back to HEAD's, or away
+/// before the first commit.
+/// `path` as a pathspec that matches only itself. git2 0.18's checkout and
+/// reset have no switch for exact matching, so a file named `*.rs` would
+/// otherwise stand for every Rust file.
+fn literal(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for c in path.chars() {
+        if matches!(c, '*' | '?' | '[' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn unstage(repo: &Repository, path: &str) -> AppResult<()> {
+    match repo.head().ok().and_then(|head| head.peel_to_commit().ok()) {
+        Some(commit) => repo.reset_default(Some(commit.as_object()), [literal(path)])?,
+        None => {
+            let mut index = repo.index()?;
+            index.remove_path(Path::new(path))?;
+            index.write()?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+// src/handoff.rs
+
+//! Terminal handoff for full-screen programs (editors, pagers, `ssh`, ...).
+//! No pseudo-terminal is involved: halo leaves the alternate screen, puts
+//! the child in its own process group as the terminal's foreground group,
+//! and blocks until it exits or stops, then takes the terminal back. The
+//! child sees the real tty, so it gets the size, keys and signals directly,
+//! but its output is not captured in the log.
+
+use crate::command::JobExit;
+use crate::error::AppResult;
+use crate::exports;
+use crate::scratch;
+use crossterm::{
+    cursor::Show,
+    event::{DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Programs that need a real terminal rather than piped stdio.
+pub const DEFAULT_INTERACTIVE: &[&str] = &[
+    "vi", "vim", "nvim", "nano", "emacs", "less", "more", "man", "top", "htop", "btop", "ssh",
+    "tmux", "screen", "watch", "fzf", "lazygit", "tig",
+];
+
+/// A command waiting for the UI loop to hand it the terminal.
+pub struct InteractiveRequest {
+    pub cmd: String,
+    pub args: Vec<String>,
+    pub cwd: PathBuf,
+}
+
+/// Leaves the alternate screen so a child can draw on the real terminal.
+pub fn suspend_tui() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, DisableFocusChange, Show)
+}
+
+pub fn resume_tui() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture, EnableFocusChange)
+}
+
+/// How a handed-off program ended.
+pub struct Outcome {
+    pub exit: JobExit,
+    /// It was stopped (Ctrl+Z) at least once and continued, since halo
+    /// cannot park a job that owns the terminal.
+    pub stopped: bool,
+}
+
+/// Runs `cmd` attached to the controlling terminal as its foreground process
+/// group and blocks until it exits, then takes the terminal back.
+pub fn run_interactive(cmd: &str, args: &[String], cwd: &Path) -> AppResult<Outcome> {
+    let mut command = Command::new(cmd);
+    command.args(args).current_dir(cwd);
+    if let Some(dir) = scratch::current() {
+        command.env(scratch::ENV, dir);
+    }
+    command.envs(exports::all());
+    // Become our own process group and grab the terminal before exec, so
+    // Ctrl+C/Ctrl+Z from the tty reach the child instead of halo.
+    // SAFETY: the hook runs in the forked child before exec and only makes
+    // async-signal-safe calls (setpgid, signal, tcsetpgrp, getpid).
+    unsafe {
+        command.pre_exec(|| {
+            libc::setpgid(0, 0);
+            let prev = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+            libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpid());
+            libc::signal(libc::SIGTTOU, prev);
+            Ok(())
+        });
+    }
+    let child = command.spawn()?;
+    let pid = child.id() as libc::pid_t;
+
+    let mut status: libc::c_int = 0;
+    let mut stopped = false;
+    let exit = loop {
+        // SAFETY: `pid` is our own child and `status` outlives the call.
+        let rc = unsafe { libc::waitpid(pid, &mut status, libc::WUNTRACED) };
+        if rc < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            reclaim_terminal();
+            return Err(err.into());
+        }
+        if libc::WIFSTOPPED(status) {
+            // halo cannot park a job that owns the terminal; keep it running.
+            // SAFETY: kill takes any pid; `-pid` is the child's own group.
+            unsafe {
+                libc::kill(-pid, libc::SIGCONT);
+            }
+            stopped = true;
+            continue;
+        }
+        break if libc::WIFEXITED(status) {
+            JobExit::code(libc::WEXITSTATUS(status))
+        } else if libc::WIFSIGNALED(status) {
+            JobExit::signal(libc::WTERMSIG(status))
+        } else {
+            JobExit::default()
+        };
+    };
+    reclaim_terminal();
+    Ok(Outcome { exit, stopped })
+}
+
+fn reclaim_terminal() {
+    // SAFETY: plain calls on halo's own stdin and process group; SIGTTOU
+    // is ignored only around tcsetpgrp, as in the child.
+    unsafe {
+        let prev = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+        libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
+        libc::signal(libc::SIGTTOU, prev);
+    }
+}
@@ -0,0 +1,81 @@
+// src/hooks.rs
+
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command as TokioCommand;
+
+/// How long halo waits for `on_exit` hooks before giving up on them.
+const EXIT_HOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// User commands from the `[hooks]` table. Each entry is run with `sh -c`, so
+/// hooks work the same no matter which shell the user normally lives in.
+#[derive(Clone, Default)]
+pub struct HooksConfig {
+    pub on_exit: Vec<String>,
+    pub on_chdir: Vec<String>,
+}
+
+impl HooksConfig {
+    pub fn from_table(tbl: &toml::value::Table) -> Self {
+        Self {
+            on_exit: string_list(tbl.get("on_exit")),
+            on_chdir: string_list(tbl.get("on_chdir")),
+        }
+    }
+}
+
+/// Accepts either a single string or an array of strings.
+fn string_list(value: Option<&toml::Value>) -> Vec<String> {
+    match value {
+        Some(toml::Value::String(s)) => vec![s.clone()],
+        Some(toml::Value::Array(items)) => items
+            .iter()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn hook_command(hook: &str, cwd: &Path, env: &[(&str, String)]) -> TokioCommand {
+    let mut cmd = TokioCommand::new("sh");
+    cmd.arg("-c")
+        .arg(hook)
+        .current_dir(cwd)
+        .envs(env.iter().map(|(k, v)| (*k, v.as_str())))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    cmd
+}
+
+/// Fires `on_chdir` hooks in the background; returns spawn errors for the log.
+pub fn run_chdir_hooks(hooks: &HooksConfig, old: &Path, new: &Path) -> Vec<String> {
+    let env = [
+        ("HALO_OLDPWD", old.display().to_string()),
+        ("HALO_PWD", new.display().to_string()),
+    ];
+    let mut errors = Vec::new();
+    for hook in &hooks.on_chdir {
+        match hook_command(hook, new, &env).spawn() {
+            Ok(mut child) => {
+                tokio::spawn(async move {
+                    let _ = child.wait().await;
+                });
+            }
+            Err(e) => errors.push(format!("[hook error] {hook}: {e}")),
+        }
+    }
+    errors
+}
+
+/// Runs `on_exit` hooks one after another, each bounded by a timeout so a
+/// stuck hook can't keep halo from quitting.
+pub async fn run_exit_hooks(hooks: &HooksConfig, cwd: &Path) {
+    let env = [("HALO_PWD", cwd.display().to_string())];
+    for hook in &hooks.on_exit {
+        if let Ok(mut child) = hook_command(hook, cwd, &env).kill_on_drop(true).spawn() {
+            let _ = tokio::time::timeout(EXIT_HOOK_TIMEOUT, child.wait()).await;
+        }
+    }
+}
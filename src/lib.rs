@@ -0,0 +1,114 @@
+// src/lib.rs
+
+//! halo's shell engine: the session state, job runner, parser and
+//! completion that the `halo-shell` binary's ratatui TUI (its `app`,
+//! `event` and `ui` modules) is built on. The documented modules are:
+//!
+//! - [`state`]: [`state::State`], everything a session knows: the command
+//!   log, history, aliases, configuration, theme and input line.
+//! - [`command`]: [`command::CommandManager`] runs commands as jobs and
+//!   reports their output and exit as [`command::CommandUpdate`]s;
+//!   [`command::CommandLog`] is one entry of the log.
+//! - [`parser`]: tokenizing and parsing command lines, with byte spans.
+//! - [`completion`]: Tab completion and suggestions.
+//! - [`doctor`]: the environment report behind `halo doctor`.
+//!
+//! None of it is a stable API yet. `State` in particular keeps its fields
+//! public for the TUI, and several of them hold types from the hidden
+//! modules below, which exist for the binary and may change in any
+//! release.
+
+#![warn(missing_docs)]
+
+pub mod command;
+pub mod completion;
+pub mod crash;
+pub mod doctor;
+pub mod error;
+pub mod lock;
+pub mod parser;
+pub mod state;
+
+// Used by the halo-shell binary; not part of the documented API.
+#[doc(hidden)]
+pub mod aliases;
+#[doc(hidden)]
+pub mod builder;
+#[doc(hidden)]
+pub mod cdfix;
+#[doc(hidden)]
+pub mod clipboard;
+#[doc(hidden)]
+pub mod colorblind;
+#[doc(hidden)]
+pub mod diffview;
+#[doc(hidden)]
+pub mod dirpicker;
+#[doc(hidden)]
+pub mod dirstack;
+#[doc(hidden)]
+pub mod disk;
+#[doc(hidden)]
+pub mod dryrun;
+#[doc(hidden)]
+pub mod export;
+#[doc(hidden)]
+pub mod exports;
+#[doc(hidden)]
+pub mod flags;
+#[doc(hidden)]
+pub mod frecency;
+#[doc(hidden)]
+pub mod git;
+#[doc(hidden)]
+pub mod gitpanel;
+#[doc(hidden)]
+pub mod handoff;
+#[doc(hidden)]
+pub mod hooks;
+#[doc(hidden)]
+pub mod links;
+#[doc(hidden)]
+pub mod normalize;
+#[doc(hidden)]
+pub mod notify;
+#[doc(hidden)]
+pub mod output;
+#[doc(hidden)]
+pub mod pager;
+#[doc(hidden)]
+pub mod panes;
+#[doc(hidden)]
+pub mod pathindex;
+#[doc(hidden)]
+pub mod perf;
+#[doc(hidden)]
+pub mod persist;
+#[doc(hidden)]
+pub mod procs;
+#[doc(hidden)]
+pub mod profile;
+#[doc(hidden)]
+pub mod prompt;
+#[doc(hidden)]
+pub mod ranking;
+#[doc(hidden)]
+pub mod restore;
+#[doc(hidden)]
+pub mod scratch;
+#[doc(hidden)]
+pub mod sessions;
+#[doc(hidden)]
+pub mod share;
+#[doc(hidden)]
+pub mod specs;
+#[doc(hidden)]
+pub mod summary;
+#[doc(hidden)]
+pub mod tabs;
+#[doc(hidden)]
+pub mod themes;
+#[doc(hidden)]
+pub mod timefmt;
+#[doc(hidden)]
+pub mod words;
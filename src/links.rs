@@ -0,0 +1,196 @@
+// src/links.rs
+
+//! Hyperlinks in command output: OSC 8 links, whose target is hidden
+//! behind their text, and bare URLs. Links are drawn underlined and open
+//! with the system opener on click or Alt+L. Output is untrusted, so only
+//! web links open directly; `file` links are asked about first and other
+//! schemes (custom URL handlers, `javascript:`, ...) never open.
+
+use crate::command::CommandLog;
+use crate::error::AppResult;
+use crate::state;
+use std::ops::Range;
+use std::process::{Command, Stdio};
+
+/// An OSC 8 link in a log entry's output.
+#[derive(Clone, Debug)]
+pub struct Link {
+    /// Output line.
+    pub line: usize,
+    /// Byte range of the link text in the displayed line.
+    pub range: Range<usize>,
+    pub url: String,
+}
+
+/// Removes OSC 8 sequences from `line`, returning the plain text and the
+/// byte ranges of linked text in it with their targets. A link is
+/// `ESC ] 8 ; params ; URI ST text ESC ] 8 ; ; ST`, ST being `ESC \` or BEL.
+pub fn strip_osc8(line: &str) -> (String, Vec<(Range<usize>, String)>) {
+    let mut text = String::with_capacity(line.len());
+    let mut links = Vec::new();
+    let mut open: Option<(usize, String)> = None;
+    let mut rest = line;
+    while let Some(at) = rest.find("\x1b]8;") {
+        text.push_str(&rest[..at]);
+        let body = &rest[at + 4..];
+        let (payload, after) = match (body.find('\x07'), body.find("\x1b\\")) {
+            (Some(bel), Some(st)) if st < bel => (&body[..st], &body[st + 2..]),
+            (Some(bel), _) => (&body[..bel], &body[bel + 1..]),
+            (None, Some(st)) => (&body[..st], &body[st + 2..]),
+            // Unterminated: keep the rest as text.
+            (None, None) => {
+                rest = &rest[at..];
+                break;
+            }
+        };
+        let url = payload.split_once(';').map_or("", |(_, url)| url);
+        if let Some((start, target)) = open.take()
+            && start < text.len()
+        {
+            links.push((start..text.len(), target));
+        }
+        if !url.is_empty() {
+            open = Some((text.len(), url.to_string()));
+        }
+        rest = after;
+    }
+    text.push_str(rest);
+    if let Some((start, target)) = open
+        && start < text.len()
+    {
+        links.push((start..text.len(), target));
+    }
+    (text, links)
+}
+
+/// Byte ranges of bare `http(s)://` and `file://` URLs in `text`.
+/// Trailing punctuation is left out, so a URL ending a sentence works.
+pub fn find_urls(text: &str) -> Vec<Range<usize>> {
+    let mut urls = Vec::new();
+    let mut from = 0;
+    while let Some(start) = ["https://", "http://", "file://"]
+        .iter()
+        .filter_map(|scheme| text[from..].find(scheme))
+        .min()
+        .map(|at| from + at)
+    {
+        let len = text[start..]
+            .find(|c: char| c.is_whitespace() || "<>\"'`".contains(c))
+            .unwrap_or(text.len() - start);
+        let url = text[start..start + len].trim_end_matches(|c: char| ".,;:!?)]}".contains(c));
+        let end = start + url.len();
+        if !url.ends_with("//") {
+            urls.push(start..end);
+        }
+        from = end.max(start + 1);
+    }
+    urls
+}
+
+/// Links in output line `line`, whose displayed text is `text`, in
+/// order: the entry's OSC 8 `links` on it and bare URLs outside them.
+pub fn line_links(links: &[Link], line: usize, text: &str) -> Vec<(Range<usize>, String)> {
+    let mut found: Vec<(Range<usize>, String)> = links
+        .iter()
+        .filter(|link| link.line == line && link.range.end <= text.len())
+        .map(|link| (link.range.clone(), link.url.clone()))
+        .collect();
+    for range in find_urls(text) {
+        if !found.iter().any(|(r, _)| r.start < range.end && range.start < r.end) {
+            found.push((range.clone(), text[range].to_string()));
+        }
+    }
+    found.sort_by_key(|(range, _)| range.start);
+    found
+}
+
+/// The last link in `log`'s output, for Alt+L.
+pub fn last_link(log: &CommandLog) -> Option<String> {
+    let lines: Vec<&str> = log.output.lines().collect();
+    lines.iter().enumerate().rev().find_map(|(k, line)| {
+        let text = state::displayed_line(line);
+        line_links(&log.links, k, text).pop().map(|(_, url)| url)
+    })
+}
+
+/// What `open` may do with a link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Safety {
+    /// `http` and `https`.
+    Open,
+    /// `file`: only once the user has seen the path and said yes.
+    Confirm,
+    Refuse,
+}
+
+/// Sorts `url` by its scheme, which is case-insensitive.
+pub fn safety(url: &str) -> Safety {
+    let scheme = url.split_once(':').map(|(scheme, _)| scheme.to_ascii_lowercase());
+    match scheme.as_deref() {
+        Some("http" | "https") => Safety::Open,
+        Some("file") => Safety::Confirm,
+        _ => Safety::Refuse,
+    }
+}
+
+/// Opens `url` with `xdg-open`, or `open` on macOS, without waiting.
+pub fn open(url: &str) -> AppResult<()> {
+    let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    let mut child = Command::new(opener)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("{opener}: {e}"))?;
+    // Reap it so it doesn't linger as a zombie.
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_osc8_with_either_terminator() {
+        let line = "see \x1b]8;;https://a.example\x1b\\docs\x1b]8;;\x1b\\ and \
+                    \x1b]8;id=1;file:///tmp/x\x07x\x1b]8;;\x07.";
+        let (text, links) = strip_osc8(line);
+        assert_eq!(text, "see docs and x.");
+        assert_eq!(
+            links,
+            [
+                (4..8, "https://a.example".to_string()),
+                (13..14, "file:///tmp/x".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn strip_osc8_unterminated_and_unclosed() {
+        let (text, links) = strip_osc8("a\x1b]8;;https://x");
+        assert_eq!(text, "a\x1b]8;;https://x");
+        assert!(links.is_empty());
+        let (text, links) = strip_osc8("\x1b]8;;https://x\x07rest");
+        assert_eq!(text, "rest");
+        assert_eq!(links, [(0..4, "https://x".to_string())]);
+    }
+
+    #[test]
+    fn find_urls_trims_punctuation() {
+        let text = "go to https://example.com/a?b=1. or (http://x.org) file:///etc/hosts";
+        let found: Vec<&str> = find_urls(text).into_iter().map(|r| &text[r]).collect();
+        assert_eq!(found, ["https://example.com/a?b=1", "http://x.org", "file:///etc/hosts"]);
+        assert!(find_urls("just https:// here").is_empty());
+        assert!(find_urls("no links").is_empty());
+    }
+
+    #[test]
+    fn safety_by_scheme() {
+        assert_eq!(safety("HTTPS://example.com"), Safety::Open);
+        assert_eq!(safety("file:///etc/passwd"), Safety::Confirm);
+        assert_eq!(safety("javascript:alert(1)"), Safety::Refuse);
+        assert_eq!(safety("no-scheme"), Safety::Refuse);
+    }
+}
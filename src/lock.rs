@@ -0,0 +1,150 @@
+// src/lock.rs
+
+//! Screen lock for shells left open on shared machines: after `[lock]
+//! idle_minutes` without input (or on the `lock` builtin) the log and input
+//! are hidden until a key is pressed, or the passphrase typed if one is set.
+//! Commands keep running underneath; only the screen is locked.
+//!
+//! The passphrase is kept as a salted PBKDF2-SHA256 hash, `[lock]
+//! passphrase_hash`, which `halo passphrase` prints.
+
+use crate::error::AppResult;
+use anyhow::anyhow;
+use crossterm::event::{KeyCode, KeyEvent};
+use pbkdf2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use pbkdf2::{Algorithm, Pbkdf2};
+use std::fs::File;
+use std::io::{self, BufRead, Read, Write};
+use std::time::Duration;
+
+/// The `[lock]` table.
+#[derive(Clone, Default)]
+pub struct LockConfig {
+    /// Locks after this long without input.
+    pub idle: Option<Duration>,
+    /// The PHC string of the passphrase, from `passphrase_hash` or hashed
+    /// here from a plain `passphrase`.
+    pub passphrase_hash: Option<String>,
+    /// The passphrase was written in plain text, under `passphrase`.
+    pub plaintext: bool,
+    /// `passphrase_hash` is set but is not a PBKDF2 hash, so it is ignored.
+    pub invalid_hash: bool,
+}
+
+impl LockConfig {
+    /// Reads the `[lock]` table of halo.toml.
+    pub fn from_table(tbl: &toml::value::Table) -> Self {
+        let idle = tbl
+            .get("idle_minutes")
+            .and_then(|v| v.as_integer())
+            .filter(|m| *m > 0)
+            .map(|m| Duration::from_secs(m as u64 * 60));
+        let hash = tbl.get("passphrase_hash").and_then(|v| v.as_str());
+        let plain = tbl
+            .get("passphrase")
+            .and_then(|v| v.as_str())
+            .filter(|p| !p.is_empty());
+        let invalid_hash = hash.is_some_and(|h| !is_valid_hash(h));
+        let passphrase_hash = match hash.filter(|h| is_valid_hash(h)) {
+            Some(hash) => Some(hash.to_string()),
+            None => plain.and_then(|p| hash_passphrase(p).ok()),
+        };
+        Self {
+            idle,
+            plaintext: hash.is_none() && plain.is_some(),
+            passphrase_hash,
+            invalid_hash,
+        }
+    }
+}
+
+/// Hashes `passphrase` with PBKDF2-SHA256 and a random salt, as the PHC
+/// string `passphrase_hash` takes.
+pub fn hash_passphrase(passphrase: &str) -> AppResult<String> {
+    let mut salt = [0u8; 16];
+    File::open("/dev/urandom")?.read_exact(&mut salt)?;
+    let salt = SaltString::b64_encode(&salt).map_err(|e| anyhow!("salt: {e}"))?;
+    let hash = Pbkdf2
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map_err(|e| anyhow!("hash: {e}"))?;
+    Ok(hash.to_string())
+}
+
+fn is_valid_hash(hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|h| Algorithm::try_from(h.algorithm).is_ok())
+}
+
+fn verify(hash: &str, typed: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|h| Pbkdf2.verify_password(typed.as_bytes(), &h).is_ok())
+}
+
+/// Asks for a passphrase on the terminal, twice and without echoing it,
+/// for `halo passphrase`.
+pub fn prompt_passphrase() -> AppResult<String> {
+    let first = read_hidden("passphrase: ")?;
+    if first.is_empty() {
+        return Err(anyhow!("the passphrase is empty"));
+    }
+    if read_hidden("again: ")? != first {
+        return Err(anyhow!("the passphrases differ"));
+    }
+    Ok(first)
+}
+
+fn read_hidden(prompt: &str) -> io::Result<String> {
+    eprint!("{prompt}");
+    io::stderr().flush()?;
+    // SAFETY: termios is plain data, which tcgetattr fills in.
+    let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+    // SAFETY: `saved` outlives the call.
+    let tty = unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut saved) } == 0;
+    if tty {
+        let mut quiet = saved;
+        quiet.c_lflag &= !libc::ECHO;
+        // SAFETY: `quiet` is a valid termios from tcgetattr.
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &quiet) };
+    }
+    let mut line = String::new();
+    let read = io::stdin().lock().read_line(&mut line);
+    if tty {
+        // SAFETY: as above, with the settings saved before.
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &saved) };
+        eprintln!();
+    }
+    read?;
+    Ok(line.trim_end_matches(['\n', '\r']).to_string())
+}
+
+/// The lock screen's input, while locked.
+#[derive(Default)]
+pub struct Lock {
+    /// What has been typed of the passphrase.
+    pub typed: String,
+    /// The last attempt was wrong.
+    pub failed: bool,
+}
+
+impl Lock {
+    /// Feeds a key to the lock screen; true once it unlocks.
+    pub fn key(&mut self, key: KeyEvent, passphrase_hash: Option<&str>) -> bool {
+        let Some(hash) = passphrase_hash else {
+            return true;
+        };
+        match key.code {
+            KeyCode::Char(c) => self.typed.push(c),
+            KeyCode::Backspace => {
+                self.typed.pop();
+            }
+            KeyCode::Esc => self.typed.clear(),
+            KeyCode::Enter => {
+                if verify(hash, &self.typed) {
+                    return true;
+                }
+                self.typed.clear();
+                self.failed = true;
+            }
+            _ => {}
+        }
+        false
+    }
+}
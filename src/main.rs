@@ -1,51 +1,107 @@
 // src/main.rs
 
 mod app;
-mod command;
-mod completion;
-mod error;
 mod event;
-mod state;
-mod themes;
 mod ui;
 
-use app::App;
+use crate::app::App;
 use crossterm::{
     cursor::Show,
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
+use halo_core::crash;
+use halo_core::doctor;
+use halo_core::error::AppResult;
+use halo_core::lock;
 use ratatui::prelude::*;
 use std::io;
 
-use crate::error::AppResult;
-
 #[tokio::main]
 async fn main() -> AppResult<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let profile_startup = args.iter().any(|arg| arg == "--profile-startup");
+    // Skips halo.toml, themes and the saved session, for when an edit to
+    // them keeps halo from starting.
+    let safe_mode = args.iter().any(|arg| arg == "--safe");
+    // `halo doctor` prints its report and exits without starting the TUI.
+    if args.first().is_some_and(|arg| arg == "doctor") {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        for line in doctor::report(&cwd) {
+            println!("{line}");
+        }
+        return Ok(());
+    }
+    // `halo passphrase` asks for a screen lock passphrase and prints the
+    // line that keeps its hash in halo.toml.
+    if args.first().is_some_and(|arg| arg == "passphrase") {
+        let hash = lock::hash_passphrase(&lock::prompt_passphrase()?)?;
+        println!("[lock]\npassphrase_hash = \"{hash}\"");
+        return Ok(());
+    }
+
     // Setup terminal with a guard to always restore state
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableFocusChange)?;
+    // Save the window title on the terminal's stack; halo renames the window.
+    let _ = io::Write::write_all(&mut stdout, b"\x1b[22;0t");
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     struct TerminalCleanupGuard;
     impl Drop for TerminalCleanupGuard {
         fn drop(&mut self) {
-            let _ = disable_raw_mode();
-            let mut stdout = io::stdout();
-            let _ = execute!(stdout, LeaveAlternateScreen, DisableMouseCapture, Show);
+            restore_terminal();
         }
     }
 
-    let _guard = TerminalCleanupGuard;
+    let guard = TerminalCleanupGuard;
+
+    // A panic message printed on the alternate screen is wiped the moment the
+    // guard restores the terminal; restore first so it stays readable. Tokio
+    // catches panics in its worker threads and would keep halo drawing on a
+    // restored terminal, so a panic off the main thread ends the process.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+        if let Some(path) = crash::write_report(&format!("panic: {info}")) {
+            eprintln!("halo crashed; report saved to {}", path.display());
+        }
+        if std::thread::current().name() != Some("main") {
+            std::process::abort();
+        }
+    }));
 
     // Create and run the application
-    let mut app = App::new()?;
-    if let Err(err) = app.run(&mut terminal).await {
+    let mut app = App::new(safe_mode)?;
+    let result = app.run(&mut terminal).await;
+
+    // Errors and reports go to the real terminal, so leave the alternate
+    // screen before printing anything.
+    drop(guard);
+    if let Err(err) = result {
         eprintln!("Error: {err:?}");
+        if let Some(path) = crash::write_report(&format!("error: {err:?}")) {
+            eprintln!("crash report saved to {}", path.display());
+        }
+    }
+    if profile_startup {
+        for line in app.state.startup.report() {
+            eprintln!("{line}");
+        }
     }
 
     Ok(())
 }
+
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let mut stdout = io::stdout();
+    let _ = execute!(stdout, LeaveAlternateScreen, DisableMouseCapture, DisableFocusChange, Show);
+    // Put back the title saved at startup.
+    let _ = io::Write::write_all(&mut stdout, b"\x1b[23;0t");
+    let _ = io::Write::flush(&mut stdout);
+}
@@ -0,0 +1,36 @@
+// src/normalize.rs
+
+//! Typographic look-alikes that arrive when commands are pasted from chat
+//! apps and documents. The shell sees `“file”` or a non-breaking space as
+//! part of a word, which ends in a baffling "No such file or directory", so
+//! the input line swaps them for their ASCII counterparts as they come in.
+
+pub enum Fix {
+    Replace(char),
+    Drop,
+}
+
+/// What to do with `c`, or None to keep it.
+pub fn fix(c: char) -> Option<Fix> {
+    match c {
+        '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => Some(Fix::Replace('\'')),
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => Some(Fix::Replace('"')),
+        '\u{00A0}' | '\u{2007}' | '\u{2009}' | '\u{202F}' => Some(Fix::Replace(' ')),
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' => Some(Fix::Drop),
+        _ => None,
+    }
+}
+
+/// Status bar text after `count` fixes, the latest being `c`.
+pub fn describe(c: char, fix: &Fix, count: usize) -> String {
+    let latest = match fix {
+        Fix::Replace(' ') => format!("non-breaking space U+{:04X} → space", c as u32),
+        Fix::Replace(ascii) => format!("{c} → {ascii}"),
+        Fix::Drop => format!("removed zero-width U+{:04X}", c as u32),
+    };
+    if count == 1 {
+        format!("normalized input: {latest}")
+    } else {
+        format!("normalized {count} characters in input (last: {latest})")
+    }
+}
@@ -0,0 +1,298 @@
+// src/notify.rs
+
+//! Telling the user a command is done. Each event type has its own list of
+//! backends in `[notify]`; every backend but the flash (which the UI draws)
+//! is a `Notifier`.
+
+use serde_json::json;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// How long a visual flash stays on screen.
+pub const FLASH_DURATION: Duration = Duration::from_millis(200);
+/// Webhook requests give up after this long.
+const WEBHOOK_TIMEOUT_SECS: u32 = 10;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NotifyEvent {
+    Finished,
+    Failed,
+}
+
+impl NotifyEvent {
+    fn label(self) -> &'static str {
+        match self {
+            NotifyEvent::Finished => "finished",
+            NotifyEvent::Failed => "failed",
+        }
+    }
+}
+
+pub struct Notification {
+    pub event: NotifyEvent,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub duration_ms: Option<u128>,
+    /// One line for humans: `exit 2 after 4.1s`.
+    pub summary: String,
+}
+
+impl Notification {
+    pub fn title(&self) -> String {
+        format!("halo: command {}", self.event.label())
+    }
+
+    pub fn body(&self) -> String {
+        format!("{} ({})", self.command, self.summary)
+    }
+}
+
+pub trait Notifier {
+    fn notify(&self, note: &Notification);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AlertKind {
+    Bell,
+    Flash,
+    /// OSC 9, understood by iTerm2, WezTerm, kitty and Windows Terminal.
+    Osc9,
+    /// OSC 777, understood by foot, Konsole and VTE terminals.
+    Osc777,
+    Desktop,
+    Webhook,
+}
+
+impl AlertKind {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "bell" => Some(AlertKind::Bell),
+            "flash" | "visual" => Some(AlertKind::Flash),
+            "osc9" => Some(AlertKind::Osc9),
+            "osc777" => Some(AlertKind::Osc777),
+            "desktop" => Some(AlertKind::Desktop),
+            "webhook" => Some(AlertKind::Webhook),
+            _ => None,
+        }
+    }
+
+    /// A single name or a list; `"off"` (or an empty list) disables.
+    fn parse_list(value: &toml::Value) -> Vec<Self> {
+        match value {
+            toml::Value::String(s) => Self::parse(s).into_iter().collect(),
+            toml::Value::Array(items) => items
+                .iter()
+                .filter_map(|v| v.as_str())
+                .filter_map(Self::parse)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// `[notify]` settings.
+#[derive(Clone, Default)]
+pub struct NotifyConfig {
+    /// Backends for a command that exits zero.
+    pub on_finish: Vec<AlertKind>,
+    /// Backends for a command that exits non-zero.
+    pub on_failure: Vec<AlertKind>,
+    /// Skip alerts for commands quicker than this, so typos don't beep.
+    pub min_duration_ms: u128,
+    /// Where the `webhook` backend POSTs.
+    pub webhook_url: Option<String>,
+    /// Stay quiet while the user is watching: the window has focus and the
+    /// log is at the bottom.
+    pub only_when_away: bool,
+}
+
+impl NotifyConfig {
+    pub fn from_table(tbl: &toml::value::Table) -> Self {
+        let mut cfg = Self::default();
+        if let Some(value) = tbl.get("on_finish") {
+            cfg.on_finish = AlertKind::parse_list(value);
+        }
+        if let Some(value) = tbl.get("on_failure") {
+            cfg.on_failure = AlertKind::parse_list(value);
+        }
+        if let Some(ms) = tbl.get("min_duration_ms").and_then(|v| v.as_integer()) {
+            cfg.min_duration_ms = ms.max(0) as u128;
+        }
+        if let Some(away) = tbl.get("only_when_away").and_then(|v| v.as_bool()) {
+            cfg.only_when_away = away;
+        }
+        cfg.webhook_url = tbl
+            .get("webhook_url")
+            .and_then(|v| v.as_str())
+            .filter(|url| !url.is_empty())
+            .map(str::to_string);
+        cfg
+    }
+
+    /// The event a finished command raises, if it ran long enough to
+    /// be worth one.
+    pub fn event_for(
+        &self,
+        exit_code: Option<i32>,
+        duration_ms: Option<u128>,
+    ) -> Option<NotifyEvent> {
+        if duration_ms.unwrap_or(0) < self.min_duration_ms {
+            return None;
+        }
+        Some(if exit_code == Some(0) {
+            NotifyEvent::Finished
+        } else {
+            NotifyEvent::Failed
+        })
+    }
+
+    pub fn backends(&self, event: NotifyEvent) -> &[AlertKind] {
+        match event {
+            NotifyEvent::Finished => &self.on_finish,
+            NotifyEvent::Failed => &self.on_failure,
+        }
+    }
+
+    /// The notifier behind `kind`; `None` for the flash, which needs the
+    /// UI, and for a webhook without a URL.
+    pub fn notifier(&self, kind: AlertKind) -> Option<Box<dyn Notifier>> {
+        match kind {
+            AlertKind::Bell => Some(Box::new(Bell)),
+            AlertKind::Flash => None,
+            AlertKind::Osc9 => Some(Box::new(Osc9)),
+            AlertKind::Osc777 => Some(Box::new(Osc777)),
+            AlertKind::Desktop => Some(Box::new(Desktop)),
+            AlertKind::Webhook => self
+                .webhook_url
+                .clone()
+                .map(|url| Box::new(Webhook { url }) as Box<dyn Notifier>),
+        }
+    }
+}
+
+pub struct Bell;
+
+impl Notifier for Bell {
+    fn notify(&self, _note: &Notification) {
+        ring_bell();
+    }
+}
+
+pub struct Osc9;
+
+impl Notifier for Osc9 {
+    fn notify(&self, note: &Notification) {
+        let text = format!("{}: {}", note.title(), note.body());
+        write_escape(&format!("\x1b]9;{}\x07", printable(&text)));
+    }
+}
+
+pub struct Osc777;
+
+impl Notifier for Osc777 {
+    fn notify(&self, note: &Notification) {
+        // `;` separates the title from the body.
+        let title = printable(&note.title()).replace(';', ",");
+        write_escape(&format!("\x1b]777;notify;{title};{}\x07", printable(&note.body())));
+    }
+}
+
+/// `notify-send` on Linux and the BSDs, `osascript` on macOS.
+pub struct Desktop;
+
+impl Notifier for Desktop {
+    fn notify(&self, note: &Notification) {
+        let (title, body) = (note.title(), note.body());
+        let mut command = if cfg!(target_os = "macos") {
+            let script = format!(
+                "display notification {} with title {}",
+                applescript_string(&body),
+                applescript_string(&title)
+            );
+            let mut command = Command::new("osascript");
+            command.args(["-e", &script]);
+            command
+        } else {
+            let mut command = Command::new("notify-send");
+            command.args(["--app-name=halo", &title, &body]);
+            command
+        };
+        spawn_detached(&mut command, None);
+    }
+}
+
+/// POSTs the notification as JSON with `curl`.
+pub struct Webhook {
+    pub url: String,
+}
+
+impl Notifier for Webhook {
+    fn notify(&self, note: &Notification) {
+        let payload = json!({
+            "event": note.event.label(),
+            "command": note.command,
+            "exit_code": note.exit_code,
+            "duration_ms": note.duration_ms.map(|ms| ms as u64),
+            "text": format!("{}: {}", note.title(), note.body()),
+        });
+        let mut command = Command::new("curl");
+        command
+            .args(["-fsS", "-m", &WEBHOOK_TIMEOUT_SECS.to_string()])
+            .args(["-H", "Content-Type: application/json", "--data-binary", "@-"])
+            .arg(&self.url);
+        // The body holds the command line, which anyone could read in argv
+        // with `ps`, so it goes on stdin.
+        spawn_detached(&mut command, Some(payload.to_string()));
+    }
+}
+
+/// Runs `command` off the UI thread with `input` on its stdin and its
+/// output discarded; a missing tool or a failed request only costs the
+/// notification.
+fn spawn_detached(command: &mut Command, input: Option<String>) {
+    let stdin = if input.is_some() { Stdio::piped() } else { Stdio::null() };
+    let child = command
+        .stdin(stdin)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Ok(mut child) = child {
+        // Reap it so it doesn't linger as a zombie.
+        std::thread::spawn(move || {
+            if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+                let _ = stdin.write_all(input.as_bytes());
+            }
+            child.wait()
+        });
+    }
+}
+
+/// Drops control characters, which would end the sequence early.
+fn printable(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Writes an escape sequence past ratatui, wrapped for tmux, which
+/// otherwise drops OSC sequences it does not know.
+fn write_escape(sequence: &str) {
+    let sequence = if std::env::var_os("TMUX").is_some() {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence.to_string()
+    };
+    let mut stdout = std::io::stdout();
+    let _ = stdout.write_all(sequence.as_bytes());
+    let _ = stdout.flush();
+}
+
+/// Writes BEL straight to the terminal; ratatui never sees it.
+pub fn ring_bell() {
+    let mut stdout = std::io::stdout();
+    let _ = stdout.write_all(b"\x07");
+    let _ = stdout.flush();
+}
@@ -0,0 +1,135 @@
+// src/output.rs
+
+//! Output of a log entry, kept as a bounded ring of lines. A command that
+//! prints a million lines would otherwise grow one giant string that every
+//! frame re-splits; here the oldest lines fall off the front in chunks, are
+//! counted, and with `[performance] spill_output` go to a file in a
+//! private directory so the full output can still be read.
+
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
+
+/// Lines kept per entry unless `[performance] max_output_lines` says otherwise.
+pub const DEFAULT_MAX_LINES: usize = 10_000;
+
+#[derive(Clone, Copy)]
+pub struct OutputLimits {
+    pub max_lines: usize,
+    /// Write dropped lines to disk instead of discarding them.
+    pub spill: bool,
+}
+
+impl Default for OutputLimits {
+    fn default() -> Self {
+        Self {
+            max_lines: DEFAULT_MAX_LINES,
+            spill: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct OutputBuffer {
+    lines: VecDeque<String>,
+    /// Lines that fell off the front.
+    dropped: usize,
+    /// Where they went, with spilling on.
+    spill: Option<PathBuf>,
+}
+
+impl OutputBuffer {
+    pub fn from_text(text: &str) -> Self {
+        let mut buffer = Self::default();
+        if !text.is_empty() {
+            buffer.lines.extend(text.split('\n').map(str::to_string));
+        }
+        buffer
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.dropped == 0
+    }
+
+    /// Lines still held.
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    pub fn spill_path(&self) -> Option<&Path> {
+        self.spill.as_deref()
+    }
+
+    pub fn lines(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator {
+        self.lines.iter().map(String::as_str)
+    }
+
+    /// Removes the newest line.
+    pub fn pop(&mut self) -> Option<String> {
+        self.lines.pop_back()
+    }
+
+    /// Appends one line. Returns how many lines fell off the front, so
+    /// line-indexed data (links) can follow. Lines go in chunks of a tenth
+    /// of the limit, which keeps spilling to one write per chunk.
+    pub fn push(&mut self, line: String, limits: &OutputLimits, entry_id: u64) -> usize {
+        self.lines.push_back(line);
+        let max = limits.max_lines.max(1);
+        if self.lines.len() <= max + max / 10 {
+            return 0;
+        }
+        let excess = self.lines.len() - max;
+        let dropped: Vec<String> = self.lines.drain(..excess).collect();
+        self.dropped += excess;
+        if limits.spill {
+            let path = self.spill.get_or_insert_with(|| spill_dir().join(format!("{entry_id}.log")));
+            if spill(path, &dropped).is_err() {
+                self.spill = None;
+            }
+        }
+        excess
+    }
+}
+
+/// Per-process directory for spilled output; removed on exit. It goes
+/// under `$XDG_RUNTIME_DIR` or the cache directory, which are the user's
+/// own, rather than next to everyone else's files in the temp directory.
+fn spill_dir() -> PathBuf {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("halo")
+        .join(format!("output-{}", std::process::id()))
+}
+
+/// Creates `dir` with mode 0700, and refuses one that already exists but
+/// is not a directory this user owns alone.
+pub fn create_private_dir(dir: &Path) -> io::Result<()> {
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    let meta = fs::symlink_metadata(dir)?;
+    if !meta.is_dir() || meta.uid() != users::get_current_uid() || meta.mode() & 0o077 != 0 {
+        return Err(io::Error::other(format!("{} is not private", dir.display())));
+    }
+    Ok(())
+}
+
+fn spill(path: &Path, lines: &[String]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        create_private_dir(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut chunk = lines.join("\n");
+    chunk.push('\n');
+    file.write_all(chunk.as_bytes())
+}
+
+/// Deletes this session's spilled output.
+pub fn remove_spills() {
+    let _ = fs::remove_dir_all(spill_dir());
+}
@@ -0,0 +1,145 @@
+// src/pager.rs
+
+//! Full-screen view of one log entry's output (Alt+P), for outputs taller
+//! than the log area: line-wise scrolling, search, and a position readout.
+
+use crate::command::CommandLog;
+use crate::links::Link;
+use crate::state::{LogSearch, displayed_line};
+
+/// Columns Left/Right scroll long lines by.
+const SIDE_STEP: usize = 8;
+
+pub struct Pager {
+    /// Log entry shown, which is also the entry of every search match.
+    pub entry: usize,
+    pub command: String,
+    /// Output lines as logged, `[stderr] ` markers included.
+    pub lines: Vec<String>,
+    /// OSC 8 links in `lines`.
+    pub links: Vec<Link>,
+    /// First line on screen.
+    pub top: usize,
+    /// Columns scrolled off the left edge.
+    pub left: usize,
+    /// Rows the last frame had for text.
+    pub height: usize,
+    pub search: Option<LogSearch>,
+}
+
+impl Pager {
+    /// Opens on a snapshot of the entry's output; later output isn't added.
+    pub fn new(entry: usize, log: &CommandLog) -> Self {
+        Self {
+            entry,
+            command: log.command.clone(),
+            lines: log.output.lines().map(str::to_string).collect(),
+            links: log.links.clone(),
+            top: 0,
+            left: 0,
+            height: 1,
+            search: None,
+        }
+    }
+
+    fn max_top(&self) -> usize {
+        self.lines.len().saturating_sub(self.height.max(1))
+    }
+
+    /// Moves the view `delta` lines down (negative: up).
+    pub fn scroll(&mut self, delta: isize) {
+        self.top = self.top.saturating_add_signed(delta).min(self.max_top());
+    }
+
+    pub fn page(&mut self, down: bool) {
+        let rows = self.height.max(1) as isize;
+        self.scroll(if down { rows } else { -rows });
+    }
+
+    pub fn go_to_start(&mut self) {
+        self.top = 0;
+    }
+
+    pub fn go_to_end(&mut self) {
+        self.top = self.max_top();
+    }
+
+    pub fn scroll_side(&mut self, right: bool) {
+        self.left = if right {
+            self.left + SIDE_STEP
+        } else {
+            self.left.saturating_sub(SIDE_STEP)
+        };
+    }
+
+    /// Share of the output at or above the bottom of the screen, like
+    /// `less -m`.
+    pub fn percent(&self) -> usize {
+        let len = self.lines.len();
+        if len == 0 {
+            return 100;
+        }
+        ((self.top + self.height).min(len) * 100) / len
+    }
+
+    /// Called after a resize; keeps the last page full.
+    pub fn set_height(&mut self, height: usize) {
+        self.height = height;
+        self.top = self.top.min(self.max_top());
+    }
+
+    pub fn start_search(&mut self) {
+        self.search = Some(LogSearch {
+            editing: true,
+            ..LogSearch::default()
+        });
+    }
+
+    /// Recomputes matches after the pattern changed and shows the first
+    /// one from the top of the screen on.
+    pub fn update_search(&mut self) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        search.matches = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| !search.ranges(displayed_line(line)).is_empty())
+            .map(|(k, _)| (self.entry, k))
+            .collect();
+        search.current = search
+            .matches
+            .iter()
+            .position(|&(_, k)| k >= self.top)
+            .unwrap_or(0);
+        self.show_match();
+    }
+
+    /// n (`forward`) / N: the next match, wrapping around.
+    pub fn step_search(&mut self, forward: bool) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        let count = search.matches.len();
+        if count == 0 {
+            return;
+        }
+        search.current = if forward {
+            (search.current + 1) % count
+        } else {
+            (search.current + count - 1) % count
+        };
+        self.show_match();
+    }
+
+    /// Scrolls the current match into view, a third of the way down.
+    fn show_match(&mut self) {
+        let Some((_, line)) = self.search.as_ref().and_then(LogSearch::current_match) else {
+            return;
+        };
+        if line < self.top || line >= self.top + self.height {
+            self.top = line.saturating_sub(self.height / 3).min(self.max_top());
+        }
+    }
+}
@@ -0,0 +1,27 @@
+// src/panes.rs
+
+//! Split panes: the log area divided so several long-running commands (a
+//! dev server next to a test watcher, say) stream side by side. Each pane
+//! has its own log and scroll position. The focused pane's live in
+//! `State::command_log` and `State::scroll_offset`, so everything that
+//! works on "the log" works on it; the others wait here.
+
+use crate::command::CommandLog;
+
+/// More than this and panes get too narrow to read.
+pub const MAX_PANES: usize = 4;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Split {
+    /// Side by side (`split -h`, Ctrl+X 3).
+    #[default]
+    Columns,
+    /// Stacked (`split -v`, Ctrl+X 2).
+    Rows,
+}
+
+#[derive(Default)]
+pub struct Pane {
+    pub command_log: Vec<CommandLog>,
+    pub scroll_offset: usize,
+}
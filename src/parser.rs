@@ -0,0 +1,732 @@
+// src/parser.rs
+
+//! Command-line tokenizer and parser. Everything that needs to understand a
+//! command line (execution, highlighting, completion) should go through here
+//! so they agree on quoting. Spans are byte ranges into the original input.
+
+use std::fmt;
+use std::ops::Range;
+
+/// A byte range of the input.
+pub type Span = Range<usize>;
+
+/// A shell word, such as a command name or an argument.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Word {
+    /// The word with quotes and escapes removed.
+    pub text: String,
+    /// Where it is in the input, quotes included.
+    pub span: Span,
+    /// True if any part of the word was quoted or escaped.
+    pub quoted: bool,
+}
+
+/// An operator between commands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    /// `|`
+    Pipe,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+    /// `;`
+    Semi,
+    /// `&`
+    Background,
+}
+
+impl Operator {
+    /// The operator as written.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Operator::Pipe => "|",
+            Operator::And => "&&",
+            Operator::Or => "||",
+            Operator::Semi => ";",
+            Operator::Background => "&",
+        }
+    }
+}
+
+/// What a redirection does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RedirectKind {
+    /// `>`
+    Write,
+    /// `>>`
+    Append,
+    /// `<`
+    Read,
+    /// `>&`, e.g. `2>&1`
+    Duplicate,
+    /// `>|`: truncate even when noclobber is set
+    Clobber,
+    /// `&>`: stdout and stderr to the same file
+    WriteBoth,
+}
+
+/// A redirection with its target, e.g. `2>> err.log`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Redirect {
+    /// Explicit descriptor such as the `2` in `2>`.
+    pub fd: Option<u32>,
+    /// The operator.
+    pub kind: RedirectKind,
+    /// The file (or, for `>&`, descriptor) it points to.
+    pub target: Word,
+    /// The operator and its target.
+    pub span: Span,
+}
+
+/// What `tokenize` splits a line into.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token {
+    /// A word.
+    Word(Word),
+    /// `|`, `&&`, `||`, `;` or `&`.
+    Operator(Operator, Span),
+    /// A redirection operator; its target is the following word.
+    Redirect(Option<u32>, RedirectKind, Span),
+}
+
+impl Token {
+    /// Where the token is in the input.
+    pub fn span(&self) -> &Span {
+        match self {
+            Token::Word(w) => &w.span,
+            Token::Operator(_, span) | Token::Redirect(_, _, span) => span,
+        }
+    }
+}
+
+/// `NAME=value` preceding the command name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Assignment {
+    /// The variable.
+    pub name: String,
+    /// Its value, unquoted.
+    pub value: String,
+    /// The whole `NAME=value` word.
+    pub span: Span,
+}
+
+/// One command of a pipeline.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SimpleCommand {
+    /// `NAME=value` words before the command name.
+    pub assignments: Vec<Assignment>,
+    /// The command name and its arguments.
+    pub words: Vec<Word>,
+    /// Redirections, wherever they appeared on the line.
+    pub redirects: Vec<Redirect>,
+}
+
+impl SimpleCommand {
+    /// The command name followed by its arguments, unquoted.
+    pub fn argv(&self) -> Vec<String> {
+        self.words.iter().map(|w| w.text.clone()).collect()
+    }
+}
+
+/// Commands joined by `|`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    /// The commands, left to right.
+    pub commands: Vec<SimpleCommand>,
+}
+
+/// A full line: pipelines joined by `&&`, `||`, `;` or `&`. Each entry
+/// carries the operator that follows it, if any.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommandList {
+    /// The pipelines, left to right.
+    pub items: Vec<(Pipeline, Option<Operator>)>,
+}
+
+impl CommandList {
+    /// The line's only command when it is a plain `cmd args...` with no
+    /// pipes, chaining, redirections or assignments.
+    pub fn as_simple(&self) -> Option<&SimpleCommand> {
+        match self.items.as_slice() {
+            [(pipeline, None | Some(Operator::Semi))] => match pipeline.commands.as_slice() {
+                [cmd] if cmd.assignments.is_empty() && cmd.redirects.is_empty() => Some(cmd),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// True for a blank line or one with only a comment.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Why a line does not parse, and where.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// What is wrong, e.g. `unterminated single quote`.
+    pub message: String,
+    /// The part of the input at fault.
+    pub span: Span,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at column {})", self.message, self.span.start + 1)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn error(message: impl Into<String>, span: Span) -> ParseError {
+    ParseError {
+        message: message.into(),
+        span,
+    }
+}
+
+/// Splits `input` into words, operators and redirections.
+pub fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    tokenize_quoting(input, &mut Vec::new())
+}
+
+/// `tokenize`, also collecting the spans of quoted strings (quotes
+/// included) and backslash escapes into `quoting`.
+fn tokenize_quoting(input: &str, quoting: &mut Vec<Span>) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    // The word being built: unquoted text, start offset, quoted flag.
+    let mut word: Option<(String, usize, bool)> = None;
+
+    fn flush(tokens: &mut Vec<Token>, word: &mut Option<(String, usize, bool)>, end: usize) {
+        if let Some((text, start, quoted)) = word.take() {
+            tokens.push(Token::Word(Word {
+                text,
+                span: start..end,
+                quoted,
+            }));
+        }
+    }
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => flush(&mut tokens, &mut word, i),
+            '#' if word.is_none() => break,
+            '\'' => {
+                let (text, _, quoted) = word.get_or_insert_with(|| (String::new(), i, false));
+                *quoted = true;
+                loop {
+                    match chars.next() {
+                        Some((j, '\'')) => {
+                            quoting.push(i..j + 1);
+                            break;
+                        }
+                        Some((_, c)) => text.push(c),
+                        None => return Err(error("unterminated single quote", i..input.len())),
+                    }
+                }
+            }
+            '"' => {
+                let (text, _, quoted) = word.get_or_insert_with(|| (String::new(), i, false));
+                *quoted = true;
+                loop {
+                    match chars.next() {
+                        Some((j, '"')) => {
+                            quoting.push(i..j + 1);
+                            break;
+                        }
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c @ ('"' | '\\' | '$' | '`'))) => text.push(c),
+                            Some((_, c)) => {
+                                text.push('\\');
+                                text.push(c);
+                            }
+                            None => return Err(error("unterminated double quote", i..input.len())),
+                        },
+                        Some((_, c)) => text.push(c),
+                        None => return Err(error("unterminated double quote", i..input.len())),
+                    }
+                }
+            }
+            '\\' => {
+                let (text, _, quoted) = word.get_or_insert_with(|| (String::new(), i, false));
+                *quoted = true;
+                match chars.next() {
+                    Some((j, c)) => {
+                        quoting.push(i..j + c.len_utf8());
+                        text.push(c);
+                    }
+                    None => return Err(error("trailing backslash", i..input.len())),
+                }
+            }
+            '|' | '&' | ';' => {
+                // `&>` writes both streams; otherwise these are operators.
+                if c == '&' && chars.next_if(|(_, c)| *c == '>').is_some() {
+                    flush(&mut tokens, &mut word, i);
+                    tokens.push(Token::Redirect(None, RedirectKind::WriteBoth, i..i + 2));
+                    continue;
+                }
+                flush(&mut tokens, &mut word, i);
+                let doubled = c != ';' && chars.next_if(|(_, n)| *n == c).is_some();
+                let (op, len) = match (c, doubled) {
+                    ('|', false) => (Operator::Pipe, 1),
+                    ('|', true) => (Operator::Or, 2),
+                    ('&', false) => (Operator::Background, 1),
+                    ('&', true) => (Operator::And, 2),
+                    _ => (Operator::Semi, 1),
+                };
+                tokens.push(Token::Operator(op, i..i + len));
+            }
+            '>' | '<' => {
+                // A bare number right before the operator names the descriptor: `2>`.
+                let (fd, start) = match &word {
+                    Some((text, start, false)) if text.chars().all(|c| c.is_ascii_digit()) => {
+                        (text.parse().ok(), *start)
+                    }
+                    _ => {
+                        flush(&mut tokens, &mut word, i);
+                        (None, i)
+                    }
+                };
+                word = None;
+                let (kind, end) = if c == '<' {
+                    (RedirectKind::Read, i + 1)
+                } else if chars.next_if(|(_, n)| *n == '>').is_some() {
+                    (RedirectKind::Append, i + 2)
+                } else if chars.next_if(|(_, n)| *n == '&').is_some() {
+                    (RedirectKind::Duplicate, i + 2)
+                } else if chars.next_if(|(_, n)| *n == '|').is_some() {
+                    (RedirectKind::Clobber, i + 2)
+                } else {
+                    (RedirectKind::Write, i + 1)
+                };
+                tokens.push(Token::Redirect(fd, kind, start..end));
+            }
+            c => word.get_or_insert_with(|| (String::new(), i, false)).0.push(c),
+        }
+    }
+    flush(&mut tokens, &mut word, input.len());
+    Ok(tokens)
+}
+
+/// Parses a full command line.
+pub fn parse(input: &str) -> Result<CommandList, ParseError> {
+    let mut list = CommandList::default();
+    let mut pipeline = Pipeline::default();
+    let mut command = SimpleCommand::default();
+    let mut tokens = tokenize(input)?.into_iter().peekable();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Word(w) => {
+                if command.words.is_empty()
+                    && let Some(assignment) = assignment(&w, input)
+                {
+                    command.assignments.push(assignment);
+                } else {
+                    command.words.push(w);
+                }
+            }
+            Token::Redirect(fd, kind, span) => match tokens.next() {
+                Some(Token::Word(target)) => {
+                    let span = span.start..target.span.end;
+                    command.redirects.push(Redirect {
+                        fd,
+                        kind,
+                        target,
+                        span,
+                    });
+                }
+                other => {
+                    let span = other.map_or(span, |t| t.span().clone());
+                    return Err(error("expected a file name after redirection", span));
+                }
+            },
+            Token::Operator(op, span) => {
+                if is_empty_command(&command) {
+                    return Err(error(format!("syntax error near `{}`", op.as_str()), span));
+                }
+                pipeline.commands.push(std::mem::take(&mut command));
+                if op != Operator::Pipe {
+                    list.items.push((std::mem::take(&mut pipeline), Some(op)));
+                } else if tokens.peek().is_none() {
+                    return Err(error("pipe with no command after it", span));
+                }
+            }
+        }
+    }
+
+    if !is_empty_command(&command) {
+        pipeline.commands.push(command);
+    }
+    if !pipeline.commands.is_empty() {
+        list.items.push((pipeline, None));
+    }
+    if let Some((_, Some(op @ (Operator::And | Operator::Or)))) = list.items.last() {
+        let end = input.trim_end().len();
+        return Err(error(
+            format!("`{}` with no command after it", op.as_str()),
+            end.saturating_sub(op.as_str().len())..end,
+        ));
+    }
+    Ok(list)
+}
+
+fn is_empty_command(cmd: &SimpleCommand) -> bool {
+    cmd.words.is_empty() && cmd.assignments.is_empty() && cmd.redirects.is_empty()
+}
+
+fn assignment(word: &Word, input: &str) -> Option<Assignment> {
+    let (name, value) = word.text.split_once('=')?;
+    // The name itself must be unquoted: `"A=b"` is a command, `A="b c"` is not.
+    if !input[word.span.clone()].starts_with(&format!("{name}=")) {
+        return None;
+    }
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then(|| Assignment {
+        name: name.to_string(),
+        value: value.to_string(),
+        span: word.span.clone(),
+    })
+}
+
+/// `word` as a single shell word, single-quoted only when it needs to be.
+pub fn quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./~:=+,@%".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// Quote and bracket structure of a line, for highlighting while typing.
+#[derive(Debug, Default)]
+pub struct Delimiters {
+    /// Byte offsets of matching `()`, `[]` and `{}` pairs.
+    pub pairs: Vec<(usize, usize)>,
+    /// Byte offsets of quotes and brackets that are never closed or opened.
+    pub unbalanced: Vec<usize>,
+    /// Quoted strings, quotes included; an unclosed one runs to the end.
+    pub strings: Vec<Span>,
+}
+
+impl Delimiters {
+    /// The partner of a bracket at `pos`.
+    pub fn partner(&self, pos: usize) -> Option<usize> {
+        self.pairs.iter().find_map(|&(open, close)| {
+            if open == pos {
+                Some(close)
+            } else if close == pos {
+                Some(open)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Finds bracket pairs and unbalanced delimiters. Goes through `tokenize`
+/// so quoting and comments read the same as when the line runs.
+pub fn delimiters(input: &str) -> Delimiters {
+    let mut found = Delimiters::default();
+    let mut quoting = Vec::new();
+    let mut unclosed = None;
+    let tokens = match tokenize_quoting(input, &mut quoting) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            // An unclosed quote runs to the end; the line before it still counts.
+            if input[e.span.start..].starts_with(['\'', '"']) {
+                unclosed = Some(e.span.clone());
+            }
+            quoting.clear();
+            tokenize_quoting(&input[..e.span.start], &mut quoting).unwrap_or_default()
+        }
+    };
+    let mut stack: Vec<(usize, char)> = Vec::new();
+    for token in &tokens {
+        let Token::Word(word) = token else {
+            continue;
+        };
+        for (offset, c) in input[word.span.clone()].char_indices() {
+            let i = word.span.start + offset;
+            if quoting.iter().any(|span| span.contains(&i)) {
+                continue;
+            }
+            match c {
+                '(' | '[' | '{' => stack.push((i, c)),
+                ')' | ']' | '}' => {
+                    let open = match c {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '{',
+                    };
+                    match stack.last() {
+                        Some(&(start, o)) if o == open => {
+                            stack.pop();
+                            found.pairs.push((start, i));
+                        }
+                        _ => found.unbalanced.push(i),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    found.unbalanced.extend(stack.into_iter().map(|(i, _)| i));
+    found.strings = quoting
+        .into_iter()
+        .filter(|span| !input[span.clone()].starts_with('\\'))
+        .collect();
+    if let Some(span) = unclosed {
+        found.unbalanced.push(span.start);
+        found.strings.push(span);
+    }
+    found
+}
+
+/// How the highlighter treats a word of the line being typed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WordRole {
+    /// The command name of a simple command, unquoted.
+    Command(String),
+    /// An argument starting with `-`.
+    Flag,
+}
+
+/// Command names and flags of a partial line. An unterminated quote cuts
+/// the line short instead of failing, since it is usually still being typed.
+pub fn word_roles(input: &str) -> Vec<(Span, WordRole)> {
+    let tokens = tokenize(input)
+        .or_else(|e| tokenize(&input[..e.span.start]))
+        .unwrap_or_default();
+    let mut roles = Vec::new();
+    let mut expect_command = true;
+    let mut redirect_target = false;
+    for token in tokens {
+        match token {
+            Token::Word(w) => {
+                if redirect_target {
+                    redirect_target = false;
+                } else if expect_command {
+                    if assignment(&w, input).is_none() {
+                        roles.push((w.span, WordRole::Command(w.text)));
+                        expect_command = false;
+                    }
+                } else if input[w.span.clone()].starts_with('-') {
+                    roles.push((w.span, WordRole::Flag));
+                }
+            }
+            Token::Operator(..) => expect_command = true,
+            Token::Redirect(..) => redirect_target = true,
+        }
+    }
+    roles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simple(input: &str) -> SimpleCommand {
+        parse(input).unwrap().as_simple().cloned().unwrap()
+    }
+
+    fn only_command(input: &str) -> SimpleCommand {
+        let list = parse(input).unwrap();
+        assert_eq!(list.items.len(), 1);
+        assert_eq!(list.items[0].0.commands.len(), 1);
+        list.items[0].0.commands[0].clone()
+    }
+
+    #[test]
+    fn words_have_spans_with_quotes() {
+        let input = r#"echo "a b" c\ d 'e'f"#;
+        let cmd = simple(input);
+        assert_eq!(cmd.argv(), ["echo", "a b", "c d", "ef"]);
+        let spans: Vec<&str> = cmd.words.iter().map(|w| &input[w.span.clone()]).collect();
+        assert_eq!(spans, ["echo", r#""a b""#, r"c\ d", "'e'f"]);
+        assert!(!cmd.words[0].quoted);
+        assert!(cmd.words[1..].iter().all(|w| w.quoted));
+    }
+
+    #[test]
+    fn double_quotes_keep_unknown_escapes() {
+        assert_eq!(simple(r#"echo "\$x \n""#).argv(), ["echo", r"$x \n"]);
+    }
+
+    #[test]
+    fn comments_and_blank_lines() {
+        assert!(parse("").unwrap().is_empty());
+        assert!(parse("   # nothing").unwrap().is_empty());
+        assert_eq!(simple("echo a#b # c").argv(), ["echo", "a#b"]);
+    }
+
+    #[test]
+    fn unterminated_quotes_fail() {
+        let err = parse("echo 'abc").unwrap_err();
+        assert_eq!(err.span, 5..9);
+        assert!(parse("echo \"abc").is_err());
+        assert!(parse("echo abc\\").is_err());
+    }
+
+    #[test]
+    fn duplicate_redirect() {
+        let input = "make 2>&1";
+        let cmd = only_command(input);
+        assert_eq!(cmd.argv(), ["make"]);
+        let [redirect] = cmd.redirects.as_slice() else {
+            panic!("expected one redirect: {:?}", cmd.redirects);
+        };
+        assert_eq!(redirect.fd, Some(2));
+        assert_eq!(redirect.kind, RedirectKind::Duplicate);
+        assert_eq!(redirect.target.text, "1");
+        assert_eq!(&input[redirect.span.clone()], "2>&1");
+    }
+
+    #[test]
+    fn redirect_kinds() {
+        let cases = [
+            ("a > f", None, RedirectKind::Write),
+            ("a >> f", None, RedirectKind::Append),
+            ("a < f", None, RedirectKind::Read),
+            ("a >| f", None, RedirectKind::Clobber),
+            ("a &> f", None, RedirectKind::WriteBoth),
+            ("a 2>> f", Some(2), RedirectKind::Append),
+        ];
+        for (input, fd, kind) in cases {
+            let cmd = only_command(input);
+            assert_eq!(cmd.argv(), ["a"], "{input}");
+            assert_eq!(cmd.redirects.len(), 1, "{input}");
+            assert_eq!(cmd.redirects[0].fd, fd, "{input}");
+            assert_eq!(cmd.redirects[0].kind, kind, "{input}");
+            assert_eq!(cmd.redirects[0].target.text, "f", "{input}");
+        }
+    }
+
+    #[test]
+    fn quoted_number_is_not_a_descriptor() {
+        let cmd = only_command("echo '2'>f");
+        assert_eq!(cmd.argv(), ["echo", "2"]);
+        assert_eq!(cmd.redirects[0].fd, None);
+    }
+
+    #[test]
+    fn redirect_needs_a_target() {
+        assert!(parse("echo >").is_err());
+        assert!(parse("echo > | cat").is_err());
+    }
+
+    #[test]
+    fn assignments_before_the_command() {
+        let input = r#"A=1 B="x y" env C=2"#;
+        let cmd = only_command(input);
+        let names: Vec<(&str, &str)> = cmd
+            .assignments
+            .iter()
+            .map(|a| (a.name.as_str(), a.value.as_str()))
+            .collect();
+        assert_eq!(names, [("A", "1"), ("B", "x y")]);
+        assert_eq!(&input[cmd.assignments[1].span.clone()], r#"B="x y""#);
+        assert_eq!(cmd.argv(), ["env", "C=2"]);
+    }
+
+    #[test]
+    fn not_assignments() {
+        assert_eq!(only_command(r#""A=b" c"#).argv(), ["A=b", "c"]);
+        assert_eq!(only_command("1A=b").argv(), ["1A=b"]);
+        let cmd = only_command("A=1");
+        assert!(cmd.words.is_empty());
+        assert_eq!(cmd.assignments.len(), 1);
+    }
+
+    #[test]
+    fn operators_split_pipelines() {
+        let list = parse("a | b && c || d; e &").unwrap();
+        let ops: Vec<Option<Operator>> = list.items.iter().map(|(_, op)| *op).collect();
+        assert_eq!(
+            ops,
+            [
+                Some(Operator::And),
+                Some(Operator::Or),
+                Some(Operator::Semi),
+                Some(Operator::Background),
+            ]
+        );
+        assert_eq!(list.items[0].0.commands.len(), 2);
+        assert_eq!(list.items[0].0.commands[1].argv(), ["b"]);
+    }
+
+    #[test]
+    fn dangling_operators_fail() {
+        for input in ["| a", "a |", "a &&", "a || ", "a && ; b"] {
+            assert!(parse(input).is_err(), "{input}");
+        }
+        let err = parse("true &&  ").unwrap_err();
+        assert_eq!(err.span, 5..7);
+    }
+
+    #[test]
+    fn as_simple_only_for_plain_commands() {
+        assert!(parse("ls -l").unwrap().as_simple().is_some());
+        assert!(parse("ls -l;").unwrap().as_simple().is_some());
+        for input in ["ls | wc", "ls && pwd", "ls &", "ls > f", "A=1 ls", ""] {
+            assert!(parse(input).unwrap().as_simple().is_none(), "{input}");
+        }
+    }
+
+    #[test]
+    fn quote_round_trips() {
+        assert_eq!(quote("plain/path-1.txt"), "plain/path-1.txt");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        for word in ["", "a b", "it's", "$HOME", "*"] {
+            assert_eq!(simple(&format!("echo {}", quote(word))).argv(), ["echo", word]);
+        }
+    }
+
+    #[test]
+    fn delimiters_pairs_and_strings() {
+        let found = delimiters("echo (a [b]) 'x(' {");
+        assert_eq!(found.pairs, [(8, 10), (5, 11)]);
+        assert_eq!(found.partner(5), Some(11));
+        assert_eq!(found.partner(11), Some(5));
+        assert_eq!(found.unbalanced, [18]);
+        assert_eq!(found.strings, vec![Span::from(13..17)]);
+    }
+
+    #[test]
+    fn delimiters_unclosed_quote_runs_to_end() {
+        let found = delimiters("echo ) \"abc");
+        assert_eq!(found.unbalanced, [5, 7]);
+        assert_eq!(found.strings, vec![Span::from(7..11)]);
+    }
+
+    #[test]
+    fn word_roles_find_commands_and_flags() {
+        let input = "A=1 git -v log > -f | grep -i 'x";
+        let roles: Vec<(&str, WordRole)> = word_roles(input)
+            .into_iter()
+            .map(|(span, role)| (&input[span], role))
+            .collect();
+        assert_eq!(
+            roles,
+            [
+                ("git", WordRole::Command("git".into())),
+                ("-v", WordRole::Flag),
+                ("grep", WordRole::Command("grep".into())),
+                ("-i", WordRole::Flag),
+            ]
+        );
+    }
+}
@@ -0,0 +1,105 @@
+// src/pathindex.rs
+
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Rescan this often so freshly installed programs show up.
+const MAX_AGE: Duration = Duration::from_secs(10);
+
+/// Names of the executables on $PATH, shared by completion and the input
+/// highlighter. Scans run on a blocking task; lookups never wait on one.
+#[derive(Clone, Default)]
+pub struct PathIndex {
+    inner: Arc<Mutex<Index>>,
+}
+
+#[derive(Default)]
+struct Index {
+    names: Option<HashSet<String>>,
+    /// $PATH and time of the last scan.
+    scanned: Option<(OsString, Instant)>,
+    scanning: bool,
+}
+
+impl PathIndex {
+    /// Whether `name` is on $PATH, or `None` until the first scan finishes.
+    pub fn contains(&self, name: &str) -> Option<bool> {
+        self.refresh_if_stale();
+        let index = self.inner.lock().ok()?;
+        index.names.as_ref().map(|names| names.contains(name))
+    }
+
+    /// Executables starting with `prefix`. Scans in place if there is no
+    /// index yet, so call it from a blocking context.
+    pub fn starting_with(&self, prefix: &str) -> Vec<String> {
+        let ready = self.inner.lock().is_ok_and(|index| index.names.is_some());
+        if ready {
+            self.refresh_if_stale();
+        } else {
+            self.store(scan());
+        }
+        let Ok(index) = self.inner.lock() else {
+            return Vec::new();
+        };
+        index
+            .names
+            .iter()
+            .flatten()
+            .filter(|name| name.starts_with(prefix))
+            .cloned()
+            .collect()
+    }
+
+    fn refresh_if_stale(&self) {
+        let path = env::var_os("PATH").unwrap_or_default();
+        {
+            let Ok(mut index) = self.inner.lock() else {
+                return;
+            };
+            let fresh = index
+                .scanned
+                .as_ref()
+                .is_some_and(|(p, at)| *p == path && at.elapsed() < MAX_AGE);
+            if fresh || index.scanning {
+                return;
+            }
+            index.scanning = true;
+        }
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || this.store(scan()));
+    }
+
+    fn store(&self, (path, names): (OsString, HashSet<String>)) {
+        if let Ok(mut index) = self.inner.lock() {
+            index.names = Some(names);
+            index.scanned = Some((path, Instant::now()));
+            index.scanning = false;
+        }
+    }
+}
+
+fn scan() -> (OsString, HashSet<String>) {
+    let path = env::var_os("PATH").unwrap_or_default();
+    let mut names = HashSet::new();
+    for dir in env::split_paths(&path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            // Follows symlinks, which is how most package managers install.
+            if let Ok(metadata) = fs::metadata(entry.path())
+                && metadata.is_file()
+                && metadata.permissions().mode() & 0o111 != 0
+                && let Some(name) = entry.file_name().to_str()
+            {
+                names.insert(name.to_string());
+            }
+        }
+    }
+    (path, names)
+}
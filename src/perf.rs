@@ -0,0 +1,140 @@
+// src/perf.rs
+
+//! Loop timings for the `:perf` overlay: how long frames take to draw,
+//! how long key and mouse events take to handle, and how much job output
+//! is waiting in the channel. Only sampled while the overlay is open.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Samples kept per measurement.
+const WINDOW: usize = 120;
+/// How often the overlay redraws itself while nothing else changes.
+const REFRESH: Duration = Duration::from_millis(500);
+
+#[derive(Default)]
+pub struct Samples {
+    values: VecDeque<Duration>,
+}
+
+impl Samples {
+    fn push(&mut self, value: Duration) {
+        if self.values.len() == WINDOW {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    fn last(&self) -> Duration {
+        self.values.back().copied().unwrap_or_default()
+    }
+
+    fn mean(&self) -> Duration {
+        if self.values.is_empty() {
+            return Duration::ZERO;
+        }
+        self.values.iter().sum::<Duration>() / self.values.len() as u32
+    }
+
+    fn max(&self) -> Duration {
+        self.values.iter().max().copied().unwrap_or_default()
+    }
+
+    /// `last / avg / max` over the window.
+    fn summary(&self) -> String {
+        format!(
+            "{:>7} {:>7} {:>7}",
+            format_duration(self.last()),
+            format_duration(self.mean()),
+            format_duration(self.max())
+        )
+    }
+}
+
+pub struct PerfStats {
+    /// `terminal.draw`, layout and flush included.
+    pub frames: Samples,
+    pub events: Samples,
+    /// The per-tick pass before drawing: job output, git, completion...
+    pub updates: Samples,
+    /// Job updates waiting when the tick started.
+    pub backlog: usize,
+    pub backlog_peak: usize,
+    /// Draw times within the last second, for the frame rate.
+    drawn: VecDeque<Instant>,
+    refreshed: Instant,
+}
+
+impl Default for PerfStats {
+    fn default() -> Self {
+        Self {
+            frames: Samples::default(),
+            events: Samples::default(),
+            updates: Samples::default(),
+            backlog: 0,
+            backlog_peak: 0,
+            drawn: VecDeque::new(),
+            refreshed: Instant::now(),
+        }
+    }
+}
+
+impl PerfStats {
+    pub fn record_frame(&mut self, took: Duration) {
+        self.frames.push(took);
+        let now = Instant::now();
+        self.drawn.push_back(now);
+        while self
+            .drawn
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > Duration::from_secs(1))
+        {
+            self.drawn.pop_front();
+        }
+    }
+
+    pub fn record_event(&mut self, took: Duration) {
+        self.events.push(took);
+    }
+
+    pub fn record_updates(&mut self, took: Duration, backlog: usize) {
+        self.updates.push(took);
+        self.backlog = backlog;
+        self.backlog_peak = self.backlog_peak.max(backlog);
+    }
+
+    /// True once per REFRESH, so the numbers move while the screen is idle.
+    pub fn due(&mut self) -> bool {
+        if self.refreshed.elapsed() < REFRESH {
+            return false;
+        }
+        self.refreshed = Instant::now();
+        true
+    }
+
+    pub fn report(&self) -> Vec<String> {
+        vec![
+            format!("{:<10} {:>7} {:>7} {:>7}", "", "last", "avg", "max"),
+            format!("{:<10} {}", "render", self.frames.summary()),
+            format!("{:<10} {}", "events", self.events.summary()),
+            format!("{:<10} {}", "updates", self.updates.summary()),
+            format!("{:<10} {:>7}", "fps", self.drawn.len()),
+            format!(
+                "{:<10} {:>7} {:>7}",
+                "backlog",
+                self.backlog,
+                format!("peak {}", self.backlog_peak)
+            ),
+        ]
+    }
+}
+
+/// `840µs`, `12.4ms`, `1.20s`.
+fn format_duration(d: Duration) -> String {
+    let micros = d.as_micros();
+    match micros {
+        0..1000 => format!("{micros}µs"),
+        1000..1_000_000 => format!("{:.1}ms", micros as f64 / 1000.0),
+        _ => format!("{:.2}s", d.as_secs_f64()),
+    }
+}
@@ -0,0 +1,159 @@
+// src/persist.rs
+
+//! Single writer for halo's state files (session, history). Callers hand
+//! over a serialized snapshot; one task owns the files, keeps only the
+//! newest snapshot per file, and writes it after a short debounce. Each
+//! write goes to a temporary file that is renamed over the old one, so a
+//! crash or a second halo never leaves a half-written file behind.
+//!
+//! The task needs a Tokio runtime, so it only starts with `App::run`;
+//! until then (and in anything that builds a `State` without running the
+//! app) each snapshot is written right away by the caller.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+/// How long a snapshot may wait for newer ones before it is written.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+enum Request {
+    Write(PathBuf, Vec<u8>),
+    Flush(oneshot::Sender<()>),
+}
+
+#[derive(Clone, Default)]
+pub struct Persister {
+    /// `None` until `start`.
+    tx: Option<mpsc::UnboundedSender<Request>>,
+    errors: Arc<Mutex<Vec<String>>>,
+}
+
+impl Persister {
+    /// Starts the owner task; must be called inside a Tokio runtime.
+    pub fn start(&mut self) {
+        if self.tx.is_none() {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(run(rx, self.errors.clone()));
+            self.tx = Some(tx);
+        }
+    }
+
+    /// Queues `bytes` as the new contents of `path`, replacing any snapshot
+    /// of it that is still waiting. Before `start` it is written at once.
+    pub fn write(&self, path: PathBuf, bytes: Vec<u8>) {
+        match &self.tx {
+            Some(tx) => {
+                let _ = tx.send(Request::Write(path, bytes));
+            }
+            None => {
+                if let Err(e) = write_atomic(&path, &bytes)
+                    && let Ok(mut errors) = self.errors.lock()
+                {
+                    errors.push(format!("{}: {e}", path.display()));
+                }
+            }
+        }
+    }
+
+    /// Writes everything queued so far, e.g. before quitting.
+    pub async fn flush(&self) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        let (done, wait) = oneshot::channel();
+        if tx.send(Request::Flush(done)).is_ok() {
+            let _ = wait.await;
+        }
+    }
+
+    /// Failed writes since the last call, for the log.
+    pub fn take_errors(&self) -> Vec<String> {
+        self.errors
+            .lock()
+            .map(|mut errors| std::mem::take(&mut *errors))
+            .unwrap_or_default()
+    }
+}
+
+async fn run(mut rx: mpsc::UnboundedReceiver<Request>, errors: Arc<Mutex<Vec<String>>>) {
+    let mut pending: HashMap<PathBuf, Vec<u8>> = HashMap::new();
+    // Counted from the first waiting snapshot, so a steady stream of
+    // updates (theme previews, cd in a loop) still gets written.
+    let mut deadline: Option<Instant> = None;
+    loop {
+        let request = match deadline {
+            Some(at) => match tokio::time::timeout_at(at, rx.recv()).await {
+                Ok(request) => request,
+                Err(_) => {
+                    write_pending(&mut pending, &errors).await;
+                    deadline = None;
+                    continue;
+                }
+            },
+            None => rx.recv().await,
+        };
+        match request {
+            Some(Request::Write(path, bytes)) => {
+                pending.insert(path, bytes);
+                deadline.get_or_insert_with(|| Instant::now() + DEBOUNCE);
+            }
+            Some(Request::Flush(done)) => {
+                write_pending(&mut pending, &errors).await;
+                deadline = None;
+                let _ = done.send(());
+            }
+            None => {
+                write_pending(&mut pending, &errors).await;
+                return;
+            }
+        }
+    }
+}
+
+async fn write_pending(pending: &mut HashMap<PathBuf, Vec<u8>>, errors: &Mutex<Vec<String>>) {
+    let batch: Vec<_> = pending.drain().collect();
+    if batch.is_empty() {
+        return;
+    }
+    let failed = tokio::task::spawn_blocking(move || {
+        batch
+            .into_iter()
+            .filter_map(|(path, bytes)| {
+                write_atomic(&path, &bytes)
+                    .err()
+                    .map(|e| format!("{}: {e}", path.display()))
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .unwrap_or_default();
+    if !failed.is_empty()
+        && let Ok(mut errors) = errors.lock()
+    {
+        errors.extend(failed);
+    }
+}
+
+/// Replaces `path` with `bytes` via a temporary file in the same directory.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let parent = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = parent.join(format!(".{name}.{}.tmp", std::process::id()));
+    let result = (|| {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
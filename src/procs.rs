@@ -0,0 +1,188 @@
+// src/procs.rs
+
+//! The process overlay (`procs` / F3): halo's jobs, and optionally every
+//! process on the system, with CPU and memory use read from /proc.
+
+use crate::command::JobTable;
+use std::collections::HashMap;
+use std::fs;
+use std::time::{Duration, Instant};
+
+/// How often the overlay samples /proc while open.
+const REFRESH: Duration = Duration::from_secs(1);
+/// System processes listed, busiest first.
+const MAX_SYSTEM_ROWS: usize = 100;
+
+pub struct ProcRow {
+    /// `None` for a job still waiting in the queue.
+    pub pid: Option<u32>,
+    /// Job id, for halo's own jobs.
+    pub job: Option<usize>,
+    pub name: String,
+    pub status: &'static str,
+    /// Percent of one CPU since the previous sample.
+    pub cpu: f64,
+    pub rss_bytes: u64,
+}
+
+#[derive(Default)]
+pub struct ProcMonitor {
+    pub rows: Vec<ProcRow>,
+    pub selected: usize,
+    pub show_system: bool,
+    /// Result of the last kill, shown in the overlay's footer.
+    pub message: Option<String>,
+    /// CPU ticks per pid at the previous sample.
+    ticks: HashMap<u32, u64>,
+    sampled: Option<Instant>,
+}
+
+struct Stat {
+    name: String,
+    state: char,
+    ticks: u64,
+    rss_pages: u64,
+}
+
+impl ProcMonitor {
+    pub fn new(show_system: bool) -> Self {
+        Self {
+            show_system,
+            ..Self::default()
+        }
+    }
+
+    pub fn due(&self) -> bool {
+        self.sampled.is_none_or(|at| at.elapsed() >= REFRESH)
+    }
+
+    /// Samples /proc again, keeping the selection on the same process.
+    pub fn refresh(&mut self, jobs: &JobTable) {
+        let now = Instant::now();
+        let elapsed = self.sampled.map(|at| now.duration_since(at).as_secs_f64());
+        let selected_pid = self.selected_row().and_then(|row| row.pid);
+        let clock_ticks = sysconf(libc::_SC_CLK_TCK).max(1) as f64;
+        let page_size = sysconf(libc::_SC_PAGESIZE).max(1) as u64;
+        let mut ticks = HashMap::new();
+        let mut sample = |pid: u32| -> Option<(Stat, f64)> {
+            let stat = read_stat(pid)?;
+            ticks.insert(pid, stat.ticks);
+            let cpu = match (elapsed, self.ticks.get(&pid)) {
+                (Some(secs), Some(&prev)) if secs > 0.0 => {
+                    stat.ticks.saturating_sub(prev) as f64 / clock_ticks / secs * 100.0
+                }
+                _ => 0.0,
+            };
+            Some((stat, cpu))
+        };
+
+        let mut rows = Vec::new();
+        for job in jobs.iter() {
+            let measured = job.pid.and_then(&mut sample);
+            rows.push(ProcRow {
+                pid: job.pid,
+                job: Some(job.id),
+                name: job.command.clone(),
+                status: job.status.label(),
+                cpu: measured.as_ref().map_or(0.0, |(_, cpu)| *cpu),
+                rss_bytes: measured.map_or(0, |(stat, _)| stat.rss_pages * page_size),
+            });
+        }
+        if self.show_system {
+            let job_pids: Vec<u32> = rows.iter().filter_map(|row| row.pid).collect();
+            let mut system: Vec<ProcRow> = all_pids()
+                .into_iter()
+                .filter(|pid| !job_pids.contains(pid))
+                .filter_map(|pid| {
+                    let (stat, cpu) = sample(pid)?;
+                    Some(ProcRow {
+                        pid: Some(pid),
+                        job: None,
+                        name: stat.name,
+                        status: state_label(stat.state),
+                        cpu,
+                        rss_bytes: stat.rss_pages * page_size,
+                    })
+                })
+                .collect();
+            system.sort_by(|a, b| b.cpu.total_cmp(&a.cpu).then(b.rss_bytes.cmp(&a.rss_bytes)));
+            system.truncate(MAX_SYSTEM_ROWS);
+            rows.extend(system);
+        }
+
+        self.rows = rows;
+        self.ticks = ticks;
+        self.sampled = Some(now);
+        self.selected = selected_pid
+            .and_then(|pid| self.rows.iter().position(|row| row.pid == Some(pid)))
+            .unwrap_or(self.selected)
+            .min(self.rows.len().saturating_sub(1));
+    }
+
+    /// Shows or hides system processes; the next tick resamples.
+    pub fn toggle_system(&mut self) {
+        self.show_system = !self.show_system;
+        self.sampled = None;
+    }
+
+    pub fn select_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_down(&mut self) {
+        if self.selected + 1 < self.rows.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected_row(&self) -> Option<&ProcRow> {
+        self.rows.get(self.selected)
+    }
+}
+
+fn sysconf(name: libc::c_int) -> i64 {
+    // SAFETY: sysconf has no memory effects.
+    unsafe { libc::sysconf(name) as i64 }
+}
+
+fn all_pids() -> Vec<u32> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        .collect()
+}
+
+/// Fields of /proc/PID/stat. The name is in parentheses and may itself
+/// contain spaces and parentheses, so fields are counted from the last `)`.
+fn read_stat(pid: u32) -> Option<Stat> {
+    let text = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let open = text.find('(')?;
+    let close = text.rfind(')')?;
+    let name = text.get(open + 1..close)?.to_string();
+    let fields: Vec<&str> = text.get(close + 1..)?.split_whitespace().collect();
+    // Fields 3 (state), 14 (utime), 15 (stime) and 24 (rss) of proc(5).
+    let state = fields.first()?.chars().next()?;
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    let rss_pages = fields.get(21)?.parse::<i64>().ok()?.max(0) as u64;
+    Some(Stat {
+        name,
+        state,
+        ticks: utime + stime,
+        rss_pages,
+    })
+}
+
+fn state_label(state: char) -> &'static str {
+    match state {
+        'R' => "Running",
+        'S' | 'I' => "Sleeping",
+        'D' => "Waiting",
+        'T' | 't' => "Stopped",
+        'Z' => "Zombie",
+        _ => "Other",
+    }
+}
@@ -0,0 +1,46 @@
+// src/profile.rs
+
+use std::time::{Duration, Instant};
+
+/// Wall-clock timings of startup phases, reported by `--profile-startup`.
+/// Recording is cheap, so phases are always timed; only the report is opt-in.
+pub struct StartupProfile {
+    started: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Default for StartupProfile {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            phases: Vec::new(),
+        }
+    }
+}
+
+impl StartupProfile {
+    /// Runs `f` and records how long it took under `name`.
+    pub fn measure<T>(&mut self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let out = f();
+        self.phases.push((name, start.elapsed()));
+        out
+    }
+
+    pub fn record(&mut self, name: &'static str, elapsed: Duration) {
+        self.phases.push((name, elapsed));
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn report(&self) -> Vec<String> {
+        let mut lines = vec!["Startup profile:".to_string()];
+        for (name, elapsed) in &self.phases {
+            let ms = elapsed.as_secs_f64() * 1000.0;
+            lines.push(format!("  {name:<18} {ms:>9.2} ms"));
+        }
+        lines
+    }
+}
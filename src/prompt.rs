@@ -0,0 +1,305 @@
+// src/prompt.rs
+
+//! Templates for the prompt (`[ui] prompt_format`), the right prompt
+//! (`rprompt_format`), the input box title (`title_format`) and the left
+//! of the status bar (`status_format`).
+//!
+//! `{name}` is a segment and `{name:style}` one drawn in a style of its
+//! own. `[...]` is a section that only shows when a segment inside it has
+//! something to show, and `[...](style)` also styles everything in it. A
+//! style is words as in rich: modifiers (`bold`, `dim`, `italic`,
+//! `underline`, `reverse`), a color, and `on` a background color, where a
+//! color is a theme name (`primary`, `error`, ...) or anything `[theme]`
+//! takes. `\` makes the next character plain text.
+//!
+//! Templates are compiled when the config is read; what each segment holds
+//! is up to the caller at render time.
+
+use crate::error::AppResult;
+use crate::state::Theme;
+use anyhow::{anyhow, bail};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Every segment a template may name.
+pub const SEGMENTS: &[&str] = &[
+    "symbol",
+    "cwd",
+    "dir",
+    "user",
+    "host",
+    "git",
+    "git_status",
+    "git_unavailable",
+    "exit_status",
+    "jobs",
+    "duration",
+    "time",
+    "version",
+];
+
+/// The `[ui] prompt` symbol, colored by the last exit status.
+pub const DEFAULT_PROMPT: &str = "{symbol}  ";
+pub const DEFAULT_TITLE: &str = r"\[ {user} \]";
+pub const DEFAULT_STATUS: &str = "[ HALO ](bold bg on primary) v{version}[ on {git}] \
+    [{git_status} ][{git_unavailable} ][ ✘ {exit_status} ](bold bg on error)";
+
+#[derive(Clone, Copy)]
+enum Paint {
+    Primary,
+    Accent,
+    Warn,
+    Error,
+    Success,
+    Fg,
+    Bg,
+    Comment,
+    Fixed(Color),
+}
+
+impl Paint {
+    fn parse(word: &str) -> Option<Self> {
+        Some(match word {
+            "primary" => Self::Primary,
+            "accent" => Self::Accent,
+            "warn" => Self::Warn,
+            "error" => Self::Error,
+            "success" => Self::Success,
+            "fg" => Self::Fg,
+            "bg" => Self::Bg,
+            "comment" => Self::Comment,
+            _ => Self::Fixed(Theme::parse_color(word)?),
+        })
+    }
+
+    /// Theme names follow theme changes, so they are looked up per frame.
+    fn color(self, theme: &Theme) -> Color {
+        match self {
+            Self::Primary => theme.primary,
+            Self::Accent => theme.accent,
+            Self::Warn => theme.warn,
+            Self::Error => theme.error,
+            Self::Success => theme.success,
+            Self::Fg => theme.fg,
+            Self::Bg => theme.bg,
+            Self::Comment => theme.comment,
+            Self::Fixed(color) => color,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct StyleSpec {
+    fg: Option<Paint>,
+    bg: Option<Paint>,
+    modifiers: Modifier,
+}
+
+impl StyleSpec {
+    fn parse(text: &str) -> AppResult<Self> {
+        let mut spec = Self {
+            fg: None,
+            bg: None,
+            modifiers: Modifier::empty(),
+        };
+        let mut words = text.split_whitespace();
+        while let Some(word) = words.next() {
+            let paint =
+                |word: &str| Paint::parse(word).ok_or_else(|| anyhow!("unknown color `{word}`"));
+            match word {
+                "bold" => spec.modifiers |= Modifier::BOLD,
+                "dim" => spec.modifiers |= Modifier::DIM,
+                "italic" => spec.modifiers |= Modifier::ITALIC,
+                "underline" => spec.modifiers |= Modifier::UNDERLINED,
+                "reverse" => spec.modifiers |= Modifier::REVERSED,
+                "on" => {
+                    let color = words.next().ok_or_else(|| anyhow!("`on` needs a color"))?;
+                    spec.bg = Some(paint(color)?);
+                }
+                _ => spec.fg = Some(paint(word)?),
+            }
+        }
+        Ok(spec)
+    }
+
+    fn style(&self, theme: &Theme) -> Style {
+        let mut style = Style::new().add_modifier(self.modifiers);
+        if let Some(fg) = self.fg {
+            style = style.fg(fg.color(theme));
+        }
+        if let Some(bg) = self.bg {
+            style = style.bg(bg.color(theme));
+        }
+        style
+    }
+}
+
+#[derive(Clone)]
+enum Node {
+    Text(String),
+    Segment {
+        name: String,
+        style: Option<StyleSpec>,
+    },
+    Section {
+        nodes: Vec<Node>,
+        style: Option<StyleSpec>,
+    },
+}
+
+#[derive(Clone)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> AppResult<Self> {
+        let nodes = parse_nodes(&mut source.chars().peekable(), false)?;
+        Ok(Self { nodes })
+    }
+
+    /// Draws the template. Plain text outside styled sections takes `base`;
+    /// `segment` gives a segment's spans in their default styles, or none
+    /// when it has nothing to show.
+    pub fn render<'a>(
+        &self,
+        theme: &Theme,
+        base: Style,
+        segment: &dyn Fn(&str) -> Vec<Span<'a>>,
+    ) -> Vec<Span<'a>> {
+        let mut spans = Vec::new();
+        render_nodes(&self.nodes, theme, base, None, segment, &mut spans);
+        spans
+    }
+}
+
+fn parse_nodes(chars: &mut Peekable<Chars>, in_section: bool) -> AppResult<Vec<Node>> {
+    let mut nodes = Vec::new();
+    let mut text = String::new();
+    let flush = |text: &mut String, nodes: &mut Vec<Node>| {
+        if !text.is_empty() {
+            nodes.push(Node::Text(std::mem::take(text)));
+        }
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => text.push(chars.next().ok_or_else(|| anyhow!("`\\` at the end"))?),
+            '{' => {
+                flush(&mut text, &mut nodes);
+                let inner = take_until(chars, '}').ok_or_else(|| anyhow!("unclosed `{{`"))?;
+                let (name, style) = match inner.split_once(':') {
+                    Some((name, style)) => (name.trim(), Some(StyleSpec::parse(style)?)),
+                    None => (inner.trim(), None),
+                };
+                if !SEGMENTS.contains(&name) {
+                    bail!("unknown segment `{name}`");
+                }
+                nodes.push(Node::Segment {
+                    name: name.to_string(),
+                    style,
+                });
+            }
+            '[' => {
+                flush(&mut text, &mut nodes);
+                let inner = parse_nodes(chars, true)?;
+                let style = if chars.next_if_eq(&'(').is_some() {
+                    let spec = take_until(chars, ')').ok_or_else(|| anyhow!("unclosed `(`"))?;
+                    Some(StyleSpec::parse(&spec)?)
+                } else {
+                    None
+                };
+                nodes.push(Node::Section {
+                    nodes: inner,
+                    style,
+                });
+            }
+            ']' if in_section => {
+                flush(&mut text, &mut nodes);
+                return Ok(nodes);
+            }
+            '}' | ']' => bail!("unmatched `{c}`"),
+            _ => text.push(c),
+        }
+    }
+    if in_section {
+        bail!("unclosed `[`");
+    }
+    flush(&mut text, &mut nodes);
+    Ok(nodes)
+}
+
+/// The text up to `end`, consumed along with it; `None` if it never comes.
+fn take_until(chars: &mut Peekable<Chars>, end: char) -> Option<String> {
+    let mut text = String::new();
+    for c in chars.by_ref() {
+        if c == end {
+            return Some(text);
+        }
+        text.push(c);
+    }
+    None
+}
+
+/// Appends `nodes` to `out`. `forced` is the style of an enclosing styled
+/// section, which segments take over their own. Returns whether there was
+/// a segment, and whether one had something to show.
+fn render_nodes<'a>(
+    nodes: &[Node],
+    theme: &Theme,
+    base: Style,
+    forced: Option<Style>,
+    segment: &dyn Fn(&str) -> Vec<Span<'a>>,
+    out: &mut Vec<Span<'a>>,
+) -> (bool, bool) {
+    let mut has_segment = false;
+    let mut shown = false;
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push(Span::styled(text.clone(), base)),
+            Node::Segment { name, style } => {
+                has_segment = true;
+                let spans = segment(name);
+                shown |= spans.iter().any(|span| !span.content.is_empty());
+                match style.map(|spec| spec.style(theme)).or(forced) {
+                    Some(style) => {
+                        out.extend(spans.into_iter().map(|span| Span::styled(span.content, style)))
+                    }
+                    None => out.extend(spans),
+                }
+            }
+            Node::Section { nodes, style } => {
+                let style = style.map(|spec| spec.style(theme));
+                let mut inner = Vec::new();
+                let (segments, inner_shown) = render_nodes(
+                    nodes,
+                    theme,
+                    style.unwrap_or(base),
+                    style.or(forced),
+                    segment,
+                    &mut inner,
+                );
+                if !segments || inner_shown {
+                    out.extend(inner);
+                }
+                has_segment |= segments;
+                shown |= inner_shown;
+            }
+        }
+    }
+    (has_segment, shown)
+}
+
+/// This machine's name up to the first dot, as `\h` gives it in bash.
+pub fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer outlives the call and its length is passed along.
+    let ok = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } == 0;
+    let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    let name = String::from_utf8_lossy(&buf[..end]);
+    match name.split('.').next() {
+        Some(short) if ok && !short.is_empty() => short.to_string(),
+        _ => "localhost".to_string(),
+    }
+}
@@ -0,0 +1,137 @@
+// src/ranking.rs
+
+//! Ranks history entries for the autosuggestion and Ctrl-R. Each entry is
+//! scored on how often, how recently, and where it was run; the weights of
+//! those signals come from `[history.ranking]`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// How much each signal counts. All signals are scaled to 0..=1 first, so
+/// the weights compare directly; set one to 0 to ignore it.
+#[derive(Clone, Copy, Debug)]
+pub struct RankWeights {
+    pub frequency: f64,
+    pub recency: f64,
+    pub directory: f64,
+    pub repo: f64,
+}
+
+impl Default for RankWeights {
+    fn default() -> Self {
+        Self {
+            frequency: 1.0,
+            recency: 2.0,
+            directory: 1.5,
+            repo: 1.0,
+        }
+    }
+}
+
+impl RankWeights {
+    pub fn from_table(tbl: &toml::value::Table) -> Self {
+        let mut weights = Self::default();
+        for (key, weight) in [
+            ("frequency", &mut weights.frequency),
+            ("recency", &mut weights.recency),
+            ("directory", &mut weights.directory),
+            ("repo", &mut weights.repo),
+        ] {
+            let value = match tbl.get(key) {
+                Some(toml::Value::Float(f)) => Some(*f),
+                Some(toml::Value::Integer(i)) => Some(*i as f64),
+                _ => None,
+            };
+            if let Some(value) = value {
+                *weight = value.max(0.0);
+            }
+        }
+        weights
+    }
+}
+
+/// Where the user is now.
+pub struct RankContext<'a> {
+    pub cwd: &'a Path,
+    pub repo: Option<&'a Path>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct EntryStats {
+    count: u32,
+    dirs: HashMap<PathBuf, u32>,
+    repos: HashMap<PathBuf, u32>,
+}
+
+/// Per-command usage counts, persisted next to the history file.
+#[derive(Default, Serialize, Deserialize)]
+pub struct HistoryStats {
+    entries: HashMap<String, EntryStats>,
+}
+
+impl HistoryStats {
+    pub fn record(&mut self, command: &str, cwd: &Path, repo: Option<&Path>) {
+        let stats = self.entries.entry(command.to_string()).or_default();
+        stats.count += 1;
+        *stats.dirs.entry(cwd.to_path_buf()).or_default() += 1;
+        if let Some(repo) = repo {
+            *stats.repos.entry(repo.to_path_buf()).or_default() += 1;
+        }
+    }
+
+    /// Drops stats for commands no longer in `history`.
+    pub fn retain_in(&mut self, history: &[String]) {
+        let kept: std::collections::HashSet<&str> = history.iter().map(String::as_str).collect();
+        self.entries.retain(|command, _| kept.contains(command.as_str()));
+    }
+
+    /// Distinct entries of `history` (oldest first) that pass `filter`,
+    /// best first.
+    pub fn rank<'a>(
+        &self,
+        history: &'a [String],
+        filter: impl Fn(&str) -> bool,
+        ctx: &RankContext,
+        weights: &RankWeights,
+    ) -> Vec<&'a str> {
+        // Position of each candidate's newest use.
+        let mut newest: HashMap<&str, usize> = HashMap::new();
+        for (i, entry) in history.iter().enumerate() {
+            if filter(entry) {
+                newest.insert(entry, i);
+            }
+        }
+        let max_count = newest
+            .keys()
+            .filter_map(|c| self.entries.get(*c))
+            .map(|s| s.count)
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let total = history.len().max(1) as f64;
+        let mut scored: Vec<(&str, usize, f64)> = newest
+            .into_iter()
+            .map(|(command, index)| {
+                let recency = (index + 1) as f64 / total;
+                let mut score = weights.recency * recency;
+                if let Some(stats) = self.entries.get(command) {
+                    let count = stats.count.max(1) as f64;
+                    let here = stats.dirs.get(ctx.cwd).copied().unwrap_or(0) as f64;
+                    let in_repo = ctx
+                        .repo
+                        .and_then(|repo| stats.repos.get(repo))
+                        .copied()
+                        .unwrap_or(0) as f64;
+                    score += weights.frequency * stats.count as f64 / max_count as f64
+                        + weights.directory * here / count
+                        + weights.repo * in_repo / count;
+                }
+                (command, index, score)
+            })
+            .collect();
+        // Ties go to the more recent entry.
+        scored.sort_by(|a, b| b.2.total_cmp(&a.2).then(b.1.cmp(&a.1)));
+        scored.into_iter().map(|(command, _, _)| command).collect()
+    }
+}
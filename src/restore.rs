@@ -0,0 +1,112 @@
+// src/restore.rs
+
+//! `[session] restore_log`: the end of the command log is saved with the
+//! session on exit and put back on the next start, so restarting halo (or
+//! the machine) doesn't lose what was on screen. Output is cut to the last
+//! `restore_lines` lines per entry to keep session.json small.
+
+use crate::command::CommandLog;
+use crate::export;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Clone, Copy)]
+pub struct RestoreConfig {
+    /// How many entries to keep; 0 turns saving the log off.
+    pub entries: usize,
+    pub lines: usize,
+}
+
+impl Default for RestoreConfig {
+    fn default() -> Self {
+        Self {
+            entries: 0,
+            lines: 200,
+        }
+    }
+}
+
+impl RestoreConfig {
+    pub fn from_table(tbl: &toml::value::Table) -> Self {
+        let mut config = Self::default();
+        match tbl.get("restore_log") {
+            Some(toml::Value::Boolean(true)) => config.entries = 100,
+            Some(toml::Value::Integer(n)) => config.entries = (*n).max(0) as usize,
+            _ => {}
+        }
+        if let Some(n) = tbl.get("restore_lines").and_then(|v| v.as_integer()) {
+            config.lines = n.max(1) as usize;
+        }
+        config
+    }
+}
+
+/// One finished entry as session.json keeps it.
+#[derive(Serialize, Deserialize)]
+pub struct SavedEntry {
+    command: String,
+    cwd: PathBuf,
+    branch: Option<String>,
+    #[serde(default)]
+    output: Vec<String>,
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+    duration_ms: Option<u64>,
+    /// RFC 3339.
+    started: Option<String>,
+    note: Option<String>,
+}
+
+/// The newest finished entries of `logs`, as `config` allows.
+pub fn save(logs: &[CommandLog], config: RestoreConfig) -> Vec<SavedEntry> {
+    let finished: Vec<&CommandLog> = logs
+        .iter()
+        .filter(|log| !log.is_running && export::is_exported(log))
+        .collect();
+    let from = finished.len().saturating_sub(config.entries);
+    finished[from..]
+        .iter()
+        .map(|log| {
+            let total = log.output.len();
+            let kept = total.min(config.lines);
+            let mut output = Vec::with_capacity(kept + 1);
+            if kept < total {
+                output.push(format!("[{} earlier lines not restored]", total - kept));
+            }
+            output.extend(log.output.lines().skip(total - kept).map(str::to_string));
+            SavedEntry {
+                command: log.command.clone(),
+                cwd: log.cwd.clone(),
+                branch: log.branch.clone(),
+                output,
+                exit_code: log.exit_code,
+                signal: log.signal,
+                duration_ms: log.duration_ms.map(|ms| ms as u64),
+                started: log.started_wall.map(|t| t.to_rfc3339()),
+                note: log.note.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Log entries for saved ones, finished and as they were left.
+pub fn restore(saved: Vec<SavedEntry>) -> Vec<CommandLog> {
+    saved
+        .into_iter()
+        .map(|entry| {
+            let output = entry.output.join("\n");
+            let mut log = CommandLog::new(entry.command, output, false, entry.cwd);
+            log.branch = entry.branch;
+            log.exit_code = entry.exit_code;
+            log.signal = entry.signal;
+            log.duration_ms = entry.duration_ms.map(u128::from);
+            log.started_wall = entry
+                .started
+                .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+                .map(|t| t.with_timezone(&Local));
+            log.note = entry.note;
+            log
+        })
+        .collect()
+}
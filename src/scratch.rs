@@ -0,0 +1,55 @@
+// src/scratch.rs
+
+//! The session's scratch directory for the `tmp` builtin: somewhere to
+//! extract an archive or try something out without picking a place first.
+//! It is created on first use, exported to children as `$HALO_TMP`, and
+//! removed when halo exits unless `tmp pin` asked to keep it.
+
+use crate::output;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The variable children see the directory under.
+pub const ENV: &str = "HALO_TMP";
+
+static CREATED: AtomicBool = AtomicBool::new(false);
+static PINNED: AtomicBool = AtomicBool::new(false);
+
+/// Where this session's directory lives, whether or not it exists yet: in
+/// the per-user runtime directory where there is one.
+pub fn path() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(format!("halo-tmp-{}", std::process::id()))
+}
+
+/// The directory, created if needed. One someone else made first at the
+/// same path is refused rather than used.
+pub fn ensure() -> io::Result<PathBuf> {
+    let dir = path();
+    output::create_private_dir(&dir)?;
+    CREATED.store(true, Ordering::Relaxed);
+    Ok(dir)
+}
+
+/// The directory once `tmp` has created it; what `$HALO_TMP` is set to.
+pub fn current() -> Option<PathBuf> {
+    CREATED.load(Ordering::Relaxed).then(path)
+}
+
+pub fn set_pinned(pinned: bool) {
+    PINNED.store(pinned, Ordering::Relaxed);
+}
+
+pub fn is_pinned() -> bool {
+    PINNED.load(Ordering::Relaxed)
+}
+
+/// Deletes the directory on exit, unless pinned.
+pub fn remove() {
+    if CREATED.load(Ordering::Relaxed) && !is_pinned() {
+        let _ = fs::remove_dir_all(path());
+    }
+}
@@ -0,0 +1,68 @@
+// src/sessions.rs
+
+//! Named sessions for `session save|load <name>`: a project context
+//! (directory, `export`ed variables, aliases as they are in memory, and
+//! the log) kept under `halo/sessions/<name>.json` in the config dir, so
+//! switching between projects doesn't mean setting each up again.
+
+use crate::persist;
+use crate::restore::SavedEntry;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub cwd: PathBuf,
+    #[serde(default)]
+    pub exports: BTreeMap<String, String>,
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    #[serde(default)]
+    pub log: Vec<SavedEntry>,
+}
+
+fn dir() -> io::Result<PathBuf> {
+    dirs::config_dir()
+        .map(|dir| dir.join("halo/sessions"))
+        .ok_or_else(|| io::Error::other("no config directory"))
+}
+
+/// The file for `name`. Names are plain file names: no `/`, no leading `.`.
+pub fn path(name: &str) -> io::Result<PathBuf> {
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a session name"));
+    }
+    Ok(dir()?.join(format!("{name}.json")))
+}
+
+pub fn save(name: &str, snapshot: &Snapshot) -> io::Result<PathBuf> {
+    let path = path(name)?;
+    persist::write_atomic(&path, &serde_json::to_vec_pretty(snapshot)?)?;
+    Ok(path)
+}
+
+pub fn load(name: &str) -> io::Result<Snapshot> {
+    let text = fs::read_to_string(path(name)?)?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+pub fn remove(name: &str) -> io::Result<()> {
+    fs::remove_file(path(name)?)
+}
+
+/// Saved session names, sorted.
+pub fn list() -> Vec<String> {
+    let Ok(entries) = dir().and_then(fs::read_dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter_map(|name| name.strip_suffix(".json").map(str::to_string))
+        .collect();
+    names.sort();
+    names
+}
@@ -0,0 +1,170 @@
+// src/share.rs
+
+//! `share`: posts one log entry (command, output, exit status, timing) to
+//! a team webhook or paste service set up in `[share]`, and reports the
+//! link the service answers with. Off until a URL is configured; nothing
+//! leaves the machine otherwise.
+
+use crate::command::CommandLog;
+use crate::export;
+use crate::timefmt::TimeFormat;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command as TokioCommand;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Requests give up after this long.
+const TIMEOUT_SECS: u32 = 20;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Payload {
+    /// The entry as `log export --format json` writes it.
+    #[default]
+    Entry,
+    /// A GitHub gist holding the entry as Markdown.
+    Gist,
+}
+
+/// `[share]` settings.
+#[derive(Clone, Default)]
+pub struct ShareConfig {
+    pub url: Option<String>,
+    pub payload: Payload,
+    /// Extra request headers, e.g. `Authorization: Bearer ...`.
+    pub headers: Vec<String>,
+}
+
+impl ShareConfig {
+    pub fn from_table(tbl: &toml::value::Table) -> Self {
+        Self {
+            url: tbl
+                .get("url")
+                .and_then(|v| v.as_str())
+                .filter(|url| !url.is_empty())
+                .map(str::to_string),
+            payload: match tbl.get("payload").and_then(|v| v.as_str()) {
+                Some("gist") => Payload::Gist,
+                _ => Payload::Entry,
+            },
+            headers: tbl
+                .get("headers")
+                .and_then(|v| v.as_array())
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|v| v.as_str())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// How a share went, for the entry of the `share` command that asked.
+pub struct Shared {
+    pub entry: u64,
+    pub command: String,
+    /// The link, or why there is none.
+    pub result: Result<String, String>,
+}
+
+/// Posts `log` to `url` with `curl` in the background; the outcome arrives
+/// on `tx`, tagged with the `share` command's own entry.
+pub fn send(
+    config: &ShareConfig,
+    url: &str,
+    log: &CommandLog,
+    time: &TimeFormat,
+    entry: u64,
+    tx: UnboundedSender<Shared>,
+) {
+    let body = payload(log, config.payload, time).to_string();
+    // Headers (tokens, often) and the body go to curl as a config file on
+    // stdin: in argv anyone could read them with `ps`.
+    let mut input = String::from("header = \"Content-Type: application/json\"\n");
+    for header in &config.headers {
+        input.push_str(&format!("header = \"{}\"\n", config_quote(header)));
+    }
+    input.push_str(&format!("data-raw = \"{}\"\n", config_quote(&body)));
+    let mut command = TokioCommand::new("curl");
+    command
+        .args(["-fsS", "-m", &TIMEOUT_SECS.to_string()])
+        .args(["--config", "-"])
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let name = log.command.clone();
+    tokio::spawn(async move {
+        let result = post(command, input).await;
+        let _ = tx.send(Shared {
+            entry,
+            command: name,
+            result,
+        });
+    });
+}
+
+/// `text` escaped for a double-quoted curl config value.
+fn config_quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+async fn post(mut command: TokioCommand, input: String) -> Result<String, String> {
+    let mut child = command.spawn().map_err(|e| format!("curl: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .await
+            .map_err(|e| format!("curl: {e}"))?;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("curl: {e}"))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(if error.is_empty() { "request failed".to_string() } else { error });
+    }
+    let response = String::from_utf8_lossy(&output.stdout);
+    link_in(&response).ok_or_else(|| "the service answered without a link".to_string())
+}
+
+fn payload(log: &CommandLog, kind: Payload, time: &TimeFormat) -> serde_json::Value {
+    match kind {
+        Payload::Entry => export::json_entry(log),
+        Payload::Gist => serde_json::json!({
+            "description": format!("halo: {}", log.command),
+            "public": false,
+            "files": { "halo-entry.md": { "content": export::markdown_entry(log, time) } },
+        }),
+    }
+}
+
+/// The link in a service's answer: `html_url` or `url` of a JSON object
+/// (gists, most paste APIs), else the first URL in a plain-text body.
+fn link_in(response: &str) -> Option<String> {
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(response) {
+        for key in ["html_url", "url", "link"] {
+            if let Some(url) = value.get(key).and_then(|v| v.as_str()) {
+                return Some(url.to_string());
+            }
+        }
+    }
+    response
+        .split_whitespace()
+        .find(|word| word.starts_with("https://") || word.starts_with("http://"))
+        .map(str::to_string)
+}
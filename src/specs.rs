@@ -0,0 +1,181 @@
+// src/specs.rs
+
+//! Completion specs: per-tool knowledge of subcommands and the values they
+//! take. A provider returns `None` when it has nothing to say about a
+//! position, and completion falls back to paths.
+
+use std::fs;
+use std::path::Path;
+
+const GIT_SUBCOMMANDS: &[&str] = &[
+    "add", "bisect", "blame", "branch", "checkout", "cherry-pick", "clean", "clone", "commit",
+    "config", "diff", "fetch", "grep", "init", "log", "merge", "mv", "pull", "push", "rebase",
+    "reflog", "remote", "reset", "restore", "revert", "rm", "show", "stash", "status", "switch",
+    "tag", "worktree",
+];
+
+const CARGO_SUBCOMMANDS: &[&str] = &[
+    "add", "bench", "build", "check", "clean", "clippy", "doc", "fetch", "fmt", "init",
+    "install", "metadata", "new", "publish", "remove", "run", "search", "test", "tree",
+    "uninstall", "update",
+];
+
+const DOCKER_SUBCOMMANDS: &[&str] = &[
+    "build", "compose", "cp", "exec", "image", "images", "inspect", "kill", "login", "logs",
+    "network", "ps", "pull", "push", "restart", "rm", "rmi", "run", "start", "stop", "system",
+    "tag", "volume",
+];
+
+const DOCKER_COMPOSE_SUBCOMMANDS: &[&str] = &[
+    "build", "config", "down", "exec", "logs", "ps", "pull", "restart", "run", "start", "stop",
+    "up",
+];
+
+const KUBECTL_SUBCOMMANDS: &[&str] = &[
+    "apply", "config", "create", "delete", "describe", "edit", "exec", "explain", "expose", "get",
+    "label", "logs", "patch", "port-forward", "rollout", "scale", "top",
+];
+
+const KUBECTL_RESOURCES: &[&str] = &[
+    "configmaps", "cronjobs", "daemonsets", "deployments", "events", "ingresses", "jobs",
+    "namespaces", "nodes", "persistentvolumeclaims", "pods", "replicasets", "secrets",
+    "serviceaccounts", "services", "statefulsets",
+];
+
+/// Suggestions for `token`, the last word of `words` (which starts with the
+/// command). `words` excludes the token itself.
+pub fn suggest(words: &[&str], token: &str, cwd: &Path) -> Option<Vec<String>> {
+    let (command, args) = words.split_first()?;
+    let candidates = match *command {
+        "git" => git(args, cwd)?,
+        "cargo" => cargo(args, cwd)?,
+        "docker" => docker(args)?,
+        "kubectl" => kubectl(args)?,
+        _ => return None,
+    };
+    let mut matches: Vec<String> = candidates
+        .into_iter()
+        .filter(|c| c.starts_with(token))
+        .collect();
+    matches.sort();
+    matches.dedup();
+    Some(matches)
+}
+
+fn words(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+/// Positional arguments only; flags don't shift what comes next.
+fn positional<'a>(args: &[&'a str]) -> Vec<&'a str> {
+    args.iter().copied().filter(|a| !a.starts_with('-')).collect()
+}
+
+fn git(args: &[&str], cwd: &Path) -> Option<Vec<String>> {
+    let args = positional(args);
+    match args.as_slice() {
+        [] => Some(words(GIT_SUBCOMMANDS)),
+        ["checkout" | "switch" | "merge" | "rebase" | "branch" | "cherry-pick" | "log", ..] => {
+            Some(git_refs(cwd, git2::BranchType::Local))
+        }
+        ["push" | "pull" | "fetch"] => Some(git_remotes(cwd)),
+        ["push" | "pull" | "fetch", _] => Some(git_refs(cwd, git2::BranchType::Local)),
+        ["remote"] => Some(words(&["add", "remove", "rename", "show", "-v"])),
+        ["stash"] => Some(words(&["apply", "drop", "list", "pop", "push", "show"])),
+        _ => None,
+    }
+}
+
+fn git_refs(cwd: &Path, kind: git2::BranchType) -> Vec<String> {
+    let Ok(repo) = git2::Repository::discover(cwd) else {
+        return Vec::new();
+    };
+    let Ok(branches) = repo.branches(Some(kind)) else {
+        return Vec::new();
+    };
+    branches
+        .filter_map(Result::ok)
+        .filter_map(|(branch, _)| branch.name().ok().flatten().map(str::to_string))
+        .collect()
+}
+
+fn git_remotes(cwd: &Path) -> Vec<String> {
+    git2::Repository::discover(cwd)
+        .and_then(|repo| repo.remotes())
+        .map(|names| names.iter().flatten().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn cargo(args: &[&str], cwd: &Path) -> Option<Vec<String>> {
+    if matches!(args.last(), Some(&"-p" | &"--package")) {
+        return Some(workspace_members(cwd));
+    }
+    match positional(args).as_slice() {
+        [] => Some(words(CARGO_SUBCOMMANDS)),
+        _ => None,
+    }
+}
+
+/// Package names of the workspace containing `cwd`, read from the manifests.
+fn workspace_members(cwd: &Path) -> Vec<String> {
+    let Some(root) = cwd
+        .ancestors()
+        .filter(|dir| dir.join("Cargo.toml").is_file())
+        .last()
+    else {
+        return Vec::new();
+    };
+    let Some(manifest) = read_manifest(root) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = package_name(&manifest).into_iter().collect();
+    let members = manifest
+        .get("workspace")
+        .and_then(|w| w.get("members"))
+        .and_then(|m| m.as_array())
+        .cloned()
+        .unwrap_or_default();
+    for member in members.iter().filter_map(|m| m.as_str()) {
+        // Only trailing `/*` globs are common enough to bother with.
+        let dirs: Vec<_> = match member.strip_suffix("/*") {
+            Some(parent) => fs::read_dir(root.join(parent))
+                .map(|entries| entries.filter_map(Result::ok).map(|e| e.path()).collect())
+                .unwrap_or_default(),
+            None => vec![root.join(member)],
+        };
+        for dir in dirs {
+            if let Some(name) = read_manifest(&dir).as_ref().and_then(package_name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+fn read_manifest(dir: &Path) -> Option<toml::Value> {
+    fs::read_to_string(dir.join("Cargo.toml")).ok()?.parse().ok()
+}
+
+fn package_name(manifest: &toml::Value) -> Option<String> {
+    manifest
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(str::to_string)
+}
+
+fn docker(args: &[&str]) -> Option<Vec<String>> {
+    match positional(args).as_slice() {
+        [] => Some(words(DOCKER_SUBCOMMANDS)),
+        ["compose"] => Some(words(DOCKER_COMPOSE_SUBCOMMANDS)),
+        _ => None,
+    }
+}
+
+fn kubectl(args: &[&str]) -> Option<Vec<String>> {
+    match positional(args).as_slice() {
+        [] => Some(words(KUBECTL_SUBCOMMANDS)),
+        ["get" | "describe" | "delete" | "edit" | "explain"] => Some(words(KUBECTL_RESOURCES)),
+        _ => None,
+    }
+}
@@ -1,14 +1,84 @@
 // src/state.rs
 
-use crate::command::CommandLog;
-use crate::completion::CompletionState;
+//! [`State`]: everything a session knows, from the command log, history
+//! and input line to the configuration and theme read from `halo.toml`.
+
+use crate::aliases;
+use crate::builder::Builder;
+use crate::colorblind::Simulation;
+use crate::command::{CommandLog, JobExit};
+use crate::completion::{self, CompletionState, FileTemplates, UserCompletions};
+use crate::crash;
+use crate::diffview::DiffView;
+use crate::dirpicker::DirPicker;
+use crate::dirstack::DirStack;
+use crate::dryrun::Step;
 use crate::error::AppResult;
+use crate::frecency::Frecency;
+use crate::git::{self, GitSegmentConfig, GitStatus};
+use crate::gitpanel::{Area, GitPanel};
+use crate::handoff;
+use crate::hooks::HooksConfig;
+use crate::lock::{Lock, LockConfig};
+use crate::normalize::{self, Fix};
+use crate::notify::NotifyConfig;
+use crate::output::{self, OutputLimits};
+use crate::pager::Pager;
+use crate::panes::{self, Pane, Split};
+use crate::parser;
+use crate::perf::PerfStats;
+use crate::persist::{self, Persister};
+use crate::procs::ProcMonitor;
+use crate::profile::StartupProfile;
+use crate::ranking::{HistoryStats, RankContext, RankWeights};
+use crate::restore::{self, RestoreConfig, SavedEntry};
+use crate::share::ShareConfig;
+use crate::summary::SessionStats;
+use crate::tabs::{self, Tab};
+use crate::prompt::{self, Template};
 use crate::themes;
+use crate::timefmt::TimeFormat;
+use crate::words::WordConfig;
 use ratatui::style::Color;
+use ratatui::layout::{Position, Rect};
+use ratatui::widgets::BorderType;
+/// The `[ui]` table.
 #[derive(Clone)]
 pub struct UiConfig {
+    /// Character the log's scrollbar thumb is drawn with.
     pub scrollbar_thumb: String,
+    /// The prompt symbol, `{symbol}` in the templates.
     pub prompt: String,
+    /// `prompt_format`: what comes before the input.
+    pub prompt_format: Template,
+    /// Flush right in the input box, like zsh's RPROMPT; off when unset.
+    pub rprompt_format: Option<Template>,
+    /// `title_format`: the input box title.
+    pub title_format: Template,
+    /// `status_format`: the left of the status bar.
+    pub status_format: Template,
+    /// `time_format` and `locale`, for every time halo shows.
+    pub time_format: TimeFormat,
+    /// A clock at the right of the status bar.
+    pub show_clock: bool,
+    /// Exit statuses of this many recent commands above the input; 0 hides it.
+    pub status_strip: usize,
+    /// Unpack the embedded themes into ~/.config/halo/themes on first use.
+    pub install_themes: bool,
+    /// Name the terminal window after the workspace.
+    pub terminal_title: bool,
+    /// Where log entries show when they ran.
+    pub timestamps: LogTimestamps,
+    /// Format of those timestamps; `time_format` when unset.
+    pub timestamp_format: Option<TimeFormat>,
+    /// Compact folds finished entries to one summary line.
+    pub compact_log: bool,
+    /// Durations at or above this are drawn in the warn color with a ⚠.
+    pub slow_command_ms: Option<u128>,
+    /// `[ui.completion]`.
+    pub completion: CompletionPopupConfig,
+    /// `[ui.git]` indicators.
+    pub git: GitSegmentConfig,
 }
 
 impl Default for UiConfig {
@@ -16,23 +86,199 @@ impl Default for UiConfig {
         Self {
             scrollbar_thumb: "█".to_string(),
             prompt: "❯".to_string(),
+            prompt_format: Template::parse(prompt::DEFAULT_PROMPT).expect("default prompt"),
+            rprompt_format: None,
+            title_format: Template::parse(prompt::DEFAULT_TITLE).expect("default title"),
+            status_format: Template::parse(prompt::DEFAULT_STATUS).expect("default status"),
+            time_format: TimeFormat::default(),
+            show_clock: false,
+            status_strip: 30,
+            install_themes: true,
+            terminal_title: true,
+            timestamps: LogTimestamps::Off,
+            timestamp_format: None,
+            compact_log: false,
+            slow_command_ms: None,
+            completion: CompletionPopupConfig::default(),
+            git: GitSegmentConfig::default(),
+        }
+    }
+}
+
+impl UiConfig {
+    /// Whether a command that took `duration_ms` is flagged as slow.
+    pub fn is_slow(&self, duration_ms: u128) -> bool {
+        self.slow_command_ms.is_some_and(|limit| duration_ms >= limit)
+    }
+
+    /// The format for log timestamps: `timestamp_format`, else `time_format`.
+    pub fn timestamp_format(&self) -> &TimeFormat {
+        self.timestamp_format.as_ref().unwrap_or(&self.time_format)
+    }
+}
+
+/// Which parts of a log entry carry its wall-clock time: the header shows
+/// when the command started, the exit line when it finished.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LogTimestamps {
+    /// Neither.
+    Off,
+    /// The header only.
+    Header,
+    /// The exit line only.
+    Meta,
+    /// Both.
+    Both,
+}
+
+impl LogTimestamps {
+    /// The `[ui] timestamps` value; `none` means off.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" | "none" => Some(Self::Off),
+            "header" => Some(Self::Header),
+            "meta" => Some(Self::Meta),
+            "both" => Some(Self::Both),
+            _ => None,
+        }
+    }
+
+    /// The header shows the start time.
+    pub fn header(self) -> bool {
+        matches!(self, Self::Header | Self::Both)
+    }
+
+    /// The exit line shows the end time.
+    pub fn meta(self) -> bool {
+        matches!(self, Self::Meta | Self::Both)
+    }
+}
+
+/// The entry history preview is on, and the offset it was shown at.
+struct ScrollAnchor {
+    entry: u64,
+    offset: usize,
+}
+
+/// Whether the completion popup opens above or below the input box.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PopupPosition {
+    /// Above, the default.
+    Above,
+    /// Below, for an input box at the top.
+    Below,
+}
+
+/// Look of the completion popup, from `[ui.completion]`.
+#[derive(Clone)]
+pub struct CompletionPopupConfig {
+    /// Most rows, border included.
+    pub max_height: u16,
+    /// Most columns, border included.
+    pub max_width: u16,
+    /// `None` draws the list without a border.
+    pub border: Option<BorderType>,
+    /// Empty strings hide the icon column (`icons = false`).
+    pub dir_icon: String,
+    /// Icon for files.
+    pub file_icon: String,
+    /// Icon for arguments from history.
+    pub history_icon: String,
+    /// Icon for `[templates]` commands.
+    pub template_icon: String,
+    /// Above or below the input box.
+    pub position: PopupPosition,
+    /// Spell out the selection in the status bar (`3/14: src/main.rs`), for
+    /// screen readers and anyone who can't rely on the highlight.
+    pub announce: bool,
+}
+
+impl Default for CompletionPopupConfig {
+    fn default() -> Self {
+        Self {
+            max_height: 10,
+            max_width: 80,
+            border: Some(BorderType::Double),
+            dir_icon: "📁".to_string(),
+            file_icon: "📄".to_string(),
+            history_icon: "🕘".to_string(),
+            template_icon: "⚙".to_string(),
+            position: PopupPosition::Above,
+            announce: false,
+        }
+    }
+}
+
+impl CompletionPopupConfig {
+    /// Reads `[ui.completion]`; anything missing or invalid keeps its default.
+    pub fn from_table(tbl: &toml::value::Table) -> Self {
+        let mut cfg = Self::default();
+        if let Some(h) = tbl.get("max_height").and_then(|v| v.as_integer()) {
+            // Room for the border plus at least one row.
+            cfg.max_height = h.clamp(3, u16::MAX as i64) as u16;
+        }
+        if let Some(w) = tbl.get("max_width").and_then(|v| v.as_integer()) {
+            cfg.max_width = w.clamp(10, u16::MAX as i64) as u16;
+        }
+        if let Some(border) = tbl.get("border").and_then(|v| v.as_str()) {
+            cfg.border = match border {
+                "none" => None,
+                "plain" => Some(BorderType::Plain),
+                "rounded" => Some(BorderType::Rounded),
+                "thick" => Some(BorderType::Thick),
+                _ => Some(BorderType::Double),
+            };
+        }
+        if tbl.get("icons").and_then(|v| v.as_bool()) == Some(false) {
+            cfg.dir_icon.clear();
+            cfg.file_icon.clear();
+            cfg.history_icon.clear();
+            cfg.template_icon.clear();
+        }
+        for (key, icon) in [
+            ("dir_icon", &mut cfg.dir_icon),
+            ("file_icon", &mut cfg.file_icon),
+            ("history_icon", &mut cfg.history_icon),
+            ("template_icon", &mut cfg.template_icon),
+        ] {
+            if let Some(s) = tbl.get(key).and_then(|v| v.as_str()) {
+                *icon = s.to_string();
+            }
         }
+        if tbl.get("position").and_then(|v| v.as_str()) == Some("below") {
+            cfg.position = PopupPosition::Below;
+        }
+        if let Some(announce) = tbl.get("announce").and_then(|v| v.as_bool()) {
+            cfg.announce = announce;
+        }
+        cfg
     }
 }
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::BufReader;
-use std::path::PathBuf;
-use std::time::Instant;
+use std::os::unix::fs::PermissionsExt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+/// The colors everything is drawn in, from `theme` or `[theme]`.
 #[derive(Clone)]
 pub struct Theme {
+    /// Command names, the prompt and most highlights.
     pub primary: Color,
+    /// Selections and matching brackets.
     pub accent: Color,
+    /// Strings and warnings.
     pub warn: Color,
+    /// Failures and unknown commands.
     pub error: Color,
+    /// Successful exits.
     pub success: Color,
+    /// Text.
     pub fg: Color,
+    /// Background.
     pub bg: Color,
+    /// Flags, hints and other secondary text.
     pub comment: Color,
 }
 
@@ -53,7 +299,8 @@ impl Default for Theme {
 }
 
 impl Theme {
-    fn parse_color(input: &str) -> Option<Color> {
+    /// A color as `[theme]` takes it: `#RRGGBB`, `#RGB`, `rgb(r,g,b)` or a name.
+    pub fn parse_color(input: &str) -> Option<Color> {
         let s = input.trim();
         // Hex: #RRGGBB or #RGB
         if let Some(hex) = s.strip_prefix('#') {
@@ -83,10 +330,10 @@ impl Theme {
             }
         }
         // 8-bit indexed: ansi:N or index:N
-        if let Some(num) = s.strip_prefix("ansi:").or_else(|| s.strip_prefix("index:")) {
-            if let Ok(v) = num.parse::<u8>() {
-                return Some(Color::Indexed(v));
-            }
+        if let Some(num) = s.strip_prefix("ansi:").or_else(|| s.strip_prefix("index:"))
+            && let Ok(v) = num.parse::<u8>()
+        {
+            return Some(Color::Indexed(v));
         }
         // Named colors
         let name = s.to_ascii_lowercase();
@@ -105,51 +352,53 @@ impl Theme {
         Some(named)
     }
 
+    /// `base` with the colors `tbl` sets; unparsable ones are ignored.
     pub fn from_table(tbl: &toml::value::Table, base: Theme) -> Theme {
         let mut t = base;
-        if let Some(v) = tbl.get("primary").and_then(|v| v.as_str()) {
-            if let Some(c) = Self::parse_color(v) {
-                t.primary = c;
-            }
+        if let Some(v) = tbl.get("primary").and_then(|v| v.as_str())
+            && let Some(c) = Self::parse_color(v)
+        {
+            t.primary = c;
         }
-        if let Some(v) = tbl.get("accent").and_then(|v| v.as_str()) {
-            if let Some(c) = Self::parse_color(v) {
-                t.accent = c;
-            }
+        if let Some(v) = tbl.get("accent").and_then(|v| v.as_str())
+            && let Some(c) = Self::parse_color(v)
+        {
+            t.accent = c;
         }
-        if let Some(v) = tbl.get("warn").and_then(|v| v.as_str()) {
-            if let Some(c) = Self::parse_color(v) {
-                t.warn = c;
-            }
+        if let Some(v) = tbl.get("warn").and_then(|v| v.as_str())
+            && let Some(c) = Self::parse_color(v)
+        {
+            t.warn = c;
         }
-        if let Some(v) = tbl.get("error").and_then(|v| v.as_str()) {
-            if let Some(c) = Self::parse_color(v) {
-                t.error = c;
-            }
+        if let Some(v) = tbl.get("error").and_then(|v| v.as_str())
+            && let Some(c) = Self::parse_color(v)
+        {
+            t.error = c;
         }
-        if let Some(v) = tbl.get("success").and_then(|v| v.as_str()) {
-            if let Some(c) = Self::parse_color(v) {
-                t.success = c;
-            }
+        if let Some(v) = tbl.get("success").and_then(|v| v.as_str())
+            && let Some(c) = Self::parse_color(v)
+        {
+            t.success = c;
         }
-        if let Some(v) = tbl.get("fg").and_then(|v| v.as_str()) {
-            if let Some(c) = Self::parse_color(v) {
-                t.fg = c;
-            }
+        if let Some(v) = tbl.get("fg").and_then(|v| v.as_str())
+            && let Some(c) = Self::parse_color(v)
+        {
+            t.fg = c;
         }
-        if let Some(v) = tbl.get("bg").and_then(|v| v.as_str()) {
-            if let Some(c) = Self::parse_color(v) {
-                t.bg = c;
-            }
+        if let Some(v) = tbl.get("bg").and_then(|v| v.as_str())
+            && let Some(c) = Self::parse_color(v)
+        {
+            t.bg = c;
         }
-        if let Some(v) = tbl.get("comment").and_then(|v| v.as_str()) {
-            if let Some(c) = Self::parse_color(v) {
-                t.comment = c;
-            }
+        if let Some(v) = tbl.get("comment").and_then(|v| v.as_str())
+            && let Some(c) = Self::parse_color(v)
+        {
+            t.comment = c;
         }
         t
     }
 
+    /// One of the built-in themes; the default for any other name.
     pub fn from_name(name: &str) -> Theme {
         match name {
             // A vibrant cyberpunk + nord fusion (current default)
@@ -190,35 +439,468 @@ impl Theme {
 }
 
 const HISTORY_LIMIT: usize = 100;
+/// How often the UI loop wakes up to poll input, drain job output and redraw.
+const DEFAULT_TICK: Duration = Duration::from_millis(100);
+/// Kills kept for Ctrl-Y / Alt-Y; older ones are dropped.
+const KILL_RING_SIZE: usize = 16;
+/// How long a status bar notice stays up.
+const NOTICE_DURATION: Duration = Duration::from_secs(3);
+
+/// `path` with the home directory written as `~`.
+pub fn tilde_path(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// Whether halo runs inside an SSH session, where every redraw costs latency.
+pub fn over_ssh() -> bool {
+    std::env::var_os("SSH_CONNECTION").is_some() || std::env::var_os("SSH_TTY").is_some()
+}
+
+/// What to do once the user answers a confirmation popup.
+pub enum ConfirmAction {
+    /// Run a command line that was held back (noclobber, a `cd` typo).
+    RunLine {
+        /// The line, already expanded.
+        line: String,
+        /// Run it as a background job.
+        background: bool,
+    },
+    /// Discard a file's changes from the git panel.
+    DiscardChanges {
+        /// The file, relative to the work tree root, as the panel lists it.
+        path: String,
+        /// The group it was listed in when asked.
+        area: Area,
+    },
+    /// Open a `file` link from the output.
+    OpenLink {
+        /// The link's target, which the prompt shows.
+        url: String,
+    },
+}
+
+/// A yes/no question shown over the UI; keys go to it until answered.
+pub struct Confirmation {
+    /// The question.
+    pub prompt: String,
+    /// What a yes does (and a no undoes).
+    pub action: ConfirmAction,
+}
+
+/// The `{name}` placeholders of an alias being asked for one at a time
+/// in the input line, before the alias runs.
+pub struct PlaceholderPrompt {
+    /// The alias name.
+    pub alias: String,
+    /// The alias value.
+    pub value: String,
+    /// Distinct placeholders in order of first use, with their kinds.
+    pub fields: Vec<(String, Option<String>)>,
+    /// Answers so far, by placeholder name.
+    pub answers: std::collections::HashMap<String, String>,
+    /// Index into `fields` of the one being asked.
+    pub current: usize,
+    /// Words typed after the alias name beyond the placeholders.
+    pub rest: Vec<String>,
+    /// The line ended in `&`.
+    pub background: bool,
+}
+
+impl PlaceholderPrompt {
+    /// `None` if `value` has no placeholders. Words given after the alias
+    /// answer the placeholders in order.
+    pub fn new(alias: &str, value: &str, args: &[String], background: bool) -> Option<Self> {
+        let mut fields: Vec<(String, Option<String>)> = Vec::new();
+        for placeholder in aliases::placeholders(value) {
+            if !fields.iter().any(|(name, _)| *name == placeholder.name) {
+                fields.push((placeholder.name, placeholder.kind));
+            }
+        }
+        if fields.is_empty() {
+            return None;
+        }
+        let given = args.len().min(fields.len());
+        let answers = fields
+            .iter()
+            .zip(args)
+            .map(|((name, _), arg)| (name.clone(), arg.clone()))
+            .collect();
+        Some(Self {
+            alias: alias.to_string(),
+            value: value.to_string(),
+            fields,
+            answers,
+            current: given,
+            rest: args[given..].to_vec(),
+            background,
+        })
+    }
+
+    /// The placeholder being asked for, with its kind; `None` once all are
+    /// answered.
+    pub fn field(&self) -> Option<&(String, Option<String>)> {
+        self.fields.get(self.current)
+    }
+
+    /// The command line once every placeholder is answered.
+    pub fn command_line(&self) -> String {
+        let mut line = aliases::fill_placeholders(&self.value, &self.answers);
+        for word in &self.rest {
+            line.push(' ');
+            line.push_str(&parser::quote(word));
+        }
+        line
+    }
+}
+
+/// Ctrl-R reverse-i-search over `State::history`.
+#[derive(Default)]
+pub struct HistorySearch {
+    /// What has been typed.
+    pub query: String,
+    /// Which match is selected, 0 being the newest.
+    pub selected: usize,
+}
+
+impl HistorySearch {
+    /// Moves to the next lower-ranked of `matches` matches, stopping at the last.
+    pub fn select_older(&mut self, matches: usize) {
+        if self.selected + 1 < matches {
+            self.selected += 1;
+        }
+    }
+}
+
+/// `/` (while previewing) or Ctrl-F search through the output log.
+#[derive(Default)]
+pub struct LogSearch {
+    /// The pattern.
+    pub query: String,
+    /// Still typing the pattern; n/N step through matches once accepted.
+    pub editing: bool,
+    /// Log entry and output line of each matching line, newest first.
+    pub matches: Vec<(usize, usize)>,
+    /// Index into `matches` of the one shown.
+    pub current: usize,
+}
+
+impl LogSearch {
+    /// Byte ranges of the pattern in `line`. Smart case: the search is
+    /// case-sensitive only if the pattern has capitals.
+    pub fn ranges(&self, line: &str) -> Vec<Range<usize>> {
+        if self.query.is_empty() {
+            return Vec::new();
+        }
+        let fold = !self.query.chars().any(char::is_uppercase);
+        // ASCII folding keeps byte offsets valid for the original line.
+        let (haystack, needle) = if fold {
+            (line.to_ascii_lowercase(), self.query.to_ascii_lowercase())
+        } else {
+            (line.to_string(), self.query.clone())
+        };
+        haystack
+            .match_indices(&needle)
+            .map(|(start, m)| start..start + m.len())
+            .collect()
+    }
+
+    /// Entry and line of the match shown.
+    pub fn current_match(&self) -> Option<(usize, usize)> {
+        self.matches.get(self.current).copied()
+    }
+}
+
+/// Cell contents of a screen area, row by row.
+#[derive(Default)]
+pub struct ScreenText {
+    /// Where on screen the rows are.
+    pub area: Rect,
+    /// One string per cell.
+    pub rows: Vec<Vec<String>>,
+}
+
+impl ScreenText {
+    /// Text from `from` to `to` (inclusive, either order), reading rows
+    /// left to right like a terminal selection. Block borders at the left
+    /// edge and trailing blanks are dropped.
+    pub fn text(&self, from: Position, to: Position) -> String {
+        let (start, end) = if (from.y, from.x) <= (to.y, to.x) {
+            (from, to)
+        } else {
+            (to, from)
+        };
+        let mut lines = Vec::new();
+        for y in start.y..=end.y {
+            let Some(row) = self.rows.get(y.saturating_sub(self.area.y) as usize) else {
+                continue;
+            };
+            let first = if y == start.y { start.x } else { self.area.x };
+            let last = if y == end.y { end.x } else { self.area.right().saturating_sub(1) };
+            let cells = (first.saturating_sub(self.area.x) as usize)
+                ..=(last.saturating_sub(self.area.x) as usize);
+            let text: String = row
+                .get(cells)
+                .unwrap_or_default()
+                .iter()
+                .map(String::as_str)
+                .collect();
+            let text = if first == self.area.x {
+                text.strip_prefix("│  ").unwrap_or(&text)
+            } else {
+                &text
+            };
+            lines.push(text.trim_end().to_string());
+        }
+        lines.join("\n")
+    }
+}
+
+/// The text of an output line as the log shows it.
+pub fn displayed_line(line: &str) -> &str {
+    line.strip_prefix("[stderr] ").unwrap_or(line)
+}
+
+/// Things that change how keystrokes are handled, mirrored from the app each
+/// loop iteration so the input box can show them as chips.
+#[derive(Default, Clone, PartialEq)]
+pub struct ModeIndicators {
+    /// Jobs running in the background.
+    pub background_jobs: usize,
+    /// Jobs stopped with Ctrl+Z.
+    pub stopped_jobs: usize,
+    /// Jobs waiting for a slot.
+    pub queued_jobs: usize,
+    /// Presentation mode (F2) is on.
+    pub presentation: bool,
+    /// Started with `--safe`.
+    pub safe: bool,
+}
+
+impl ModeIndicators {
+    /// The chips to show, e.g. `⚙ 2 bg`.
+    pub fn chips(&self) -> Vec<String> {
+        let mut chips = Vec::new();
+        if self.safe {
+            chips.push("🛟 safe mode".to_string());
+        }
+        if self.presentation {
+            chips.push("👁 read-only (F2)".to_string());
+        }
+        if self.background_jobs > 0 {
+            chips.push(format!("⚙ {} bg", self.background_jobs));
+        }
+        if self.stopped_jobs > 0 {
+            chips.push(format!("⏸ {} stopped", self.stopped_jobs));
+        }
+        if self.queued_jobs > 0 {
+            chips.push(format!("⏳ {} queued", self.queued_jobs));
+        }
+        chips
+    }
+}
 
+/// One session: what the UI draws and what builtins and keys change.
 pub struct State {
+    /// `exit` ran; the event loop ends after this iteration.
     pub should_quit: bool,
+    /// Something changed since the last frame.
     pub needs_redraw: bool,
+    /// The login name, for the title and `{user}`.
     pub username: String,
+    /// This machine's short name, for `{host}`.
+    pub hostname: String,
+    /// The shell's working directory, which commands start in.
     pub cwd: PathBuf,
-    pub git_branch: Option<String>, // Added to store git branch info
+    /// `pushd` / `popd` / `cd -`; every `cd` pushes the directory it leaves.
+    pub dir_stack: DirStack,
+    /// Every directory visited, for `z` and `cd` completion; saved with the history.
+    pub frecency: Frecency,
+    /// Branch (or short commit) checked out in `cwd`'s repository.
+    pub git_branch: Option<String>,
+    /// Work tree root of the repository containing `cwd`.
+    pub git_root: Option<PathBuf>,
+    /// Git lookups for the cwd time out (a hung network mount, say).
+    pub git_unavailable: bool,
+    /// Ahead/behind, change and stash counts for the status bar.
+    pub git_status: GitStatus,
+    /// Name set with the `title` builtin, replacing the automatic one.
+    pub title_override: Option<String>,
+    /// Usage counts behind history ranking, saved with the history.
+    pub history_stats: HistoryStats,
+    /// `[history.ranking]` weights.
+    pub rank_weights: RankWeights,
+    /// Totals for the `summary` builtin.
+    pub session_stats: SessionStats,
+    /// Writes session.json and the history files.
+    pub persister: Persister,
+    /// The process overlay, while open.
+    pub procs: Option<ProcMonitor>,
+    /// The git panel, while open.
+    pub git_panel: Option<GitPanel>,
+    /// A file's diff (`diff <file>`, Enter in the git panel), while open.
+    pub diff_view: Option<DiffView>,
+    /// Loop timings for the `:perf` overlay, while it is open.
+    pub perf: Option<PerfStats>,
+    /// The full-screen output pager, while open.
+    pub pager: Option<Pager>,
+    /// The input line.
     pub input_buffer: String,
+    /// Byte offset of the cursor in `input_buffer`.
     pub cursor_position: usize,
+    /// First visible display column of the input line when it overflows the box.
+    pub input_scroll: usize,
+    /// Command lines run, oldest first.
     pub history: Vec<String>,
+    /// The history entry Up/Down put in the input, if any.
     pub history_index: Option<usize>,
+    /// The log of the focused pane, oldest first; the last entry is current.
     pub command_log: Vec<CommandLog>,
+    /// Entries back from the newest while previewing; 0 follows the log.
     pub scroll_offset: usize,
+    /// The previewed entry, so the preview stays on it as the log changes.
+    scroll_anchor: Option<ScrollAnchor>,
+    /// All panes once the log is split, in screen order; empty otherwise.
+    /// The focused one's slot is a placeholder: its log is `command_log`.
+    pub panes: Vec<Pane>,
+    /// Index into `panes` of the focused one.
+    pub focused_pane: usize,
+    /// Side by side or stacked.
+    pub split: Split,
+    /// All tabs once there is more than one, in bar order; empty otherwise.
+    /// The active one's slot is a placeholder: its session is this `State`.
+    pub tabs: Vec<Tab>,
+    /// Index into `tabs` of the active one.
+    pub active_tab: usize,
+    /// Id of the active tab, which jobs started here are filed under.
+    pub tab_id: u64,
+    next_tab_id: u64,
+    /// The tab each job was started (or last resumed) in.
+    pub job_tabs: std::collections::HashMap<usize, u64>,
+    /// The completion popup.
     pub completion_state: CompletionState,
+    /// `[aliases]` and the `alias` builtin.
     pub aliases: std::collections::HashMap<String, String>,
+    /// `[abbr]`: expanded in the input buffer when followed by a space.
+    pub abbreviations: std::collections::HashMap<String, String>,
+    /// `[hooks]`.
+    pub hooks: HooksConfig,
+    /// `[notify]`.
+    pub notify: NotifyConfig,
+    /// `[share]`: where the `share` builtin posts entries.
+    pub share: ShareConfig,
+    /// `[session] restore_log` / `restore_lines`.
+    pub restore: RestoreConfig,
+    /// `[lock]`: idle timeout and passphrase.
+    pub lock: LockConfig,
+    /// The lock screen, while locked.
+    pub locked: Option<Lock>,
+    /// Last key or mouse event, for the idle lock.
+    pub last_input: Instant,
+    /// While set and in the future, the UI draws a failure flash border.
+    pub flash_until: Option<Instant>,
+    /// The terminal window has focus, as far as focus reports tell.
+    pub focused: bool,
+    /// Short message in the status bar and when it goes away.
+    pub notice: Option<(String, Instant)>,
+    /// `[jobs] max_parallel`; extra commands wait in CommandManager's queue.
+    pub max_parallel_jobs: Option<usize>,
+    /// `[performance] tick_ms`: event poll interval of the UI loop.
+    pub tick: Duration,
+    /// `[performance] low_bandwidth`, on by default over SSH: batched
+    /// redraws, no animations, no emoji.
+    pub low_bandwidth: bool,
+    /// `[performance] max_output_lines` / `spill_output`.
+    pub output_limits: OutputLimits,
+    /// Exit status of the last foreground command, exposed as `$?`.
+    pub last_exit_code: i32,
+    /// Commands that get the real terminal instead of piped output.
+    pub interactive_commands: std::collections::HashSet<String>,
     // Reserved for future: drive highlight from state rather than recomputing
     // pub active_preview_index: Option<usize>,
-    _last_start_time: Option<Instant>,
+    /// The colors in use.
     pub theme: Theme,
+    /// Name of the theme in use, `custom` for `[theme]` colors.
     pub theme_name: String,
+    /// `[ui]`.
     pub ui: UiConfig,
-    // Theme selection mode
+    /// Chips in the input box.
+    pub modes: ModeIndicators,
+    /// Ctrl+R search, while open.
+    pub history_search: Option<HistorySearch>,
+    /// The Ctrl+G recent directories popup, while open.
+    pub dir_picker: Option<DirPicker>,
+    /// Log search, while open.
+    pub log_search: Option<LogSearch>,
+    /// The yes/no popup, while one is asked.
+    pub confirmation: Option<Confirmation>,
+    /// An alias's placeholders being asked for, one by one.
+    pub placeholder_prompt: Option<PlaceholderPrompt>,
+    /// The `build` form, while open; it writes the command into the input.
+    pub builder: Option<Builder>,
+    /// Alt+Enter's preview of what the input would run; any key closes it.
+    pub dry_run: Option<Vec<Step>>,
+    /// `[shell] noclobber` / `set -C`: ask before `>` truncates a file.
+    pub noclobber: bool,
+    /// `[shell] normalize_input`: smart quotes, non-breaking and zero-width
+    /// spaces become plain ASCII as they are typed or pasted.
+    pub normalize_input: bool,
+    /// `[shell.words]`: where Ctrl-W and word motion stop.
+    pub words: WordConfig,
+    /// Characters normalized since the input line was last empty.
+    normalized_chars: usize,
+    /// Newest history entry extending the current input, shown as ghost text.
+    pub suggestion: Option<String>,
+    /// Ctrl-X was pressed and the next key completes the chord.
+    pub ctrl_x_pending: bool,
+    /// Text removed by Ctrl-K/U/W and Alt-D, newest last.
+    pub kill_ring: Vec<String>,
+    /// Span of the text just yanked and its kill ring index, for Alt-Y.
+    pub last_yank: Option<(Range<usize>, usize)>,
+    /// Read-only mode: scrolling works, editing and execution don't.
+    pub presentation_mode: bool,
+    /// `--safe`: started on built-in defaults. halo.toml and the session
+    /// are neither read nor written, and bundled themes aren't unpacked.
+    pub safe_mode: bool,
+    /// Last rendered clock text; only set while `ui.show_clock` is on.
+    pub clock: Option<String>,
+    /// Screen area of each log block drawn in the last frame.
+    pub log_areas: Vec<(Rect, usize)>,
+    /// Log block under the mouse pointer.
+    pub hovered_log: Option<usize>,
+    /// Screen cells of each link drawn in the last frame, with its target.
+    pub link_targets: Vec<(Rect, String)>,
+    /// Text of the log area as last drawn, for mouse selection.
+    pub log_screen: ScreenText,
+    /// Where a left-button press in the log started, until release.
+    pub drag_start: Option<Position>,
+    /// Dragged-over text in the log, in screen cells (anchor, head).
+    pub selection: Option<(Position, Position)>,
+    /// Where the exit status strip was drawn and the log entry behind each
+    /// of its columns, for mouse clicks.
+    pub status_strip: Option<(Rect, Vec<usize>)>,
+    /// The Ctrl+T theme gallery is open.
     pub theme_selection_mode: bool,
+    /// Themes the gallery lists.
     pub available_themes: Vec<String>,
+    /// The theme highlighted in the gallery.
     pub theme_selection_index: usize,
+    /// Theme in effect when selection began, restored if the user cancels.
+    pub theme_before_preview: Option<Theme>,
+    /// Color vision deficiency the gallery is simulated with, if any.
+    pub theme_simulation: Option<Simulation>,
+    /// Phase timings collected while starting up (`--profile-startup`).
+    pub startup: StartupProfile,
+    themes_checked: bool,
 }
 
 impl State {
-    pub fn new() -> AppResult<Self> {
+    /// A session in the current directory with history, config and the
+    /// saved session loaded; `safe_mode` skips the config and session.
+    pub fn new(safe_mode: bool) -> AppResult<Self> {
         let cwd = std::env::current_dir()?;
         let mut state = Self {
             should_quit: false,
@@ -226,10 +908,27 @@ impl State {
             username: users::get_current_username()
                 .and_then(|name| name.into_string().ok())
                 .unwrap_or_else(|| "user".to_string()),
+            hostname: prompt::hostname(),
             cwd: cwd.clone(),
+            dir_stack: DirStack::default(),
+            frecency: Frecency::default(),
             git_branch: None,
+            git_root: None,
+            git_unavailable: false,
+            git_status: GitStatus::default(),
+            title_override: None,
+            history_stats: HistoryStats::default(),
+            rank_weights: RankWeights::default(),
+            session_stats: SessionStats::default(),
+            persister: Persister::default(),
+            procs: None,
+            git_panel: None,
+            diff_view: None,
+            perf: None,
+            pager: None,
             input_buffer: String::new(),
             cursor_position: 0,
+            input_scroll: 0,
             history: Vec::new(),
             history_index: None,
             command_log: vec![CommandLog::new(
@@ -239,52 +938,624 @@ impl State {
                 cwd,
             )],
             scroll_offset: 0,
+            scroll_anchor: None,
+            panes: Vec::new(),
+            focused_pane: 0,
+            split: Split::default(),
+            tabs: Vec::new(),
+            active_tab: 0,
+            tab_id: 0,
+            next_tab_id: 1,
+            job_tabs: std::collections::HashMap::new(),
             completion_state: CompletionState::new(),
             aliases: Default::default(),
-            _last_start_time: None,
+            abbreviations: Default::default(),
+            hooks: HooksConfig::default(),
+            notify: NotifyConfig::default(),
+            share: ShareConfig::default(),
+            restore: RestoreConfig::default(),
+            lock: LockConfig::default(),
+            locked: None,
+            last_input: Instant::now(),
+            flash_until: None,
+            focused: true,
+            notice: None,
+            max_parallel_jobs: None,
+            tick: DEFAULT_TICK,
+            low_bandwidth: over_ssh(),
+            output_limits: OutputLimits::default(),
+            last_exit_code: 0,
+            interactive_commands: handoff::DEFAULT_INTERACTIVE
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
             theme: Theme::default(),
             theme_name: "cyber-nord".to_string(),
             ui: UiConfig::default(),
+            modes: ModeIndicators::default(),
+            history_search: None,
+            dir_picker: None,
+            log_search: None,
+            confirmation: None,
+            placeholder_prompt: None,
+            builder: None,
+            dry_run: None,
+            noclobber: false,
+            normalize_input: true,
+            words: WordConfig::default(),
+            normalized_chars: 0,
+            suggestion: None,
+            ctrl_x_pending: false,
+            kill_ring: Vec::new(),
+            last_yank: None,
+            presentation_mode: false,
+            safe_mode,
+            clock: None,
+            log_areas: Vec::new(),
+            hovered_log: None,
+            link_targets: Vec::new(),
+            log_screen: ScreenText::default(),
+            drag_start: None,
+            selection: None,
+            status_strip: None,
             // Theme selection mode
             theme_selection_mode: false,
             available_themes: Vec::new(),
             theme_selection_index: 0,
+            theme_before_preview: None,
+            theme_simulation: None,
+            startup: StartupProfile::default(),
+            themes_checked: false,
         };
+        // Timed by hand rather than with `measure`, which would hold the
+        // profile while `ensure_themes` records theme extraction into it.
+        let started = Instant::now();
         state.load_history()?;
-        state.load_config();
-        let _ = state.load_session();
+        state.startup.record("history load", started.elapsed());
+        if safe_mode {
+            state.ui.install_themes = false;
+        } else {
+            let started = Instant::now();
+            state.load_config();
+            state.startup.record("config parse", started.elapsed());
+            let started = Instant::now();
+            let _ = state.load_session();
+            state.startup.record("session load", started.elapsed());
+        }
+        let total = state.startup.elapsed();
+        state.startup.record("State::new total", total);
         Ok(state)
     }
 
+    // cursor_position is a byte offset into input_buffer, always kept on a
+    // char boundary so multi-byte input can't split a character.
+    /// Moves the cursor one character left.
     pub fn move_cursor_left(&mut self) {
-        self.cursor_position = self.cursor_position.saturating_sub(1);
+        if let Some(c) = self.input_buffer[..self.cursor_position].chars().next_back() {
+            self.cursor_position -= c.len_utf8();
+        }
     }
 
+    /// Moves the cursor one character right.
     pub fn move_cursor_right(&mut self) {
-        if self.cursor_position < self.input_buffer.len() {
-            self.cursor_position += 1;
+        if let Some(c) = self.input_buffer[self.cursor_position..].chars().next() {
+            self.cursor_position += c.len_utf8();
         }
     }
 
+    /// Types `c` at the cursor, normalized if `[shell] normalize_input` is on.
     pub fn insert_char(&mut self, c: char) {
+        if self.input_buffer.is_empty() {
+            self.normalized_chars = 0;
+        }
+        let c = match normalize::fix(c).filter(|_| self.normalize_input) {
+            Some(fix) => {
+                self.normalized_chars += 1;
+                self.show_notice(normalize::describe(c, &fix, self.normalized_chars));
+                match fix {
+                    Fix::Replace(ascii) => ascii,
+                    Fix::Drop => return,
+                }
+            }
+            None => c,
+        };
         self.input_buffer.insert(self.cursor_position, c);
-        self.cursor_position += 1;
+        self.cursor_position += c.len_utf8();
+        self.update_suggestion();
     }
 
+    /// Deletes the character before the cursor.
     pub fn backspace(&mut self) {
-        if self.cursor_position > 0 {
-            self.cursor_position -= 1;
+        if let Some(c) = self.input_buffer[..self.cursor_position].chars().next_back() {
+            self.cursor_position -= c.len_utf8();
             self.input_buffer.remove(self.cursor_position);
         }
+        self.update_suggestion();
+    }
+
+    /// Opens log search with an empty pattern.
+    pub fn start_log_search(&mut self) {
+        self.log_search = Some(LogSearch {
+            editing: true,
+            ..LogSearch::default()
+        });
+    }
+
+    /// Closes log search.
+    pub fn close_log_search(&mut self) {
+        self.log_search = None;
+        self.needs_redraw = true;
+    }
+
+    /// Recomputes matches after the pattern changed and previews the newest.
+    pub fn update_log_search(&mut self) {
+        self.find_log_matches();
+        if let Some(search) = &mut self.log_search {
+            search.current = 0;
+        }
+        self.jump_to_log_match();
+    }
+
+    /// n (`older`) / N: moves to the next match, wrapping around.
+    pub fn step_log_search(&mut self, older: bool) {
+        // The log may have grown or been cleared since the last step.
+        self.find_log_matches();
+        let Some(search) = &mut self.log_search else {
+            return;
+        };
+        let count = search.matches.len();
+        if count == 0 {
+            return;
+        }
+        search.current = if older {
+            (search.current + 1) % count
+        } else {
+            (search.current + count - 1) % count
+        };
+        self.jump_to_log_match();
+    }
+
+    fn find_log_matches(&mut self) {
+        let Some(search) = &mut self.log_search else {
+            return;
+        };
+        search.matches.clear();
+        for (i, log) in self.command_log.iter().enumerate().rev() {
+            for (k, line) in log.output.lines().enumerate() {
+                if !search.ranges(displayed_line(line)).is_empty() {
+                    search.matches.push((i, k));
+                }
+            }
+        }
+        search.current = search.current.min(search.matches.len().saturating_sub(1));
+    }
+
+    fn jump_to_log_match(&mut self) {
+        if let Some((index, _)) = self.log_search.as_ref().and_then(LogSearch::current_match) {
+            self.jump_to_entry(index);
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Hides the log and input behind the lock screen.
+    pub fn lock_screen(&mut self) {
+        self.locked = Some(Lock::default());
+        // Nothing from the session should show through once unlocked
+        // either; popups reopen on demand.
+        self.pager = None;
+        self.completion_state.stop_completion();
+        self.history_search = None;
+        self.dir_picker = None;
+        self.dry_run = None;
+        self.needs_redraw = true;
+    }
+
+    /// Shows `text` in the status bar for a few seconds.
+    pub fn show_notice(&mut self, text: impl Into<String>) {
+        self.notice = Some((text.into(), Instant::now() + NOTICE_DURATION));
+        self.needs_redraw = true;
+    }
+
+    /// The entry copy actions work on: the previewed one, or else the
+    /// newest with a command. `skip_current` passes over the last entry,
+    /// which is the `copy` builtin's own.
+    pub fn copy_source(&self, skip_current: bool) -> Option<&CommandLog> {
+        self.command_log.get(self.source_index(skip_current)?)
+    }
+
+    fn source_index(&self, skip_current: bool) -> Option<usize> {
+        if self.scroll_offset > 0 {
+            return self.command_log.len().checked_sub(1 + self.scroll_offset);
+        }
+        let end = self.command_log.len().saturating_sub(usize::from(skip_current));
+        self.command_log[..end]
+            .iter()
+            .rposition(|log| !log.command.is_empty())
+    }
+
+    /// Tab while answering a `path` or `dir` placeholder: completes the
+    /// typed path as far as it is unambiguous.
+    pub fn complete_placeholder_path(&mut self) {
+        let filter = match self.placeholder_prompt.as_ref().and_then(|p| p.field()) {
+            Some((_, Some(kind))) if kind == "path" => completion::PathFilter::All,
+            Some((_, Some(kind))) if kind == "dir" => completion::PathFilter::DirectoriesOnly,
+            _ => return,
+        };
+        let matches = completion::suggest_paths(&self.input_buffer, &self.cwd, filter);
+        let Some(first) = matches.first() else {
+            return;
+        };
+        let common = matches[1..].iter().fold(first.len(), |len, other| {
+            first[..len]
+                .char_indices()
+                .zip(other.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(len.min(other.len()), |((i, _), _)| i)
+        });
+        if common > self.input_buffer.len() {
+            self.input_buffer = first[..common].to_string();
+            self.cursor_position = self.input_buffer.len();
+        }
+    }
+
+    /// What this session is working on: the `title` override, else the
+    /// git repository's name, else the directory's.
+    pub fn workspace_name(&self) -> String {
+        if let Some(name) = &self.title_override {
+            return name.clone();
+        }
+        let dir = self.git_root.as_deref().unwrap_or(&self.cwd);
+        if dirs::home_dir().is_some_and(|home| home == dir) {
+            return "~".to_string();
+        }
+        dir.file_name()
+            .map_or_else(|| dir.display().to_string(), |name| name.to_string_lossy().into_owned())
+    }
+
+    /// Alt+P: opens the pager on the previewed entry, or the newest.
+    pub fn open_pager(&mut self) {
+        let Some(index) = self.source_index(false) else {
+            return;
+        };
+        let pager = Pager::new(index, &self.command_log[index]);
+        if pager.lines.is_empty() {
+            self.show_notice("pager: no output");
+            return;
+        }
+        self.pager = Some(pager);
+        self.needs_redraw = true;
+    }
+
+    /// Previews log entry `index`, or returns to the live view for the newest.
+    pub fn jump_to_entry(&mut self, index: usize) {
+        self.scroll_offset = self.command_log.len().saturating_sub(1).saturating_sub(index);
+    }
+
+    /// Moves the preview to the previous (`older`) or next entry that ran a
+    /// command, passing over empty prompts and job notices. Past the newest
+    /// one, preview ends.
+    pub fn jump_to_block(&mut self, older: bool) {
+        let Some(last) = self.command_log.len().checked_sub(1) else {
+            return;
+        };
+        let current = last - self.scroll_offset.min(last);
+        let is_block = |log: &CommandLog| !log.command.is_empty();
+        let target = if older {
+            self.command_log[..current].iter().rposition(is_block)
+        } else {
+            self.command_log
+                .get(current + 1..last)
+                .and_then(|newer| newer.iter().position(is_block))
+                .map(|i| current + 1 + i)
+        };
+        match target {
+            Some(index) => self.jump_to_entry(index),
+            None if !older => self.exit_preview_mode(),
+            None => {}
+        }
+    }
+
+    /// Expands the command word ending at the cursor if it names an
+    /// abbreviation. Returns whether the line changed.
+    pub fn expand_abbreviation(&mut self) -> bool {
+        let Some((span, expansion)) = self.abbreviation_at(self.cursor_position) else {
+            return false;
+        };
+        self.cursor_position = span.start + expansion.len();
+        self.input_buffer.replace_range(span, &expansion);
+        self.update_suggestion();
+        true
+    }
+
+    /// The abbreviated command word ending at `pos`, and its expansion.
+    pub fn abbreviation_at(&self, pos: usize) -> Option<(Range<usize>, String)> {
+        let before = &self.input_buffer[..pos];
+        parser::word_roles(before)
+            .into_iter()
+            .filter_map(|(span, role)| match role {
+                parser::WordRole::Command(name) => Some((span, name)),
+                _ => None,
+            })
+            // Quoted or escaped words are left alone, as in fish.
+            .filter(|(span, name)| span.end == before.len() && before[span.clone()] == *name)
+            .find_map(|(span, name)| Some((span, self.abbreviations.get(&name)?.clone())))
+    }
+
+    /// Replaces the whole input line, with the cursor at its end.
+    pub fn set_input(&mut self, text: String) {
+        self.input_buffer = text;
+        self.cursor_position = self.input_buffer.len();
+        self.history_index = None;
+        self.update_suggestion();
+    }
+
+    /// Ctrl-K: deletes from the cursor to the end of the line.
+    pub fn kill_to_end(&mut self) {
+        self.kill(self.cursor_position..self.input_buffer.len());
+    }
+
+    /// Ctrl-U: deletes from the start of the line to the cursor.
+    pub fn kill_to_start(&mut self) {
+        self.kill(0..self.cursor_position);
+    }
+
+    /// Ctrl-W: deletes back to the previous whitespace, like readline's
+    /// `unix-word-rubout`, or `[shell.words] kill` boundary.
+    pub fn delete_word_back(&mut self) {
+        let start = self.words.kill.word_start(&self.input_buffer[..self.cursor_position]);
+        self.kill(start..self.cursor_position);
+    }
+
+    /// Alt-D: deletes to the end of the next word.
+    pub fn delete_word_forward(&mut self) {
+        self.kill(self.cursor_position..self.next_word_end());
+    }
+
+    /// Ctrl+Right / Alt-F: moves to the end of the next word.
+    pub fn move_word_right(&mut self) {
+        self.cursor_position = self.next_word_end();
+    }
+
+    /// Ctrl+Left / Alt-B: moves to the start of the previous word.
+    pub fn move_word_left(&mut self) {
+        self.cursor_position = self.prev_word_start();
+    }
+
+    /// End of the next word after the cursor; by default words are runs
+    /// of alphanumerics.
+    fn next_word_end(&self) -> usize {
+        let rest = &self.input_buffer[self.cursor_position..];
+        self.cursor_position + self.words.motion.word_end(rest)
+    }
+
+    /// Start of the word before the cursor.
+    fn prev_word_start(&self) -> usize {
+        self.words.motion.word_start(&self.input_buffer[..self.cursor_position])
+    }
+
+    /// Removes `range` from the input, saving it on the kill ring.
+    fn kill(&mut self, range: Range<usize>) {
+        let killed: String = self.input_buffer.drain(range.clone()).collect();
+        if self.cursor_position > range.start {
+            self.cursor_position = range.start;
+        }
+        if !killed.is_empty() {
+            if self.kill_ring.len() == KILL_RING_SIZE {
+                self.kill_ring.remove(0);
+            }
+            self.kill_ring.push(killed);
+        }
+        self.update_suggestion();
+    }
+
+    /// Ctrl-Y: inserts the most recent kill at the cursor.
+    pub fn yank(&mut self) {
+        if let Some(text) = self.kill_ring.last() {
+            let index = self.kill_ring.len() - 1;
+            self.insert_yank(text.clone(), index);
+        }
+    }
+
+    /// Alt-Y: right after a yank, swaps it for the next older kill.
+    pub fn yank_pop(&mut self) {
+        let Some((range, index)) = self.last_yank.take() else {
+            return;
+        };
+        let older = index.checked_sub(1).unwrap_or(self.kill_ring.len() - 1);
+        self.input_buffer.drain(range.clone());
+        self.cursor_position = range.start;
+        self.insert_yank(self.kill_ring[older].clone(), older);
+    }
+
+    fn insert_yank(&mut self, text: String, index: usize) {
+        let start = self.cursor_position;
+        self.input_buffer.insert_str(start, &text);
+        self.cursor_position += text.len();
+        self.last_yank = Some((start..self.cursor_position, index));
+        self.update_suggestion();
+    }
+
+    fn update_suggestion(&mut self) {
+        // History has nothing to offer for a placeholder's answer.
+        self.suggestion = if self.input_buffer.is_empty() || self.placeholder_prompt.is_some() {
+            None
+        } else {
+            let input = self.input_buffer.as_str();
+            self.ranked_history(|entry| entry.len() > input.len() && entry.starts_with(input))
+                .first()
+                .map(|entry| entry.to_string())
+        };
+    }
+
+    /// Distinct history entries passing `filter`, best first for the
+    /// current directory and repository.
+    pub fn ranked_history(&self, filter: impl Fn(&str) -> bool) -> Vec<&str> {
+        let ctx = RankContext {
+            cwd: &self.cwd,
+            repo: self.git_root.as_deref(),
+        };
+        self.history_stats
+            .rank(&self.history, filter, &ctx, &self.rank_weights)
+    }
+
+    /// Ctrl-R matches for the current query, best first.
+    pub fn history_matches(&self) -> Vec<&str> {
+        let query = self.history_search.as_ref().map_or("", |s| s.query.as_str());
+        self.ranked_history(|entry| entry.contains(query))
+    }
+
+    /// Adds a submitted line to the history and its usage stats, and saves both.
+    pub fn record_history(&mut self, line: &str) -> AppResult<()> {
+        self.history_stats
+            .record(line, &self.cwd, self.git_root.as_deref());
+        self.session_stats.command_started(&self.cwd);
+        if self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+        }
+        self.history_stats.retain_in(&self.history);
+        self.save_history()
+    }
+
+    /// The part of the suggestion still to be typed. Only offered with the
+    /// cursor at the end, and dropped once the input no longer matches.
+    pub fn suggestion_suffix(&self) -> Option<&str> {
+        if self.cursor_position != self.input_buffer.len() || self.input_buffer.is_empty() {
+            return None;
+        }
+        self.suggestion
+            .as_deref()
+            .and_then(|s| s.strip_prefix(self.input_buffer.as_str()))
+            .filter(|rest| !rest.is_empty())
+    }
+
+    /// Completes the input with the suggestion; false if there was none.
+    pub fn accept_suggestion(&mut self) -> bool {
+        let Some(rest) = self.suggestion_suffix().map(str::to_string) else {
+            return false;
+        };
+        self.input_buffer.push_str(&rest);
+        self.cursor_position = self.input_buffer.len();
+        self.suggestion = None;
+        true
+    }
+
+    /// Opens Ctrl+R search.
+    pub fn start_history_search(&mut self) {
+        self.completion_state.stop_completion();
+        self.history_search = Some(HistorySearch::default());
+        self.needs_redraw = true;
+    }
+
+    /// Opens the Ctrl+G popup: directories by frecency, then any others on
+    /// the directory stack, leaving out the cwd.
+    pub fn open_dir_picker(&mut self) {
+        self.completion_state.stop_completion();
+        let mut dirs = self.frecency.ranked();
+        for dir in self.dir_stack.iter() {
+            if !dirs.iter().any(|d| d == dir) {
+                dirs.push(dir.to_path_buf());
+            }
+        }
+        dirs.retain(|dir| *dir != self.cwd);
+        self.dir_picker = Some(DirPicker::new(dirs));
+        self.needs_redraw = true;
+    }
+
+    /// Puts the selected match into the input buffer and closes the search.
+    pub fn accept_history_search(&mut self) {
+        let entry = self.history_search.as_ref().and_then(|search| {
+            let matches = self.history_matches();
+            matches.get(search.selected).map(|entry| entry.to_string())
+        });
+        self.history_search = None;
+        if let Some(entry) = entry {
+            self.input_buffer = entry;
+            self.cursor_position = self.input_buffer.len();
+            self.history_index = None;
+        }
+        self.needs_redraw = true;
     }
 
+    /// Turns read-only presentation mode on or off.
+    pub fn toggle_presentation_mode(&mut self) {
+        self.presentation_mode = !self.presentation_mode;
+        self.completion_state.stop_completion();
+        self.needs_redraw = true;
+    }
+
+    /// Goes back to following the newest entry.
     pub fn exit_preview_mode(&mut self) {
         self.scroll_offset = 0;
     }
 
+    /// Keeps history preview on the same entry while the log changes under
+    /// it. `scroll_offset` counts entries back from the newest, so a new
+    /// entry (a finished background job, say) would otherwise move the
+    /// preview onto its neighbour. Runs before each frame; a changed offset
+    /// means the user scrolled, and re-anchors to the entry now shown.
+    pub fn anchor_scroll(&mut self) {
+        let last = self.command_log.len().saturating_sub(1);
+        if let Some(anchor) = &self.scroll_anchor
+            && anchor.offset == self.scroll_offset
+            && let Some(index) = self.command_log.iter().rposition(|l| l.id == anchor.entry)
+            && index < last
+            && self.scroll_offset != last - index
+        {
+            self.scroll_offset = last - index;
+            self.needs_redraw = true;
+        }
+        self.scroll_offset = self.scroll_offset.min(last);
+        self.scroll_anchor = (self.scroll_offset > 0).then(|| ScrollAnchor {
+            entry: self.command_log[last - self.scroll_offset].id,
+            offset: self.scroll_offset,
+        });
+    }
+
+    /// The user can see new output arrive: the window has focus and the
+    /// log is at the bottom, with no pager over it.
+    pub fn is_watching(&self) -> bool {
+        self.focused && self.scroll_offset == 0 && self.pager.is_none()
+    }
+
+    /// Clears the marks of entries that finished while the user was away,
+    /// once they are back watching.
+    pub fn mark_seen(&mut self) {
+        if !self.is_watching() {
+            return;
+        }
+        for log in self.command_log.iter_mut().filter(|l| l.unseen) {
+            log.unseen = false;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// The command of the entry highlighted in history preview.
+    pub fn previewed_command(&self) -> Option<String> {
+        let index = self.command_log.len().checked_sub(1 + self.scroll_offset)?;
+        Some(self.command_log[index].command.clone()).filter(|c| !c.is_empty())
+    }
+
+    /// Leaves preview with `note ^N <current note>` in the input, where N
+    /// points at the previewed entry once the note command itself is logged.
+    pub fn start_note_for_preview(&mut self) {
+        let back = self.scroll_offset + 1;
+        let index = self.command_log.len().saturating_sub(back);
+        let current = self
+            .command_log
+            .get(index)
+            .and_then(|log| log.note.clone())
+            .unwrap_or_default();
+        self.input_buffer = format!("note ^{back} {current}");
+        self.cursor_position = self.input_buffer.len();
+        self.exit_preview_mode();
+        self.needs_redraw = true;
+    }
+
+    /// Starts a running entry for `command`; the oldest entries fall off.
     pub fn add_log_entry(&mut self, command: String, cwd: PathBuf) {
-        self.command_log
-            .push(CommandLog::new(command, String::new(), true, cwd));
+        let mut log = CommandLog::new(command, String::new(), true, cwd);
+        log.branch = self.git_branch.as_deref().map(|b| git::branch_name(b).to_string());
+        self.command_log.push(log);
         if self.command_log.len() > HISTORY_LIMIT {
             // Keep the newest HISTORY_LIMIT entries by draining from the front
             let excess = self.command_log.len() - HISTORY_LIMIT;
@@ -292,16 +1563,50 @@ impl State {
         }
     }
 
+    /// `clear` / Ctrl-L: drops the log down to an empty prompt marker.
+    /// Entries of running jobs stay, since their output is still arriving.
+    pub fn clear_log(&mut self) {
+        self.command_log.retain(|log| log.is_running && log.job_id.is_some());
+        self.command_log.push(CommandLog::new(
+            String::new(),
+            String::new(),
+            false,
+            self.cwd.clone(),
+        ));
+        self.scroll_offset = 0;
+        self.hovered_log = None;
+        self.needs_redraw = true;
+    }
+
+    /// Swaps the log for `entries` (a loaded session's), keeping the ones
+    /// jobs still stream into and the current one at the end.
+    pub fn replace_log(&mut self, entries: Vec<CommandLog>) {
+        let current = self.command_log.pop();
+        self.command_log.retain(|log| log.is_running && log.job_id.is_some());
+        self.command_log.splice(0..0, entries);
+        self.command_log.extend(current);
+        self.scroll_offset = 0;
+        self.scroll_anchor = None;
+        self.hovered_log = None;
+        self.needs_redraw = true;
+    }
+
+    /// Adds a line of output to the newest entry.
     pub fn append_to_last_log(&mut self, line: String) {
         if let Some(last) = self.command_log.last_mut() {
-            if !last.output.is_empty() {
-                last.output.push('\n');
-            }
-            last.output.push_str(&line);
+            last.push_line(&line, &self.output_limits);
             self.needs_redraw = true;
         }
     }
 
+    /// Reports a non-fatal problem in the log. Anything that goes wrong while
+    /// the TUI owns the screen should come through here, never `eprintln!`,
+    /// which would scribble over the alternate screen.
+    pub fn warn(&mut self, msg: impl Into<String>) {
+        self.append_to_last_log(format!("[warning] {}", msg.into()));
+    }
+
+    /// Marks the newest entry done, without an exit status.
     pub fn finish_last_log(&mut self) {
         if let Some(last) = self.command_log.last_mut() {
             last.is_running = false;
@@ -309,29 +1614,310 @@ impl State {
         }
     }
 
-    // runtime fields for duration tracking
-    #[allow(dead_code)]
-    fn now() -> Instant {
-        Instant::now()
-    }
+    /// Records that the newest entry's command started now.
     pub fn mark_last_log_started(&mut self) {
-        // Store start time in a sidecar map keyed by index if needed; easiest is to stash in output
-        // but we will track via a local Instant until finish and compute delta. Use a hidden field on State.
-        self._last_start_time = Some(Self::now());
+        if let Some(last) = self.command_log.last_mut() {
+            last.started_at = Some(Instant::now());
+            last.started_wall = Some(chrono::Local::now());
+            crash::record(last);
+        }
     }
 
-    pub fn finish_last_log_with_result(&mut self, exit_code: Option<i32>) {
+    /// Marks the newest entry done with `exit` and its duration.
+    pub fn finish_last_log_with_result(&mut self, exit: JobExit) {
         if let Some(last) = self.command_log.last_mut() {
             last.is_running = false;
-            last.exit_code = exit_code;
-            if let Some(start) = self._last_start_time.take() {
-                let elapsed = start.elapsed().as_millis();
-                last.duration_ms = Some(elapsed);
+            last.exit_code = exit.code;
+            last.signal = exit.signal;
+            if let Some(start) = last.started_at {
+                last.duration_ms = Some(start.elapsed().as_millis());
             }
+            crash::record(last);
+            self.session_stats
+                .command_finished(&last.command, exit.code != Some(0), last.duration_ms);
             self.needs_redraw = true;
         }
     }
 
+    /// Whether `cmd` (a name or a path) gets the terminal handed over.
+    pub fn is_interactive_command(&self, cmd: &str) -> bool {
+        let name = std::path::Path::new(cmd)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(cmd);
+        self.interactive_commands.contains(name)
+    }
+
+    /// Routes the last entry's output to the given job.
+    pub fn attach_job_to_last_log(&mut self, job_id: usize) {
+        if let Some(last) = self.command_log.last_mut() {
+            last.job_id = Some(job_id);
+        }
+        self.job_tabs.insert(job_id, self.tab_id);
+    }
+
+    /// Stops an entry from receiving output for `job_id`, closing it with `note`.
+    pub fn detach_job_log(&mut self, job_id: usize, note: String) {
+        let limits = self.output_limits;
+        if let Some(log) = self.job_log_mut(job_id) {
+            log.job_id = None;
+            log.is_running = false;
+            log.push_line(&note, &limits);
+            self.needs_redraw = true;
+        }
+    }
+
+    /// The entry streaming `job_id`'s output, in whichever pane or tab it is.
+    fn job_log_mut(&mut self, job_id: usize) -> Option<&mut CommandLog> {
+        let others = self.panes.iter_mut().flat_map(|p| p.command_log.iter_mut().rev());
+        let tabs = self.tabs.iter_mut().flat_map(|tab| {
+            let panes = tab.panes.iter_mut().flat_map(|p| p.command_log.iter_mut().rev());
+            tab.command_log.iter_mut().rev().chain(panes)
+        });
+        self.command_log
+            .iter_mut()
+            .rev()
+            .chain(others)
+            .chain(tabs)
+            .find(|l| l.job_id == Some(job_id))
+    }
+
+    /// Id of the entry job `job_id` writes to, in any pane or tab.
+    pub fn job_entry_id(&mut self, job_id: usize) -> Option<u64> {
+        self.job_log_mut(job_id).map(|log| log.id)
+    }
+
+    /// Entry `id` of any pane or tab.
+    pub fn entry_mut(&mut self, id: u64) -> Option<&mut CommandLog> {
+        let others = self.panes.iter_mut().flat_map(|p| p.command_log.iter_mut());
+        let tabs = self.tabs.iter_mut().flat_map(|tab| {
+            let panes = tab.panes.iter_mut().flat_map(|p| p.command_log.iter_mut());
+            tab.command_log.iter_mut().chain(panes)
+        });
+        self.command_log
+            .iter_mut()
+            .chain(others)
+            .chain(tabs)
+            .find(|l| l.id == id)
+    }
+
+    /// Splits the log area and focuses a new, empty pane after the current
+    /// one. False when there are already `MAX_PANES`.
+    pub fn split_pane(&mut self, split: Split) -> bool {
+        if self.panes.len() >= panes::MAX_PANES {
+            return false;
+        }
+        if self.panes.is_empty() {
+            self.panes.push(Pane::default());
+        }
+        self.split = split;
+        let at = self.focused_pane + 1;
+        self.panes.insert(at, Pane::default());
+        self.focus_pane(at);
+        self.command_log.push(CommandLog::new(
+            String::new(),
+            String::new(),
+            false,
+            self.cwd.clone(),
+        ));
+        true
+    }
+
+    /// Moves focus to pane `index`; new commands run there.
+    pub fn focus_pane(&mut self, index: usize) {
+        if index == self.focused_pane || index >= self.panes.len() {
+            return;
+        }
+        let current = &mut self.panes[self.focused_pane];
+        std::mem::swap(&mut current.command_log, &mut self.command_log);
+        current.scroll_offset = self.scroll_offset;
+        let next = &mut self.panes[index];
+        std::mem::swap(&mut next.command_log, &mut self.command_log);
+        self.scroll_offset = next.scroll_offset;
+        self.focused_pane = index;
+        // All of these point into the log that just moved away.
+        self.scroll_anchor = None;
+        self.hovered_log = None;
+        self.selection = None;
+        self.log_search = None;
+        self.pager = None;
+        self.needs_redraw = true;
+    }
+
+    /// Ctrl+X o: the next pane, wrapping around.
+    pub fn focus_next_pane(&mut self) {
+        if !self.panes.is_empty() {
+            self.focus_pane((self.focused_pane + 1) % self.panes.len());
+        }
+    }
+
+    /// Closes the focused pane and focuses its neighbour. Refused while
+    /// jobs stream into it, as their output would have nowhere to go.
+    pub fn close_pane(&mut self) -> Result<(), String> {
+        if self.panes.is_empty() {
+            return Err("the log is not split".to_string());
+        }
+        let running = self
+            .command_log
+            .iter()
+            .filter(|log| log.is_running && log.job_id.is_some())
+            .count();
+        if running > 0 {
+            return Err(format!("{running} job(s) still running in this pane"));
+        }
+        let closing = self.focused_pane;
+        let next = if closing + 1 < self.panes.len() { closing + 1 } else { closing - 1 };
+        self.focus_pane(next);
+        self.panes.remove(closing);
+        if closing < self.focused_pane {
+            self.focused_pane -= 1;
+        }
+        if self.panes.len() == 1 {
+            self.panes.clear();
+            self.focused_pane = 0;
+        }
+        Ok(())
+    }
+
+    /// Adds a tab in the current directory after the active one, without
+    /// switching to it. False when there are already `MAX_TABS`.
+    pub fn open_tab(&mut self) -> bool {
+        if self.tabs.len() >= tabs::MAX_TABS {
+            return false;
+        }
+        if self.tabs.is_empty() {
+            self.tabs.push(Tab::default());
+        }
+        let tab = Tab::new(self.next_tab_id, self.cwd.clone());
+        self.next_tab_id += 1;
+        self.tabs.insert(self.active_tab + 1, tab);
+        true
+    }
+
+    /// Makes tab `index` the active one. `foreground` is the job in the
+    /// foreground now, kept with the tab being left; returns the one the
+    /// new tab had.
+    pub fn switch_tab(&mut self, index: usize, foreground: Option<usize>) -> Option<usize> {
+        if index == self.active_tab || index >= self.tabs.len() {
+            return foreground;
+        }
+        let mut current = std::mem::take(&mut self.tabs[self.active_tab]);
+        self.swap_tab(&mut current);
+        current.foreground = foreground;
+        self.tabs[self.active_tab] = current;
+        let mut next = std::mem::take(&mut self.tabs[index]);
+        self.swap_tab(&mut next);
+        self.tabs[index] = next;
+        self.active_tab = index;
+        // Children inherit halo's directory; keep it the tab's.
+        let _ = std::env::set_current_dir(&self.cwd);
+        // All of these point into the session that just moved away.
+        self.scroll_anchor = None;
+        self.hovered_log = None;
+        self.selection = None;
+        self.log_search = None;
+        self.pager = None;
+        self.dry_run = None;
+        self.history_index = None;
+        self.input_scroll = 0;
+        self.completion_state.stop_completion();
+        self.update_suggestion();
+        self.needs_redraw = true;
+        std::mem::take(&mut self.tabs[index].foreground)
+    }
+
+    /// Exchanges everything per-tab between this `State` and `tab`.
+    fn swap_tab(&mut self, tab: &mut Tab) {
+        use std::mem::swap;
+        swap(&mut self.tab_id, &mut tab.id);
+        swap(&mut self.cwd, &mut tab.cwd);
+        swap(&mut self.dir_stack, &mut tab.dir_stack);
+        swap(&mut self.git_branch, &mut tab.git_branch);
+        swap(&mut self.git_status, &mut tab.git_status);
+        swap(&mut self.git_root, &mut tab.git_root);
+        swap(&mut self.command_log, &mut tab.command_log);
+        swap(&mut self.scroll_offset, &mut tab.scroll_offset);
+        swap(&mut self.panes, &mut tab.panes);
+        swap(&mut self.focused_pane, &mut tab.focused_pane);
+        swap(&mut self.split, &mut tab.split);
+        swap(&mut self.input_buffer, &mut tab.input_buffer);
+        swap(&mut self.cursor_position, &mut tab.cursor_position);
+        swap(&mut self.last_exit_code, &mut tab.last_exit_code);
+    }
+
+    /// Drops tab `index`, which must not be the active one.
+    pub fn remove_tab(&mut self, index: usize) {
+        if index == self.active_tab || index >= self.tabs.len() {
+            return;
+        }
+        self.tabs.remove(index);
+        if index < self.active_tab {
+            self.active_tab -= 1;
+        }
+        if self.tabs.len() == 1 {
+            self.tabs.clear();
+            self.active_tab = 0;
+        }
+    }
+
+    /// Jobs started in the active tab and not reaped yet.
+    pub fn tab_job_count(&self) -> usize {
+        self.job_tabs.values().filter(|tab| **tab == self.tab_id).count()
+    }
+
+    /// Whether `job_id` belongs to the active tab, for `jobs`, `fg` and `bg`.
+    pub fn owns_job(&self, job_id: usize) -> bool {
+        self.job_tabs.get(&job_id).is_none_or(|tab| *tab == self.tab_id)
+    }
+
+    /// Forgets a reaped job. If it was another tab's foreground job, its
+    /// status becomes that tab's `$?`.
+    pub fn forget_job(&mut self, job_id: usize, status: i32) {
+        self.job_tabs.remove(&job_id);
+        if let Some(tab) = self.tabs.iter_mut().find(|t| t.foreground == Some(job_id)) {
+            tab.foreground = None;
+            tab.last_exit_code = status;
+        }
+    }
+
+    /// Adds a line of output to the entry of job `job_id`.
+    pub fn append_to_job_log(&mut self, job_id: usize, line: String) {
+        let limits = self.output_limits;
+        if let Some(log) = self.job_log_mut(job_id) {
+            log.push_line(&line, &limits);
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Output read from a job's pipes; `progress` for a line ended by a
+    /// bare `\r`.
+    pub fn stream_to_job_log(&mut self, job_id: usize, line: String, progress: bool) {
+        let limits = self.output_limits;
+        if let Some(log) = self.job_log_mut(job_id) {
+            log.push_stream_line(&line, progress, &limits);
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Closes the entry owned by `job_id`, returning how long it ran.
+    pub fn finish_job_log(&mut self, job_id: usize, exit: JobExit) -> Option<u128> {
+        let log = self.job_log_mut(job_id)?;
+        log.is_running = false;
+        log.exit_code = exit.code;
+        log.signal = exit.signal;
+        log.job_id = None;
+        if let Some(start) = log.started_at {
+            log.duration_ms = Some(start.elapsed().as_millis());
+        }
+        crash::record(log);
+        let duration = log.duration_ms;
+        let command = log.command.clone();
+        self.session_stats
+            .command_finished(&command, exit.code != Some(0), duration);
+        self.needs_redraw = true;
+        duration
+    }
+
     fn history_path() -> Option<std::path::PathBuf> {
         dirs::config_dir().map(|mut p| {
             p.push("halo/history");
@@ -339,27 +1925,120 @@ impl State {
         })
     }
 
+    /// Reads the history, its ranking stats and visited directories.
     pub fn load_history(&mut self) -> AppResult<()> {
-        if let Some(path) = Self::history_path() {
-            if let Ok(file) = fs::File::open(&path) {
-                let reader = BufReader::new(file);
-                self.history = serde_json::from_reader(reader).unwrap_or_default();
-            }
+        if let Some(path) = Self::history_path()
+            && let Ok(file) = fs::File::open(&path)
+        {
+            let reader = BufReader::new(file);
+            self.history = serde_json::from_reader(reader).unwrap_or_default();
+        }
+        if let Some(path) = Self::history_stats_path()
+            && let Ok(file) = fs::File::open(&path)
+        {
+            let reader = BufReader::new(file);
+            self.history_stats = serde_json::from_reader(reader).unwrap_or_default();
+        }
+        if let Some(path) = Self::frecency_path()
+            && let Ok(file) = fs::File::open(&path)
+        {
+            let reader = BufReader::new(file);
+            self.frecency = serde_json::from_reader(reader).unwrap_or_default();
+            self.completion_state.directories = self.frecency.ranked();
         }
         Ok(())
     }
 
+    fn frecency_path() -> Option<std::path::PathBuf> {
+        dirs::config_dir().map(|mut p| {
+            p.push("halo/frecency.json");
+            p
+        })
+    }
+
+    /// Counts a visit to `dir` for `z` and `cd` completion.
+    pub fn visit_dir(&mut self, dir: &Path) {
+        self.frecency.visit(dir);
+        self.save_frecency();
+    }
+
+    /// Drops `dir` from `z`, e.g. because it no longer exists.
+    pub fn forget_dir(&mut self, dir: &Path) {
+        self.frecency.remove(dir);
+        self.save_frecency();
+    }
+
+    fn save_frecency(&mut self) {
+        self.completion_state.directories = self.frecency.ranked();
+        if let Some(path) = Self::frecency_path()
+            && let Ok(bytes) = serde_json::to_vec(&self.frecency)
+        {
+            self.persister.write(path, bytes);
+        }
+    }
+
+    fn history_stats_path() -> Option<std::path::PathBuf> {
+        dirs::config_dir().map(|mut p| {
+            p.push("halo/history_stats.json");
+            p
+        })
+    }
+
+    /// Queues the history and its ranking stats to be written.
     pub fn save_history(&self) -> AppResult<()> {
         if let Some(path) = Self::history_path() {
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            let file = fs::File::create(&path)?;
-            serde_json::to_writer_pretty(file, &self.history)?;
+            self.persister.write(path, serde_json::to_vec_pretty(&self.history)?);
+        }
+        if let Some(path) = Self::history_stats_path() {
+            self.persister.write(path, serde_json::to_vec(&self.history_stats)?);
         }
         Ok(())
     }
 
+    fn config_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|mut p| {
+            p.push("halo/halo.toml");
+            p
+        })
+    }
+
+    /// Sets aliases in halo.toml's `[aliases]` table, or removes those whose
+    /// value is `None`, leaving its other entries and the rest of the file
+    /// as they are. Aliases that only live in memory, such as a loaded
+    /// session's, are not written.
+    pub fn save_aliases(&self, changes: &[(String, Option<String>)]) -> AppResult<()> {
+        if self.safe_mode {
+            anyhow::bail!("safe mode");
+        }
+        let Some(path) = Self::config_path() else {
+            return Ok(());
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let value: toml::Value = text.parse()?;
+        let mut saved: std::collections::HashMap<String, String> = value
+            .get("aliases")
+            .and_then(|v| v.as_table())
+            .into_iter()
+            .flatten()
+            .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+            .collect();
+        for (name, value) in changes {
+            match value {
+                Some(value) => saved.insert(name.clone(), value.clone()),
+                None => saved.remove(name),
+            };
+        }
+        let text = aliases::replace_section(&text, "aliases", &saved)?;
+        persist::write_atomic(&path, text.as_bytes())?;
+        Ok(())
+    }
+
+    /// (Re)reads halo.toml, creating a commented default on first run.
+    /// Problems are reported in the log and leave the defaults in place.
     pub fn load_config(&mut self) {
         // Read minimal halo.toml from config dir, parse aliases table if present
         if let Some(mut path) = dirs::config_dir() {
@@ -369,13 +2048,148 @@ impl State {
             // Config file path
             path.push("halo.toml");
             if let Ok(text) = fs::read_to_string(&path) {
-                if let Ok(value) = text.parse::<toml::Value>() {
+                let parsed = text.parse::<toml::Value>();
+                if let Err(e) = &parsed {
+                    self.warn(format!("{} not loaded: {}", path.display(), e.message()));
+                }
+                if let Ok(value) = parsed {
                     if let Some(aliases) = value.get("aliases").and_then(|v| v.as_table()) {
                         self.aliases = aliases
                             .iter()
                             .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
                             .collect();
                     }
+                    if let Some(abbrs) = value.get("abbr").and_then(|v| v.as_table()) {
+                        self.abbreviations = abbrs
+                            .iter()
+                            .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                            .collect();
+                    }
+                    self.hooks = value
+                        .get("hooks")
+                        .and_then(|v| v.as_table())
+                        .map(HooksConfig::from_table)
+                        .unwrap_or_default();
+                    self.notify = value
+                        .get("notify")
+                        .and_then(|v| v.as_table())
+                        .map(NotifyConfig::from_table)
+                        .unwrap_or_default();
+                    self.share = value
+                        .get("share")
+                        .and_then(|v| v.as_table())
+                        .map(ShareConfig::from_table)
+                        .unwrap_or_default();
+                    self.restore = value
+                        .get("session")
+                        .and_then(|v| v.as_table())
+                        .map(RestoreConfig::from_table)
+                        .unwrap_or_default();
+                    self.lock = value
+                        .get("lock")
+                        .and_then(|v| v.as_table())
+                        .map(LockConfig::from_table)
+                        .unwrap_or_default();
+                    if self.lock.passphrase_hash.is_some()
+                        && fs::metadata(&path).is_ok_and(|m| m.permissions().mode() & 0o077 != 0)
+                    {
+                        self.warn(format!(
+                            "{} holds the lock passphrase but others can read it (chmod 600)",
+                            path.display()
+                        ));
+                    }
+                    if self.lock.plaintext {
+                        self.warn(
+                            "[lock] passphrase is plain text; replace it with the \
+                             passphrase_hash line `halo passphrase` prints",
+                        );
+                    }
+                    if self.lock.invalid_hash {
+                        self.warn("[lock] passphrase_hash is not a PBKDF2 hash; ignored");
+                    }
+                    self.max_parallel_jobs = value
+                        .get("jobs")
+                        .and_then(|v| v.get("max_parallel"))
+                        .and_then(|v| v.as_integer())
+                        .filter(|n| *n > 0)
+                        .map(|n| n as usize);
+                    self.completion_state.user = value
+                        .get("completions")
+                        .and_then(|v| v.as_table())
+                        .map(UserCompletions::from_table)
+                        .unwrap_or_default();
+                    self.rank_weights = value
+                        .get("history")
+                        .and_then(|v| v.get("ranking"))
+                        .and_then(|v| v.as_table())
+                        .map(RankWeights::from_table)
+                        .unwrap_or_default();
+                    self.completion_state.templates = value
+                        .get("templates")
+                        .and_then(|v| v.as_table())
+                        .map(FileTemplates::from_table)
+                        .unwrap_or_default();
+                    self.noclobber = value
+                        .get("shell")
+                        .and_then(|v| v.get("noclobber"))
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false);
+                    self.normalize_input = value
+                        .get("shell")
+                        .and_then(|v| v.get("normalize_input"))
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(true);
+                    self.words = value
+                        .get("shell")
+                        .and_then(|v| v.get("words"))
+                        .and_then(|v| v.as_table())
+                        .map(WordConfig::from_table)
+                        .unwrap_or_default();
+                    self.completion_state.separators = self.words.completion.clone();
+                    self.tick = value
+                        .get("performance")
+                        .and_then(|v| v.get("tick_ms"))
+                        .and_then(|v| v.as_integer())
+                        .map(|ms| Duration::from_millis(ms.clamp(10, 5000) as u64))
+                        .unwrap_or(DEFAULT_TICK);
+                    self.low_bandwidth = value
+                        .get("performance")
+                        .and_then(|v| v.get("low_bandwidth"))
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or_else(over_ssh);
+                    let performance = value.get("performance");
+                    self.output_limits = OutputLimits {
+                        max_lines: performance
+                            .and_then(|v| v.get("max_output_lines"))
+                            .and_then(|v| v.as_integer())
+                            .map_or(output::DEFAULT_MAX_LINES, |n| {
+                                n.clamp(100, 10_000_000) as usize
+                            }),
+                        spill: performance
+                            .and_then(|v| v.get("spill_output"))
+                            .and_then(|v| v.as_bool())
+                            .unwrap_or(false),
+                    };
+                    if let Some(handoff_tbl) = value.get("handoff").and_then(|v| v.as_table()) {
+                        if let Some(list) = handoff_tbl.get("commands").and_then(|v| v.as_array()) {
+                            self.interactive_commands = list
+                                .iter()
+                                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                                .collect();
+                        }
+                        if let Some(extra) = handoff_tbl.get("extra").and_then(|v| v.as_array()) {
+                            self.interactive_commands
+                                .extend(extra.iter().filter_map(|v| v.as_str().map(|s| s.to_string())));
+                        }
+                    }
+                    // Read before the theme: naming one unpacks the bundled themes.
+                    if let Some(on) = value
+                        .get("ui")
+                        .and_then(|ui| ui.get("install_themes"))
+                        .and_then(|v| v.as_bool())
+                    {
+                        self.ui.install_themes = on;
+                    }
                     if let Some(theme_name) = value.get("theme").and_then(|v| v.as_str()) {
                         if !self.load_theme_from_file(theme_name) {
                             // Fallback to built-in theme if file not found
@@ -394,21 +2208,117 @@ impl State {
                         if let Some(sym) = ui_tbl.get("prompt").and_then(|v| v.as_str()) {
                             self.ui.prompt = sym.to_string();
                         }
+                        // A template that doesn't compile keeps the default; `doctor`
+                        // says what is wrong with it.
+                        for (key, template) in [
+                            ("prompt_format", &mut self.ui.prompt_format),
+                            ("title_format", &mut self.ui.title_format),
+                            ("status_format", &mut self.ui.status_format),
+                        ] {
+                            if let Some(source) = ui_tbl.get(key).and_then(|v| v.as_str())
+                                && let Ok(compiled) = Template::parse(source)
+                            {
+                                *template = compiled;
+                            }
+                        }
+                        if let Some(source) = ui_tbl.get("rprompt_format").and_then(|v| v.as_str())
+                            && let Ok(compiled) = Template::parse(source)
+                        {
+                            self.ui.rprompt_format = (!source.is_empty()).then_some(compiled);
+                        }
+                        let pattern = ui_tbl.get("time_format").and_then(|v| v.as_str());
+                        let locale = ui_tbl.get("locale").and_then(|v| v.as_str());
+                        if pattern.is_some() || locale.is_some() {
+                            let pattern = pattern.unwrap_or(&self.ui.time_format.pattern);
+                            if let Some(fmt) = TimeFormat::new(pattern, locale) {
+                                self.ui.time_format = fmt;
+                            }
+                        }
+                        if let Some(on) = ui_tbl.get("show_clock").and_then(|v| v.as_bool()) {
+                            self.ui.show_clock = on;
+                        }
+                        if let Some(n) = ui_tbl.get("status_strip").and_then(|v| v.as_integer()) {
+                            self.ui.status_strip = n.max(0) as usize;
+                        }
+                        if let Some(on) = ui_tbl.get("terminal_title").and_then(|v| v.as_bool()) {
+                            self.ui.terminal_title = on;
+                        }
+                        match ui_tbl.get("timestamps") {
+                            Some(toml::Value::Boolean(on)) => {
+                                self.ui.timestamps =
+                                    if *on { LogTimestamps::Both } else { LogTimestamps::Off };
+                            }
+                            Some(toml::Value::String(s)) => {
+                                if let Some(placement) = LogTimestamps::parse(s) {
+                                    self.ui.timestamps = placement;
+                                }
+                            }
+                            _ => {}
+                        }
+                        match ui_tbl.get("log_style").and_then(|v| v.as_str()) {
+                            Some("compact") => self.ui.compact_log = true,
+                            Some("full") => self.ui.compact_log = false,
+                            _ => {}
+                        }
+                        if let Some(ms) = ui_tbl.get("slow_command_ms").and_then(|v| v.as_integer())
+                        {
+                            self.ui.slow_command_ms = (ms > 0).then_some(ms as u128);
+                        }
+                        if let Some(pattern) =
+                            ui_tbl.get("timestamp_format").and_then(|v| v.as_str())
+                        {
+                            let locale = ui_tbl.get("locale").and_then(|v| v.as_str());
+                            self.ui.timestamp_format = TimeFormat::new(pattern, locale);
+                        }
+                        if let Some(tbl) = ui_tbl.get("completion").and_then(|v| v.as_table()) {
+                            self.ui.completion = CompletionPopupConfig::from_table(tbl);
+                        }
+                        if let Some(tbl) = ui_tbl.get("git").and_then(|v| v.as_table()) {
+                            self.ui.git = GitSegmentConfig::from_table(tbl);
+                        }
                     }
                 }
             } else {
                 // Create a starter config with current (softened) defaults
-                let default_cfg = format!(
-                    "# Halo config – created on first run\n# Set a named theme or define [theme] colors.\n# Available names: cyber-nord, dracula, gruvbox-dark, one-dark\n\n# theme = \"cyber-nord\"\n\n[theme]\nprimary = \"#64B5FF\"\naccent  = \"#FF40A0\"\nwarn    = \"#E7D98C\"\nerror   = \"#FF5555\"\nfg      = \"#DDE3EA\"\nbg      = \"#171A22\"\ncomment = \"#5A6473\"\n\n[ui]\nscrollbar_thumb = \"█\"\nprompt = \"❯\"\n\n# [aliases]\n# ll = \"ls -alF\"\n# gs = \"git status\"\n"
-                );
+                let default_cfg = "# Halo config – created on first run\n# Set a named theme or define [theme] colors.\n# Available names: cyber-nord, dracula, gruvbox-dark, one-dark\n\n# theme = \"cyber-nord\"\n\n[theme]\nprimary = \"#64B5FF\"\naccent  = \"#FF40A0\"\nwarn    = \"#E7D98C\"\nerror   = \"#FF5555\"\nfg      = \"#DDE3EA\"\nbg      = \"#171A22\"\ncomment = \"#5A6473\"\n\n[ui]\nscrollbar_thumb = \"█\"\nprompt = \"❯\"\n# time_format = \"%H:%M:%S\"  # strftime\n# locale = \"en_US\"         # defaults to $LC_TIME / $LANG\n# show_clock = false\n# status_strip = 30          # recent exit statuses above the input; 0 hides\n# install_themes = true     # unpack bundled themes on first use\n# terminal_title = true     # window title follows the repo / directory (`title` renames)\n# timestamps = \"off\"         # when commands ran: header, meta (exit line), both\n# timestamp_format = \"%H:%M:%S\"  # defaults to time_format\n# log_style = \"full\"        # compact: one line per finished command until highlighted\n# slow_command_ms = 5000    # durations from here on are flagged ⚠ in the warn color\n\n# The prompt, the input box title and the left of the status bar, as\n# templates: {segment} or {segment:style}; [...] only shows when a segment in\n# it has something to show, [...](style) styles all of it; \\ escapes. Segments:\n# symbol (prompt above), cwd, dir, user, host, git, git_status, git_unavailable,\n# exit_status, jobs, duration (of the last command), time, version. Styles: bold, dim, italic, underline,\n# reverse, a color, \"on\" a color; colors are theme names or as in [theme].\n# prompt_format = \"{symbol}  \"\n# prompt_format = \"{cwd} [{git:primary} ][{exit_status} ]❯ \"\n# rprompt_format = \"[{duration} ]{time}\"  # right of the input, hidden when text reaches it\n# title_format = '\\[ {user}@{host} \\]'\n# status_format = '[ HALO ](bold bg on primary) v{version}[ on {git}] [{git_status} ][{git_unavailable} ][ ✘ {exit_status} ](bold bg on error)'\n\n# Completion popup: border is double, rounded, plain, thick or none;\n# position is above or below the input. `icons = false` hides icons.\n# [ui.completion]\n# max_height = 10\n# max_width = 80\n# border = \"double\"\n# position = \"above\"\n# announce = true          # selection as plain text in the status bar, e.g. 3/14: src/main.rs\n# dir_icon = \"📁\"\n# file_icon = \"📄\"\n\n# After the branch in the status bar: ⇡ahead ⇣behind the upstream, +staged,\n# !unstaged and ?untracked files, $stashes. Each can be turned off.\n# [ui.git]\n# ahead_behind = true\n# staged = true\n# unstaged = true\n# untracked = true\n# stash = true\n\n# Programs that are handed the terminal until they exit (no pty: their\n# output stays out of the log). `commands` replaces the built-in list,\n# `extra` adds to it; `handoff <cmd>` does it once.\n# [handoff]\n# extra = [\"k9s\"]\n\n# Commands run with `sh -c`; on_chdir sees $HALO_OLDPWD and $HALO_PWD.\n# [hooks]\n# on_exit = [\"tmux rename-window shell\"]\n# on_chdir = []\n\n# Limit concurrently running jobs; extra commands wait in a queue.\n# [jobs]\n# max_parallel = 4\n\n# Ask before `>` overwrites an existing file (`>|` forces). Also `set -C`.\n# [shell]\n# noclobber = true\n# normalize_input = true   # smart quotes, non-breaking / zero-width spaces → ASCII\n\n# Where words end besides whitespace: \"punctuation\" (anything but letters\n# and digits) or the characters to stop at.\n# [shell.words]\n# kill = \"\"                # Ctrl+W; \"/\" stops at path components\n# motion = \"punctuation\"   # Alt+B/F/D, Ctrl+←/→\n# completion = \"\"          # Tab completes the path after these, e.g. \"=:\"\n\n# Raise on battery or slow SSH links: fewer wakeups and redraws,\n# at the cost of input and output latency (10-5000 ms).\n# [performance]\n# tick_ms = 100\n# low_bandwidth = true      # batch redraws, no animations or emoji (default over SSH)\n# max_output_lines = 10000  # per command; older lines are dropped\n# spill_output = false      # keep dropped lines in a temp file instead\n\n# Alert when a command finishes or fails. Each takes one or a list of\n# \"bell\", \"flash\", \"osc9\", \"osc777\" (terminal notifications),\n# \"desktop\" (notify-send / osascript) and \"webhook\" (JSON POST); \"off\".\n# Commands that finish while you are away are also marked in the log.\n# [notify]\n# on_failure = \"bell\"\n# on_finish = [\"osc9\"]\n# min_duration_ms = 2000\n# only_when_away = true     # skip while the window has focus and the log is at the bottom\n# webhook_url = \"https://example.com/hooks/halo\"\n\n# `share [^N]` posts a log entry (command, output, exit status) here and\n# prints the link in the answer. payload = \"gist\" for the GitHub gist API.\n# [share]\n# url = \"https://paste.example.com/api\"\n# payload = \"entry\"\n# headers = [\"Authorization: Bearer TOKEN\"]\n\n# Keep the end of the command log across restarts (saved on exit with the\n# session); output is cut to the last restore_lines lines per entry.\n# [session]\n# restore_log = 50          # entries; true for 100\n# restore_lines = 200\n\n# Hide the screen after this long without input, until a key is pressed\n# or the passphrase typed (keep this file chmod 600 then). `lock` locks now.\n# [lock]\n# idle_minutes = 15\n# passphrase_hash = \"...\"  # printed by `halo passphrase`\n\n# Per-command suggestions: a word list, or a command printing one per line\n# (gets $1=command $2=word $3=previous word, like bash `complete -C`).\n# [completions]\n# deploy = [\"staging\", \"production\"]\n# kill = \"ps -u $USER -o pid=\"\n\n# Commands offered for a file by extension: Tab on a lone file name, or\n# Ctrl+O for the last word. `{}` is the path (appended if missing).\n# [templates]\n# py = [\"python3 {}\", \"python3 -m pdb {}\"]\n# \"tar.gz\" = \"tar xzf {}\"\n\n# How autosuggestions and Ctrl+R order history: how often, how recently,\n# and whether in this directory / git repo a command ran. 0 ignores one.\n# [history.ranking]\n# frequency = 1.0\n# recency = 2.0\n# directory = 1.5\n# repo = 1.0\n\n# `{name}` in an alias is asked for when it runs (words after the alias\n# answer in order); `{name:path}` / `{name:dir}` complete paths on Tab.\n# [aliases]\n# ll = \"ls -alF\"\n# gs = \"git status\"\n# deploy = \"kubectl apply -f {file:path} -n {ns}\"\n\n# Abbreviations expand in place after a space or Enter, so the full\n# command is what ends up in history.\n# [abbr]\n# gco = \"git checkout\"\n".to_string();
                 let _ = fs::write(&path, default_cfg);
             }
         }
-        
-        // Extract themes from archive if needed
+    }
+
+    /// Whether `name` would run: a builtin, an alias, a path to an executable
+    /// or a program on $PATH. `None` while $PATH is still being indexed.
+    pub fn command_known(&self, name: &str) -> Option<bool> {
+        if completion::BUILTINS.contains(&name)
+            || self.aliases.contains_key(name)
+            || self.abbreviations.contains_key(name)
+        {
+            return Some(true);
+        }
+        if name.contains('/') {
+            let path = match (name.strip_prefix("~/"), dirs::home_dir()) {
+                (Some(rest), Some(home)) => home.join(rest),
+                _ => self.cwd.join(name),
+            };
+            let executable = fs::metadata(path)
+                .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0);
+            return Some(executable);
+        }
+        self.completion_state.path_index.contains(name)
+    }
+
+    /// Installs the bundled themes the first time they are needed, unless
+    /// `[ui] install_themes = false`. Checked at most once per session.
+    fn ensure_themes(&mut self) {
+        if self.themes_checked || !self.ui.install_themes {
+            return;
+        }
+        self.themes_checked = true;
+        let started = Instant::now();
         if let Err(e) = themes::extract_themes_if_needed() {
-            eprintln!("Warning: Failed to extract themes: {}", e);
+            self.warn(format!("failed to install themes: {e}"));
         }
+        self.startup.record("theme extraction", started.elapsed());
     }
 
     fn session_path() -> Option<std::path::PathBuf> {
@@ -418,70 +2328,91 @@ impl State {
         })
     }
 
+    /// Restores the last directory, theme and (with `[session]
+    /// restore_log`) the end of the log.
     pub fn load_session(&mut self) -> AppResult<()> {
-        if let Some(path) = Self::session_path() {
-            if let Ok(file) = fs::File::open(&path) {
-                let reader = BufReader::new(file);
-                #[derive(Deserialize)]
-                struct Session {
-                    last_cwd: String,
-                    last_theme_name: Option<String>,
-                }
-                if let Ok(session) = serde_json::from_reader::<_, Session>(reader) {
-                    let candidate = PathBuf::from(session.last_cwd);
-                    if candidate.is_dir() {
-                        if let Err(_e) = std::env::set_current_dir(&candidate) {
-                            // ignore failure, keep current cwd
-                        }
-                        self.cwd = candidate;
-                    }
-                    if let Some(name) = session.last_theme_name {
-                        self.theme = Theme::from_name(&name);
-                        self.theme_name = name;
+        if let Some(path) = Self::session_path()
+            && let Ok(file) = fs::File::open(&path)
+        {
+            let reader = BufReader::new(file);
+            #[derive(Deserialize)]
+            struct Session {
+                last_cwd: String,
+                last_theme_name: Option<String>,
+                #[serde(default)]
+                log: Vec<SavedEntry>,
+            }
+            let session = serde_json::from_reader::<_, Session>(reader);
+            if let Err(e) = &session {
+                self.warn(format!("session not restored: {e}"));
+            }
+            if let Ok(session) = session {
+                let candidate = PathBuf::from(session.last_cwd);
+                if candidate.is_dir() {
+                    if let Err(_e) = std::env::set_current_dir(&candidate) {
+                        // ignore failure, keep current cwd
                     }
+                    self.cwd = candidate;
+                }
+                if let Some(name) = session.last_theme_name {
+                    self.theme = Theme::from_name(&name);
+                    self.theme_name = name;
+                }
+                if self.restore.entries > 0 && !session.log.is_empty() {
+                    let restored = restore::restore(session.log);
+                    self.show_notice(format!(
+                        "restored {} log entries from the last session",
+                        restored.len()
+                    ));
+                    self.command_log.splice(0..0, restored);
                 }
             }
         }
         Ok(())
     }
 
+    /// Queues the directory, theme and log to be written; not in safe mode.
     pub fn save_session(&self) -> AppResult<()> {
+        // Safe mode starts in the launch directory on the default theme;
+        // saving that would lose the session it skipped.
+        if self.safe_mode {
+            return Ok(());
+        }
         if let Some(path) = Self::session_path() {
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
-            }
             #[derive(Serialize)]
             struct Session {
                 last_cwd: String,
                 last_theme_name: String,
+                #[serde(skip_serializing_if = "Vec::is_empty")]
+                log: Vec<SavedEntry>,
             }
             let data = Session {
                 last_cwd: self.cwd.to_string_lossy().to_string(),
                 last_theme_name: self.theme_name.clone(),
+                log: restore::save(&self.command_log, self.restore),
             };
-            let file = fs::File::create(&path)?;
-            serde_json::to_writer_pretty(file, &data)?;
+            self.persister.write(path, serde_json::to_vec_pretty(&data)?);
         }
         Ok(())
     }
 
 
 
-    pub fn get_available_themes(&self) -> Vec<String> {
+    /// Names of the installed themes, sorted.
+    pub fn get_available_themes(&mut self) -> Vec<String> {
+        self.ensure_themes();
         let mut themes = Vec::new();
         
         if let Some(mut themes_dir) = dirs::config_dir() {
             themes_dir.push("halo/themes");
             if let Ok(entries) = fs::read_dir(themes_dir) {
                 for entry in entries.filter_map(Result::ok) {
-                    if let Some(extension) = entry.path().extension() {
-                        if extension == "toml" {
-                            if let Some(stem) = entry.path().file_stem() {
-                                if let Some(name) = stem.to_str() {
-                                    themes.push(name.to_string());
-                                }
-                            }
-                        }
+                    if let Some(extension) = entry.path().extension()
+                        && extension == "toml"
+                        && let Some(stem) = entry.path().file_stem()
+                        && let Some(name) = stem.to_str()
+                    {
+                        themes.push(name.to_string());
                     }
                 }
             }
@@ -491,37 +2422,52 @@ impl State {
         themes
     }
 
+    /// Switches to installed theme `theme_name`; false if there is none.
     pub fn load_theme_from_file(&mut self, theme_name: &str) -> bool {
+        self.ensure_themes();
         if let Some(mut theme_path) = dirs::config_dir() {
             theme_path.push(format!("halo/themes/{}.toml", theme_name));
             
-            if let Ok(content) = fs::read_to_string(theme_path) {
-                if let Ok(value) = content.parse::<toml::Value>() {
-                    if let Some(theme_tbl) = value.as_table() {
-                        self.theme = Theme::from_table(theme_tbl, Theme::default());
-                        self.theme_name = theme_name.to_string();
-                        return true;
-                    }
-                }
+            if let Ok(content) = fs::read_to_string(theme_path)
+                && let Ok(value) = content.parse::<toml::Value>()
+                && let Some(theme_tbl) = value.as_table()
+            {
+                self.theme = Theme::from_table(theme_tbl, Theme::default());
+                self.theme_name = theme_name.to_string();
+                return true;
             }
         }
         false
     }
 
+    /// Opens the theme gallery.
     pub fn enter_theme_selection_mode(&mut self) {
         self.theme_selection_mode = true;
         self.available_themes = self.get_available_themes();
         self.theme_selection_index = 0;
+        self.theme_before_preview = Some(self.theme.clone());
         self.needs_redraw = true;
     }
 
+    /// Closes the theme gallery, keeping the theme shown.
     pub fn exit_theme_selection_mode(&mut self) {
         self.theme_selection_mode = false;
         self.available_themes.clear();
         self.theme_selection_index = 0;
+        self.theme_before_preview = None;
+        self.theme_simulation = None;
         self.needs_redraw = true;
     }
 
+    /// Leaves selection mode and puts back the theme that was active before previewing.
+    pub fn cancel_theme_selection(&mut self) {
+        if let Some(theme) = self.theme_before_preview.take() {
+            self.theme = theme;
+        }
+        self.exit_theme_selection_mode();
+    }
+
+    /// Previews the previous theme in the gallery.
     pub fn select_theme_up(&mut self) {
         if self.theme_selection_mode && !self.available_themes.is_empty() {
             self.theme_selection_index = self.theme_selection_index.saturating_sub(1);
@@ -530,6 +2476,7 @@ impl State {
         }
     }
 
+    /// Previews the next theme in the gallery, wrapping around.
     pub fn select_theme_down(&mut self) {
         if self.theme_selection_mode && !self.available_themes.is_empty() {
             self.theme_selection_index = (self.theme_selection_index + 1) % self.available_themes.len();
@@ -538,6 +2485,7 @@ impl State {
         }
     }
 
+    /// Applies and saves the highlighted theme; false if it failed to load.
     pub fn confirm_theme_selection(&mut self) -> bool {
         if self.theme_selection_mode && !self.available_themes.is_empty() {
             let theme_name = self.available_themes[self.theme_selection_index].clone();
@@ -551,20 +2499,21 @@ impl State {
         false
     }
 
+    /// Shows the highlighted theme without making it the session's.
     pub fn preview_selected_theme(&mut self) {
-        if self.theme_selection_mode && !self.available_themes.is_empty() {
-            if let Some(theme_name) = self.available_themes.get(self.theme_selection_index) {
-                // Temporarily load the theme for preview without changing the theme_name
-                if let Some(mut theme_path) = dirs::config_dir() {
-                    theme_path.push(format!("halo/themes/{}.toml", theme_name));
-                    
-                    if let Ok(content) = fs::read_to_string(theme_path) {
-                        if let Ok(value) = content.parse::<toml::Value>() {
-                            if let Some(theme_tbl) = value.as_table() {
-                                self.theme = Theme::from_table(theme_tbl, Theme::default());
-                            }
-                        }
-                    }
+        if self.theme_selection_mode
+            && !self.available_themes.is_empty()
+            && let Some(theme_name) = self.available_themes.get(self.theme_selection_index)
+        {
+            // Temporarily load the theme for preview without changing the theme_name
+            if let Some(mut theme_path) = dirs::config_dir() {
+                theme_path.push(format!("halo/themes/{}.toml", theme_name));
+
+                if let Ok(content) = fs::read_to_string(theme_path)
+                    && let Ok(value) = content.parse::<toml::Value>()
+                    && let Some(theme_tbl) = value.as_table()
+                {
+                    self.theme = Theme::from_table(theme_tbl, Theme::default());
                 }
             }
         }
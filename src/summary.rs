@@ -0,0 +1,91 @@
+// src/summary.rs
+
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Longest-running commands kept for the recap.
+const LONGEST_KEPT: usize = 5;
+
+/// Running totals for the `summary` builtin. Kept separately from the log,
+/// which only holds the newest entries.
+pub struct SessionStats {
+    started: Instant,
+    commands: usize,
+    failures: usize,
+    total_ms: u128,
+    /// In order of first visit.
+    dirs: Vec<PathBuf>,
+    /// Slowest commands, slowest first.
+    longest: Vec<(String, u128)>,
+}
+
+impl Default for SessionStats {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            commands: 0,
+            failures: 0,
+            total_ms: 0,
+            dirs: Vec::new(),
+            longest: Vec::new(),
+        }
+    }
+}
+
+impl SessionStats {
+    pub fn command_started(&mut self, cwd: &Path) {
+        self.commands += 1;
+        self.visit(cwd);
+    }
+
+    pub fn visit(&mut self, dir: &Path) {
+        if !self.dirs.iter().any(|d| d == dir) {
+            self.dirs.push(dir.to_path_buf());
+        }
+    }
+
+    pub fn command_finished(&mut self, command: &str, failed: bool, duration_ms: Option<u128>) {
+        if failed {
+            self.failures += 1;
+        }
+        let Some(ms) = duration_ms else {
+            return;
+        };
+        self.total_ms += ms;
+        let pos = self.longest.partition_point(|(_, longer)| *longer >= ms);
+        if pos < LONGEST_KEPT {
+            self.longest.insert(pos, (command.to_string(), ms));
+            self.longest.truncate(LONGEST_KEPT);
+        }
+    }
+
+    pub fn report(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("Session: {}", format_ms(self.started.elapsed().as_millis())),
+            format!("  commands run  {} ({} failed)", self.commands, self.failures),
+            format!("  time in cmds  {}", format_ms(self.total_ms)),
+            format!("  directories   {}", self.dirs.len()),
+        ];
+        lines.extend(self.dirs.iter().map(|d| format!("    {}", d.display())));
+        if !self.longest.is_empty() {
+            lines.push("  longest:".to_string());
+            lines.extend(
+                self.longest
+                    .iter()
+                    .map(|(command, ms)| format!("    {:>8}  {command}", format_ms(*ms))),
+            );
+        }
+        lines
+    }
+}
+
+/// `850ms`, `12.3s`, `4m05s` or `1h02m`.
+pub fn format_ms(ms: u128) -> String {
+    let secs = ms / 1000;
+    match secs {
+        0 => format!("{ms}ms"),
+        1..60 => format!("{:.1}s", ms as f64 / 1000.0),
+        60..3600 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
@@ -0,0 +1,54 @@
+// src/tabs.rs
+
+//! Tabs: separate sessions in one window, each with its own directory, log
+//! (panes included), input line and jobs. As with panes, the active tab's
+//! live directly in `State`, so nothing else needs to know about tabs; a
+//! switch swaps them with the tab's slot here.
+
+use crate::command::CommandLog;
+use crate::dirstack::DirStack;
+use crate::git::GitStatus;
+use crate::panes::{Pane, Split};
+use std::path::PathBuf;
+
+/// Alt+1 to Alt+9 reach them all.
+pub const MAX_TABS: usize = 9;
+
+#[derive(Default)]
+pub struct Tab {
+    /// Stable across closing other tabs; jobs remember it.
+    pub id: u64,
+    pub cwd: PathBuf,
+    pub dir_stack: DirStack,
+    pub git_branch: Option<String>,
+    pub git_status: GitStatus,
+    pub git_root: Option<PathBuf>,
+    pub command_log: Vec<CommandLog>,
+    pub scroll_offset: usize,
+    pub panes: Vec<Pane>,
+    pub focused_pane: usize,
+    pub split: Split,
+    pub input_buffer: String,
+    pub cursor_position: usize,
+    pub last_exit_code: i32,
+    /// The job Ctrl+C and Ctrl+Z act on while this tab is active.
+    pub foreground: Option<usize>,
+}
+
+impl Tab {
+    pub fn new(id: u64, cwd: PathBuf) -> Self {
+        Self {
+            id,
+            command_log: vec![CommandLog::new(String::new(), String::new(), false, cwd.clone())],
+            cwd,
+            ..Self::default()
+        }
+    }
+
+    /// What the tab bar calls it: the last component of its directory.
+    pub fn label(cwd: &std::path::Path) -> String {
+        cwd.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| cwd.display().to_string())
+    }
+}
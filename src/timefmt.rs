@@ -0,0 +1,122 @@
+// src/timefmt.rs
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, Locale};
+use std::fmt::Write;
+
+const DEFAULT_PATTERN: &str = "%H:%M:%S";
+
+/// A strftime pattern paired with the locale used to render it.
+/// Every place that shows a date or time goes through this, so users
+/// configure the format once in `[ui]` instead of per feature.
+#[derive(Clone)]
+pub struct TimeFormat {
+    pub pattern: String,
+    pub locale: Locale,
+}
+
+impl Default for TimeFormat {
+    fn default() -> Self {
+        Self {
+            pattern: DEFAULT_PATTERN.to_string(),
+            locale: locale_from_env(),
+        }
+    }
+}
+
+impl TimeFormat {
+    /// Builds a format from config values. Returns None if the pattern
+    /// contains invalid strftime specifiers or the locale is unknown.
+    pub fn new(pattern: &str, locale: Option<&str>) -> Option<Self> {
+        let locale = match locale {
+            Some(name) => parse_locale(name)?,
+            None => locale_from_env(),
+        };
+        let has_error = StrftimeItems::new_with_locale(pattern, locale)
+            .any(|item| matches!(item, Item::Error));
+        if has_error {
+            return None;
+        }
+        Some(Self {
+            pattern: pattern.to_string(),
+            locale,
+        })
+    }
+
+    pub fn format(&self, time: &DateTime<Local>) -> String {
+        let mut out = String::new();
+        if write!(out, "{}", time.format_localized(&self.pattern, self.locale)).is_err() {
+            // Patterns are validated up front, but never let a bad one panic the UI.
+            out = time.format(DEFAULT_PATTERN).to_string();
+        }
+        out
+    }
+
+    pub fn now(&self) -> String {
+        self.format(&Local::now())
+    }
+}
+
+/// Parses names like `de_DE`, `de_DE.UTF-8` or `sr_RS@latin`. A modifier
+/// picks its own variant where there is one (`sr_RS@latin` is Latin
+/// script); otherwise it is dropped.
+fn parse_locale(name: &str) -> Option<Locale> {
+    let (head, modifier) = name.split_once('@').unwrap_or((name, ""));
+    let base = head.split('.').next().unwrap_or(head);
+    if base.is_empty() || base == "C" || base == "POSIX" {
+        return Some(Locale::POSIX);
+    }
+    let base = base.replace('-', "_");
+    Locale::try_from(format!("{base}@{modifier}").as_str())
+        .or_else(|_| Locale::try_from(base.as_str()))
+        .ok()
+}
+
+/// Resolves the locale the same way libc does for LC_TIME.
+fn locale_from_env() -> Locale {
+    ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|v| !v.is_empty())
+        .and_then(|v| parse_locale(&v))
+        .unwrap_or(Locale::POSIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn parse_locale_names() {
+        assert_eq!(parse_locale("de_DE"), Some(Locale::de_DE));
+        assert_eq!(parse_locale("de_DE.UTF-8"), Some(Locale::de_DE));
+        assert_eq!(parse_locale("en-US"), Some(Locale::en_US));
+        assert_eq!(parse_locale("C"), Some(Locale::POSIX));
+        assert_eq!(parse_locale("POSIX.UTF-8"), Some(Locale::POSIX));
+        assert_eq!(parse_locale(""), Some(Locale::POSIX));
+        assert_eq!(parse_locale("xx_YY"), None);
+    }
+
+    #[test]
+    fn parse_locale_modifiers() {
+        assert_eq!(parse_locale("sr_RS@latin"), Some(Locale::sr_RS_latin));
+        assert_eq!(parse_locale("sr_RS.UTF-8@latin"), Some(Locale::sr_RS_latin));
+        assert_eq!(parse_locale("sr_RS"), Some(Locale::sr_RS));
+        assert_eq!(parse_locale("en_US@nonsense"), Some(Locale::en_US));
+    }
+
+    #[test]
+    fn new_rejects_bad_patterns_and_locales() {
+        assert!(TimeFormat::new("%H:%M", Some("C")).is_some());
+        assert!(TimeFormat::new("%Q", Some("C")).is_none());
+        assert!(TimeFormat::new("%H", Some("xx_YY")).is_none());
+    }
+
+    #[test]
+    fn format_uses_the_locale() {
+        let time = Local.with_ymd_and_hms(2024, 3, 5, 14, 7, 9).unwrap();
+        let format = TimeFormat::new("%A %H:%M:%S", Some("de_DE")).unwrap();
+        assert_eq!(format.format(&time), "Dienstag 14:07:09");
+    }
+}
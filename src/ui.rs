@@ -1,59 +1,310 @@
 // src/ui.rs
 
-use crate::command::CommandLog;
-use crate::state::{State, Theme};
+use halo_core::builder::FieldKind;
+use halo_core::command::{self, CommandLog, RichOutput};
+use halo_core::completion::SuggestionSource;
+use halo_core::diffview::LineKind;
+use halo_core::disk;
+use halo_core::gitpanel::Area;
+use halo_core::links;
+use halo_core::lock::Lock;
+use halo_core::panes::Split;
+use halo_core::parser;
+use halo_core::state::{self, LogSearch, PopupPosition, ScreenText, State, Theme, UiConfig};
+use halo_core::summary;
+use halo_core::tabs::Tab;
 use ratatui::{
+    buffer::Buffer,
     prelude::*,
-    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{
+        Block, BorderType, Borders, Clear, LineGauge, List, ListItem, ListState, Paragraph, Wrap,
+    },
 };
+use std::ops::Range;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 // Colors are now taken from state's theme
 
 pub fn draw(frame: &mut Frame, state: &mut State) {
     let theme = &state.theme;
     frame.render_widget(Block::new().bg(theme.bg), frame.area());
+    if let Some(lock) = &state.locked {
+        render_lock_screen(frame, state, lock);
+        return;
+    }
+    if state.flash_until.is_some() {
+        // The layout margin leaves exactly one cell for this border.
+        frame.render_widget(
+            Block::new()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .border_style(Style::new().fg(theme.error)),
+            frame.area(),
+        );
+    }
 
+    let strip_height = u16::from(state.ui.status_strip > 0);
+    let tab_height = u16::from(!state.tabs.is_empty());
     let main_layout = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
         .constraints([
             Constraint::Min(0),
+            Constraint::Length(tab_height),
             Constraint::Length(1),
+            Constraint::Length(strip_height),
             Constraint::Length(3),
         ])
         .split(frame.area());
+    let input_area = main_layout[4];
+
+    let input_inner = Block::default().borders(Borders::ALL).inner(input_area);
+    let prompt_width = Line::from(prompt_spans(state)).width() as u16;
+    let input_width = input_inner.width.saturating_sub(prompt_width) as usize;
+    let cursor_col = state.input_buffer[..state.cursor_position].width();
+    update_input_scroll(state, cursor_col, input_width);
 
     render_output_log(frame, main_layout[0], state);
-    render_status_bar(frame, main_layout[1], state);
-    render_input_box(frame, main_layout[2], state);
+    render_tab_bar(frame, main_layout[1], state);
+    match state.completion_state.announcement() {
+        Some(text) if state.completion_state.active && state.ui.completion.announce => {
+            render_completion_announcement(frame, main_layout[2], state, text);
+        }
+        _ => render_status_bar(frame, main_layout[2], state),
+    }
+    render_status_strip(frame, main_layout[3], state);
+    render_input_box(frame, input_area, state, input_width);
 
     if state.completion_state.active {
-        render_completion_popup(frame, main_layout[2], state);
+        render_completion_popup(frame, input_area, state);
+    }
+
+    if state.history_search.is_some() {
+        render_history_search_popup(frame, input_area, state);
+    }
+
+    if state.dir_picker.is_some() {
+        render_dir_picker_popup(frame, input_area, state);
     }
 
     if state.theme_selection_mode {
         render_theme_selection_popup(frame, state);
+        if let Some(simulation) = state.theme_simulation {
+            simulation.apply_to_buffer(frame.buffer_mut());
+        }
     }
 
-    if state.scroll_offset == 0 {
-        let input_block = Block::default().borders(Borders::ALL);
-        let inner_area = input_block.inner(main_layout[2]);
-        let prompt_width = 3;
+    if state.log_search.is_some() {
+        render_log_search_bar(frame, input_area, state);
+    }
 
-        frame.set_cursor_position((
-            inner_area.x + prompt_width + state.cursor_position as u16,
-            inner_area.y,
-        ));
+    if state.procs.is_some() {
+        render_procs_popup(frame, state);
+    }
+
+    if state.git_panel.is_some() {
+        render_git_panel(frame, state);
+    }
+
+    if state.diff_view.is_some() {
+        render_diff_view(frame, state);
+    }
+
+    if state.builder.is_some() {
+        render_builder_popup(frame, input_area, state);
+    }
+
+    if state.dry_run.is_some() {
+        render_dry_run_popup(frame, input_area, state);
+    }
+
+    if state.perf.is_some() {
+        render_perf_overlay(frame, main_layout[0], state);
+    }
+
+    if state.confirmation.is_some() {
+        render_confirmation_popup(frame, state);
+    }
+
+    if state.pager.is_some() {
+        render_pager(frame, state);
+        return;
+    }
+
+    if state.scroll_offset == 0 && !state.presentation_mode {
+        let visible_col = cursor_col.saturating_sub(state.input_scroll) as u16;
+        frame.set_cursor_position((input_inner.x + prompt_width + visible_col, input_inner.y));
+    }
+}
+
+/// Scrolls the input line horizontally just enough to keep the cursor visible.
+fn update_input_scroll(state: &mut State, cursor_col: usize, width: usize) {
+    if width == 0 || cursor_col < state.input_scroll {
+        state.input_scroll = cursor_col;
+    } else if cursor_col >= state.input_scroll + width {
+        state.input_scroll = cursor_col + 1 - width;
+    }
+    // Don't leave blank space on the left once the line shrinks again.
+    let total = state.input_buffer.width();
+    if total < state.input_scroll + width {
+        state.input_scroll = (total + 1).saturating_sub(width).min(state.input_scroll);
+    }
+}
+
+/// The slice of `text` starting at display column `scroll` that fits in `width` columns.
+fn visible_slice(text: &str, scroll: usize, width: usize) -> &str {
+    &text[visible_range(text, scroll, width)]
+}
+
+/// Byte range of `text` that fits in `width` columns after skipping `scroll`.
+fn visible_range(text: &str, scroll: usize, width: usize) -> std::ops::Range<usize> {
+    let mut col = 0;
+    let mut start = text.len();
+    let mut end = text.len();
+    for (i, c) in text.char_indices() {
+        // A newline in an edited buffer shows as `↵`.
+        let w = if c == '\n' { 1 } else { c.width().unwrap_or(0) };
+        if col >= scroll && start == text.len() {
+            start = i;
+        }
+        if col + w > scroll + width {
+            end = i;
+            break;
+        }
+        col += w;
+    }
+    if start > end {
+        start = end;
+    }
+    start..end
+}
+
+/// The visible part of the input, syntax highlighted: commands in primary
+/// (error if not found), strings in warn and flags in comment. Unbalanced
+/// quotes/brackets and the partner of the bracket at the cursor stand out.
+fn input_spans(state: &State, input_width: usize) -> Vec<Span<'_>> {
+    let theme = &state.theme;
+    let text = &state.input_buffer;
+    let range = visible_range(text, state.input_scroll, input_width);
+    let offset = range.start;
+    let visible = &text[range];
+    // A placeholder's answer isn't a command line.
+    if state.placeholder_prompt.is_some() {
+        return vec![Span::styled(visible, Style::new().fg(theme.fg))];
+    }
+
+    let delims = parser::delimiters(text);
+    let cursor = state.cursor_position;
+    // The bracket under the cursor, or the one just typed.
+    let partner = delims.partner(cursor).map(|p| (cursor, p)).or_else(|| {
+        let before = text[..cursor].char_indices().next_back()?.0;
+        delims.partner(before).map(|p| (before, p))
+    });
+
+    let base = Style::new().fg(theme.fg);
+    let words: Vec<(parser::Span, Style)> = parser::word_roles(text)
+        .into_iter()
+        .map(|(span, role)| {
+            let style = match role {
+                parser::WordRole::Command(name) => match state.command_known(&name) {
+                    Some(true) => Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
+                    Some(false) => Style::new().fg(theme.error),
+                    // Still indexing $PATH; don't flash everything red.
+                    None => base,
+                },
+                parser::WordRole::Flag => Style::new().fg(theme.comment),
+            };
+            (span, style)
+        })
+        .collect();
+    let style_at = |i: usize| {
+        if delims.unbalanced.contains(&i) {
+            Style::new().fg(theme.error).add_modifier(Modifier::BOLD)
+        } else if partner.is_some_and(|(a, b)| i == a || i == b) {
+            Style::new().fg(theme.bg).bg(theme.accent)
+        } else if delims.strings.iter().any(|s| s.contains(&i)) {
+            Style::new().fg(theme.warn)
+        } else if let Some((_, style)) = words.iter().find(|(s, _)| s.contains(&i)) {
+            *style
+        } else {
+            base
+        }
+    };
+
+    let mut spans = Vec::new();
+    let mut run_start = 0;
+    let mut run_style = base;
+    for (i, c) in visible.char_indices() {
+        if c == '\n' {
+            if i > run_start {
+                spans.push(Span::styled(&visible[run_start..i], run_style));
+            }
+            spans.push(Span::styled("↵", Style::new().fg(theme.comment)));
+            run_start = i + 1;
+            continue;
+        }
+        let style = style_at(offset + i);
+        if style != run_style {
+            if i > run_start {
+                spans.push(Span::styled(&visible[run_start..i], run_style));
+            }
+            run_start = i;
+            run_style = style;
+        }
+    }
+    spans.push(Span::styled(&visible[run_start..], run_style));
+    spans
+}
+
+/// The autosuggestion tail, clipped to whatever room the input line has left.
+fn ghost_text(state: &State, input_width: usize) -> &str {
+    let Some(rest) = state.suggestion_suffix() else {
+        return "";
+    };
+    let used = state.input_buffer.width().saturating_sub(state.input_scroll);
+    visible_slice(rest, 0, input_width.saturating_sub(used))
+}
+
+/// The log area: one log, or each pane's once it is split.
+fn render_output_log(frame: &mut Frame, area: Rect, state: &mut State) {
+    let count = state.panes.len();
+    if count == 0 {
+        render_focused_log(frame, area, state, " [[[ CONSOLE LOG ]]] ".to_string());
+        return;
+    }
+    let direction = match state.split {
+        Split::Columns => Direction::Horizontal,
+        Split::Rows => Direction::Vertical,
+    };
+    let areas = Layout::new(direction, vec![Constraint::Ratio(1, count as u32); count])
+        .spacing(1)
+        .split(area);
+    for (index, pane_area) in areas.iter().enumerate() {
+        let title = format!(" [[[ PANE {} ]]] ", index + 1);
+        if index == state.focused_pane {
+            render_focused_log(frame, *pane_area, state, title);
+        } else {
+            render_other_pane(frame, *pane_area, state, index, title);
+        }
     }
 }
 
-fn render_output_log(frame: &mut Frame, area: Rect, state: &State) {
+/// What `render_log_blocks` draws.
+struct LogView<'a> {
+    logs: &'a [CommandLog],
+    scroll_offset: usize,
+    hovered: Option<usize>,
+    search: Option<&'a LogSearch>,
+}
+
+/// The log that has focus, with scrollbar, hover, selection and links.
+fn render_focused_log(frame: &mut Frame, area: Rect, state: &mut State, title: String) {
     let theme = &state.theme;
     let output_block = Block::new()
         .borders(Borders::TOP)
         .border_style(Style::new().fg(theme.comment))
         .title(Span::styled(
-            " [[[ CONSOLE LOG ]]] ",
+            title,
             Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
         ));
 
@@ -63,15 +314,97 @@ fn render_output_log(frame: &mut Frame, area: Rect, state: &State) {
         vertical: 1,
         horizontal: 0,
     });
+    let view = LogView {
+        logs: &state.command_log,
+        scroll_offset: state.scroll_offset,
+        hovered: state.hovered_log,
+        search: state.log_search.as_ref(),
+    };
+    let mut link_targets = Vec::new();
+    let log_areas = render_log_blocks(frame, inner_area, &view, state, &mut link_targets);
+    let total_logs = state.command_log.len();
+    // The scrollbar column is not part of the text.
+    let text_area = Rect {
+        width: inner_area.width.saturating_sub(1),
+        ..inner_area
+    };
+    state.log_screen = capture_text(frame.buffer_mut(), text_area);
+    if let Some((anchor, head)) = state.selection {
+        highlight_selection(frame.buffer_mut(), text_area, anchor, head);
+    }
+
+    // Draw a minimal scrollbar track on the right if there are logs
+    let theme = &state.theme;
+    if total_logs > 0 {
+        let track_x = area.right().saturating_sub(1);
+        let track_area = Rect::new(track_x, inner_area.y, 1, inner_area.height);
+        // Compute thumb size relative to number of blocks (simple heuristic)
+        let min_thumb = 1u16;
+        let thumb_h = (inner_area.height / 4).max(min_thumb);
+        let max_scroll = total_logs.saturating_sub(1) as u16;
+        let scroll = state.scroll_offset.min(max_scroll as usize) as u16;
+        let top_space = if max_scroll == 0 {
+            0
+        } else {
+            (inner_area.height - thumb_h) * scroll / max_scroll.max(1)
+        };
+        let thumb_y = inner_area.y + top_space;
+        // track
+        frame.render_widget(Block::new().bg(theme.bg), track_area);
+        // thumb
+        let thumb_char = state.ui.scrollbar_thumb.as_str();
+        let thumb = Paragraph::new(Span::styled(thumb_char, Style::new().fg(theme.primary)));
+        for y in 0..thumb_h {
+            let cell = Rect::new(track_x, thumb_y + y, 1, 1);
+            frame.render_widget(thumb.clone(), cell);
+        }
+    }
+    state.log_areas = log_areas;
+    state.link_targets = link_targets;
+}
+
+/// A pane without focus: its log as it was left, dimmer title, no mouse.
+fn render_other_pane(frame: &mut Frame, area: Rect, state: &State, index: usize, title: String) {
+    let theme = &state.theme;
+    let block = Block::new()
+        .borders(Borders::TOP)
+        .border_style(Style::new().fg(theme.comment))
+        .title(Span::styled(title, Style::new().fg(theme.comment)));
+    frame.render_widget(block, area);
+    let pane = &state.panes[index];
+    let view = LogView {
+        logs: &pane.command_log,
+        scroll_offset: pane.scroll_offset,
+        hovered: None,
+        search: None,
+    };
+    let inner_area = area.inner(Margin {
+        vertical: 1,
+        horizontal: 0,
+    });
+    render_log_blocks(frame, inner_area, &view, state, &mut Vec::new());
+}
+
+/// Draws the entries of `view` bottom-up into `inner_area`, adding the
+/// links on screen to `link_targets`. Returns where each entry landed.
+fn render_log_blocks(
+    frame: &mut Frame,
+    inner_area: Rect,
+    view: &LogView,
+    state: &State,
+    link_targets: &mut Vec<(Rect, String)>,
+) -> Vec<(Rect, usize)> {
+    let mut log_areas = Vec::new();
+    let theme = &state.theme;
     let mut current_y = inner_area.height;
 
     // Determine which log entry should be highlighted and where to end rendering (scrolling)
-    let total_logs = state.command_log.len();
-    let active_log_index = if state.scroll_offset > 0 {
+    let total_logs = view.logs.len();
+    let active_log_index = if view.scroll_offset > 0 {
         Some(
             total_logs
                 .saturating_sub(1)
-                .saturating_sub(state.scroll_offset),
+                .saturating_sub(view.scroll_offset),
         )
     } else {
         None
@@ -80,19 +413,40 @@ fn render_output_log(frame: &mut Frame, area: Rect, state: &State) {
     // Implement real scrolling: start from an end index based on scroll_offset and render upwards.
     let mut i_opt = total_logs
         .checked_sub(1)
-        .map(|last| last.saturating_sub(state.scroll_offset));
+        .map(|last| last.saturating_sub(view.scroll_offset));
     while let Some(i) = i_opt {
-        let log = &state.command_log[i];
-        let mut block_lines = build_log_block(log, &state.theme);
+        let log = &view.logs[i];
+        let search = view.search;
+        // Output lines above the remaining rows would be cut off anyway.
+        let skip = log.output.len().saturating_sub(current_y as usize);
+        // Compact style folds finished entries; the highlighted and the
+        // newest one stay open.
+        let folded = state.ui.compact_log
+            && !log.is_running
+            && active_log_index != Some(i)
+            && i + 1 != total_logs;
+        let mut block_lines = if folded {
+            folded_log_line(log, &state.theme, &state.ui, state.low_bandwidth)
+        } else {
+            build_log_block(log, i, &state.theme, &state.ui, state.low_bandwidth, search, skip)
+        };
         let block_height = block_lines.len() as u16;
+        if block_height as usize + skip > inner_area.height as usize
+            && let Some(footer) = block_lines.iter_mut().rev().nth(1)
+        {
+            footer.push_span(Span::styled(" Alt+P pager", Style::new().fg(theme.comment)));
+        }
 
         // Highlight the active preview block if it matches our calculated index.
         if let Some(active_idx) = active_log_index
             && i == active_idx
         {
+            // Search matches keep their own colors.
             for line in &mut block_lines {
                 for span in &mut line.spans {
-                    span.style = span.style.fg(theme.accent);
+                    if span.style.bg.is_none() {
+                        span.style = span.style.fg(theme.accent);
+                    }
                 }
             }
         }
@@ -106,8 +460,17 @@ fn render_output_log(frame: &mut Frame, area: Rect, state: &State) {
                 inner_area.width,
                 block_height,
             );
-            let paragraph = Paragraph::new(block_lines).wrap(Wrap { trim: false });
+            let mut paragraph = Paragraph::new(block_lines).wrap(Wrap { trim: false });
+            if view.hovered == Some(i) {
+                paragraph = paragraph.style(Style::new().bg(hover_bg(theme)));
+            }
             frame.render_widget(paragraph, block_area);
+            if !folded {
+                render_rich_output(frame, block_area, 0, log, theme, state.low_bandwidth);
+                let links = block_links(log, skip);
+                map_links(frame.buffer_mut(), block_area, &links, link_targets);
+            }
+            log_areas.push((block_area, i));
         } else {
             // Render only the bottom part of the block that fits the remaining space.
             let visible_height = current_y;
@@ -119,8 +482,18 @@ fn render_output_log(frame: &mut Frame, area: Rect, state: &State) {
             let visible_lines: Vec<Line> = block_lines[start_index..].to_vec();
             let block_area =
                 Rect::new(inner_area.x, inner_area.y, inner_area.width, visible_height);
-            let paragraph = Paragraph::new(visible_lines).wrap(Wrap { trim: false });
+            let mut paragraph = Paragraph::new(visible_lines).wrap(Wrap { trim: false });
+            if view.hovered == Some(i) {
+                paragraph = paragraph.style(Style::new().bg(hover_bg(theme)));
+            }
             frame.render_widget(paragraph, block_area);
+            if !folded {
+                render_rich_output(frame, block_area, start_index, log, theme, state.low_bandwidth);
+                let mut links = block_links(log, skip);
+                links.retain(|(line, _, _)| *line >= start_index);
+                map_links(frame.buffer_mut(), block_area, &links, link_targets);
+            }
+            log_areas.push((block_area, i));
             break;
         }
 
@@ -129,34 +502,120 @@ fn render_output_log(frame: &mut Frame, area: Rect, state: &State) {
         }
         i_opt = Some(i - 1);
     }
-    // Draw a minimal scrollbar track on the right if there are logs
-    if total_logs > 0 {
-        let track_x = area.right().saturating_sub(1);
-        let track_area = Rect::new(track_x, inner_area.y, 1, inner_area.height);
-        // Compute thumb size relative to number of blocks (simple heuristic)
-        let min_thumb = 1u16;
-        let thumb_h = (inner_area.height / 4).max(min_thumb);
-        let max_scroll = total_logs.saturating_sub(1) as u16;
-        let scroll = state.scroll_offset.min(max_scroll as usize) as u16;
-        let top_space = if max_scroll == 0 {
-            0
-        } else {
-            (inner_area.height - thumb_h) * scroll / max_scroll.max(1)
+    log_areas
+}
+
+/// Draws the widgets of a builtin's rich output over the rows that
+/// `build_log_block` left blank. `skip` block lines are scrolled out of
+/// the top of `area`.
+fn render_rich_output(
+    frame: &mut Frame,
+    area: Rect,
+    skip: usize,
+    log: &CommandLog,
+    theme: &Theme,
+    plain: bool,
+) {
+    let Some(RichOutput::Disk(mounts)) = &log.rich else {
+        return;
+    };
+    // After the entry header, the border and the column titles.
+    const FIRST_ROW: usize = 2;
+    const BORDER: u16 = 3;
+    let x = area.x + BORDER + disk::mount_width(mounts) as u16 + 1;
+    let width = (disk::GAUGE_WIDTH as u16).min(area.right().saturating_sub(x));
+    if width == 0 {
+        return;
+    }
+    for (k, mount) in mounts.iter().enumerate() {
+        let Some(row) = (FIRST_ROW + k).checked_sub(skip) else {
+            continue;
         };
-        let thumb_y = inner_area.y + top_space;
-        // track
-        frame.render_widget(Block::new().bg(theme.bg), track_area);
-        // thumb
-        let thumb_char = state.ui.scrollbar_thumb.as_str();
-        let thumb = Paragraph::new(Span::styled(thumb_char, Style::new().fg(theme.primary)));
-        for y in 0..thumb_h {
-            let cell = Rect::new(track_x, thumb_y + y, 1, 1);
-            frame.render_widget(thumb.clone(), cell);
+        if row >= area.height as usize {
+            break;
+        }
+        let ratio = mount.ratio().clamp(0.0, 1.0);
+        let color = match ratio {
+            r if r >= 0.9 => theme.error,
+            r if r >= 0.75 => theme.warn,
+            _ => theme.success,
+        };
+        let gauge = LineGauge::default()
+            .ratio(ratio)
+            .label(format!("{:>3.0}%", ratio * 100.0))
+            .line_set(if plain { symbols::line::NORMAL } else { symbols::line::THICK })
+            .filled_style(Style::new().fg(color))
+            .unfilled_style(Style::new().fg(theme.comment));
+        frame.render_widget(gauge, Rect::new(x, area.y + row as u16, width, 1));
+    }
+}
+
+fn capture_text(buf: &Buffer, area: Rect) -> ScreenText {
+    let rows = (area.top()..area.bottom())
+        .map(|y| {
+            let mut covered = 0;
+            (area.left()..area.right())
+                .map(|x| {
+                    // The cells under the right half of a wide character
+                    // hold a filler space.
+                    if covered > 0 {
+                        covered -= 1;
+                        return String::new();
+                    }
+                    let symbol = buf[(x, y)].symbol();
+                    covered = symbol.width().saturating_sub(1);
+                    symbol.to_string()
+                })
+                .collect()
+        })
+        .collect();
+    ScreenText { area, rows }
+}
+
+/// Reverses the cells from `anchor` to `head`, row by row.
+fn highlight_selection(buf: &mut Buffer, area: Rect, anchor: Position, head: Position) {
+    let (start, end) = if (anchor.y, anchor.x) <= (head.y, head.x) {
+        (anchor, head)
+    } else {
+        (head, anchor)
+    };
+    for y in start.y.max(area.top())..=end.y.min(area.bottom().saturating_sub(1)) {
+        let first = if y == start.y { start.x } else { area.left() };
+        let last = if y == end.y { end.x } else { area.right().saturating_sub(1) };
+        for x in first.max(area.left())..=last.min(area.right().saturating_sub(1)) {
+            buf[(x, y)].modifier.insert(Modifier::REVERSED);
+        }
+    }
+}
+
+/// A background one step off the theme's, for the block under the mouse.
+fn hover_bg(theme: &Theme) -> Color {
+    match (theme.bg, theme.fg) {
+        (Color::Rgb(br, bg, bb), Color::Rgb(fr, fg, fb)) => {
+            let mix = |b: u8, f: u8| (b as u16 * 9 / 10 + f as u16 / 10) as u8;
+            Color::Rgb(mix(br, fr), mix(bg, fg), mix(bb, fb))
         }
+        _ => theme.comment,
     }
 }
 
-fn build_log_block<'a>(log: &'a CommandLog, theme: &'a Theme) -> Vec<Line<'a>> {
+/// `fancy` normally, `plain` in low-bandwidth mode, which avoids emoji and
+/// Nerd Font glyphs that are slow to send and often mis-measured.
+fn glyph(low_bandwidth: bool, fancy: &'static str, plain: &'static str) -> &'static str {
+    if low_bandwidth { plain } else { fancy }
+}
+
+/// Lines of log entry `index`. Matches of an open log search are marked,
+/// the current one more strongly.
+fn build_log_block<'a>(
+    log: &'a CommandLog,
+    index: usize,
+    theme: &'a Theme,
+    ui: &UiConfig,
+    plain: bool,
+    search: Option<&LogSearch>,
+    skip: usize,
+) -> Vec<Line<'a>> {
     let mut lines = Vec::new();
     let is_empty_prompt = log.command.is_empty() && log.output.is_empty();
 
@@ -170,7 +629,7 @@ fn build_log_block<'a>(log: &'a CommandLog, theme: &'a Theme) -> Vec<Line<'a>> {
     }
 
     let cwd_str = log.cwd.display().to_string();
-    lines.push(Line::from(vec![
+    let mut header = Line::from(vec![
         Span::styled("╭───", Style::new().fg(theme.comment)),
         Span::styled("❯ ", Style::new().fg(theme.accent)),
         Span::styled(
@@ -183,60 +642,114 @@ fn build_log_block<'a>(log: &'a CommandLog, theme: &'a Theme) -> Vec<Line<'a>> {
             cwd_str,
             Style::new().fg(theme.comment).add_modifier(Modifier::DIM),
         ),
-        Span::styled(")", Style::new().fg(theme.comment)),
-    ]));
+    ]);
+    if let Some(branch) = &log.branch {
+        header.push_span(Span::styled(" on ", Style::new().fg(theme.comment)));
+        header.push_span(Span::styled(branch, Style::new().fg(theme.primary)));
+    }
+    header.push_span(Span::styled(")", Style::new().fg(theme.comment)));
+    if log.unseen {
+        header.push_span(Span::raw("  "));
+        header.push_span(unseen_marker(theme, plain));
+    }
+    if ui.timestamps.header()
+        && let Some(started) = &log.started_wall
+    {
+        header.push_span(Span::raw("  "));
+        header.push_span(Span::styled(
+            ui.timestamp_format().format(started),
+            Style::new().fg(theme.comment),
+        ));
+    }
+    if let Some(note) = &log.note {
+        header.push_span(Span::raw("  "));
+        header.push_span(Span::styled(
+            format!("{} {note}", glyph(plain, "✎", "note:")),
+            Style::new().fg(theme.warn).add_modifier(Modifier::ITALIC),
+        ));
+    }
+    lines.push(header);
 
-    if !log.output.is_empty() {
-        for output_line in log.output.lines() {
-            let content = if let Some(stderr) = output_line.strip_prefix("[stderr] ") {
-                Span::styled(
-                    stderr,
-                    Style::new().fg(theme.error).add_modifier(Modifier::ITALIC),
-                )
-            } else {
-                Span::raw(output_line).fg(theme.fg)
-            };
+    if let Some(RichOutput::Disk(mounts)) = &log.rich {
+        // Blank gauge columns; `render_rich_output` draws the gauges.
+        let width = disk::mount_width(mounts);
+        let gauge = " ".repeat(disk::GAUGE_WIDTH);
+        lines.push(Line::from(vec![
+            Span::styled("│  ", Style::new().fg(theme.comment)),
+            Span::styled(disk::header(width), Style::new().fg(theme.comment)),
+        ]));
+        for mount in mounts {
+            lines.push(Line::from(vec![
+                Span::styled("│  ", Style::new().fg(theme.comment)),
+                Span::styled(disk::row(mount, width, &gauge), Style::new().fg(theme.fg)),
+            ]));
+        }
+    } else if !log.output.is_empty() {
+        if let Some(note) = dropped_note(log) {
             lines.push(Line::from(vec![
                 Span::styled("│  ", Style::new().fg(theme.comment)),
-                content,
+                Span::styled(note, Style::new().fg(theme.comment).add_modifier(Modifier::ITALIC)),
             ]));
         }
+        let current = search.and_then(LogSearch::current_match);
+        for (k, output_line) in log.output.lines().enumerate().skip(skip) {
+            let style = if output_line.starts_with("[stderr] ") {
+                Style::new().fg(theme.error).add_modifier(Modifier::ITALIC)
+            } else {
+                Style::new().fg(theme.fg)
+            };
+            let text = state::displayed_line(output_line);
+            let mut spans = vec![Span::styled("│  ", Style::new().fg(theme.comment))];
+            let is_current = current == Some((index, k));
+            let link_ranges: Vec<Range<usize>> = links::line_links(&log.links, k, text)
+                .into_iter()
+                .map(|(range, _)| range)
+                .collect();
+            spans.extend(output_spans(text, style, search, is_current, &link_ranges, theme));
+            lines.push(Line::from(spans));
+        }
     }
 
     if log.is_running {
+        let mut style = Style::new().fg(theme.warn);
+        if !plain {
+            style = style.add_modifier(Modifier::SLOW_BLINK);
+        }
         lines.push(Line::from(vec![
             Span::styled("│  ", Style::new().fg(theme.comment)),
-            Span::styled(
-                "⚙️  Running...",
-                Style::new()
-                    .fg(theme.warn)
-                    .add_modifier(Modifier::SLOW_BLINK),
-            ),
+            Span::styled(glyph(plain, "⚙️  Running...", "Running..."), style),
         ]));
-    } else if log.exit_code.is_some() || log.duration_ms.is_some() {
-        let code_text = log
-            .exit_code
-            .map(|c| format!("exit={}", c))
-            .unwrap_or_else(|| "exit=?".into());
+    } else if log.exit_code.is_some() || log.signal.is_some() || log.duration_ms.is_some() {
+        let (code_text, code_style) = exit_status(log, theme);
+        let slow = log.duration_ms.is_some_and(|ms| ui.is_slow(ms));
         let dur_text = log
             .duration_ms
-            .map(|d| format!("time={}ms", d))
+            .map(|d| format!("{}time={}ms", if slow { slow_marker(plain) } else { "" }, d))
             .unwrap_or_default();
         let mut meta = vec![
             Span::styled("│  ", Style::new().fg(theme.comment)),
-            Span::styled("⏱ ", Style::new().fg(theme.comment)),
-            Span::styled(
-                code_text,
-                if log.exit_code == Some(0) {
-                    Style::new().fg(Color::Green)
-                } else {
-                    Style::new().fg(theme.error)
-                },
-            ),
+            Span::styled(glyph(plain, "⏱ ", ""), Style::new().fg(theme.comment)),
+            Span::styled(code_text, code_style),
         ];
         if !dur_text.is_empty() {
             meta.push(Span::raw("  "));
-            meta.push(Span::styled(dur_text, Style::new().fg(theme.fg)));
+            let style = if slow {
+                Style::new().fg(theme.warn).add_modifier(Modifier::BOLD)
+            } else {
+                Style::new().fg(theme.fg)
+            };
+            meta.push(Span::styled(dur_text, style));
+        }
+        if ui.timestamps.meta()
+            && let Some(started) = log.started_wall
+        {
+            let ms = log.duration_ms.unwrap_or(0).min(i64::MAX as u128) as i64;
+            let finished = started + chrono::Duration::milliseconds(ms);
+            meta.push(Span::raw("  "));
+            meta.push(Span::styled(
+                format!("done {}", ui.timestamp_format().format(&finished)),
+                Style::new().fg(theme.comment),
+            ));
         }
         lines.push(Line::from(meta));
     }
@@ -250,105 +763,535 @@ fn build_log_block<'a>(log: &'a CommandLog, theme: &'a Theme) -> Vec<Line<'a>> {
     lines
 }
 
-fn render_status_bar(frame: &mut Frame, area: Rect, state: &State) {
-    let status_layout =
-        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).split(area);
-    let theme = &state.theme;
-    let version = env!("CARGO_PKG_VERSION");
-    let git = state
-        .git_branch
-        .as_deref()
-        .map(|b| format!(" on  {}", b))
-        .unwrap_or_default();
-    let brand = Paragraph::new(Line::from(vec![
-        Span::styled(
-            " HALO ",
-            Style::new()
-                .fg(theme.bg)
-                .bg(theme.primary)
-                .add_modifier(Modifier::BOLD),
-        ),
-        Span::styled(
-            format!(" v{}{} ", version, git),
-            Style::new().fg(theme.accent),
-        ),
-    ]))
-    .alignment(Alignment::Left);
-    let total_logs = state.command_log.len();
-    let pos = if state.scroll_offset > 0 {
-        total_logs
-            .saturating_sub(1)
-            .saturating_sub(state.scroll_offset)
-            .saturating_add(1)
-    } else {
-        total_logs
+/// `exit=N` or the signal, in green, red, or bold for a crash.
+fn exit_status(log: &CommandLog, theme: &Theme) -> (String, Style) {
+    let text = match (log.exit_code, log.signal) {
+        (Some(code), _) => format!("exit={code}"),
+        (None, Some(signal)) => command::describe_signal(signal),
+        (None, None) => "exit=?".into(),
     };
-    let right_text = Line::from(vec![
-        Span::styled("📁 ", Style::new().fg(theme.accent)),
-        Span::styled(state.cwd.display().to_string(), Style::new().fg(theme.accent)),
-        Span::raw("  |  "),
-        Span::styled("📄 ", Style::new().fg(theme.accent)),
-        Span::styled(format!("{}/{} ", pos, total_logs), Style::new().fg(theme.accent)),
-    ]);
-    let cwd = Paragraph::new(right_text).alignment(Alignment::Right);
-    frame.render_widget(brand, status_layout[0]);
-    frame.render_widget(cwd, status_layout[1]);
+    // Crashes stand out from ordinary failures.
+    let style = match (log.exit_code, log.signal) {
+        (Some(0), _) => Style::new().fg(Color::Green),
+        (None, Some(_)) => Style::new().fg(theme.accent).add_modifier(Modifier::BOLD),
+        _ => Style::new().fg(theme.error),
+    };
+    (text, style)
 }
 
-fn render_input_box(frame: &mut Frame, area: Rect, state: &State) {
-    let is_previewing = state.scroll_offset > 0;
+/// Prefix of a duration over `[ui] slow_command_ms`.
+fn slow_marker(plain: bool) -> &'static str {
+    glyph(plain, "⚠ ", "! ")
+}
 
-    let theme = &state.theme;
-    let (text, style, border_style, title_span) = if is_previewing {
-        // The command to preview is at (len - 1 - scroll_offset), saturating at 0.
-        let log_index = state
-            .command_log
-            .len()
-            .saturating_sub(1)
-            .saturating_sub(state.scroll_offset);
-        let command_text = state
-            .command_log
-            .get(log_index)
-            .map_or("", |log| &log.command);
+/// Badge on an entry that finished while the user was away.
+fn unseen_marker(theme: &Theme, plain: bool) -> Span<'static> {
+    Span::styled(
+        format!(" {} finished ", glyph(plain, "●", "*")),
+        Style::new().fg(theme.bg).bg(theme.accent).add_modifier(Modifier::BOLD),
+    )
+}
 
-        (
-            Line::from(vec![
-                Span::styled(format!("{}  ", state.ui.prompt), Style::new()),
-                Span::styled(command_text, Style::new()),
-            ]),
-            Style::new().fg(theme.accent).add_modifier(Modifier::BOLD),
-            Style::new().fg(theme.accent),
-            {
-                const DECOR: &str = "────────────";
-                Line::from(vec![
-                    Span::styled(DECOR, Style::new().fg(theme.accent)),
-                    Span::styled(
-                        "[[[ HISTORY PREVIEW ]]]",
+/// A finished entry in the compact log style: prompt, command, exit status,
+/// duration and how much output is folded away. Empty prompts vanish.
+fn folded_log_line<'a>(
+    log: &'a CommandLog,
+    theme: &Theme,
+    ui: &UiConfig,
+    plain: bool,
+) -> Vec<Line<'a>> {
+    if log.command.is_empty() && log.output.is_empty() {
+        return Vec::new();
+    }
+    let mut line = Line::from(vec![
+        Span::styled("├ ", Style::new().fg(theme.comment)),
+        Span::styled("❯ ", Style::new().fg(theme.accent)),
+        Span::styled(&log.command, Style::new().fg(theme.fg).add_modifier(Modifier::BOLD)),
+    ]);
+    let comment = Style::new().fg(theme.comment);
+    if log.exit_code.is_some() || log.signal.is_some() {
+        let (text, style) = exit_status(log, theme);
+        line.push_span(Span::raw("  "));
+        line.push_span(Span::styled(text, style));
+    }
+    if let Some(ms) = log.duration_ms {
+        if ui.is_slow(ms) {
+            line.push_span(Span::styled(
+                format!("  {}{}", slow_marker(plain), summary::format_ms(ms)),
+                Style::new().fg(theme.warn).add_modifier(Modifier::BOLD),
+            ));
+        } else {
+            line.push_span(Span::styled(format!("  {}", summary::format_ms(ms)), comment));
+        }
+    }
+    if log.unseen {
+        line.push_span(Span::raw("  "));
+        line.push_span(unseen_marker(theme, plain));
+    }
+    let lines = log.output.len() + log.output.dropped();
+    if lines > 0 {
+        let unit = if lines == 1 { "line" } else { "lines" };
+        line.push_span(Span::styled(format!("  {lines} {unit}"), comment));
+    }
+    if ui.timestamps.header()
+        && let Some(started) = &log.started_wall
+    {
+        line.push_span(Span::styled(
+            format!("  {}", ui.timestamp_format().format(started)),
+            comment,
+        ));
+    }
+    if let Some(note) = &log.note {
+        line.push_span(Span::styled(
+            format!("  {} {note}", glyph(plain, "✎", "note:")),
+            Style::new().fg(theme.warn).add_modifier(Modifier::ITALIC),
+        ));
+    }
+    vec![line]
+}
+
+/// Output line `text` in `style`, with matches of `search` marked and
+/// `links` underlined; `is_current` picks out the line the search is on.
+fn output_spans<'a>(
+    text: &'a str,
+    style: Style,
+    search: Option<&LogSearch>,
+    is_current: bool,
+    links: &[Range<usize>],
+    theme: &Theme,
+) -> Vec<Span<'a>> {
+    let marks = search.map(|s| s.ranges(text)).unwrap_or_default();
+    if marks.is_empty() && links.is_empty() {
+        return vec![Span::styled(text, style)];
+    }
+    let mark = if is_current {
+        Style::new().fg(theme.bg).bg(theme.accent).add_modifier(Modifier::BOLD)
+    } else {
+        Style::new().fg(theme.bg).bg(theme.warn)
+    };
+    let mut cuts: Vec<usize> = marks
+        .iter()
+        .chain(links)
+        .flat_map(|range| [range.start, range.end])
+        .chain([0, text.len()])
+        .collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+    let inside = |ranges: &[Range<usize>], piece: &[usize]| {
+        ranges.iter().any(|r| r.start <= piece[0] && piece[1] <= r.end)
+    };
+    cuts.windows(2)
+        .map(|piece| {
+            let mut piece_style = if inside(&marks, piece) { mark } else { style };
+            if inside(links, piece) {
+                piece_style = piece_style.add_modifier(Modifier::UNDERLINED);
+            }
+            Span::styled(&text[piece[0]..piece[1]], piece_style)
+        })
+        .collect()
+}
+
+/// `… 4210 earlier lines dropped`, for output that outgrew its ring.
+fn dropped_note(log: &CommandLog) -> Option<String> {
+    let dropped = log.output.dropped();
+    if dropped == 0 {
+        return None;
+    }
+    Some(match log.output.spill_path() {
+        Some(path) => format!("… {dropped} earlier lines in {}", path.display()),
+        None => format!("… {dropped} earlier lines dropped"),
+    })
+}
+
+/// Link texts and targets of `log`'s output in reading order, with the
+/// block line each is on. `skip` is as for `build_log_block`.
+fn block_links(log: &CommandLog, skip: usize) -> Vec<(usize, String, String)> {
+    if log.rich.is_some() {
+        return Vec::new();
+    }
+    // The header, and the dropped-lines note, come first.
+    let first = 1 + usize::from(dropped_note(log).is_some());
+    let mut found = Vec::new();
+    for (k, line) in log.output.lines().enumerate().skip(skip) {
+        let text = state::displayed_line(line);
+        for (range, url) in links::line_links(&log.links, k, text) {
+            found.push((first + k - skip, text[range].to_string(), url));
+        }
+    }
+    found
+}
+
+/// Records where each link of a block landed on screen, by walking the
+/// underlined cell runs of `area` in reading order. A wrapped link gets
+/// one target per row.
+fn map_links(
+    buf: &Buffer,
+    area: Rect,
+    links: &[(usize, String, String)],
+    targets: &mut Vec<(Rect, String)>,
+) {
+    let mut links = links.iter();
+    let Some(mut current) = links.next() else {
+        return;
+    };
+    let mut remaining = current.1.width();
+    for y in area.top()..area.bottom() {
+        let mut x = area.left();
+        while x < area.right() {
+            if !buf[(x, y)].modifier.contains(Modifier::UNDERLINED) {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < area.right()
+                && buf[(x, y)].modifier.contains(Modifier::UNDERLINED)
+                && usize::from(x - start) < remaining
+            {
+                x += 1;
+            }
+            targets.push((Rect::new(start, y, x - start, 1), current.2.clone()));
+            remaining -= usize::from(x - start);
+            if remaining == 0 {
+                let Some(next) = links.next() else {
+                    return;
+                };
+                current = next;
+                remaining = current.1.width();
+            }
+        }
+    }
+}
+
+fn render_status_bar(frame: &mut Frame, area: Rect, state: &State) {
+    let status_layout =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).split(area);
+    let theme = &state.theme;
+    let brand_spans = state.ui.status_format.render(
+        theme,
+        Style::new().fg(theme.accent),
+        &|name| prompt_segment(state, name),
+    );
+    let brand = Paragraph::new(Line::from(brand_spans)).alignment(Alignment::Left);
+    let total_logs = state.command_log.len();
+    let pos = if state.scroll_offset > 0 {
+        total_logs
+            .saturating_sub(1)
+            .saturating_sub(state.scroll_offset)
+            .saturating_add(1)
+    } else {
+        total_logs
+    };
+    let mut right_spans = Vec::new();
+    if let Some((notice, _)) = &state.notice {
+        right_spans.push(Span::styled(notice.as_str(), Style::new().fg(theme.primary)));
+        right_spans.push(Span::raw("  |  "));
+    }
+    if let Some(i) = state.hovered_log
+        && let Some(log) = state.command_log.get(i)
+    {
+        let mut hover = format!("#{}", i + 1);
+        if let Some(started) = &log.started_wall {
+            hover.push_str(&format!(" @ {}", state.ui.time_format.format(started)));
+        }
+        right_spans.push(Span::styled(hover, Style::new().fg(theme.primary)));
+        right_spans.push(Span::raw("  |  "));
+    }
+    if let Some(clock) = &state.clock {
+        right_spans.push(Span::styled(clock.as_str(), Style::new().fg(theme.comment)));
+        right_spans.push(Span::raw("  |  "));
+    }
+    right_spans.extend([
+        Span::styled(glyph(state.low_bandwidth, "📁 ", ""), Style::new().fg(theme.accent)),
+        Span::styled(state.cwd.display().to_string(), Style::new().fg(theme.accent)),
+        Span::raw("  |  "),
+        Span::styled(glyph(state.low_bandwidth, "📄 ", "#"), Style::new().fg(theme.accent)),
+        Span::styled(format!("{}/{} ", pos, total_logs), Style::new().fg(theme.accent)),
+    ]);
+    let right_text = Line::from(right_spans);
+    let cwd = Paragraph::new(right_text).alignment(Alignment::Right);
+    frame.render_widget(brand, status_layout[0]);
+    frame.render_widget(cwd, status_layout[1]);
+}
+
+/// What a template segment holds, in its default style; nothing when there
+/// is nothing to show.
+fn prompt_segment(state: &State, name: &str) -> Vec<Span<'static>> {
+    let theme = &state.theme;
+    let accent = Style::new().fg(theme.accent);
+    let one = |text: String, style: Style| vec![Span::styled(text, style)];
+    match name {
+        "symbol" => one(state.ui.prompt.clone(), prompt_style(state)),
+        "cwd" => one(state::tilde_path(&state.cwd), accent),
+        "dir" => {
+            let path = state::tilde_path(&state.cwd);
+            match path.rsplit_once('/') {
+                Some((_, last)) if !last.is_empty() => one(last.to_string(), accent),
+                _ => one(path, accent),
+            }
+        }
+        "user" => one(state.username.clone(), accent.add_modifier(Modifier::BOLD)),
+        "host" => one(state.hostname.clone(), accent),
+        "git" => match &state.git_branch {
+            Some(branch) => one(
+                format!("{}{branch}", glyph(state.low_bandwidth, " ", "")),
+                accent,
+            ),
+            None => Vec::new(),
+        },
+        "git_status" if state.git_branch.is_some() => git_indicators(state),
+        "git_unavailable" if state.git_unavailable => one(
+            "git: unavailable".to_string(),
+            Style::new().fg(theme.comment).add_modifier(Modifier::DIM),
+        ),
+        "exit_status" if state.last_exit_code != 0 => one(
+            state.last_exit_code.to_string(),
+            Style::new().fg(theme.error).add_modifier(Modifier::BOLD),
+        ),
+        "jobs" => match state.command_log.iter().filter(|log| log.is_running).count() {
+            0 => Vec::new(),
+            running => one(running.to_string(), Style::new().fg(theme.warn)),
+        },
+        "duration" => match state.command_log.iter().rev().find_map(|log| log.duration_ms) {
+            Some(ms) if state.ui.is_slow(ms) => one(
+                summary::format_ms(ms),
+                Style::new().fg(theme.warn).add_modifier(Modifier::BOLD),
+            ),
+            Some(ms) => one(summary::format_ms(ms), Style::new().fg(theme.comment)),
+            None => Vec::new(),
+        },
+        "time" => one(state.ui.time_format.now(), Style::new().fg(theme.comment)),
+        "version" => one(env!("CARGO_PKG_VERSION").to_string(), accent),
+        _ => Vec::new(),
+    }
+}
+
+/// The prompt symbol's style: red after a failure.
+fn prompt_style(state: &State) -> Style {
+    let color = if state.last_exit_code == 0 {
+        state.theme.primary
+    } else {
+        state.theme.error
+    };
+    Style::new().fg(color).add_modifier(Modifier::BOLD)
+}
+
+/// `prompt_format`, drawn before the input.
+fn prompt_spans(state: &State) -> Vec<Span<'static>> {
+    state
+        .ui
+        .prompt_format
+        .render(&state.theme, prompt_style(state), &|name| prompt_segment(state, name))
+}
+
+/// `rprompt_format`, if it is set, has something to show and fits after
+/// what has been typed with a cell to spare.
+fn right_prompt(state: &State, input_width: usize) -> Option<Vec<Span<'static>>> {
+    let template = state.ui.rprompt_format.as_ref()?;
+    let spans = template.render(
+        &state.theme,
+        Style::new().fg(state.theme.comment),
+        &|name| prompt_segment(state, name),
+    );
+    let width: usize = spans.iter().map(Span::width).sum();
+    let typed = state.input_buffer.width();
+    (width > 0 && state.input_scroll == 0 && typed + 1 + width <= input_width).then_some(spans)
+}
+
+/// `[ui.git]` counts after the branch, e.g. `⇡2 +1 !3`; zeros are left out.
+fn git_indicators(state: &State) -> Vec<Span<'static>> {
+    let theme = &state.theme;
+    let config = &state.ui.git;
+    let status = &state.git_status;
+    let low = state.low_bandwidth;
+    [
+        (config.ahead_behind, glyph(low, "⇡", "^"), status.ahead, theme.primary),
+        (config.ahead_behind, glyph(low, "⇣", "v"), status.behind, theme.primary),
+        (config.staged, "+", status.staged, theme.success),
+        (config.unstaged, "!", status.unstaged, theme.warn),
+        (config.untracked, "?", status.untracked, theme.comment),
+        (config.stash, "$", status.stashes, theme.accent),
+    ]
+    .into_iter()
+    .filter(|&(on, _, count, _)| on && count > 0)
+    .enumerate()
+    .flat_map(|(i, (_, symbol, count, color))| {
+        let gap = (i > 0).then(|| Span::raw(" "));
+        gap.into_iter()
+            .chain([Span::styled(format!("{symbol}{count}"), Style::new().fg(color))])
+    })
+    .collect()
+}
+
+/// One cell per tab, numbered for Alt+N: its directory, and a dot while
+/// it has commands running.
+fn render_tab_bar(frame: &mut Frame, area: Rect, state: &State) {
+    if area.height == 0 {
+        return;
+    }
+    let theme = &state.theme;
+    let mut spans = vec![Span::raw(" ")];
+    for (index, tab) in state.tabs.iter().enumerate() {
+        let active = index == state.active_tab;
+        let (cwd, logs, panes) = if active {
+            (&state.cwd, &state.command_log, &state.panes)
+        } else {
+            (&tab.cwd, &tab.command_log, &tab.panes)
+        };
+        let busy = logs.iter().any(|log| log.is_running)
+            || panes.iter().any(|p| p.command_log.iter().any(|log| log.is_running));
+        let style = if active {
+            Style::new().fg(theme.bg).bg(theme.primary).add_modifier(Modifier::BOLD)
+        } else {
+            Style::new().fg(theme.comment)
+        };
+        let dot = if busy { " ●" } else { "" };
+        spans.push(Span::styled(
+            format!(" {} {}{dot} ", index + 1, Tab::label(cwd)),
+            style,
+        ));
+        spans.push(Span::raw(" "));
+    }
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+/// One cell per recent command: success, failure, or still running.
+fn render_status_strip(frame: &mut Frame, area: Rect, state: &mut State) {
+    state.status_strip = None;
+    if area.height == 0 {
+        return;
+    }
+    let theme = &state.theme;
+    let area = Rect {
+        x: area.x + 1,
+        width: area.width.saturating_sub(2),
+        ..area
+    };
+    let finished: Vec<usize> = state
+        .command_log
+        .iter()
+        .enumerate()
+        .filter(|(_, log)| log.is_running || log.exit_code.is_some() || log.signal.is_some())
+        .map(|(i, _)| i)
+        .collect();
+    let shown = finished.len().min(state.ui.status_strip).min(area.width as usize);
+    let indices = finished[finished.len() - shown..].to_vec();
+    let previewed = state
+        .command_log
+        .len()
+        .checked_sub(1 + state.scroll_offset)
+        .filter(|_| state.scroll_offset > 0);
+    let cells: Vec<Span> = indices
+        .iter()
+        .map(|&i| {
+            let log = &state.command_log[i];
+            let color = match (log.exit_code, log.signal) {
+                _ if log.is_running => theme.warn,
+                (Some(0), _) => theme.success,
+                (None, Some(_)) => theme.accent,
+                _ => theme.error,
+            };
+            let mut style = Style::new().fg(color);
+            if previewed == Some(i) {
+                style = style.bg(theme.comment);
+            }
+            Span::styled("▌", style)
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(Line::from(cells)), area);
+    state.status_strip = Some((area, indices));
+}
+
+fn render_input_box(frame: &mut Frame, area: Rect, state: &State, input_width: usize) {
+    let is_previewing = state.scroll_offset > 0;
+    let right = right_prompt(state, input_width).filter(|_| !is_previewing);
+    let right_width: usize = right.iter().flatten().map(Span::width).sum();
+    // The suggestion stops short of the right prompt, too.
+    let input_width = match right {
+        Some(_) => input_width - right_width - 1,
+        None => input_width,
+    };
+
+    let theme = &state.theme;
+    let (text, style, border_style, title_span) = if is_previewing {
+        // The command to preview is at (len - 1 - scroll_offset), saturating at 0.
+        let log_index = state
+            .command_log
+            .len()
+            .saturating_sub(1)
+            .saturating_sub(state.scroll_offset);
+        let command_text = state
+            .command_log
+            .get(log_index)
+            .map_or("", |log| &log.command);
+
+        (
+            Line::from(
+                // Plain, so the preview's own style shows through.
+                prompt_spans(state)
+                    .into_iter()
+                    .map(|span| Span::raw(span.content))
+                    .chain([Span::raw(command_text)])
+                    .collect::<Vec<_>>(),
+            ),
+            Style::new().fg(theme.accent).add_modifier(Modifier::BOLD),
+            Style::new().fg(theme.accent),
+            {
+                const DECOR: &str = "────────────";
+                Line::from(vec![
+                    Span::styled(DECOR, Style::new().fg(theme.accent)),
+                    Span::styled(
+                        "[[[ HISTORY PREVIEW ]]]",
                         Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
                     ),
+                    Span::styled(" Enter run · Ctrl+Enter edit ", Style::new().fg(theme.comment)),
                 ])
             },
         )
     } else {
         (
-            Line::from(vec![
-                Span::styled(
-                    format!("{}  ", state.ui.prompt),
-                    Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(&state.input_buffer, Style::new().fg(theme.fg)),
-            ]),
+            Line::from(
+                prompt_spans(state)
+                    .into_iter()
+                    .chain(input_spans(state, input_width))
+                    .chain(std::iter::once(Span::styled(
+                        ghost_text(state, input_width),
+                        Style::new().fg(theme.comment),
+                    )))
+                    .collect::<Vec<_>>(),
+            ),
             Style::default(),
             Style::new().fg(theme.primary),
             {
                 const DECOR: &str = "────────────";
-                Line::from(vec![
-                    Span::styled(DECOR, Style::new().fg(theme.primary)),
-                    Span::styled(
-                        format!("[ {} ]", state.username),
-                        Style::new().fg(theme.accent).add_modifier(Modifier::BOLD),
-                    ),
-                ])
+                let mut spans = vec![Span::styled(DECOR, Style::new().fg(theme.primary))];
+                spans.extend(state.ui.title_format.render(
+                    theme,
+                    Style::new().fg(theme.accent).add_modifier(Modifier::BOLD),
+                    &|name| prompt_segment(state, name),
+                ));
+                if let Some(prompt) = &state.placeholder_prompt
+                    && let Some((name, kind)) = prompt.field()
+                {
+                    let kind = kind.as_ref().map(|k| format!(" ({k})")).unwrap_or_default();
+                    spans.push(Span::raw(" "));
+                    spans.push(Span::styled(
+                        format!(
+                            " {} › {name}{kind} {}/{} · Esc cancels ",
+                            prompt.alias,
+                            prompt.current + 1,
+                            prompt.fields.len()
+                        ),
+                        Style::new().fg(theme.bg).bg(theme.accent).add_modifier(Modifier::BOLD),
+                    ));
+                }
+                for chip in state.modes.chips() {
+                    spans.push(Span::raw(" "));
+                    spans.push(Span::styled(
+                        format!(" {chip} "),
+                        Style::new().fg(theme.bg).bg(theme.warn),
+                    ));
+                }
+                Line::from(spans)
             },
         )
     };
@@ -362,21 +1305,33 @@ fn render_input_box(frame: &mut Frame, area: Rect, state: &State) {
     );
 
     frame.render_widget(input_paragraph, area);
+    if let Some(spans) = right {
+        let inner = Block::new().borders(Borders::ALL).inner(area);
+        let width = (right_width as u16).min(inner.width);
+        let right_area = Rect {
+            x: inner.right() - width,
+            width,
+            height: inner.height.min(1),
+            ..inner
+        };
+        frame.render_widget(Paragraph::new(Line::from(spans)), right_area);
+    }
 }
 
-fn render_completion_popup(frame: &mut Frame, area: Rect, state: &mut State) {
-    let suggestions = &state.completion_state.suggestions;
-    let items: Vec<ListItem> = suggestions
-        .iter()
-        .map(|s| {
-            let icon = if s.ends_with('/') { "📁" } else { "📄" };
-            ListItem::new(Line::from(vec![
-                Span::raw(icon),
-                Span::raw(" "),
-                Span::raw(s),
-            ]))
-        })
-        .collect();
+fn render_history_search_popup(frame: &mut Frame, area: Rect, state: &State) {
+    let Some(search) = &state.history_search else {
+        return;
+    };
+    let theme = &state.theme;
+    let matches = state.history_matches();
+    let items: Vec<ListItem> = if matches.is_empty() {
+        vec![ListItem::new(Span::styled(
+            "no matches",
+            Style::new().fg(theme.comment).add_modifier(Modifier::ITALIC),
+        ))]
+    } else {
+        matches.iter().map(|m| ListItem::new(*m)).collect()
+    };
     let height = (items.len() + 2).min(10) as u16;
     let popup_area = Rect {
         x: area.x,
@@ -384,16 +1339,219 @@ fn render_completion_popup(frame: &mut Frame, area: Rect, state: &mut State) {
         width: area.width.min(80),
         height,
     };
+    let title = Line::from(vec![
+        Span::styled(" reverse-i-search: ", Style::new().fg(theme.primary)),
+        Span::styled(
+            format!("{}▏", search.query),
+            Style::new().fg(theme.fg).add_modifier(Modifier::BOLD),
+        ),
+    ]);
+    let list = List::new(items)
+        .block(
+            Block::new()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::new().fg(theme.accent)),
+        )
+        .style(Style::new().bg(theme.bg).fg(theme.fg))
+        .highlight_style(
+            Style::new()
+                .bg(theme.primary)
+                .fg(theme.bg)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("▶ ");
+    let selected = (!matches.is_empty()).then_some(search.selected);
+    let mut list_state = ListState::default().with_selected(selected);
+    frame.render_widget(Clear, popup_area);
+    frame.render_stateful_widget(list, popup_area, &mut list_state);
+}
+
+fn render_dir_picker_popup(frame: &mut Frame, area: Rect, state: &State) {
+    let Some(picker) = &state.dir_picker else {
+        return;
+    };
     let theme = &state.theme;
+    let matches = picker.matches();
+    let items: Vec<ListItem> = if matches.is_empty() {
+        vec![ListItem::new(Span::styled(
+            "no visited directories match",
+            Style::new().fg(theme.comment).add_modifier(Modifier::ITALIC),
+        ))]
+    } else {
+        matches.iter().map(|dir| ListItem::new(state::tilde_path(dir))).collect()
+    };
+    let height = (items.len() + 2).min(12) as u16;
+    let popup_area = Rect {
+        x: area.x,
+        y: area.y.saturating_sub(height),
+        width: area.width.min(80),
+        height,
+    };
+    let title = Line::from(vec![
+        Span::styled(" cd: ", Style::new().fg(theme.primary)),
+        Span::styled(
+            format!("{}▏", picker.query),
+            Style::new().fg(theme.fg).add_modifier(Modifier::BOLD),
+        ),
+    ]);
     let list = List::new(items)
         .block(
             Block::new()
-                .title("💡 Suggestions")
-                .title_alignment(Alignment::Center)
+                .title(title)
+                .title_bottom(Span::styled(
+                    " Enter cd · Esc close ",
+                    Style::new().fg(theme.comment),
+                ))
                 .borders(Borders::ALL)
-                .border_type(BorderType::Double)
-                .border_style(Style::new().fg(theme.warn)),
+                .border_type(BorderType::Rounded)
+                .border_style(Style::new().fg(theme.accent)),
         )
+        .style(Style::new().bg(theme.bg).fg(theme.fg))
+        .highlight_style(
+            Style::new()
+                .bg(theme.primary)
+                .fg(theme.bg)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("▶ ");
+    let selected = (!matches.is_empty()).then_some(picker.selected);
+    let mut list_state = ListState::default().with_selected(selected);
+    frame.render_widget(Clear, popup_area);
+    frame.render_stateful_widget(list, popup_area, &mut list_state);
+}
+
+fn render_log_search_bar(frame: &mut Frame, area: Rect, state: &State) {
+    let Some(search) = &state.log_search else {
+        return;
+    };
+    let theme = &state.theme;
+    let status = match search.matches.len() {
+        0 if search.query.is_empty() => String::new(),
+        0 => "no matches".to_string(),
+        n if search.editing => format!("{n} lines · Enter to step with n/N"),
+        n => format!("{}/{n} · n older · N newer · Esc close", search.current + 1),
+    };
+    let cursor = if search.editing { "▏" } else { "" };
+    let title = Line::from(vec![
+        Span::styled(" search log: ", Style::new().fg(theme.primary)),
+        Span::styled(
+            format!("{}{cursor} ", search.query),
+            Style::new().fg(theme.fg).add_modifier(Modifier::BOLD),
+        ),
+    ]);
+    let bar = Paragraph::new(Span::styled(status, Style::new().fg(theme.comment)))
+        .block(
+            Block::new()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::new().fg(theme.accent)),
+        )
+        .style(Style::new().bg(theme.bg).fg(theme.fg));
+    let height = 3;
+    let popup_area = Rect {
+        x: area.x,
+        y: area.y.saturating_sub(height),
+        width: area.width.min(80),
+        height,
+    };
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(bar, popup_area);
+}
+
+fn render_completion_popup(frame: &mut Frame, area: Rect, state: &mut State) {
+    let theme = &state.theme;
+    let cfg = &state.ui.completion;
+    let suggestions = &state.completion_state.suggestions;
+    // Deep paths are shown relative to the directory already typed, which
+    // goes in the title instead of being repeated on every row.
+    let token = if state.input_buffer.ends_with(' ') {
+        ""
+    } else {
+        state.input_buffer.split_whitespace().last().unwrap_or("")
+    };
+    let base = token.rfind('/').map_or("", |i| &token[..=i]);
+    let partial = &token[base.len()..];
+    let items: Vec<ListItem> = suggestions
+        .iter()
+        .map(|s| {
+            let (icon, tag) = match s.source {
+                SuggestionSource::History => (cfg.history_icon.as_str(), Some("history")),
+                SuggestionSource::Template => (cfg.template_icon.as_str(), Some("template")),
+                SuggestionSource::Frecent => (cfg.dir_icon.as_str(), Some("frecent")),
+                _ if s.text.ends_with('/') => (cfg.dir_icon.as_str(), None),
+                _ => (cfg.file_icon.as_str(), None),
+            };
+            let mut spans = Vec::new();
+            if !icon.is_empty() && !state.low_bandwidth {
+                spans.push(Span::raw(icon));
+                spans.push(Span::raw(" "));
+            }
+            match s.text.strip_prefix(base).filter(|_| !base.is_empty()) {
+                Some(rest) if s.source == SuggestionSource::Completion => {
+                    let typed = if rest.starts_with(partial) { partial.len() } else { 0 };
+                    spans.push(Span::raw(&rest[..typed]));
+                    spans.push(Span::styled(
+                        &rest[typed..],
+                        Style::new().add_modifier(Modifier::BOLD),
+                    ));
+                }
+                _ => spans.push(Span::raw(s.text.as_str())),
+            }
+            if let Some(tag) = tag {
+                spans.push(Span::styled(
+                    format!("  {tag}"),
+                    Style::new().fg(theme.comment).add_modifier(Modifier::ITALIC),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let frame_area = frame.area();
+    // Border rows, or just the title row when borderless.
+    let chrome = if cfg.border.is_some() { 2 } else { 1 };
+    let wanted = (items.len() as u16 + chrome).min(cfg.max_height);
+    // Clamp to whatever room the frame leaves on the chosen side.
+    let (y, height) = match cfg.position {
+        PopupPosition::Above => {
+            let height = wanted.min(area.y - frame_area.y);
+            (area.y - height, height)
+        }
+        PopupPosition::Below => {
+            let height = wanted.min(frame_area.bottom().saturating_sub(area.bottom()));
+            (area.bottom(), height)
+        }
+    };
+    let popup_area = Rect {
+        x: area.x,
+        y,
+        width: area.width.min(cfg.max_width),
+        height,
+    };
+    let bulb = glyph(state.low_bandwidth, "💡 ", "");
+    let mut title = match base {
+        "" => format!("{bulb}Suggestions"),
+        base => format!("{bulb}{base}"),
+    };
+    // Relative paths depend on where they were completed.
+    if !base.starts_with(['/', '~'])
+        && let Some(dir) = &state.completion_state.cwd
+    {
+        title.push_str(&format!(" · completing in {}", state::tilde_path(dir)));
+    }
+    let mut block = Block::new()
+        .title(title)
+        .title_alignment(Alignment::Center);
+    if let Some(border) = cfg.border {
+        block = block
+            .borders(Borders::ALL)
+            .border_type(border)
+            .border_style(Style::new().fg(theme.warn));
+    }
+    let list = List::new(items)
+        .block(block)
         .highlight_style(
             Style::new()
                 .bg(theme.primary)
@@ -408,10 +1566,74 @@ fn render_completion_popup(frame: &mut Frame, area: Rect, state: &mut State) {
     frame.render_stateful_widget(list, popup_area, &mut list_state);
 }
 
+/// `[ui.completion] announce`: the selection as plain text where the
+/// status bar was, always on the same row and without color coding.
+fn render_completion_announcement(frame: &mut Frame, area: Rect, state: &State, text: String) {
+    let theme = &state.theme;
+    frame.render_widget(
+        Paragraph::new(format!(" {text}")).style(Style::new().fg(theme.fg).bg(theme.bg)),
+        area,
+    );
+}
+
+/// Nothing of the session, just how to get back to it.
+fn render_lock_screen(frame: &mut Frame, state: &State, lock: &Lock) {
+    let theme = &state.theme;
+    let mut lines = vec![Line::styled(
+        "halo is locked",
+        Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
+    )];
+    if state.lock.passphrase_hash.is_some() {
+        lines.push(Line::styled(
+            format!("passphrase: {}▏", "•".repeat(lock.typed.chars().count())),
+            Style::new().fg(theme.fg),
+        ));
+        if lock.failed {
+            lines.push(Line::styled("wrong passphrase", Style::new().fg(theme.error)));
+        }
+    } else {
+        lines.push(Line::styled("press any key", Style::new().fg(theme.comment)));
+    }
+    let area = centered_rect(40, lines.len() as u16, frame.area());
+    frame.render_widget(Paragraph::new(lines).alignment(Alignment::Center), area);
+}
+
+fn render_confirmation_popup(frame: &mut Frame, state: &State) {
+    let Some(confirmation) = &state.confirmation else {
+        return;
+    };
+    let theme = &state.theme;
+    let popup_area = centered_rect(50, 4, frame.area());
+    let body = vec![
+        Line::from(Span::styled(
+            confirmation.prompt.as_str(),
+            Style::new().fg(theme.fg).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(vec![
+            Span::styled("[y]", Style::new().fg(theme.error).add_modifier(Modifier::BOLD)),
+            Span::styled(" yes   ", Style::new().fg(theme.fg)),
+            Span::styled("[n]", Style::new().fg(theme.primary).add_modifier(Modifier::BOLD)),
+            Span::styled(" no", Style::new().fg(theme.fg)),
+        ]),
+    ];
+    let popup = Paragraph::new(body).alignment(Alignment::Center).block(
+        Block::new()
+            .title(" Confirm ")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::new().fg(theme.warn))
+            .style(Style::new().bg(theme.bg)),
+    );
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(popup, popup_area);
+}
+
 fn render_theme_selection_popup(frame: &mut Frame, state: &State) {
     let theme = &state.theme;
     let popup_width = 50;
-    let popup_height = state.available_themes.len().min(15) as u16 + 4; // +4 for title and borders
+    // +6 for title, borders and the swatch row
+    let popup_height = state.available_themes.len().min(15) as u16 + 6;
     
     let popup_area = centered_rect(popup_width, popup_height, frame.area());
     
@@ -437,25 +1659,488 @@ fn render_theme_selection_popup(frame: &mut Frame, state: &State) {
         items.push(ListItem::new(item_text).style(style));
     }
     
-    let theme_list = List::new(items)
-        .block(
-            Block::default()
-                .title(Span::styled(
-                    " Select Theme ",
-                    Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
-                ))
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-                .border_style(Style::new().fg(theme.accent))
-        )
+    let simulation = match state.theme_simulation {
+        Some(simulation) => simulation.label(),
+        None => "off",
+    };
+    let block = Block::default()
+        .title(Span::styled(
+            " Select Theme ",
+            Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Span::styled(
+            format!(" c: color blindness {simulation} "),
+            Style::new().fg(theme.comment),
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::new().fg(theme.accent))
         .style(Style::new().bg(theme.bg).fg(theme.fg));
-    
+    let inner = block.inner(popup_area);
+    let [list_area, swatch_area] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(2)]).areas(inner);
+
+    // The colors that have to stay apart, as the log uses them.
+    let swatch = |label: &'static str, color: Color| {
+        Span::styled(format!(" {label} "), Style::new().fg(theme.bg).bg(color))
+    };
+    let swatches = Paragraph::new(vec![
+        Line::raw(""),
+        Line::from(vec![
+            swatch("exit=0", Color::Green),
+            Span::raw(" "),
+            swatch("error", theme.error),
+            Span::raw(" "),
+            swatch("warn", theme.warn),
+            Span::raw(" "),
+            swatch("accent", theme.accent),
+            Span::raw(" "),
+            swatch("primary", theme.primary),
+        ]),
+    ]);
+
     // Render background overlay
     let overlay = Block::default()
         .style(Style::new().bg(Color::Black).fg(Color::Black));
     frame.render_widget(overlay, frame.area());
-    
-    frame.render_widget(theme_list, popup_area);
+
+    frame.render_widget(block, popup_area);
+    frame.render_widget(List::new(items), list_area);
+    frame.render_widget(swatches, swatch_area);
+}
+
+fn render_procs_popup(frame: &mut Frame, state: &State) {
+    let Some(monitor) = &state.procs else {
+        return;
+    };
+    let theme = &state.theme;
+    let height = (monitor.rows.len() as u16 + 4)
+        .max(6)
+        .min(frame.area().height.saturating_sub(4));
+    let popup_area = centered_rect(80, height, frame.area());
+
+    let header = format!(
+        "  {:<5} {:>7} {:>6} {:>8}  {:<9} COMMAND",
+        "JOB", "PID", "CPU%", "MEM", "STATE"
+    );
+    let items: Vec<ListItem> = monitor
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let selected = i == monitor.selected;
+            let text = format!(
+                "{} {:<5} {:>7} {:>6.1} {:>8}  {:<9} {}",
+                if selected { "▶" } else { " " },
+                row.job.map(|id| format!("%{id}")).unwrap_or_default(),
+                row.pid.map(|pid| pid.to_string()).unwrap_or_else(|| "-".into()),
+                row.cpu,
+                disk::human_size(row.rss_bytes),
+                row.status,
+                row.name
+            );
+            let style = if selected {
+                Style::new()
+                    .fg(theme.bg)
+                    .bg(theme.accent)
+                    .add_modifier(Modifier::BOLD)
+            } else if row.job.is_some() {
+                Style::new().fg(theme.primary)
+            } else {
+                Style::new().fg(theme.fg)
+            };
+            ListItem::new(text).style(style)
+        })
+        .collect();
+
+    let footer = monitor.message.clone().unwrap_or_else(|| {
+        let all = if monitor.show_system { "jobs only" } else { "all processes" };
+        format!("↑/↓ select · k TERM · K KILL · a {all} · Esc close")
+    });
+    let block = Block::default()
+        .title(Span::styled(
+            " Processes ",
+            Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Span::styled(format!(" {footer} "), Style::new().fg(theme.comment)))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::new().fg(theme.accent));
+    let inner = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block.style(Style::new().bg(theme.bg).fg(theme.fg)), popup_area);
+
+    let [header_area, list_area] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(inner);
+    frame.render_widget(
+        Paragraph::new(header).style(Style::new().fg(theme.comment).add_modifier(Modifier::BOLD)),
+        header_area,
+    );
+    if monitor.rows.is_empty() {
+        frame.render_widget(
+            Paragraph::new("  no jobs running; press a to list all processes")
+                .style(Style::new().fg(theme.comment)),
+            list_area,
+        );
+        return;
+    }
+    let mut list_state = ListState::default().with_selected(Some(monitor.selected));
+    frame.render_stateful_widget(List::new(items), list_area, &mut list_state);
+}
+
+fn render_git_panel(frame: &mut Frame, state: &State) {
+    let Some(panel) = &state.git_panel else {
+        return;
+    };
+    let theme = &state.theme;
+    // A heading line before each group.
+    let mut items = Vec::new();
+    let mut selected_line = None;
+    let mut area = None;
+    for (i, row) in panel.rows.iter().enumerate() {
+        if area != Some(row.area) {
+            area = Some(row.area);
+            items.push(ListItem::new(Span::styled(
+                row.area.label(),
+                Style::new().fg(theme.comment).add_modifier(Modifier::BOLD),
+            )));
+        }
+        let selected = i == panel.selected;
+        if selected {
+            selected_line = Some(items.len());
+        }
+        let color = match row.area {
+            Area::Conflicted => theme.error,
+            Area::Staged => theme.success,
+            Area::Unstaged => theme.warn,
+            Area::Untracked => theme.comment,
+        };
+        let style = if selected {
+            Style::new()
+                .fg(theme.bg)
+                .bg(theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::new().fg(color)
+        };
+        let marker = if selected { "▶" } else { " " };
+        items.push(ListItem::new(format!("{marker} {} {}", row.kind, row.path)).style(style));
+    }
+    let height = (items.len() as u16 + 2)
+        .max(5)
+        .min(frame.area().height.saturating_sub(4));
+    let popup_area = centered_rect(80, height, frame.area());
+
+    let title = match (&panel.root, &panel.branch) {
+        (Some(root), Some(branch)) => format!(" git: {} on {branch} ", state::tilde_path(root)),
+        (Some(root), None) => format!(" git: {} ", state::tilde_path(root)),
+        _ => " git ".to_string(),
+    };
+    let footer = panel.message.clone().unwrap_or_else(|| {
+        "Enter diff · Space stage/unstage · d discard · r refresh · Esc close".into()
+    });
+    let block = Block::default()
+        .title(Span::styled(
+            title,
+            Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Span::styled(format!(" {footer} "), Style::new().fg(theme.comment)))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::new().fg(theme.accent));
+    let inner = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block.style(Style::new().bg(theme.bg).fg(theme.fg)), popup_area);
+    if panel.rows.is_empty() {
+        let text = if panel.loading() {
+            "  reading status…"
+        } else if panel.root.is_some() {
+            "  nothing to commit, working tree clean"
+        } else {
+            ""
+        };
+        frame.render_widget(
+            Paragraph::new(text).style(Style::new().fg(theme.comment)),
+            inner,
+        );
+        return;
+    }
+    let mut list_state = ListState::default().with_selected(selected_line);
+    frame.render_stateful_widget(List::new(items), inner, &mut list_state);
+}
+
+fn render_diff_view(frame: &mut Frame, state: &mut State) {
+    let theme = &state.theme;
+    let Some(view) = state.diff_view.as_mut() else {
+        return;
+    };
+    let area = frame.area();
+    let popup_area = centered_rect(
+        area.width.saturating_sub(8).max(20),
+        area.height.saturating_sub(4).max(5),
+        area,
+    );
+    let block = Block::default()
+        .title(Span::styled(
+            format!(" {} ", view.title),
+            Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Span::styled(
+            " ↑/↓ PgUp/PgDn g/G · q close ",
+            Style::new().fg(theme.comment),
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::new().fg(theme.accent));
+    let inner = block.inner(popup_area);
+    view.set_height(inner.height as usize);
+    let last = (view.top + view.height).min(view.lines.len());
+    let position = Line::from(Span::styled(
+        format!(" {}-{last}/{} ", view.top + 1, view.lines.len()),
+        Style::new().fg(theme.primary),
+    ))
+    .right_aligned();
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(
+        block
+            .title_bottom(position)
+            .style(Style::new().bg(theme.bg).fg(theme.fg)),
+        popup_area,
+    );
+    let lines: Vec<Line> = view
+        .lines
+        .iter()
+        .skip(view.top)
+        .take(view.height)
+        .map(|(kind, text)| {
+            let style = match kind {
+                LineKind::Header => Style::new().fg(theme.accent).add_modifier(Modifier::BOLD),
+                LineKind::Hunk => Style::new().fg(theme.primary),
+                LineKind::Added => Style::new().fg(theme.success),
+                LineKind::Removed => Style::new().fg(theme.error),
+                LineKind::Context => Style::new().fg(theme.fg),
+            };
+            Line::styled(text.as_str(), style)
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+/// The `build` form, above the input line where its command appears.
+fn render_builder_popup(frame: &mut Frame, input_area: Rect, state: &State) {
+    let Some(form) = &state.builder else {
+        return;
+    };
+    let theme = &state.theme;
+    let fields = form.tool.fields;
+    let label_width = fields.iter().map(|f| f.label.width()).max().unwrap_or(0);
+    let mut lines: Vec<Line> = fields
+        .iter()
+        .zip(&form.values)
+        .enumerate()
+        .map(|(i, (field, value))| {
+            let selected = i == form.selected;
+            let shown = match field.kind {
+                FieldKind::Text if selected => format!("{value}▏"),
+                FieldKind::Text => value.clone(),
+                FieldKind::Toggle => if value.is_empty() { "[ ]" } else { "[x]" }.to_string(),
+                FieldKind::Choice(_) => format!("‹ {value} ›"),
+            };
+            let label_style = if selected {
+                Style::new().fg(theme.bg).bg(theme.accent).add_modifier(Modifier::BOLD)
+            } else {
+                Style::new().fg(theme.comment)
+            };
+            Line::from(vec![
+                Span::styled(format!(" {:<label_width$} ", field.label), label_style),
+                Span::raw("  "),
+                Span::styled(shown, Style::new().fg(theme.fg)),
+            ])
+        })
+        .collect();
+    lines.push(Line::raw(""));
+    lines.push(Line::styled(
+        format!(" {}", form.field().help),
+        Style::new().fg(theme.primary).add_modifier(Modifier::ITALIC),
+    ));
+
+    let width = input_area.width.min(90);
+    let height = (lines.len() as u16 + 2).min(input_area.y);
+    let area = Rect::new(input_area.x, input_area.y - height, width, height);
+    let block = Block::default()
+        .title(Span::styled(
+            format!(" build {} — {} ", form.tool.name, form.tool.about),
+            Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Span::styled(
+            " ↑/↓ field · type, Space or ←/→ to change · Enter keep · Esc cancel ",
+            Style::new().fg(theme.comment),
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::new().fg(theme.accent));
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(block)
+            .style(Style::new().bg(theme.bg)),
+        area,
+    );
+}
+
+/// Alt+Enter's preview, dimmed so it doesn't read as output.
+fn render_dry_run_popup(frame: &mut Frame, input_area: Rect, state: &State) {
+    let Some(steps) = &state.dry_run else {
+        return;
+    };
+    let theme = &state.theme;
+    let label_width = steps.iter().map(|s| s.label.width()).max().unwrap_or(0);
+    let lines: Vec<Line> = steps
+        .iter()
+        .map(|step| {
+            Line::from(vec![
+                Span::styled(
+                    format!(" {:>label_width$}  ", step.label),
+                    Style::new().fg(theme.comment),
+                ),
+                Span::styled(
+                    step.text.clone(),
+                    Style::new().fg(theme.fg).add_modifier(Modifier::DIM),
+                ),
+            ])
+        })
+        .collect();
+
+    let width = input_area.width.min(100);
+    let height = (lines.len() as u16 + 2).min(input_area.y);
+    let area = Rect::new(input_area.x, input_area.y - height, width, height);
+    let block = Block::default()
+        .title(Span::styled(" dry run · nothing executed ", Style::new().fg(theme.comment)))
+        .title_bottom(Span::styled(" any key closes ", Style::new().fg(theme.comment)))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::new().fg(theme.comment));
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(block)
+            .style(Style::new().bg(theme.bg)),
+        area,
+    );
+}
+
+/// `:perf` timings in the top-right corner of the log.
+fn render_perf_overlay(frame: &mut Frame, area: Rect, state: &State) {
+    let Some(perf) = &state.perf else {
+        return;
+    };
+    let theme = &state.theme;
+    let lines = perf.report();
+    let width = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 4;
+    let height = lines.len() as u16 + 2;
+    if area.width < width || area.height < height {
+        return;
+    }
+    let popup_area = Rect::new(area.right() - width - 1, area.y + 1, width, height);
+    let block = Block::default()
+        .title(Span::styled(
+            " perf ",
+            Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::new().fg(theme.comment));
+    let text: Vec<Line> = lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let style = if i == 0 {
+                Style::new().fg(theme.comment)
+            } else {
+                Style::new().fg(theme.fg)
+            };
+            Line::styled(format!(" {line}"), style)
+        })
+        .collect();
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(
+        Paragraph::new(text)
+            .block(block)
+            .style(Style::new().bg(theme.bg)),
+        popup_area,
+    );
+}
+
+/// The output pager, over the whole screen.
+fn render_pager(frame: &mut Frame, state: &mut State) {
+    let theme = &state.theme;
+    let Some(pager) = state.pager.as_mut() else {
+        return;
+    };
+    let area = frame.area();
+    let footer = match &pager.search {
+        Some(search) if search.editing => format!("/{}▏", search.query),
+        Some(search) if search.matches.is_empty() => format!("/{}: no matches", search.query),
+        Some(search) => format!(
+            "/{} {}/{} · n next · N previous · Esc",
+            search.query,
+            search.current + 1,
+            search.matches.len()
+        ),
+        None => "↑/↓ PgUp/PgDn g/G · / search · q close".to_string(),
+    };
+    let block = Block::default()
+        .title(Span::styled(
+            format!(" ❯ {} ", pager.command),
+            Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Span::styled(format!(" {footer} "), Style::new().fg(theme.comment)))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::new().fg(theme.accent));
+    let inner = block.inner(area);
+    pager.set_height(inner.height as usize);
+    let last = (pager.top + pager.height).min(pager.lines.len());
+    let position = Line::from(Span::styled(
+        format!(" {}-{last}/{}  {}% ", pager.top + 1, pager.lines.len(), pager.percent()),
+        Style::new().fg(theme.primary),
+    ))
+    .right_aligned();
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        block
+            .title_bottom(position)
+            .style(Style::new().bg(theme.bg).fg(theme.fg)),
+        area,
+    );
+
+    let search = pager.search.as_ref();
+    let current = search.and_then(LogSearch::current_match);
+    let lines: Vec<Line> = pager
+        .lines
+        .iter()
+        .enumerate()
+        .skip(pager.top)
+        .take(pager.height)
+        .map(|(k, line)| {
+            let style = if line.starts_with("[stderr] ") {
+                Style::new().fg(theme.error).add_modifier(Modifier::ITALIC)
+            } else {
+                Style::new().fg(theme.fg)
+            };
+            let is_current = current == Some((pager.entry, k));
+            let text = state::displayed_line(line);
+            let link_ranges: Vec<Range<usize>> = links::line_links(&pager.links, k, text)
+                .into_iter()
+                .map(|(range, _)| range)
+                .collect();
+            Line::from(output_spans(text, style, search, is_current, &link_ranges, theme))
+        })
+        .collect();
+    let left = pager.left.min(u16::MAX as usize) as u16;
+    frame.render_widget(Paragraph::new(lines).scroll((0, left)), inner);
 }
 
 fn centered_rect(percent_x: u16, height: u16, r: Rect) -> Rect {
@@ -0,0 +1,132 @@
+// src/words.rs
+
+//! Where words end for line editing and completion, from `[shell.words]`.
+//! Readline users expect Ctrl-W to eat a whole path and Alt-B to stop at
+//! every `-` or `.`; people who live in paths often want the opposite, so
+//! each is configurable on its own.
+
+/// Characters that end a word, besides whitespace.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Boundaries {
+    /// Anything but letters and digits, like emacs `forward-word`.
+    Punctuation,
+    /// Only these.
+    Chars(String),
+}
+
+impl Boundaries {
+    fn parse(value: &str) -> Self {
+        match value {
+            "punctuation" => Self::Punctuation,
+            chars => Self::Chars(chars.to_string()),
+        }
+    }
+
+    pub fn is_boundary(&self, c: char) -> bool {
+        c.is_whitespace()
+            || match self {
+                Self::Punctuation => !c.is_alphanumeric(),
+                Self::Chars(chars) => chars.contains(c),
+            }
+    }
+
+    /// Start of the word before the end of `before`, skipping boundaries
+    /// right in front of it.
+    pub fn word_start(&self, before: &str) -> usize {
+        let word = before.trim_end_matches(|c| self.is_boundary(c));
+        word.rfind(|c| self.is_boundary(c))
+            .map_or(0, |i| i + word[i..].chars().next().map_or(1, char::len_utf8))
+    }
+
+    /// Length of `rest` up to the end of its first word.
+    pub fn word_end(&self, rest: &str) -> usize {
+        let word = rest.trim_start_matches(|c| self.is_boundary(c));
+        let len = word.find(|c| self.is_boundary(c)).unwrap_or(word.len());
+        rest.len() - word.len() + len
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WordConfig {
+    /// Ctrl-W. Whitespace only by default, as readline's `unix-word-rubout`.
+    pub kill: Boundaries,
+    /// Alt-B/F/D and Ctrl+←/→.
+    pub motion: Boundaries,
+    /// Split a path argument for Tab, so `--out=bu` completes `bu` with `=`
+    /// here. `/` never splits; it is part of the path being completed.
+    pub completion: String,
+}
+
+impl Default for WordConfig {
+    fn default() -> Self {
+        Self {
+            kill: Boundaries::Chars(String::new()),
+            motion: Boundaries::Punctuation,
+            completion: String::new(),
+        }
+    }
+}
+
+impl WordConfig {
+    pub fn from_table(tbl: &toml::value::Table) -> Self {
+        let mut config = Self::default();
+        if let Some(kill) = tbl.get("kill").and_then(|v| v.as_str()) {
+            config.kill = Boundaries::parse(kill);
+        }
+        if let Some(motion) = tbl.get("motion").and_then(|v| v.as_str()) {
+            config.motion = Boundaries::parse(motion);
+        }
+        if let Some(completion) = tbl.get("completion").and_then(|v| v.as_str()) {
+            config.completion = completion.chars().filter(|c| *c != '/').collect();
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whitespace_only_kill_takes_whole_paths() {
+        let kill = WordConfig::default().kill;
+        assert_eq!(kill.word_start("cd ~/src/halo"), 3);
+        assert_eq!(kill.word_start("cd ~/src/halo   "), 3);
+        assert_eq!(kill.word_start("make"), 0);
+        assert_eq!(kill.word_end("  --out=x y"), 9);
+    }
+
+    #[test]
+    fn punctuation_motion_stops_at_symbols() {
+        let motion = Boundaries::Punctuation;
+        assert_eq!(motion.word_start("cd ~/src/halo"), 9);
+        assert_eq!(motion.word_start("git log --oneline-"), 10);
+        assert_eq!(motion.word_end("/src/halo"), 4);
+        assert_eq!(motion.word_start("echo héllo"), 5);
+    }
+
+    #[test]
+    fn chars_boundaries() {
+        let chars = Boundaries::parse("/=");
+        assert_eq!(chars, Boundaries::Chars("/=".into()));
+        assert_eq!(chars.word_start("--out=build/debug"), 12);
+        assert_eq!(chars.word_end("build/debug"), 5);
+        assert!(!chars.is_boundary('-'));
+    }
+
+    #[test]
+    fn from_table_keeps_slash_out_of_completion() {
+        let tbl: toml::value::Table = toml::from_str(
+            r#"
+            kill = "punctuation"
+            motion = "-"
+            completion = "=/:"
+            "#,
+        )
+        .unwrap();
+        let config = WordConfig::from_table(&tbl);
+        assert_eq!(config.kill, Boundaries::Punctuation);
+        assert_eq!(config.motion, Boundaries::Chars("-".into()));
+        assert_eq!(config.completion, "=:");
+    }
+}